@@ -0,0 +1,82 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Compares decoding `Submit` frames by copying the solution out of the read buffer with
+//! decoding them as slices of it: `cargo bench --bench decode`.
+
+use ::bytes::{Buf, BytesMut};
+use snarkvm::prelude::{Address, AffineCurve, Group, PartialSolution, ProverSolution, Testnet3};
+use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+use std::time::Instant;
+use zkwork_aleo_protocol::message::{Data, PoolMessageCS};
+
+type CurrentNetwork = Testnet3;
+
+const FRAMES: usize = 1_000_000;
+
+/// Returns `FRAMES` `Submit` frames back to back, as a pool reads them from a busy agent.
+fn submits() -> anyhow::Result<BytesMut> {
+    let solution = ProverSolution::<CurrentNetwork>::new(
+        PartialSolution::new(
+            Address::new(Group::generator()),
+            42,
+            KZGCommitment(AffineCurve::prime_subgroup_generator()),
+        ),
+        KZGProof {
+            w: AffineCurve::prime_subgroup_generator(),
+            random_v: None,
+        },
+    );
+    let message = PoolMessageCS::<CurrentNetwork>::Submit(1, 0, 7, Data::Object(solution), None);
+    let frame = message.to_vec()?;
+    let mut buffer = BytesMut::with_capacity(frame.len() * FRAMES);
+    for _ in 0..FRAMES {
+        buffer.extend_from_slice(&frame);
+    }
+    Ok(buffer)
+}
+
+fn report(name: &str, start: Instant) {
+    println!(
+        "{:<8} {:>6.1} ns/frame",
+        name,
+        start.elapsed().as_nanos() as f64 / FRAMES as f64
+    );
+}
+
+fn main() -> anyhow::Result<()> {
+    // Both paths read the same length prefix; they differ only in how the body is handed over.
+    for sliced in [false, true] {
+        let mut buffer = submits()?;
+        let start = Instant::now();
+        let mut decoded = 0;
+        while buffer.len() >= 4 {
+            let length = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+            buffer.advance(4);
+            let message = if sliced {
+                // Split the body off the buffer and keep the solution as a slice of it.
+                PoolMessageCS::<CurrentNetwork>::deserialize_bytes(
+                    buffer.split_to(length).freeze(),
+                )?
+            } else {
+                // Deserialize from a borrowed slice, copying the solution, then advance.
+                let message = PoolMessageCS::<CurrentNetwork>::deserialize(&buffer[..length])?;
+                buffer.advance(length);
+                message
+            };
+            std::hint::black_box(message);
+            decoded += 1;
+        }
+        assert_eq!(decoded, FRAMES);
+        report(if sliced { "sliced" } else { "copied" }, start);
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Compares bytes on the wire and codec throughput of the u32 and varint length prefixes
+//! for a Ping-heavy workload: `cargo bench --bench wire_overhead`.
+
+use bytes::BytesMut;
+use snarkvm::prelude::Testnet3;
+use std::time::Instant;
+use tokio_util::codec::{Decoder, Encoder};
+use zkwork_aleo_protocol::message::{PoolCodecCS, PoolMessageCS, CAP_SEQUENCE, CAP_VARINT_LENGTH};
+
+type CurrentNetwork = Testnet3;
+
+const FRAMES: usize = 1_000_000;
+
+/// Encodes and decodes `FRAMES` messages, 9 `Ping`s for each `JobStatus`.
+fn run(name: &str, capabilities: u32) -> anyhow::Result<()> {
+    let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+    let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+    encoder.set_capabilities(capabilities);
+    decoder.set_capabilities(capabilities);
+
+    let start = Instant::now();
+    let mut buffer = BytesMut::new();
+    for index in 0..FRAMES {
+        let message = match index % 10 {
+            0 => PoolMessageCS::JobStatus(1, index as u64),
+            _ => PoolMessageCS::Ping,
+        };
+        encoder.encode(message, &mut buffer)?;
+    }
+    let bytes = buffer.len();
+    let mut decoded = 0;
+    while decoder.decode(&mut buffer)?.is_some() {
+        decoded += 1;
+    }
+    assert_eq!(decoded, FRAMES);
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<18} {:>10} bytes  {:.2} bytes/frame  {:>6.1} ns/frame",
+        name,
+        bytes,
+        bytes as f64 / FRAMES as f64,
+        elapsed.as_nanos() as f64 / FRAMES as f64
+    );
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    run("u32 length", 0)?;
+    run("varint length", CAP_VARINT_LENGTH)?;
+    run("u32 + sequence", CAP_SEQUENCE)?;
+    run("varint + sequence", CAP_VARINT_LENGTH | CAP_SEQUENCE)?;
+    Ok(())
+}
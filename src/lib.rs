@@ -1 +1,7 @@
 pub mod message;
+
+#[cfg(feature = "noise")]
+pub mod noise;
+
+#[cfg(feature = "tls")]
+pub mod tls;
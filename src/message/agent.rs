@@ -0,0 +1,68 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::difficulty::Difficulty;
+
+/// Splits a `JobHint` among the rigs behind an agent, given each rig's benchmark in
+/// solutions per second, so that every rig submits shares at about the same rate.
+/// The agent's total difficulty stays `suggested * rigs`, shared in proportion to speed.
+/// Rigs without a benchmark (0) keep the suggested difficulty, and no rig drops below 1.
+pub fn device_difficulties(suggested: Difficulty, solutions_per_sec: &[u64]) -> Vec<Difficulty> {
+    let benchmarked = solutions_per_sec.iter().filter(|speed| **speed > 0).count() as u128;
+    let total_speed = solutions_per_sec
+        .iter()
+        .map(|speed| *speed as u128)
+        .sum::<u128>();
+    solutions_per_sec
+        .iter()
+        .map(|speed| match speed {
+            0 => suggested,
+            speed => {
+                let share = suggested.get() as u128 * benchmarked * *speed as u128 / total_speed;
+                Difficulty::new(share.clamp(1, u64::MAX as u128) as u64)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn difficulties(suggested: u64, solutions_per_sec: &[u64]) -> Vec<u64> {
+        device_difficulties(Difficulty::new(suggested), solutions_per_sec)
+            .into_iter()
+            .map(Difficulty::get)
+            .collect()
+    }
+
+    #[test]
+    fn test_device_difficulties_proportional() {
+        assert_eq!(difficulties(1000, &[10, 30]), [500, 1500]);
+        assert_eq!(difficulties(1000, &[25, 25, 25, 25]), [1000; 4]);
+        // The total stays at suggested * rigs, up to rounding down.
+        let split = difficulties(1000, &[1, 2, 4]);
+        assert_eq!(split, [428, 857, 1714]);
+        assert!(split.iter().sum::<u64>() <= 3000);
+        assert!(difficulties(1000, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_device_difficulties_edges() {
+        // Rigs without a benchmark keep the hint, the others share their part of the budget.
+        assert_eq!(difficulties(1000, &[0, 10, 30]), [1000, 500, 1500]);
+        assert_eq!(difficulties(1000, &[0, 0]), [1000, 1000]);
+        // A very slow rig still gets a usable difficulty.
+        assert_eq!(difficulties(1, &[1, 1_000_000]), [1, 1]);
+        // Large hints do not overflow.
+        assert_eq!(difficulties(u64::MAX, &[1, 3]), [u64::MAX / 2, u64::MAX]);
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Length of the HMAC-SHA256 tag appended to authenticated frames.
+pub const AUTH_TAG_SIZE: usize = 32;
+
+const SESSION_KEY_CONTEXT: &[u8] = b"zkwork-aleo-session-key";
+
+/// Returns whether `a` and `b` hold the same bytes, in a time that depends on their lengths only,
+/// so comparing a secret such as a signature or key does not reveal how many leading bytes match.
+pub fn secure_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let difference = a
+        .iter()
+        .zip(b)
+        .fold(0u8, |difference, (a, b)| difference | (a ^ b));
+    // Keep the compiler from returning at the first difference.
+    std::hint::black_box(difference) == 0
+}
+
+/// The key authenticating frames once the handshake is complete.
+#[derive(Clone, Eq)]
+pub struct SessionKey([u8; 32]);
+
+impl PartialEq for SessionKey {
+    fn eq(&self, other: &Self) -> bool {
+        secure_eq(&self.0, &other.0)
+    }
+}
+
+impl SessionKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Derives the session key from the worker id and signature issued in an accepted `ConnectAck`.
+    /// The key is only as secret as that signature: when the handshake itself may be observed,
+    /// run the connection over an encrypted transport as well.
+    pub fn from_connect_ack(worker_id: u32, signature: &str) -> Self {
+        let mut mac = HmacSha256::new_from_slice(signature.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(SESSION_KEY_CONTEXT);
+        mac.update(&worker_id.to_le_bytes());
+        Self(mac.finalize().into_bytes().into())
+    }
+
+    /// Returns the tag authenticating `data`.
+    pub fn sign(&self, data: &[u8]) -> [u8; AUTH_TAG_SIZE] {
+        let mut mac = self.mac();
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Checks `tag` against `data` in constant time.
+    pub fn verify(&self, data: &[u8], tag: &[u8]) -> bool {
+        let mut mac = self.mac();
+        mac.update(data);
+        mac.verify_slice(tag).is_ok()
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.0).expect("HMAC accepts keys of any length")
+    }
+}
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SessionKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_key() {
+        let key = SessionKey::from_connect_ack(1, "testsignature");
+        assert_eq!(key, SessionKey::from_connect_ack(1, "testsignature"));
+        assert_ne!(key, SessionKey::from_connect_ack(2, "testsignature"));
+        assert_ne!(key, SessionKey::from_connect_ack(1, "testsignaturf"));
+
+        let tag = key.sign(b"payload");
+        assert!(key.verify(b"payload", &tag));
+        assert!(!key.verify(b"payloae", &tag));
+        assert!(!key.verify(b"payload", &tag[..AUTH_TAG_SIZE - 1]));
+        assert_eq!(format!("{:?}", key), "SessionKey(..)");
+    }
+
+    #[test]
+    fn test_secure_eq() {
+        assert!(secure_eq(b"", b""));
+        assert!(secure_eq(b"testsignature", b"testsignature"));
+        assert!(!secure_eq(b"testsignature", b"testsignaturf"));
+        assert!(!secure_eq(b"testsignature", b"uestsignature"));
+        assert!(!secure_eq(b"testsignature", b"testsignatur"));
+        assert!(!secure_eq(b"", b"t"));
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use ::bytes::BytesMut;
+use std::io;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// The recommended `max_buffered` of a pool's per-worker writer: 64 KiB holds over a hundred
+/// full frames, ample for a worker that keeps reading, and bounds a stalled one to a small
+/// fixed cost even across thousands of connections.
+pub const RECOMMENDED_MAX_BUFFERED: usize = 64 * 1024;
+
+/// Wraps an encoder to refuse messages while the outbound buffer holds `max_buffered` bytes
+/// or more, so a worker that stops reading cannot grow it without bound.
+/// A refused `encode` fails with an `io::ErrorKind::WouldBlock` error and leaves `dst`
+/// untouched; the caller should stop producing for that connection, e.g. drop or coalesce
+/// its jobs, until the buffer drains. Frames are appended whole, so the buffer may exceed
+/// `max_buffered` by at most one message.
+#[derive(Debug)]
+pub struct BoundedEncoder<E> {
+    inner: E,
+    max_buffered: usize,
+}
+
+impl<E> BoundedEncoder<E> {
+    pub fn new(inner: E, max_buffered: usize) -> Self {
+        Self {
+            inner,
+            max_buffered,
+        }
+    }
+
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    pub fn max_buffered(&self) -> usize {
+        self.max_buffered
+    }
+
+    /// Returns whether `dst` has room for another message, e.g. before taking one off a queue.
+    pub fn has_capacity(&self, dst: &BytesMut) -> bool {
+        dst.len() < self.max_buffered
+    }
+}
+
+/// Returns whether an encoder error is a `BoundedEncoder` refusing a message.
+pub fn is_would_block(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|error| error.kind() == io::ErrorKind::WouldBlock)
+}
+
+impl<M, E: Encoder<M>> Encoder<M> for BoundedEncoder<E> {
+    type Error = E::Error;
+
+    fn encode(&mut self, message: M, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if !self.has_capacity(dst) {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!(
+                    "Outbound buffer of {} bytes is over its limit of {}",
+                    dst.len(),
+                    self.max_buffered
+                ),
+            )
+            .into());
+        }
+        self.inner.encode(message, dst)
+    }
+}
+
+impl<E: Decoder> Decoder for BoundedEncoder<E> {
+    type Item = E::Item;
+    type Error = E::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_eof(src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{capabilities::*, codec::*, poolmessage::*};
+    use anyhow::Result;
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_bounded_encoder() -> Result<()> {
+        // Room for two 9 byte frames, the second overshooting the limit.
+        let mut encoder = BoundedEncoder::new(PoolCodecSC::<CurrentNetwork>::new(), 10);
+        let mut dst = BytesMut::new();
+        encoder.encode(PoolMessageSC::Throttle(500), &mut dst)?;
+        assert!(encoder.has_capacity(&dst));
+        encoder.encode(PoolMessageSC::Throttle(500), &mut dst)?;
+        assert_eq!(dst.len(), 18);
+        assert!(!encoder.has_capacity(&dst));
+
+        let error = encoder
+            .encode(PoolMessageSC::Pong(None), &mut dst)
+            .unwrap_err();
+        assert!(is_would_block(&error));
+        assert_eq!(dst.len(), 18);
+
+        // Once the writer drains the buffer, messages flow again.
+        let _ = dst.split_to(9);
+        encoder.encode(PoolMessageSC::Pong(None), &mut dst)?;
+        assert_eq!(dst.len(), 9 + 5);
+
+        // Other encoder errors are not mistaken for backpressure.
+        encoder.get_mut().set_capabilities(CAP_HMAC);
+        let error = encoder
+            .encode(PoolMessageSC::Pong(None), &mut BytesMut::new())
+            .unwrap_err();
+        assert!(!is_would_block(&error));
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::poolmessage::*;
+use snarkvm::prelude::Network;
+
+use anyhow::Result;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The window a `BandwidthEstimator` averages over by default.
+pub const DEFAULT_BANDWIDTH_WINDOW: Duration = Duration::from_secs(60);
+
+/// Rolling average of the bytes one connection carries, per message id, over a time window.
+/// Pool-to-client and client-to-pool ids do not overlap, so one estimator covers both directions.
+/// Sizes are those of `encoded_len`, i.e. legacy frames without negotiated fields.
+/// Feed it the messages of one worker at the expected share rate to size the link of a farm.
+#[derive(Clone, Debug)]
+pub struct BandwidthEstimator {
+    window: Duration,
+    started: Option<Instant>,
+    samples: VecDeque<(Instant, u8, usize)>,
+}
+
+impl Default for BandwidthEstimator {
+    fn default() -> Self {
+        Self::new(DEFAULT_BANDWIDTH_WINDOW)
+    }
+}
+
+impl BandwidthEstimator {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            started: None,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a frame of `len` bytes carrying message `id`, sent or received at `now`.
+    pub fn record_at(&mut self, id: u8, len: usize, now: Instant) {
+        self.started.get_or_insert(now);
+        while let Some(&(at, ..)) = self.samples.front() {
+            if now.saturating_duration_since(at) < self.window {
+                break;
+            }
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, id, len));
+    }
+
+    /// Records a pool-to-client message at `now`.
+    pub fn record_sc_at<N: Network>(
+        &mut self,
+        message: &PoolMessageSC<N>,
+        now: Instant,
+    ) -> Result<()> {
+        self.record_at(message.id(), message.encoded_len()?, now);
+        Ok(())
+    }
+
+    /// Records a client-to-pool message at `now`.
+    pub fn record_cs_at<N: Network>(
+        &mut self,
+        message: &PoolMessageCS<N>,
+        now: Instant,
+    ) -> Result<()> {
+        self.record_at(message.id(), message.encoded_len()?, now);
+        Ok(())
+    }
+
+    /// Returns the average bytes per second of message `id` over the window ending at `now`,
+    /// or since the first record if that is more recent.
+    pub fn bytes_per_sec(&self, id: u8, now: Instant) -> f64 {
+        self.average(now, |sample_id| sample_id == id)
+    }
+
+    /// Returns the average bytes per second of all messages over the window ending at `now`.
+    pub fn total_bytes_per_sec(&self, now: Instant) -> f64 {
+        self.average(now, |_| true)
+    }
+
+    /// Returns (id, bytes per second) of every message seen in the window ending at `now`,
+    /// sorted by id.
+    pub fn rates(&self, now: Instant) -> Vec<(u8, f64)> {
+        let mut ids: Vec<u8> = self.in_window(now).map(|(_, id, _)| *id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.into_iter()
+            .map(|id| (id, self.bytes_per_sec(id, now)))
+            .collect()
+    }
+
+    fn in_window(&self, now: Instant) -> impl Iterator<Item = &(Instant, u8, usize)> {
+        self.samples
+            .iter()
+            .filter(move |(at, ..)| *at <= now && now.saturating_duration_since(*at) < self.window)
+    }
+
+    fn average(&self, now: Instant, filter: impl Fn(u8) -> bool) -> f64 {
+        let span = match self.started {
+            Some(started) => now.saturating_duration_since(started).min(self.window),
+            None => return 0.0,
+        };
+        if span.is_zero() {
+            return 0.0;
+        }
+        let bytes: usize = self
+            .in_window(now)
+            .filter(|(_, id, _)| filter(*id))
+            .map(|(.., len)| len)
+            .sum();
+        bytes as f64 / span.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::message_id::{cs, sc};
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_bandwidth_estimator() -> Result<()> {
+        let start = Instant::now();
+        let mut estimator = BandwidthEstimator::new(Duration::from_secs(10));
+        assert_eq!(estimator.total_bytes_per_sec(start), 0.0);
+
+        // A ping and its pong every second, a throttle every other second, for a minute.
+        for second in 0..60 {
+            let now = start + Duration::from_secs(second);
+            estimator.record_cs_at(&PoolMessageCS::<CurrentNetwork>::Ping, now)?;
+            estimator.record_sc_at(&PoolMessageSC::<CurrentNetwork>::Pong(None), now)?;
+            if second % 2 == 0 {
+                estimator.record_sc_at(&PoolMessageSC::<CurrentNetwork>::Throttle(500), now)?;
+            }
+        }
+        let now = start + Duration::from_secs(59);
+        assert_eq!(estimator.bytes_per_sec(cs::PING, now), 5.0);
+        assert_eq!(estimator.bytes_per_sec(sc::THROTTLE, now), 4.5);
+        assert_eq!(estimator.total_bytes_per_sec(now), 14.5);
+        assert_eq!(
+            estimator.rates(now),
+            vec![(sc::PONG, 5.0), (sc::THROTTLE, 4.5), (cs::PING, 5.0)]
+        );
+        // Old samples are dropped.
+        assert_eq!(estimator.samples.len(), 25);
+
+        // Averages over the time since the first record until the window is full.
+        let mut estimator = BandwidthEstimator::default();
+        estimator.record_at(cs::PING, 100, start);
+        estimator.record_at(cs::PING, 100, start + Duration::from_secs(1));
+        assert_eq!(
+            estimator.bytes_per_sec(cs::PING, start + Duration::from_secs(2)),
+            100.0
+        );
+
+        // Nothing is left once the window has passed.
+        assert_eq!(
+            estimator.total_bytes_per_sec(start + Duration::from_secs(120)),
+            0.0
+        );
+        assert!(estimator.rates(start + Duration::from_secs(120)).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use ::bytes::BytesMut;
+use std::time::{Duration, Instant};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Wraps an encoder to coalesce small messages, e.g. bursts of `Pong`s, into one buffer.
+/// Encoded frames are held back until `max_msgs` are pending or the oldest has waited
+/// `max_delay`, then handed to the writer together, saving a socket write per message.
+/// Each message is still encoded as its own frame, so the decoder is unchanged.
+///
+/// A batch is only released by the next `encode`, so a writer that goes idle must move
+/// `take_pending` into its write buffer once `deadline` passes, then flush.
+#[derive(Debug)]
+pub struct BatchingEncoder<E> {
+    inner: E,
+    max_delay: Duration,
+    max_msgs: usize,
+    pending: BytesMut,
+    pending_msgs: usize,
+    oldest: Option<Instant>,
+}
+
+impl<E> BatchingEncoder<E> {
+    /// A `max_msgs` of 0 or 1, or a zero `max_delay`, writes every message right away.
+    pub fn new(inner: E, max_delay: Duration, max_msgs: usize) -> Self {
+        Self {
+            inner,
+            max_delay,
+            max_msgs: max_msgs.max(1),
+            pending: BytesMut::new(),
+            pending_msgs: 0,
+            oldest: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    /// Returns the number of messages held back.
+    pub fn pending_len(&self) -> usize {
+        self.pending_msgs
+    }
+
+    /// Returns when the pending batch is due, `None` if nothing is pending.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.oldest.map(|oldest| oldest + self.max_delay)
+    }
+
+    /// Returns the pending frames, leaving the batch empty.
+    pub fn take_pending(&mut self) -> BytesMut {
+        self.pending_msgs = 0;
+        self.oldest = None;
+        self.pending.split()
+    }
+
+    /// Encodes `message` into the batch and moves the batch to `dst` once it is due at `now`.
+    pub fn encode_at<M>(
+        &mut self,
+        message: M,
+        dst: &mut BytesMut,
+        now: Instant,
+    ) -> Result<(), E::Error>
+    where
+        E: Encoder<M>,
+    {
+        self.inner.encode(message, &mut self.pending)?;
+        self.pending_msgs += 1;
+        let oldest = *self.oldest.get_or_insert(now);
+        if self.pending_msgs >= self.max_msgs
+            || now.saturating_duration_since(oldest) >= self.max_delay
+        {
+            dst.unsplit(self.take_pending());
+        }
+        Ok(())
+    }
+}
+
+impl<M, E: Encoder<M>> Encoder<M> for BatchingEncoder<E> {
+    type Error = E::Error;
+
+    fn encode(&mut self, message: M, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_at(message, dst, Instant::now())
+    }
+}
+
+impl<E: Decoder> Decoder for BatchingEncoder<E> {
+    type Item = E::Item;
+    type Error = E::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_eof(src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{codec::*, poolmessage::*, replay::*};
+    use anyhow::Result;
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_batching_max_msgs() -> Result<()> {
+        let start = Instant::now();
+        let mut encoder = BatchingEncoder::new(
+            PoolCodecSC::<CurrentNetwork>::new(),
+            Duration::from_millis(10),
+            3,
+        );
+        let mut dst = BytesMut::new();
+        encoder.encode_at(PoolMessageSC::Pong(None), &mut dst, start)?;
+        encoder.encode_at(PoolMessageSC::Throttle(500), &mut dst, start)?;
+        assert!(dst.is_empty());
+        assert_eq!(encoder.pending_len(), 2);
+        assert_eq!(encoder.deadline(), Some(start + Duration::from_millis(10)));
+
+        encoder.encode_at(PoolMessageSC::Pong(None), &mut dst, start)?;
+        assert_eq!(encoder.pending_len(), 0);
+        assert_eq!(encoder.deadline(), None);
+
+        // The batch keeps every frame boundary.
+        let names = FrameReader::new(&dst[..])
+            .decode(PoolCodecSC::<CurrentNetwork>::new())
+            .map(|message| message.map(|message| message.name().to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(names, ["Pong", "Throttle", "Pong"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_batching_max_delay() -> Result<()> {
+        let start = Instant::now();
+        let mut encoder = BatchingEncoder::new(
+            PoolCodecSC::<CurrentNetwork>::new(),
+            Duration::from_millis(10),
+            16,
+        );
+        let mut dst = BytesMut::new();
+        encoder.encode_at(PoolMessageSC::Pong(None), &mut dst, start)?;
+        encoder.encode_at(
+            PoolMessageSC::Pong(None),
+            &mut dst,
+            start + Duration::from_millis(5),
+        )?;
+        assert!(dst.is_empty());
+        encoder.encode_at(
+            PoolMessageSC::Pong(None),
+            &mut dst,
+            start + Duration::from_millis(10),
+        )?;
+        assert_eq!(
+            dst.len(),
+            3 * PoolMessageSC::<CurrentNetwork>::Pong(None).to_vec()?.len()
+        );
+
+        // An idle writer flushes the leftovers itself.
+        encoder.encode_at(
+            PoolMessageSC::Pong(None),
+            &mut dst,
+            start + Duration::from_millis(11),
+        )?;
+        assert_eq!(encoder.take_pending().len(), 5);
+        assert_eq!(encoder.pending_len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_batching_disabled() -> Result<()> {
+        let mut encoder =
+            BatchingEncoder::new(PoolCodecSC::<CurrentNetwork>::new(), Duration::ZERO, 16);
+        let mut dst = BytesMut::new();
+        encoder.encode(PoolMessageSC::Pong(None), &mut dst)?;
+        assert_eq!(dst.len(), 5);
+        assert_eq!(encoder.pending_len(), 0);
+        Ok(())
+    }
+}
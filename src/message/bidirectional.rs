@@ -0,0 +1,239 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{codec::*, framing::*, poolmessage::*};
+use snarkvm::prelude::Network;
+
+use ::bytes::BytesMut;
+use anyhow::{anyhow, Result};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// A message of either direction, e.g. for a relay that reads the frames of workers and of the
+/// upstream pool with one codec.
+#[derive(Clone, Debug)]
+pub enum PoolMessage<N: Network> {
+    ClientToServer(PoolMessageCS<N>),
+    ServerToClient(PoolMessageSC<N>),
+}
+
+impl<N: Network> From<PoolMessageCS<N>> for PoolMessage<N> {
+    fn from(message: PoolMessageCS<N>) -> Self {
+        Self::ClientToServer(message)
+    }
+}
+
+impl<N: Network> From<PoolMessageSC<N>> for PoolMessage<N> {
+    fn from(message: PoolMessageSC<N>) -> Self {
+        Self::ServerToClient(message)
+    }
+}
+
+impl<N: Network> TryFrom<PoolMessage<N>> for PoolMessageCS<N> {
+    type Error = PoolMessage<N>;
+
+    fn try_from(message: PoolMessage<N>) -> Result<Self, Self::Error> {
+        match message {
+            PoolMessage::ClientToServer(message) => Ok(message),
+            message => Err(message),
+        }
+    }
+}
+
+impl<N: Network> TryFrom<PoolMessage<N>> for PoolMessageSC<N> {
+    type Error = PoolMessage<N>;
+
+    fn try_from(message: PoolMessage<N>) -> Result<Self, Self::Error> {
+        match message {
+            PoolMessage::ServerToClient(message) => Ok(message),
+            message => Err(message),
+        }
+    }
+}
+
+impl<N: Network> PoolMessage<N> {
+    /// Returns the message name.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::ClientToServer(message) => message.name(),
+            Self::ServerToClient(message) => message.name(),
+        }
+    }
+
+    /// Returns the message ID.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::ClientToServer(message) => message.id(),
+            Self::ServerToClient(message) => message.id(),
+        }
+    }
+
+    /// Returns the direction the message travels in.
+    pub fn direction(&self) -> Direction {
+        match self {
+            Self::ClientToServer(..) => Direction::ClientToPool,
+            Self::ServerToClient(..) => Direction::PoolToClient,
+        }
+    }
+
+    /// Serializes the message into a frame := << length(u32), message >>, see `PoolMessageSC::to_vec`.
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::ClientToServer(message) => message.to_vec(),
+            Self::ServerToClient(message) => message.to_vec(),
+        }
+    }
+
+    /// Deserializes the given buffer into a message of the direction its id belongs to.
+    pub fn deserialize(buffer: &[u8]) -> Result<Self> {
+        match buffer.first().map(|id| Direction::of(*id)) {
+            Some(Direction::ClientToPool) => PoolMessageCS::deserialize(buffer).map(Into::into),
+            Some(Direction::PoolToClient) => PoolMessageSC::deserialize(buffer).map(Into::into),
+            None => Err(anyhow!("Invalid message buffer")),
+        }
+    }
+}
+
+/// Codec for messages of both directions := << length(u32), id, data >>, the framing of `to_vec`.
+/// Each frame is decoded by the codec of the direction its id belongs to, ids 0..=127 from the pool
+/// and 128..=255 from clients. Capabilities change where the id sits in a frame, so this codec
+/// only speaks the framing before any are negotiated; relays negotiating them keep one
+/// `MessageCodec` per direction.
+#[derive(Clone, Debug)]
+pub struct PoolMessageCodec<N: Network> {
+    cs: PoolCodecCS<N>,
+    sc: PoolCodecSC<N>,
+}
+
+impl<N: Network> Default for PoolMessageCodec<N> {
+    fn default() -> Self {
+        Self {
+            cs: PoolCodecCS::new(),
+            sc: PoolCodecSC::new(),
+        }
+    }
+}
+
+impl<N: Network> PoolMessageCodec<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the codec of client-to-pool frames, e.g. to set its `UnknownIdPolicy`.
+    pub fn cs_mut(&mut self) -> &mut PoolCodecCS<N> {
+        &mut self.cs
+    }
+
+    /// Returns the codec of pool-to-client frames.
+    pub fn sc_mut(&mut self) -> &mut PoolCodecSC<N> {
+        &mut self.sc
+    }
+}
+
+impl<N: Network> Encoder<PoolMessage<N>> for PoolMessageCodec<N> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: PoolMessage<N>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match message {
+            PoolMessage::ClientToServer(message) => self.cs.encode(message, dst),
+            PoolMessage::ServerToClient(message) => self.sc.encode(message, dst),
+        }
+    }
+}
+
+impl<N: Network> Decoder for PoolMessageCodec<N> {
+    type Error = std::io::Error;
+    type Item = PoolMessage<N>;
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match source.get(4).map(|id| Direction::of(*id)) {
+            Some(Direction::ClientToPool) => Ok(self.cs.decode(source)?.map(Into::into)),
+            Some(Direction::PoolToClient) => Ok(self.sc.decode(source)?.map(Into::into)),
+            // The id has not arrived yet.
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match source.get(4).map(|id| Direction::of(*id)) {
+            Some(Direction::PoolToClient) => Ok(self.sc.decode_eof(source)?.map(Into::into)),
+            // Either codec reports a frame cut before its id as truncated.
+            _ => Ok(self.cs.decode_eof(source)?.map(Into::into)),
+        }
+    }
+}
+
+impl<N: Network> PoolCodec<PoolMessage<N>> for PoolMessageCodec<N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::message_id::{cs, sc};
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    /// A relay forwarding client frames upstream and pool frames downstream, both read with one codec.
+    #[test]
+    fn test_pool_message_codec_proxy() -> Result<()> {
+        let mut codec = PoolMessageCodec::<CurrentNetwork>::new();
+        let mut inbound = BytesMut::new();
+        for message in [
+            PoolMessage::from(PoolMessageCS::Ping),
+            PoolMessageSC::Pong(Some(200)).into(),
+            PoolMessageCS::JobStatus(1, 7).into(),
+            PoolMessageSC::Throttle(500).into(),
+            PoolMessageCS::DisConnect(1).into(),
+        ] {
+            codec.encode(message, &mut inbound)?;
+        }
+
+        let (mut upstream, mut downstream) = (BytesMut::new(), BytesMut::new());
+        while let Some(message) = codec.decode(&mut inbound)? {
+            match message.direction() {
+                Direction::ClientToPool => codec.encode(message, &mut upstream)?,
+                Direction::PoolToClient => codec.encode(message, &mut downstream)?,
+            }
+        }
+        assert!(inbound.is_empty());
+
+        let mut pool = PoolCodecCS::<CurrentNetwork>::new();
+        let mut ids = Vec::new();
+        while let Some(message) = pool.decode(&mut upstream)? {
+            ids.push(message.id());
+        }
+        assert_eq!(ids, [cs::PING, cs::JOB_STATUS, cs::DISCONNECT]);
+
+        let mut worker = PoolCodecSC::<CurrentNetwork>::new();
+        let mut ids = Vec::new();
+        while let Some(message) = worker.decode(&mut downstream)? {
+            ids.push(message.id());
+        }
+        assert_eq!(ids, [sc::PONG, sc::THROTTLE]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_message_codec_partial() -> Result<()> {
+        let mut codec = PoolMessageCodec::<CurrentNetwork>::new();
+        let mut buffer = BytesMut::new();
+        codec.encode(PoolMessageSC::ShutDown.into(), &mut buffer)?;
+        let mut partial = BytesMut::from(&buffer[..4]);
+        assert!(codec.decode(&mut partial)?.is_none());
+        assert!(codec.decode_eof(&mut partial).is_err());
+
+        let message = codec.decode(&mut buffer)?.expect("a complete frame");
+        assert!(PoolMessageCS::try_from(message.clone()).is_err());
+        assert!(matches!(
+            PoolMessageSC::try_from(message),
+            Ok(PoolMessageSC::ShutDown)
+        ));
+        Ok(())
+    }
+}
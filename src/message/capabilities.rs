@@ -0,0 +1,269 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{compression::*, error::*, poolmessage::*, tlv::*};
+use snarkvm::prelude::Network;
+
+/// Frames carry a per-direction sequence number after the length prefix.
+pub const CAP_SEQUENCE: u32 = 1 << 0;
+
+/// Frames carry an HMAC-SHA256 tag keyed by the session key, see `SessionKey`.
+pub const CAP_HMAC: u32 = 1 << 1;
+
+/// Frames carry a channel id so one connection can multiplex many workers, see `ChannelCodec`.
+pub const CAP_CHANNELS: u32 = 1 << 2;
+
+/// Messages larger than a frame are sent as `Chunk` frames and reassembled, see `Reassembler`.
+pub const CAP_CHUNKS: u32 = 1 << 3;
+
+/// Message bodies are JSON instead of the binary layout, see `WireFormat`.
+pub const CAP_JSON: u32 = 1 << 4;
+
+/// `Submit` may carry the worker's signature of the solution, see `sign_solution`.
+/// Codecs reject signed `Submit`s unless it was agreed.
+pub const CAP_SIGNED_SHARES: u32 = 1 << 5;
+
+/// `Pong` carries the gateway's load factor, see `Endpoints`.
+pub const CAP_LOAD_FACTOR: u32 = 1 << 6;
+
+/// Binary message bodies use the V2 layout, see `ProtocolCodec`.
+pub const CAP_PROTOCOL_V2: u32 = 1 << 7;
+
+/// Frames carry a CRC32C checksum after everything else, see `crc32c`.
+pub const CAP_CHECKSUM: u32 = 1 << 8;
+
+/// Frames carry the `ProtocolVersion` of their body right after the length prefix.
+pub const CAP_VERSION_BYTE: u32 = 1 << 9;
+
+/// Frames start with a LEB128 varint length instead of a u32, saving 3 bytes on small frames.
+pub const CAP_VARINT_LENGTH: u32 = 1 << 10;
+
+/// Frames carry a flags byte and bodies above the codec's threshold are zstd-compressed.
+/// Only negotiated with the `compress-zstd` cargo feature, see `MessageCodec::with_compression_threshold`.
+pub const CAP_ZSTD: u32 = 1 << 11;
+
+/// Like `CAP_ZSTD` with lz4, which is cheaper to decompress.
+/// Only negotiated with the `compress-lz4` cargo feature.
+pub const CAP_LZ4: u32 = 1 << 12;
+
+/// The compression algorithms, of which `negotiate_capabilities` keeps at most one.
+pub const COMPRESSION_CAPABILITIES: u32 = CAP_ZSTD | CAP_LZ4;
+
+/// Every capability the protocol defines, whether or not this build implements it.
+pub const KNOWN_CAPABILITIES: u32 = CAP_SEQUENCE
+    | CAP_HMAC
+    | CAP_CHANNELS
+    | CAP_CHUNKS
+    | CAP_JSON
+    | CAP_SIGNED_SHARES
+    | CAP_LOAD_FACTOR
+    | CAP_PROTOCOL_V2
+    | CAP_CHECKSUM
+    | CAP_VERSION_BYTE
+    | CAP_VARINT_LENGTH
+    | CAP_ZSTD
+    | CAP_LZ4;
+
+/// The capabilities behind cargo features.
+const OPTIONAL_CAPABILITIES: u32 = match cfg!(feature = "compress-zstd") {
+    true => CAP_ZSTD,
+    false => 0,
+} | match cfg!(feature = "compress-lz4") {
+    true => CAP_LZ4,
+    false => 0,
+};
+
+/// Every capability this library implements.
+pub const SUPPORTED_CAPABILITIES: u32 = CAP_SEQUENCE
+    | CAP_HMAC
+    | CAP_CHANNELS
+    | CAP_CHUNKS
+    | CAP_JSON
+    | CAP_SIGNED_SHARES
+    | CAP_LOAD_FACTOR
+    | CAP_PROTOCOL_V2
+    | CAP_CHECKSUM
+    | CAP_VERSION_BYTE
+    | CAP_VARINT_LENGTH
+    | OPTIONAL_CAPABILITIES;
+
+/// Bits without a meaning yet, which must be zero in `Hello` and `HelloAck`.
+pub const RESERVED_CAPABILITIES: u32 = !KNOWN_CAPABILITIES;
+
+/// Returns the capabilities both peers agreed on.
+/// The client advertises its mask in `Hello` or `Connect`, the pool answers with the result in
+/// `HelloAck` or `ConnectAck`, and both sides apply it to their codecs for every frame after the
+/// answer. Only features in the agreed mask are used.
+/// Of the compression algorithms both peers support, the pool picks one for the connection.
+pub fn negotiate_capabilities(local: u32, remote: u32) -> u32 {
+    let agreed = local & remote & SUPPORTED_CAPABILITIES;
+    agreed & !COMPRESSION_CAPABILITIES
+        | CompressionAlgorithm::from_capabilities(agreed).capability()
+}
+
+/// Returns the TLV advertising the given capability mask.
+pub fn capabilities_tlv(capabilities: u32) -> Tlv {
+    Tlv::new(TAG_CAPABILITIES, capabilities.to_le_bytes().to_vec())
+}
+
+/// Reads the capability mask of a `Hello` or `HelloAck`, rejecting reserved bits.
+pub(crate) fn read_capability_mask(data: &[u8]) -> Result<u32, PoolProtocolError> {
+    let capabilities = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    match capabilities & RESERVED_CAPABILITIES {
+        0 => Ok(capabilities),
+        reserved => Err(PoolProtocolError::ReservedCapabilities(reserved)),
+    }
+}
+
+fn read_capabilities(tlvs: &[Tlv]) -> u32 {
+    match find_tlv(tlvs, TAG_CAPABILITIES) {
+        Some(&[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]),
+        _ => 0,
+    }
+}
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Returns the capabilities advertised by a `Hello` or `Connect`, 0 for older clients.
+    pub fn capabilities(&self) -> u32 {
+        match self {
+            Self::Hello(capabilities) => *capabilities,
+            Self::Connect(.., tlvs) => read_capabilities(tlvs),
+            _ => 0,
+        }
+    }
+}
+
+impl<N: Network> PoolMessageSC<N> {
+    /// Returns the capabilities agreed in a `HelloAck` or `ConnectAck`, 0 for older pools.
+    pub fn capabilities(&self) -> u32 {
+        match self {
+            Self::HelloAck(capabilities) => *capabilities,
+            Self::ConnectAck(.., tlvs) => read_capabilities(tlvs),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::codec::*;
+    use ::bytes::BytesMut;
+    use ::rand::thread_rng;
+    use snarkvm::prelude::{Address, Testnet3, Uniform};
+    use tokio_util::codec::{Decoder, Encoder};
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_capabilities_negotiation() -> anyhow::Result<()> {
+        let message = PoolMessageCS::Connect::<CurrentNetwork>(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            vec![capabilities_tlv(CAP_SEQUENCE | 1 << 31)],
+        );
+        let mut buffer = BytesMut::new();
+        PoolCodecCS::<CurrentNetwork>::new().encode(message, &mut buffer)?;
+        let message = PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut buffer)?
+            .unwrap();
+        assert_eq!(message.capabilities(), CAP_SEQUENCE | 1 << 31);
+
+        let agreed = negotiate_capabilities(SUPPORTED_CAPABILITIES, message.capabilities());
+        assert_eq!(agreed, CAP_SEQUENCE);
+
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(&mut thread_rng()));
+        let message = PoolMessageSC::ConnectAck::<CurrentNetwork>(
+            true,
+            address,
+            Some(1),
+            Some(String::from("testsignature")),
+            None,
+            vec![capabilities_tlv(agreed)],
+        );
+        let mut buffer = BytesMut::new();
+        PoolCodecSC::<CurrentNetwork>::new().encode(message, &mut buffer)?;
+        let message = PoolCodecSC::<CurrentNetwork>::new()
+            .decode(&mut buffer)?
+            .unwrap();
+        assert_eq!(message.capabilities(), CAP_SEQUENCE);
+
+        // Peers predating capabilities negotiate nothing.
+        assert_eq!(
+            PoolMessageSC::<CurrentNetwork>::Pong(None).capabilities(),
+            0
+        );
+        assert_eq!(negotiate_capabilities(SUPPORTED_CAPABILITIES, 0), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_negotiation() -> anyhow::Result<()> {
+        let hello = PoolMessageCS::<CurrentNetwork>::Hello(CAP_SEQUENCE | CAP_SIGNED_SHARES);
+        let bytes = hello.to_vec()?;
+        let hello = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(hello.capabilities(), CAP_SEQUENCE | CAP_SIGNED_SHARES);
+
+        let agreed = negotiate_capabilities(CAP_SIGNED_SHARES | CAP_JSON, hello.capabilities());
+        let bytes = PoolMessageSC::<CurrentNetwork>::HelloAck(agreed).to_vec()?;
+        let hello_ack = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(hello_ack.capabilities(), CAP_SIGNED_SHARES);
+
+        // Reserved bits are rejected in both directions.
+        let mut bytes = PoolMessageCS::<CurrentNetwork>::Hello(0).to_vec()?;
+        bytes[8] = 0x80;
+        let error = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..]).unwrap_err();
+        assert_eq!(
+            error.downcast::<PoolProtocolError>()?,
+            PoolProtocolError::ReservedCapabilities(1 << 31)
+        );
+        let mut bytes = PoolMessageSC::<CurrentNetwork>::HelloAck(0).to_vec()?;
+        bytes[6] = 0x20;
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..]).is_err());
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..8]).is_err());
+        Ok(())
+    }
+
+    #[cfg(all(feature = "compress-zstd", feature = "compress-lz4"))]
+    #[test]
+    fn test_compression_negotiation() -> anyhow::Result<()> {
+        // The pool picks zstd when the worker supports both, or the one the worker supports.
+        let pool = SUPPORTED_CAPABILITIES;
+        assert_eq!(
+            negotiate_capabilities(pool, CAP_LZ4 | CAP_ZSTD | CAP_SEQUENCE),
+            CAP_ZSTD | CAP_SEQUENCE
+        );
+        assert_eq!(negotiate_capabilities(pool, CAP_LZ4), CAP_LZ4);
+        assert_eq!(negotiate_capabilities(pool, CAP_ZSTD), CAP_ZSTD);
+        assert_eq!(negotiate_capabilities(pool, CAP_SEQUENCE), CAP_SEQUENCE);
+        assert_eq!(negotiate_capabilities(CAP_ZSTD, CAP_LZ4 | CAP_SEQUENCE), 0);
+
+        // Both sides apply the agreed algorithm.
+        let agreed = negotiate_capabilities(pool, CAP_LZ4 | CAP_CHECKSUM);
+        let mut encoder = PoolCodecSC::<CurrentNetwork>::new();
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        encoder.set_capabilities(agreed);
+        decoder.set_capabilities(agreed);
+        assert_eq!(encoder.compression(), CompressionAlgorithm::Lz4);
+        let message = PoolMessageSC::<CurrentNetwork>::ShareAckBatch(vec![(1, 0); 64]);
+        let mut buffer = BytesMut::new();
+        encoder.encode(message.clone(), &mut buffer)?;
+        assert_eq!(buffer[4], FLAG_COMPRESSED);
+        assert!(buffer.len() < message.to_vec()?.len());
+        let decoded = decoder.decode(&mut buffer)?.unwrap();
+        assert_eq!(decoded.to_vec()?, message.to_vec()?);
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{capabilities::*, codec::*};
+
+use ::bytes::BytesMut;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Codec for a multiplexed connection := (channel_id, message)
+/// Each channel runs its own Connect/ConnectAck and carries its own Submits,
+/// so an agent can tunnel many workers over one TCP connection.
+#[derive(Clone, Debug)]
+pub struct ChannelCodec<M> {
+    inner: MessageCodec<M>,
+}
+
+impl<M> Default for ChannelCodec<M> {
+    fn default() -> Self {
+        Self::new(MessageCodec::new())
+    }
+}
+
+impl<M> ChannelCodec<M> {
+    /// Wraps a codec, enabling the channel id on every frame.
+    pub fn new(mut inner: MessageCodec<M>) -> Self {
+        inner.set_capabilities(inner.capabilities() | CAP_CHANNELS);
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &MessageCodec<M> {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut MessageCodec<M> {
+        &mut self.inner
+    }
+}
+
+impl<M: WireMessage> Encoder<(u16, M)> for ChannelCodec<M> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, (channel, message): (u16, M), dst: &mut BytesMut) -> Result<()> {
+        self.inner.encode_frame(channel, &message, dst)
+    }
+}
+
+impl<M: WireMessage> Decoder for ChannelCodec<M> {
+    type Error = std::io::Error;
+    type Item = (u16, M);
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_next(source)
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_next_eof(source)
+    }
+}
+
+/// Routes decoded (channel_id, message) pairs to one queue per channel.
+#[derive(Debug)]
+pub struct ChannelDemux<M> {
+    channels: HashMap<u16, mpsc::UnboundedSender<M>>,
+}
+
+impl<M> Default for ChannelDemux<M> {
+    fn default() -> Self {
+        Self {
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl<M> ChannelDemux<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a channel and returns the receiving end of its queue.
+    pub fn open(&mut self, channel: u16) -> mpsc::UnboundedReceiver<M> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.channels.insert(channel, sender);
+        receiver
+    }
+
+    /// Closes a channel; later frames for it are rejected.
+    pub fn close(&mut self, channel: u16) {
+        self.channels.remove(&channel);
+    }
+
+    /// Forwards a message to its channel's queue.
+    pub fn dispatch(&mut self, (channel, message): (u16, M)) -> Result<()> {
+        let sender = self
+            .channels
+            .get(&channel)
+            .ok_or_else(|| anyhow!("Unknown channel {}", channel))?;
+        if sender.send(message).is_err() {
+            self.channels.remove(&channel);
+            return Err(anyhow!("Channel {} is closed", channel));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{error::*, poolmessage::*};
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    fn connect(name: &str) -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            name.to_string(),
+            "215587407@qq.com".to_string(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_channel_codec_interleaved() -> Result<()> {
+        let mut encoder = ChannelCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        let mut buffer = BytesMut::new();
+        for (channel, message) in [
+            (1, connect("rig-01")),
+            (2, connect("rig-02")),
+            (1, PoolMessageCS::JobStatus(11, 5)),
+            (3, connect("rig-03")),
+            (2, PoolMessageCS::JobStatus(12, 5)),
+            (3, PoolMessageCS::Ping),
+            (1, PoolMessageCS::DisConnect(11)),
+        ] {
+            encoder.encode((channel, message), &mut buffer)?;
+        }
+
+        let mut decoder = ChannelCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        let mut demux = ChannelDemux::new();
+        let mut receivers = [demux.open(1), demux.open(2), demux.open(3)];
+        while let Some(frame) = decoder.decode(&mut buffer)? {
+            demux.dispatch(frame)?;
+        }
+
+        let names = receivers
+            .iter_mut()
+            .map(|receiver| {
+                let mut names = Vec::new();
+                while let Ok(message) = receiver.try_recv() {
+                    names.push(message.name().to_string());
+                }
+                names
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names[0], ["Connect", "JobStatus", "Disconnect"]);
+        assert_eq!(names[1], ["Connect", "JobStatus"]);
+        assert_eq!(names[2], ["Connect", "Ping"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_demux_unknown_and_closed() -> Result<()> {
+        let mut demux = ChannelDemux::<PoolMessageCS<CurrentNetwork>>::new();
+        assert!(demux.dispatch((1, PoolMessageCS::Ping)).is_err());
+
+        let receiver = demux.open(1);
+        demux.dispatch((1, PoolMessageCS::Ping))?;
+        drop(receiver);
+        assert!(demux.dispatch((1, PoolMessageCS::Ping)).is_err());
+
+        demux.open(2);
+        demux.close(2);
+        assert!(demux.dispatch((2, PoolMessageCS::Ping)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_codec_rejects_other_channels() -> Result<()> {
+        let mut encoder = ChannelCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        let mut buffer = BytesMut::new();
+        encoder.encode((0, PoolMessageCS::Ping), &mut buffer)?;
+        encoder.encode((4, PoolMessageCS::Ping), &mut buffer)?;
+
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_CHANNELS);
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageCS::Ping)
+        ));
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::UnexpectedChannel(4))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::error::*;
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// The most bytes a chunk frame carries, leaving room for the optional frame fields.
+pub const CHUNK_PAYLOAD_SIZE: usize = 448;
+/// The bytes a binary chunk := << id, transfer_id(u32), index(u16), total(u16) >> adds to its payload.
+pub(crate) const CHUNK_HEADER_SIZE: usize = 1 + 4 + 2 + 2;
+/// The most bytes a JSON chunk frame carries, leaving room for base64 and the field names.
+pub const JSON_CHUNK_PAYLOAD_SIZE: usize = 256;
+/// The largest message that may be reassembled from chunks.
+pub const MAXIMUM_REASSEMBLED_SIZE: usize = 64 * 1024;
+/// The most transfers a receiver keeps open at once.
+pub const MAXIMUM_TRANSFERS: usize = 4;
+/// How long a transfer may wait for its next chunk before it is dropped.
+pub const TRANSFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub(crate) fn write_chunk<W: Write>(
+    writer: &mut W,
+    transfer_id: u32,
+    index: u16,
+    total: u16,
+    bytes: &[u8],
+) -> Result<()> {
+    writer.write_all(&transfer_id.to_le_bytes())?;
+    writer.write_all(&index.to_le_bytes())?;
+    writer.write_all(&total.to_le_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+pub(crate) fn read_chunk(data: &[u8]) -> Option<(u32, u16, u16, Vec<u8>)> {
+    match data.len() >= 8 {
+        true => Some((
+            u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+            u16::from_le_bytes([data[4], data[5]]),
+            u16::from_le_bytes([data[6], data[7]]),
+            data[8..].to_vec(),
+        )),
+        false => None,
+    }
+}
+
+/// Splits a serialized message into chunk payloads of at most `payload_size` bytes.
+pub(crate) fn split_chunks(
+    message: &[u8],
+    payload_size: usize,
+) -> Result<Vec<&[u8]>, PoolProtocolError> {
+    match message.len() <= MAXIMUM_REASSEMBLED_SIZE {
+        true => Ok(message.chunks(payload_size).collect()),
+        false => Err(PoolProtocolError::TransferTooLarge(message.len())),
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Transfer {
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    size: usize,
+    last_update: Instant,
+}
+
+/// The incomplete transfers of a receiver, keyed by `K`.
+#[derive(Clone, Debug)]
+pub(crate) struct Transfers<K> {
+    transfers: HashMap<K, Transfer>,
+    max_transfers: usize,
+}
+
+impl<K: Copy + Eq + Hash> Transfers<K> {
+    pub(crate) fn new(max_transfers: usize) -> Self {
+        Self {
+            transfers: HashMap::new(),
+            max_transfers,
+        }
+    }
+
+    /// Adds a chunk and returns the whole payload once its last missing chunk arrives.
+    /// A chunk that breaks the limits or disagrees with earlier chunks drops its transfer
+    /// and fails with `invalid`.
+    pub(crate) fn insert(
+        &mut self,
+        key: K,
+        index: u16,
+        total: u16,
+        bytes: Vec<u8>,
+        now: Instant,
+        invalid: PoolProtocolError,
+    ) -> Result<Option<Vec<u8>>, PoolProtocolError> {
+        if index >= total || bytes.len() > CHUNK_PAYLOAD_SIZE {
+            self.transfers.remove(&key);
+            return Err(invalid);
+        }
+        if !self.transfers.contains_key(&key) {
+            self.collect_garbage(now);
+            if self.transfers.len() >= self.max_transfers {
+                return Err(PoolProtocolError::TooManyTransfers);
+            }
+            self.transfers.insert(
+                key,
+                Transfer {
+                    chunks: vec![None; total as usize],
+                    received: 0,
+                    size: 0,
+                    last_update: now,
+                },
+            );
+        }
+        let transfer = self.transfers.get_mut(&key).expect("transfer was inserted");
+        if transfer.chunks.len() != total as usize {
+            self.transfers.remove(&key);
+            return Err(invalid);
+        }
+        let slot = &mut transfer.chunks[index as usize];
+        if slot.is_some() {
+            return Ok(None);
+        }
+        transfer.size += bytes.len();
+        if transfer.size > MAXIMUM_REASSEMBLED_SIZE {
+            let size = transfer.size;
+            self.transfers.remove(&key);
+            return Err(PoolProtocolError::TransferTooLarge(size));
+        }
+        *slot = Some(bytes);
+        transfer.received += 1;
+        transfer.last_update = now;
+        if transfer.received < transfer.chunks.len() {
+            return Ok(None);
+        }
+        let transfer = self.transfers.remove(&key).expect("transfer exists");
+        Ok(Some(
+            transfer.chunks.into_iter().flatten().flatten().collect(),
+        ))
+    }
+
+    /// Drops the transfers that have not received a chunk within `TRANSFER_TIMEOUT`.
+    pub(crate) fn collect_garbage(&mut self, now: Instant) {
+        self.transfers
+            .retain(|_, transfer| now.duration_since(transfer.last_update) < TRANSFER_TIMEOUT);
+    }
+
+    /// Drops a transfer.
+    pub(crate) fn remove(&mut self, key: K) {
+        self.transfers.remove(&key);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.transfers.len()
+    }
+}
+
+/// Collects the chunks of messages sent with chunked transfer.
+/// Transfers are keyed by channel and transfer id, chunks may arrive in any order,
+/// and repeated chunks are ignored.
+#[derive(Clone, Debug)]
+pub struct Reassembler {
+    transfers: Transfers<(u16, u32)>,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self {
+            transfers: Transfers::new(MAXIMUM_TRANSFERS),
+        }
+    }
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk and returns the whole message once its last missing chunk arrives.
+    /// A chunk that breaks the limits or disagrees with earlier chunks drops its transfer.
+    pub fn insert(
+        &mut self,
+        channel: u16,
+        transfer_id: u32,
+        index: u16,
+        total: u16,
+        bytes: Vec<u8>,
+        now: Instant,
+    ) -> Result<Option<Vec<u8>>, PoolProtocolError> {
+        let invalid = PoolProtocolError::InvalidChunk {
+            transfer_id,
+            index,
+            total,
+        };
+        self.transfers
+            .insert((channel, transfer_id), index, total, bytes, now, invalid)
+    }
+
+    /// Drops the transfers that have not received a chunk within `TRANSFER_TIMEOUT`.
+    pub fn collect_garbage(&mut self, now: Instant) {
+        self.transfers.collect_garbage(now)
+    }
+
+    /// Returns the number of incomplete transfers.
+    pub fn len(&self) -> usize {
+        self.transfers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(message: &[u8]) -> Vec<(u16, u16, Vec<u8>)> {
+        let parts = split_chunks(message, CHUNK_PAYLOAD_SIZE).unwrap();
+        let total = parts.len() as u16;
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(index, bytes)| (index as u16, total, bytes.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_reassembler_out_of_order() -> Result<()> {
+        let message = (0..2000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut parts = chunks(&message);
+        assert_eq!(parts.len(), 5);
+        parts.reverse();
+        parts.swap(1, 3);
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::new();
+        let last = parts.pop().unwrap();
+        for (index, total, bytes) in parts {
+            assert_eq!(reassembler.insert(0, 1, index, total, bytes, now)?, None);
+        }
+        assert_eq!(reassembler.len(), 1);
+        let (index, total, bytes) = last;
+        assert_eq!(
+            reassembler.insert(0, 1, index, total, bytes, now)?,
+            Some(message)
+        );
+        assert!(reassembler.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reassembler_duplicates() -> Result<()> {
+        let message = vec![7u8; 1000];
+        let parts = chunks(&message);
+        let now = Instant::now();
+        let mut reassembler = Reassembler::new();
+        for (index, total, bytes) in &parts[..2] {
+            assert_eq!(
+                reassembler.insert(0, 1, *index, *total, bytes.clone(), now)?,
+                None
+            );
+            assert_eq!(
+                reassembler.insert(0, 1, *index, *total, bytes.clone(), now)?,
+                None
+            );
+        }
+        let (index, total, bytes) = parts[2].clone();
+        assert_eq!(
+            reassembler.insert(0, 1, index, total, bytes, now)?,
+            Some(message)
+        );
+
+        // The same transfer id on another channel is another transfer.
+        let (index, total, bytes) = parts[0].clone();
+        reassembler.insert(1, 1, index, total, bytes.clone(), now)?;
+        reassembler.insert(2, 1, index, total, bytes, now)?;
+        assert_eq!(reassembler.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reassembler_garbage_collection() -> Result<()> {
+        let now = Instant::now();
+        let mut reassembler = Reassembler::new();
+        for transfer_id in 0..MAXIMUM_TRANSFERS as u32 {
+            reassembler.insert(0, transfer_id, 0, 3, vec![1], now)?;
+        }
+        assert_eq!(
+            reassembler.insert(0, 99, 0, 3, vec![1], now),
+            Err(PoolProtocolError::TooManyTransfers)
+        );
+
+        // Transfer 0 keeps receiving chunks, the others are abandoned.
+        reassembler.insert(0, 0, 1, 3, vec![1], now + TRANSFER_TIMEOUT / 2)?;
+        reassembler.collect_garbage(now + TRANSFER_TIMEOUT);
+        assert_eq!(reassembler.len(), 1);
+        assert_eq!(
+            reassembler.insert(0, 0, 2, 3, vec![1], now + TRANSFER_TIMEOUT)?,
+            Some(vec![1, 1, 1])
+        );
+
+        // Opening a transfer collects the abandoned ones first.
+        for transfer_id in 0..MAXIMUM_TRANSFERS as u32 {
+            reassembler.insert(0, transfer_id, 0, 3, vec![1], now)?;
+        }
+        reassembler.insert(0, 99, 0, 3, vec![1], now + TRANSFER_TIMEOUT)?;
+        assert_eq!(reassembler.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reassembler_limits() {
+        let now = Instant::now();
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler.insert(0, 1, 2, 2, vec![], now).is_err());
+        assert!(reassembler
+            .insert(0, 1, 0, 2, vec![0; CHUNK_PAYLOAD_SIZE + 1], now)
+            .is_err());
+        assert!(split_chunks(&vec![0; MAXIMUM_REASSEMBLED_SIZE + 1], CHUNK_PAYLOAD_SIZE).is_err());
+
+        let total = (MAXIMUM_REASSEMBLED_SIZE / CHUNK_PAYLOAD_SIZE + 2) as u16;
+        let mut result = Ok(None);
+        for index in 0..total - 1 {
+            result = reassembler.insert(0, 1, index, total, vec![0; CHUNK_PAYLOAD_SIZE], now);
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!(matches!(
+            result,
+            Err(PoolProtocolError::TransferTooLarge(_))
+        ));
+        assert!(reassembler.is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::poolmessage::*;
+use snarkvm::prelude::*;
+
+use std::primitive::str;
+
+/// The start of the reason of a `ConnectAck` rejecting a client below the pool's minimum version.
+pub const CLIENT_TOO_OLD: &str = "client too old";
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Returns the client version := (major, minor, patch) of a `Connect`.
+    pub fn version_tuple(&self) -> Option<(u8, u8, u8)> {
+        match self {
+            Self::Connect(_, _, major, minor, patch, ..) => Some((*major, *minor, *patch)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a `Connect` comes from a client at version `min` or later.
+    /// Versions compare major first, then minor, then patch; other messages are never supported.
+    pub fn is_supported(&self, min: (u8, u8, u8)) -> bool {
+        self.version_tuple().is_some_and(|version| version >= min)
+    }
+}
+
+impl<N: Network> PoolMessageSC<N> {
+    /// Returns the `ConnectAck` rejecting a client below `min`, whose reason names the version to
+    /// upgrade to, e.g. `client too old, upgrade to 1.2.0`.
+    pub fn client_too_old(address: Address<N>, min: (u8, u8, u8)) -> Self {
+        let (major, minor, patch) = min;
+        Self::rejected(
+            address,
+            &format!(
+                "{}, upgrade to {}.{}.{}",
+                CLIENT_TOO_OLD, major, minor, patch
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    type CurrentNetwork = Testnet3;
+
+    fn connect(major: u8, minor: u8, patch: u8) -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            ADDRESS_TYPE_EMAIL,
+            major,
+            minor,
+            patch,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_client_version() -> Result<()> {
+        let bytes = connect(1, 2, 3).to_vec()?;
+        let message = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(message.version_tuple(), Some((1, 2, 3)));
+        assert!(message.is_supported((1, 2, 3)));
+        assert!(message.is_supported((1, 1, 9)));
+        assert!(message.is_supported((0, 9, 9)));
+        assert!(!message.is_supported((1, 2, 4)));
+        assert!(!message.is_supported((1, 3, 0)));
+        assert!(!message.is_supported((2, 0, 0)));
+        assert!(!PoolMessageCS::<CurrentNetwork>::Ping.is_supported((0, 0, 0)));
+
+        let address = Address::<CurrentNetwork>::new(Group::generator());
+        let message = PoolMessageSC::client_too_old(address, (1, 3, 0));
+        let bytes = message.to_vec()?;
+        let message = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(
+            message.reject_reason().as_deref(),
+            Some("client too old, upgrade to 1.3.0")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,2465 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(feature = "metrics")]
+use super::metrics::MESSAGE_METRICS;
+use super::{
+    auth::*,
+    capabilities::*,
+    checksum::*,
+    chunk::*,
+    compression::*,
+    error::*,
+    format::*,
+    limits::*,
+    message_id::{cs, sc, SC_IDS},
+    poolmessage::*,
+    v2::*,
+    version::*,
+};
+use snarkvm::prelude::Network;
+
+use ::bytes::{Buf, Bytes, BytesMut};
+use anyhow::{anyhow, Result};
+use std::{io::Write, marker::PhantomData, primitive::str, time::Instant};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// The direction a message travels in.
+/// Pool-to-client ids are in `SC_IDS`, client-to-pool ids are in `CS_IDS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    PoolToClient,
+    ClientToPool,
+}
+
+impl Direction {
+    /// Returns the direction of a message id.
+    pub fn of(id: u8) -> Self {
+        match SC_IDS.contains(&id) {
+            true => Self::PoolToClient,
+            false => Self::ClientToPool,
+        }
+    }
+}
+
+/// A message that can be carried in a pool frame.
+pub trait WireMessage: Sized {
+    /// The direction every message of this type travels in.
+    const DIRECTION: Direction;
+    /// The id of the `Chunk` message of this direction.
+    const CHUNK_ID: u8;
+    /// Returns the message name.
+    fn name(&self) -> &str;
+    /// Returns the message ID.
+    fn id(&self) -> u8;
+    /// Returns the binary size of the message, id included, to size buffers before serializing.
+    fn serialized_size(&self) -> usize;
+    /// Serializes the message, id included.
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<()>;
+    /// Deserializes a frame body into a message.
+    fn deserialize(buffer: &[u8]) -> Result<Self>;
+    /// Serializes the message in the binary layout of `version`, id included.
+    fn serialize_versioned_into<W: Write>(
+        &self,
+        version: ProtocolVersion,
+        writer: &mut W,
+    ) -> Result<()>;
+    /// Deserializes a frame body laid out in `version` into a message.
+    fn deserialize_versioned(version: ProtocolVersion, buffer: &[u8]) -> Result<Self>;
+    /// Deserializes a frame body laid out in `version` like `deserialize_versioned`,
+    /// but may keep slices of `buffer` instead of copying them.
+    fn deserialize_versioned_bytes(version: ProtocolVersion, buffer: Bytes) -> Result<Self> {
+        Self::deserialize_versioned(version, &buffer)
+    }
+    /// Serializes the message as JSON.
+    fn to_json(&self) -> Result<Vec<u8>>;
+    /// Deserializes a message from JSON.
+    fn from_json(buffer: &[u8]) -> Result<Self>;
+    /// Returns the `Chunk` message of this direction.
+    fn chunk(transfer_id: u32, index: u16, total: u16, bytes: Vec<u8>) -> Self;
+    /// Returns the fields of a `Chunk` message, or the message itself.
+    fn into_chunk(self) -> Result<(u32, u16, u16, Vec<u8>), Self>;
+    /// Returns the `Unused` message standing in for an unknown id.
+    fn unused(id: u8) -> Self;
+    /// Returns the capabilities the peers must have agreed on to exchange this message.
+    fn required_capabilities(&self) -> u32 {
+        0
+    }
+}
+
+impl<N: Network> WireMessage for PoolMessageSC<N> {
+    const DIRECTION: Direction = Direction::PoolToClient;
+    const CHUNK_ID: u8 = sc::CHUNK;
+
+    fn name(&self) -> &str {
+        PoolMessageSC::name(self)
+    }
+
+    fn id(&self) -> u8 {
+        PoolMessageSC::id(self)
+    }
+
+    fn serialized_size(&self) -> usize {
+        PoolMessageSC::serialized_size(self)
+    }
+
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<()> {
+        PoolMessageSC::serialize_into(self, writer)
+    }
+
+    fn deserialize(buffer: &[u8]) -> Result<Self> {
+        PoolMessageSC::deserialize(buffer)
+    }
+
+    fn serialize_versioned_into<W: Write>(
+        &self,
+        version: ProtocolVersion,
+        writer: &mut W,
+    ) -> Result<()> {
+        match version {
+            ProtocolVersion::V1 => PoolMessageSC::serialize_into(self, writer),
+            ProtocolVersion::V2 => PoolMessageSCv2::serialize_message_into(self, writer),
+        }
+    }
+
+    fn deserialize_versioned(version: ProtocolVersion, buffer: &[u8]) -> Result<Self> {
+        match version {
+            ProtocolVersion::V1 => PoolMessageSC::deserialize(buffer),
+            ProtocolVersion::V2 => PoolMessageSCv2::deserialize(buffer).map(Into::into),
+        }
+    }
+
+    fn to_json(&self) -> Result<Vec<u8>> {
+        PoolMessageSC::to_json(self)
+    }
+
+    fn from_json(buffer: &[u8]) -> Result<Self> {
+        PoolMessageSC::from_json(buffer)
+    }
+
+    fn chunk(transfer_id: u32, index: u16, total: u16, bytes: Vec<u8>) -> Self {
+        Self::Chunk(transfer_id, index, total, bytes)
+    }
+
+    fn into_chunk(self) -> Result<(u32, u16, u16, Vec<u8>), Self> {
+        match self {
+            Self::Chunk(transfer_id, index, total, bytes) => Ok((transfer_id, index, total, bytes)),
+            message => Err(message),
+        }
+    }
+
+    fn unused(id: u8) -> Self {
+        Self::Unused(id)
+    }
+}
+
+impl<N: Network> WireMessage for PoolMessageCS<N> {
+    const DIRECTION: Direction = Direction::ClientToPool;
+    const CHUNK_ID: u8 = cs::CHUNK;
+
+    fn name(&self) -> &str {
+        PoolMessageCS::name(self)
+    }
+
+    fn id(&self) -> u8 {
+        PoolMessageCS::id(self)
+    }
+
+    fn serialized_size(&self) -> usize {
+        PoolMessageCS::serialized_size(self)
+    }
+
+    fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<()> {
+        PoolMessageCS::serialize_into(self, writer)
+    }
+
+    fn deserialize(buffer: &[u8]) -> Result<Self> {
+        PoolMessageCS::deserialize(buffer)
+    }
+
+    fn serialize_versioned_into<W: Write>(
+        &self,
+        version: ProtocolVersion,
+        writer: &mut W,
+    ) -> Result<()> {
+        match version {
+            ProtocolVersion::V1 => PoolMessageCS::serialize_into(self, writer),
+            ProtocolVersion::V2 => PoolMessageCSv2::serialize_message_into(self, writer),
+        }
+    }
+
+    fn deserialize_versioned(version: ProtocolVersion, buffer: &[u8]) -> Result<Self> {
+        match version {
+            ProtocolVersion::V1 => PoolMessageCS::deserialize(buffer),
+            ProtocolVersion::V2 => PoolMessageCSv2::deserialize(buffer).map(Into::into),
+        }
+    }
+
+    fn deserialize_versioned_bytes(version: ProtocolVersion, buffer: Bytes) -> Result<Self> {
+        match version {
+            ProtocolVersion::V1 => PoolMessageCS::deserialize_bytes(buffer),
+            ProtocolVersion::V2 => PoolMessageCSv2::deserialize_bytes(buffer).map(Into::into),
+        }
+    }
+
+    fn to_json(&self) -> Result<Vec<u8>> {
+        PoolMessageCS::to_json(self)
+    }
+
+    fn from_json(buffer: &[u8]) -> Result<Self> {
+        PoolMessageCS::from_json(buffer)
+    }
+
+    fn chunk(transfer_id: u32, index: u16, total: u16, bytes: Vec<u8>) -> Self {
+        Self::Chunk(transfer_id, index, total, bytes)
+    }
+
+    fn into_chunk(self) -> Result<(u32, u16, u16, Vec<u8>), Self> {
+        match self {
+            Self::Chunk(transfer_id, index, total, bytes) => Ok((transfer_id, index, total, bytes)),
+            message => Err(message),
+        }
+    }
+
+    fn unused(id: u8) -> Self {
+        Self::Unused(id)
+    }
+
+    fn required_capabilities(&self) -> u32 {
+        match self {
+            Self::Submit(.., Some(_)) => CAP_SIGNED_SHARES,
+            _ => 0,
+        }
+    }
+}
+
+/// What a `MessageCodec` does with a binary message id it does not know,
+/// e.g. one added by a newer peer. JSON messages of unknown types are always errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownIdPolicy {
+    /// Fail with `UnknownMessageId`, which ends the connection.
+    #[default]
+    Error,
+    /// Drop the frame and count it, see `MessageCodec::unknown_ids_skipped`.
+    Skip,
+    /// Yield `Unused(id)` and let the caller decide.
+    ReturnUnused,
+}
+
+/// What a `MessageCodec` does with a frame whose body fails to deserialize.
+/// Frames that are too large, fail their checksum, tag or sequence check are always errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameErrorPolicy {
+    /// Fail, which ends the connection.
+    #[default]
+    Fatal,
+    /// Drop the frame and count it, see `MessageCodec::invalid_frames_skipped`, so one
+    /// malformed message does not drop a connection multiplexing many workers.
+    SkipFrame,
+}
+
+/// What a `MessageCodec` does with a frame whose sequence number is not the expected one,
+/// under `CAP_SEQUENCE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SequencePolicy {
+    /// Fail with `SequenceGap` or `SequenceRewind`, which ends the connection.
+    #[default]
+    Error,
+    /// Deliver the message and count the anomaly, see `MessageCodec::sequence_anomalies`,
+    /// e.g. to diagnose a proxy that reorders or drops frames. Replayed frames are delivered
+    /// too, so keep `Error` where `CAP_HMAC` guards against replay.
+    Report,
+}
+
+/// The byte order of the u32 length prefix of a `MessageCodec`.
+/// Both peers must use the same order; it is configured, not negotiated, and does not apply
+/// to the varint prefix under `CAP_VARINT_LENGTH`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LengthEndianness {
+    /// The order pools speak.
+    #[default]
+    Little,
+    /// Network order, the default of `tokio_util::codec::LengthDelimitedCodec` and most tooling,
+    /// e.g. for sidecar analyzers and non-Rust clients.
+    Big,
+}
+
+impl LengthEndianness {
+    /// Returns `length` as a prefix in this order.
+    pub fn to_bytes(self, length: u32) -> [u8; 4] {
+        match self {
+            Self::Little => length.to_le_bytes(),
+            Self::Big => length.to_be_bytes(),
+        }
+    }
+
+    /// Reads a prefix in this order.
+    pub fn from_bytes(self, prefix: [u8; 4]) -> u32 {
+        match self {
+            Self::Little => u32::from_le_bytes(prefix),
+            Self::Big => u32::from_be_bytes(prefix),
+        }
+    }
+}
+
+/// Tracks the sequence numbers received in one direction.
+/// Sequence numbers start at 0 and wrap around after `u32::MAX`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SequenceTracker {
+    expected: u32,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the sequence number the next frame should carry.
+    pub fn expected(&self) -> u32 {
+        self.expected
+    }
+
+    /// Checks a received sequence number.
+    /// A number ahead of the expected one reports a gap and resynchronizes past it,
+    /// a number behind it reports a rewind (duplicate or replay) and leaves the tracker untouched.
+    pub fn check(&mut self, received: u32) -> Result<(), PoolProtocolError> {
+        let expected = self.expected;
+        let distance = received.wrapping_sub(expected);
+        if distance == 0 {
+            self.expected = expected.wrapping_add(1);
+            Ok(())
+        } else if distance < 1 << 31 {
+            self.expected = received.wrapping_add(1);
+            Err(PoolProtocolError::SequenceGap { expected, received })
+        } else {
+            Err(PoolProtocolError::SequenceRewind { expected, received })
+        }
+    }
+}
+
+/// The longest varint length prefix, enough for any u32.
+pub const MAXIMUM_VARINT_SIZE: usize = 5;
+
+/// Appends `value` as an unsigned LEB128 varint: 7 bits per byte, low bits first,
+/// the high bit set on every byte but the last.
+pub fn write_varint(mut value: u32, dst: &mut BytesMut) {
+    while value >= 0x80 {
+        dst.extend_from_slice(&[value as u8 | 0x80]);
+        value >>= 7;
+    }
+    dst.extend_from_slice(&[value as u8]);
+}
+
+/// Reads a minimal LEB128 varint from the start of `source`, returning (value, size),
+/// or `None` until its last byte has arrived.
+pub fn read_varint(source: &[u8]) -> Result<Option<(u32, usize)>, PoolProtocolError> {
+    let mut value = 0u64;
+    for (index, byte) in source.iter().take(MAXIMUM_VARINT_SIZE).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (7 * index);
+        if byte & 0x80 == 0 {
+            // A trailing zero byte would make a longer encoding of the same value.
+            if index > 0 && *byte == 0 || value > u32::MAX as u64 {
+                return Err(PoolProtocolError::InvalidLengthPrefix);
+            }
+            return Ok(Some((value as u32, index + 1)));
+        }
+    }
+    match source.len() < MAXIMUM_VARINT_SIZE {
+        true => Ok(None),
+        false => Err(PoolProtocolError::InvalidLengthPrefix),
+    }
+}
+
+/// A pool codec := << length(u32), [version(u8)], [flags(u8)], [channel(u16)], [sequence(u32)],
+/// id, data, [auth_tag], [checksum] >>, the length a varint instead under `CAP_VARINT_LENGTH`.
+/// Optional frame fields are enabled by the capabilities negotiated during the handshake.
+#[derive(Clone, Debug)]
+pub struct MessageCodec<M> {
+    capabilities: u32,
+    session_key: Option<SessionKey>,
+    send_sequence: u32,
+    recv_sequence: SequenceTracker,
+    next_transfer_id: u32,
+    reassembler: Reassembler,
+    unknown_id_policy: UnknownIdPolicy,
+    unknown_ids_skipped: u64,
+    frame_error_policy: FrameErrorPolicy,
+    invalid_frames_skipped: u64,
+    sequence_policy: SequencePolicy,
+    sequence_anomalies: u64,
+    last_sequence: Option<u32>,
+    protocol_version: ProtocolVersion,
+    max_frame_size: usize,
+    frame_limits: Option<FrameLimits>,
+    compression_threshold: usize,
+    length_endianness: LengthEndianness,
+    _message: PhantomData<fn() -> M>,
+}
+
+/// Codec for pool-to-client messages.
+pub type PoolCodecSC<N> = MessageCodec<PoolMessageSC<N>>;
+/// Codec for client-to-pool messages.
+pub type PoolCodecCS<N> = MessageCodec<PoolMessageCS<N>>;
+
+impl<M> Default for MessageCodec<M> {
+    fn default() -> Self {
+        Self {
+            capabilities: 0,
+            session_key: None,
+            send_sequence: 0,
+            recv_sequence: SequenceTracker::new(),
+            next_transfer_id: 0,
+            reassembler: Reassembler::new(),
+            unknown_id_policy: UnknownIdPolicy::Error,
+            unknown_ids_skipped: 0,
+            frame_error_policy: FrameErrorPolicy::Fatal,
+            invalid_frames_skipped: 0,
+            sequence_policy: SequencePolicy::Error,
+            sequence_anomalies: 0,
+            last_sequence: None,
+            protocol_version: ProtocolVersion::V1,
+            max_frame_size: MAXIMUM_MESSAGE_SIZE,
+            frame_limits: None,
+            compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            length_endianness: LengthEndianness::Little,
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<M> MessageCodec<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the largest frame, length prefix excluded, the decoder accepts and the encoder
+    /// writes before splitting into chunks. Both peers should use the same limit.
+    /// Work messages may still grow to `WORK_MESSAGE_LIMIT`, see `default_frame_limit`.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Enforces per-message-id limits on binary bodies, e.g. `FrameLimits::default()`.
+    /// Ids with a limit may exceed `max_frame_size`, ids without one are bound by it.
+    pub fn with_frame_limits(mut self, frame_limits: FrameLimits) -> Self {
+        self.frame_limits = Some(frame_limits);
+        self
+    }
+
+    pub fn frame_limits(&self) -> Option<&FrameLimits> {
+        self.frame_limits.as_ref()
+    }
+
+    /// Sets the body length from which frames are compressed once `CAP_ZSTD` or `CAP_LZ4`
+    /// is agreed.
+    /// Bodies that would not shrink are still sent as is.
+    pub fn with_compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.compression_threshold = compression_threshold;
+        self
+    }
+
+    pub fn compression_threshold(&self) -> usize {
+        self.compression_threshold
+    }
+
+    /// Sets the byte order of the u32 length prefix, for both encoding and decoding.
+    /// Both ends must be configured alike; pools speak `LengthEndianness::Little`.
+    pub fn with_length_endianness(mut self, length_endianness: LengthEndianness) -> Self {
+        self.length_endianness = length_endianness;
+        self
+    }
+
+    pub fn length_endianness(&self) -> LengthEndianness {
+        self.length_endianness
+    }
+
+    /// Returns the capabilities applied to the framing.
+    pub fn capabilities(&self) -> u32 {
+        self.capabilities
+    }
+
+    /// Applies the capabilities agreed in the handshake to every following frame.
+    pub fn set_capabilities(&mut self, capabilities: u32) {
+        self.capabilities = capabilities;
+    }
+
+    /// Sets the key used for authenticated frames.
+    pub fn set_session_key(&mut self, session_key: SessionKey) {
+        self.session_key = Some(session_key);
+    }
+
+    pub fn unknown_id_policy(&self) -> UnknownIdPolicy {
+        self.unknown_id_policy
+    }
+
+    /// Sets what the decoder does with message ids this build does not know.
+    pub fn set_unknown_id_policy(&mut self, policy: UnknownIdPolicy) {
+        self.unknown_id_policy = policy;
+    }
+
+    /// Returns the number of frames dropped under `UnknownIdPolicy::Skip`.
+    pub fn unknown_ids_skipped(&self) -> u64 {
+        self.unknown_ids_skipped
+    }
+
+    pub fn frame_error_policy(&self) -> FrameErrorPolicy {
+        self.frame_error_policy
+    }
+
+    /// Sets what the decoder does with frames whose body is malformed.
+    pub fn set_frame_error_policy(&mut self, policy: FrameErrorPolicy) {
+        self.frame_error_policy = policy;
+    }
+
+    /// Returns the number of malformed frames dropped under `FrameErrorPolicy::SkipFrame`.
+    pub fn invalid_frames_skipped(&self) -> u64 {
+        self.invalid_frames_skipped
+    }
+
+    pub fn sequence_policy(&self) -> SequencePolicy {
+        self.sequence_policy
+    }
+
+    /// Sets what the decoder does with frames received out of sequence.
+    pub fn set_sequence_policy(&mut self, policy: SequencePolicy) {
+        self.sequence_policy = policy;
+    }
+
+    /// Returns the number of gaps and rewinds delivered under `SequencePolicy::Report`.
+    pub fn sequence_anomalies(&self) -> u64 {
+        self.sequence_anomalies
+    }
+
+    /// Returns the sequence number of the last frame received, `None` before the first one
+    /// or without `CAP_SEQUENCE`. With chunks, it is the number of the last chunk.
+    pub fn last_sequence(&self) -> Option<u32> {
+        self.last_sequence
+    }
+
+    /// Returns the sequence number the next received frame should carry.
+    pub fn expected_sequence(&self) -> u32 {
+        self.recv_sequence.expected()
+    }
+
+    /// Returns the binary layout of message bodies, V1 unless switched by a `ProtocolCodec`.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    pub(crate) fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.protocol_version = version;
+    }
+
+    /// Returns the format of message bodies, JSON once negotiated.
+    pub fn wire_format(&self) -> WireFormat {
+        WireFormat::from_capabilities(self.capabilities)
+    }
+
+    /// Returns the algorithm compressing bodies, picked from the agreed capabilities.
+    pub fn compression(&self) -> CompressionAlgorithm {
+        CompressionAlgorithm::from_capabilities(self.capabilities)
+    }
+
+    /// Writes a keepalive := a frame of length 0, which decoders consume without yielding a message.
+    /// It is cheaper than a `Ping` for keeping middleboxes from closing an idle connection,
+    /// carries no sequence number and is not answered.
+    pub fn encode_keepalive(&self, dst: &mut BytesMut) {
+        match self.has(CAP_VARINT_LENGTH) {
+            true => write_varint(0, dst),
+            false => dst.extend_from_slice(&self.length_endianness.to_bytes(0)),
+        }
+    }
+
+    fn has(&self, capability: u32) -> bool {
+        self.capabilities & capability != 0
+    }
+
+    /// Returns the bytes the optional frame fields add before a message.
+    fn header_len(&self) -> usize {
+        let mut header_len = 0;
+        if self.has(CAP_VERSION_BYTE) {
+            header_len += 1;
+        }
+        if self.has(COMPRESSION_CAPABILITIES) {
+            header_len += 1;
+        }
+        if self.has(CAP_CHANNELS) {
+            header_len += 2;
+        }
+        if self.has(CAP_SEQUENCE) {
+            header_len += 4;
+        }
+        header_len
+    }
+
+    /// Returns the bytes the optional frame fields add to a message.
+    fn frame_overhead(&self) -> usize {
+        let mut overhead = self.header_len();
+        if self.has(CAP_HMAC) {
+            overhead += AUTH_TAG_SIZE;
+        }
+        if self.has(CAP_CHECKSUM) {
+            overhead += CHECKSUM_SIZE;
+        }
+        overhead
+    }
+
+    /// Returns the most message bytes one chunk carries, so that binary chunk frames fit
+    /// `max_frame_size`. JSON chunks keep their fixed size and are checked once serialized.
+    fn chunk_payload_size(&self) -> usize {
+        let format = self.wire_format();
+        match format {
+            WireFormat::Binary => {
+                let available = self
+                    .max_frame_size
+                    .saturating_sub(self.frame_overhead() + CHUNK_HEADER_SIZE);
+                format.chunk_payload_size().min(available).max(1)
+            }
+            WireFormat::Json => format.chunk_payload_size(),
+        }
+    }
+
+    /// Returns the largest frame, length prefix excluded, the decoder accepts.
+    fn max_frame_len(&self) -> usize {
+        match &self.frame_limits {
+            Some(frame_limits) => frame_limits.max(self.max_frame_size),
+            None if self.wire_format() == WireFormat::Binary => {
+                self.max_frame_size.max(WORK_MESSAGE_LIMIT)
+            }
+            None => self.max_frame_size,
+        }
+    }
+
+    /// Returns `body` compressed if it reaches the compression threshold and compressing it
+    /// with the agreed algorithm pays off.
+    fn compress_body(&self, body: &[u8]) -> Option<Vec<u8>> {
+        match body.len() >= self.compression_threshold {
+            true => self.compression().compress(body),
+            false => None,
+        }
+    }
+
+    /// Decompresses a body, bounded by the largest frame the decoder accepts.
+    fn decompress_body(&self, body: &[u8]) -> Result<Vec<u8>, PoolProtocolError> {
+        self.compression().decompress(body, self.max_frame_len())
+    }
+}
+
+impl<M: WireMessage> MessageCodec<M> {
+    /// Fails if `message` needs capabilities the peers did not agree on.
+    fn check_capabilities(&self, message: &M) -> Result<(), PoolProtocolError> {
+        match message.required_capabilities() & !self.capabilities {
+            0 => Ok(()),
+            capabilities => Err(PoolProtocolError::CapabilityNotAgreed {
+                name: message.name().to_string(),
+                capabilities,
+            }),
+        }
+    }
+
+    /// Appends the frames carrying a message to `dst`: one frame, or `Chunk` frames when the
+    /// message does not fit and chunked transfer was negotiated.
+    /// Nothing is written if the message cannot be serialized or is larger than a decoder with
+    /// the same limits accepts, see `check_encoded_size`.
+    pub(crate) fn encode_frame(
+        &mut self,
+        channel: u16,
+        message: &M,
+        dst: &mut BytesMut,
+    ) -> Result<()> {
+        self.check_capabilities(message)?;
+        let format = self.wire_format();
+        // A hint only: JSON and protocol v2 bodies differ in size.
+        let mut body = Vec::with_capacity(message.serialized_size());
+        format.serialize_versioned(self.protocol_version, message, &mut body)?;
+        if !self.has(CAP_CHUNKS) || self.frame_overhead() + body.len() <= self.max_frame_size {
+            self.check_encoded_size(message, body.len())?;
+            let start = dst.len();
+            if let Err(error) = self.write_frame(channel, &body, dst) {
+                dst.truncate(start);
+                return Err(error);
+            }
+            #[cfg(feature = "metrics")]
+            MESSAGE_METRICS.record_encoded(message.id());
+            return Ok(());
+        }
+        let transfer_id = self.next_transfer_id;
+        self.next_transfer_id = transfer_id.wrapping_add(1);
+        let parts = split_chunks(&body, self.chunk_payload_size())?;
+        let total = u16::try_from(parts.len())
+            .map_err(|_| PoolProtocolError::TransferTooLarge(body.len()))?;
+        // Serialize and check every chunk before writing any, so a failure leaves no partial
+        // transfer.
+        let chunks = parts
+            .into_iter()
+            .enumerate()
+            .map(|(index, bytes)| {
+                let chunk_message = M::chunk(transfer_id, index as u16, total, bytes.to_vec());
+                let mut chunk = Vec::new();
+                format.serialize_versioned(self.protocol_version, &chunk_message, &mut chunk)?;
+                self.check_encoded_size(&chunk_message, chunk.len())?;
+                Ok(chunk)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let start = dst.len();
+        for chunk in chunks {
+            if let Err(error) = self.write_frame(channel, &chunk, dst) {
+                dst.truncate(start);
+                return Err(error);
+            }
+        }
+        #[cfg(feature = "metrics")]
+        MESSAGE_METRICS.record_encoded(message.id());
+        Ok(())
+    }
+
+    /// Checks an uncompressed body of `body_len` bytes against the limits the decoder applies to
+    /// its frame, so an oversized message fails on the sending side instead of dropping the
+    /// connection at the peer. Both peers should be configured alike.
+    fn check_encoded_size(&self, message: &M, body_len: usize) -> Result<(), PoolProtocolError> {
+        let length = self.frame_overhead() + body_len;
+        let (length, limit) = match (self.wire_format(), &self.frame_limits) {
+            (WireFormat::Binary, Some(frame_limits)) => (
+                body_len,
+                frame_limits.limit(message.id(), self.max_frame_size),
+            ),
+            (WireFormat::Binary, None) => (
+                length,
+                default_frame_limit(message.id(), self.max_frame_size),
+            ),
+            _ => (length, self.max_frame_len()),
+        };
+        match length > limit {
+            true => Err(PoolProtocolError::EncodeTooLarge {
+                name: message.name().to_string(),
+                length,
+                limit,
+            }),
+            false => Ok(()),
+        }
+    }
+
+    /// Appends a frame := << length, [version], [flags], [channel], [sequence], body, [auth_tag], [checksum] >>
+    /// to `dst`, the length a u32 or, under `CAP_VARINT_LENGTH`, a varint.
+    /// The body is compressed if `FLAG_COMPRESSED` is set in the flags.
+    /// The tag and checksum cover everything after the length prefix.
+    fn write_frame(&mut self, channel: u16, body: &[u8], dst: &mut BytesMut) -> Result<()> {
+        let session_key = match self.has(CAP_HMAC) {
+            true => Some(
+                self.session_key
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Missing session key"))?,
+            ),
+            false => None,
+        };
+        let compressed = self.compress_body(body);
+        let (flags, body) = match &compressed {
+            Some(compressed) => (FLAG_COMPRESSED, compressed.as_slice()),
+            None => (0, body),
+        };
+        let length = u32::try_from(self.frame_overhead() + body.len())
+            .map_err(|_| anyhow!("Frame of {} bytes is too long", body.len()))?;
+        // Grow `dst` once; the length prefix takes at most `MAXIMUM_VARINT_SIZE` bytes.
+        dst.reserve(MAXIMUM_VARINT_SIZE + length as usize);
+        match self.has(CAP_VARINT_LENGTH) {
+            true => write_varint(length, dst),
+            false => dst.extend_from_slice(&self.length_endianness.to_bytes(length)),
+        }
+        let start = dst.len();
+        if self.has(CAP_VERSION_BYTE) {
+            dst.extend_from_slice(&[self.protocol_version.to_u8()]);
+        }
+        if self.has(COMPRESSION_CAPABILITIES) {
+            dst.extend_from_slice(&[flags]);
+        }
+        if self.has(CAP_CHANNELS) {
+            dst.extend_from_slice(&channel.to_le_bytes());
+        }
+        if self.has(CAP_SEQUENCE) {
+            dst.extend_from_slice(&self.send_sequence.to_le_bytes());
+        }
+        dst.extend_from_slice(body);
+        if let Some(session_key) = session_key {
+            let tag = session_key.sign(&dst[start..]);
+            dst.extend_from_slice(&tag);
+        }
+        if self.has(CAP_CHECKSUM) {
+            let checksum = crc32c(&dst[start..]);
+            dst.extend_from_slice(&checksum.to_le_bytes());
+        }
+        if self.has(CAP_SEQUENCE) {
+            self.send_sequence = self.send_sequence.wrapping_add(1);
+        }
+        debug_assert_eq!(dst.len() - start, length as usize);
+        Ok(())
+    }
+
+    /// Decodes the next message from `source` into (channel, message).
+    /// With chunked transfer negotiated, chunks are collected until their message is complete.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    pub(crate) fn decode_next(
+        &mut self,
+        source: &mut BytesMut,
+    ) -> Result<Option<(u16, M)>, std::io::Error> {
+        let message = self.reassemble_next(source);
+        match &message {
+            #[cfg(feature = "metrics")]
+            Ok(Some((_, message))) => MESSAGE_METRICS.record_decoded(message.id()),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            Err(error) => report_decode_error(error),
+            _ => (),
+        }
+        message
+    }
+
+    /// Decodes the last messages of a closed connection. Bytes of an incomplete frame left at
+    /// the end fail with `TruncatedFrame` instead of being dropped.
+    pub(crate) fn decode_next_eof(
+        &mut self,
+        source: &mut BytesMut,
+    ) -> Result<Option<(u16, M)>, std::io::Error> {
+        match self.decode_next(source)? {
+            None if !source.is_empty() => Err(self.truncated_frame(source)),
+            message => Ok(message),
+        }
+    }
+
+    /// Returns the error for the bytes of an incomplete frame left at the end of a connection.
+    fn truncated_frame(&self, source: &[u8]) -> std::io::Error {
+        let error = PoolProtocolError::TruncatedFrame {
+            pending: source.len(),
+            length: self
+                .read_length(source)
+                .ok()
+                .flatten()
+                .map(|(_, length)| length),
+        }
+        .into();
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        report_decode_error(&error);
+        error
+    }
+
+    /// Removes the next frame from `source` like `decode_next`, but returns its body
+    /// := << id, data >> without deserializing it, so it can be routed by id first.
+    /// Chunks are reassembled under `CAP_CHUNKS`. Bodies only start with their id in
+    /// `WireFormat::Binary`.
+    pub(crate) fn decode_next_raw(
+        &mut self,
+        source: &mut BytesMut,
+    ) -> Result<Option<(u16, Bytes)>, std::io::Error> {
+        if self.wire_format() != WireFormat::Binary {
+            return Err(invalid_data(anyhow!(
+                "Raw frames require the binary wire format"
+            )));
+        }
+        loop {
+            let Some(frame) = self.take_frame(source)? else {
+                return Ok(None);
+            };
+            let (channel, body) = self.unwrap_frame(frame)?;
+            if body.first() != Some(&M::CHUNK_ID) || !self.has(CAP_CHUNKS) {
+                match self.check_raw_body(&body)? {
+                    true => return Ok(Some(record_raw(channel, body))),
+                    false => continue,
+                }
+            }
+            let Some(chunk) = self.deserialize_body(body)? else {
+                continue;
+            };
+            let Ok((transfer_id, index, total, bytes)) = chunk.into_chunk() else {
+                continue;
+            };
+            let reassembled = self.reassembler.insert(
+                channel,
+                transfer_id,
+                index,
+                total,
+                bytes,
+                Instant::now(),
+            )?;
+            if let Some(body) = reassembled {
+                // Chunks never nest.
+                if body.first() == Some(&M::CHUNK_ID) {
+                    return Err(PoolProtocolError::InvalidChunk {
+                        transfer_id,
+                        index,
+                        total,
+                    }
+                    .into());
+                }
+                if self.check_raw_body(&body)? {
+                    return Ok(Some(record_raw(channel, body.into())));
+                }
+            }
+        }
+    }
+
+    /// Decodes the last raw frames of a closed connection like `decode_next_eof`.
+    pub(crate) fn decode_next_raw_eof(
+        &mut self,
+        source: &mut BytesMut,
+    ) -> Result<Option<(u16, Bytes)>, std::io::Error> {
+        match self.decode_next_raw(source)? {
+            None if !source.is_empty() => Err(self.truncated_frame(source)),
+            frame => Ok(frame),
+        }
+    }
+
+    /// Returns whether a raw body may be yielded: it is not empty and its id belongs to the
+    /// direction of `M`. Other bodies are skipped under `FrameErrorPolicy::SkipFrame`.
+    fn check_raw_body(&mut self, body: &[u8]) -> Result<bool, std::io::Error> {
+        let error = match body.is_empty() {
+            true => anyhow!("Invalid message buffer"),
+            false => match check_direction::<M>(body) {
+                Ok(()) => return Ok(true),
+                Err(error) => error.into(),
+            },
+        };
+        let error = invalid_data(error);
+        if self.frame_error_policy == FrameErrorPolicy::Fatal {
+            return Err(error);
+        }
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        report_decode_error(&error);
+        self.invalid_frames_skipped += 1;
+        Ok(false)
+    }
+
+    /// Decodes frames until a message is complete, reassembling chunks if negotiated.
+    fn reassemble_next(
+        &mut self,
+        source: &mut BytesMut,
+    ) -> Result<Option<(u16, M)>, std::io::Error> {
+        loop {
+            let Some(frame) = self.take_frame(source)? else {
+                return Ok(None);
+            };
+            let (channel, message) = self.decode_frame(frame)?;
+            let Some(message) = message else {
+                continue;
+            };
+            if !self.has(CAP_CHUNKS) {
+                return Ok(Some((channel, message)));
+            }
+            let (transfer_id, index, total, bytes) = match message.into_chunk() {
+                Ok(chunk) => chunk,
+                Err(message) => return Ok(Some((channel, message))),
+            };
+            let reassembled = self.reassembler.insert(
+                channel,
+                transfer_id,
+                index,
+                total,
+                bytes,
+                Instant::now(),
+            )?;
+            if let Some(bytes) = reassembled {
+                let Some(message) = self.deserialize_body(bytes.into())? else {
+                    continue;
+                };
+                // Chunks never nest.
+                return match message.into_chunk() {
+                    Ok(_) => Err(PoolProtocolError::InvalidChunk {
+                        transfer_id,
+                        index,
+                        total,
+                    }
+                    .into()),
+                    Err(message) => Ok(Some((channel, message))),
+                };
+            }
+        }
+    }
+
+    /// Removes the next complete frame from `source`, length prefix excluded, skipping keepalives.
+    /// Returns `None` until the frame is complete.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(frame_len = tracing::field::Empty))
+    )]
+    fn take_frame(&mut self, source: &mut BytesMut) -> Result<Option<Bytes>, std::io::Error> {
+        let (prefix_len, length) = loop {
+            let Some((prefix_len, length)) = self.read_length(source)? else {
+                return Ok(None);
+            };
+            if length != 0 {
+                break (prefix_len, length);
+            }
+            source.advance(prefix_len);
+        };
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("frame_len", length);
+        // Check that the length is not too large to avoid a denial of
+        // service attack where the node server runs out of memory.
+        if length > self.max_frame_len() {
+            return Err(PoolProtocolError::FrameTooLarge(length).into());
+        }
+        self.check_frame_limit(&source[prefix_len..], length)?;
+
+        if source.len() < prefix_len + length {
+            // The full message has not yet arrived.
+            source.reserve(prefix_len + length - source.len());
+            return Ok(None);
+        }
+
+        // Take the frame out of the source without copying it, so messages may keep slices of it.
+        source.advance(prefix_len);
+        Ok(Some(source.split_to(length).freeze()))
+    }
+
+    /// Reads the length prefix := u32 in `length_endianness`, or a varint under `CAP_VARINT_LENGTH`,
+    /// returning (prefix_len, length), or `None` until the whole prefix has arrived.
+    fn read_length(&self, source: &[u8]) -> Result<Option<(usize, usize)>, PoolProtocolError> {
+        if self.has(CAP_VARINT_LENGTH) {
+            return read_varint(source)
+                .map(|prefix| prefix.map(|(length, prefix_len)| (prefix_len, length as usize)));
+        }
+        match source.get(..4) {
+            Some(prefix) => Ok(Some((
+                4,
+                self.length_endianness
+                    .from_bytes([prefix[0], prefix[1], prefix[2], prefix[3]])
+                    as usize,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Checks a binary frame, length prefix excluded, against the limit of its id once the id
+    /// has arrived. Without `FrameLimits` only work messages may exceed `max_frame_size`.
+    /// Compressed frames are checked once decompressed.
+    fn check_frame_limit(&self, frame: &[u8], length: usize) -> Result<(), PoolProtocolError> {
+        let header_len = self.header_len();
+        if self.wire_format() != WireFormat::Binary || length <= header_len {
+            return Ok(());
+        }
+        let flags_offset = usize::from(self.has(CAP_VERSION_BYTE));
+        if self.has(COMPRESSION_CAPABILITIES)
+            && frame
+                .get(flags_offset)
+                .is_some_and(|flags| flags & FLAG_COMPRESSED != 0)
+        {
+            return Ok(());
+        }
+        let Some(&id) = frame.get(header_len) else {
+            return Ok(());
+        };
+        self.check_message_limit(id, length)
+    }
+
+    /// Checks a binary message in a frame of `length` bytes, length prefix excluded,
+    /// against the limit of its id.
+    fn check_message_limit(&self, id: u8, length: usize) -> Result<(), PoolProtocolError> {
+        let Some(frame_limits) = &self.frame_limits else {
+            return match length > default_frame_limit(id, self.max_frame_size) {
+                true => Err(PoolProtocolError::FrameTooLarge(length)),
+                false => Ok(()),
+            };
+        };
+        let body_len = length.saturating_sub(self.frame_overhead());
+        match body_len > frame_limits.limit(id, self.max_frame_size) {
+            true => Err(PoolProtocolError::MessageTooLarge {
+                id,
+                length: body_len,
+            }),
+            false => Ok(()),
+        }
+    }
+
+    /// Strips the optional fields of a complete frame and deserializes the rest into
+    /// (channel, message). The message is `None` if the frame was skipped under
+    /// `UnknownIdPolicy::Skip` or `FrameErrorPolicy::SkipFrame`.
+    fn decode_frame(&mut self, bytes: Bytes) -> Result<(u16, Option<M>), std::io::Error> {
+        let (channel, body) = self.unwrap_frame(bytes)?;
+        Ok((channel, self.deserialize_body(body)?))
+    }
+
+    /// Strips the optional fields of a complete frame, returning (channel, body) with the body
+    /// decompressed. The checksum is verified first, so corruption is never reported as a
+    /// protocol violation.
+    fn unwrap_frame(&mut self, bytes: Bytes) -> Result<(u16, Bytes), std::io::Error> {
+        let mut frame = &bytes[..];
+        if self.has(CAP_CHECKSUM) {
+            if frame.len() < CHECKSUM_SIZE {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Frame is too short for its checksum",
+                ));
+            }
+            let (data, checksum) = frame.split_at(frame.len() - CHECKSUM_SIZE);
+            let expected = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+            let found = crc32c(data);
+            if found != expected {
+                return Err(PoolProtocolError::ChecksumMismatch { expected, found }.into());
+            }
+            frame = data;
+        }
+        if self.has(CAP_HMAC) {
+            let session_key = self.session_key.as_ref().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Missing session key")
+            })?;
+            if frame.len() < AUTH_TAG_SIZE {
+                return Err(PoolProtocolError::MissingAuthTag.into());
+            }
+            let (data, tag) = frame.split_at(frame.len() - AUTH_TAG_SIZE);
+            if !session_key.verify(data, tag) {
+                return Err(PoolProtocolError::AuthenticationFailed.into());
+            }
+            frame = data;
+        }
+        if self.has(CAP_VERSION_BYTE) {
+            let Some((&version, rest)) = frame.split_first() else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Frame is too short for its version",
+                ));
+            };
+            if ProtocolVersion::from_u8(version) != Some(self.protocol_version) {
+                return Err(PoolProtocolError::UnsupportedVersion(version).into());
+            }
+            frame = rest;
+        }
+        let mut flags = 0;
+        if self.has(COMPRESSION_CAPABILITIES) {
+            let Some((&first, rest)) = frame.split_first() else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Frame is too short for its flags",
+                ));
+            };
+            if first & !KNOWN_FRAME_FLAGS != 0 {
+                return Err(PoolProtocolError::InvalidFrameFlags(first).into());
+            }
+            flags = first;
+            frame = rest;
+        }
+        let mut channel = 0;
+        if self.has(CAP_CHANNELS) {
+            if frame.len() < 2 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Frame is too short for its channel id",
+                ));
+            }
+            channel = u16::from_le_bytes([frame[0], frame[1]]);
+            frame = &frame[2..];
+        }
+        if self.has(CAP_SEQUENCE) {
+            if frame.len() < 4 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Frame is too short for its sequence number",
+                ));
+            }
+            let sequence = u32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]);
+            frame = &frame[4..];
+            self.last_sequence = Some(sequence);
+            if let Err(error) = self.recv_sequence.check(sequence) {
+                if self.sequence_policy == SequencePolicy::Error {
+                    return Err(error.into());
+                }
+                self.sequence_anomalies += 1;
+                #[cfg(feature = "tracing")]
+                tracing::warn!(category = error.category(), %error, "Pool frame out of sequence");
+            }
+        }
+        if flags & FLAG_COMPRESSED != 0 {
+            let body = self.decompress_body(frame)?;
+            if let (WireFormat::Binary, Some(&id)) = (self.wire_format(), body.first()) {
+                self.check_message_limit(id, self.frame_overhead() + body.len())?;
+            }
+            return Ok((channel, body.into()));
+        }
+        Ok((channel, bytes.slice_ref(frame)))
+    }
+
+    /// Deserializes a message body, applying the `UnknownIdPolicy` to unknown ids and the
+    /// `FrameErrorPolicy` to other failures.
+    fn deserialize_body(&mut self, body: Bytes) -> Result<Option<M>, std::io::Error> {
+        let error = match self
+            .wire_format()
+            .deserialize_versioned_bytes(self.protocol_version, body)
+        {
+            Ok(message) => match self.check_capabilities(&message) {
+                Ok(()) => return Ok(Some(message)),
+                Err(error) => error.into(),
+            },
+            Err(error) => error,
+        };
+        match (self.unknown_id_policy, error.downcast_ref()) {
+            (UnknownIdPolicy::Skip, Some(PoolProtocolError::UnknownMessageId(_))) => {
+                self.unknown_ids_skipped += 1;
+                Ok(None)
+            }
+            (UnknownIdPolicy::ReturnUnused, Some(PoolProtocolError::UnknownMessageId(id))) => {
+                Ok(Some(M::unused(*id)))
+            }
+            _ => {
+                let error = invalid_data(error);
+                if self.frame_error_policy == FrameErrorPolicy::Fatal {
+                    return Err(error);
+                }
+                #[cfg(any(feature = "tracing", feature = "metrics"))]
+                report_decode_error(&error);
+                self.invalid_frames_skipped += 1;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<M: WireMessage> Encoder<M> for MessageCodec<M> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: M, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_frame(0, &message, dst)
+    }
+}
+
+/// Encodes a shared message, e.g. one `Notify` broadcast to every connection without cloning it.
+impl<M: WireMessage> Encoder<&M> for MessageCodec<M> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: &M, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_frame(0, message, dst)
+    }
+}
+
+impl<M: WireMessage> Decoder for MessageCodec<M> {
+    type Error = std::io::Error;
+    type Item = M;
+
+    /// Decodes the next frame. With multiplexing negotiated only channel 0 is accepted,
+    /// use `ChannelCodec` to receive the other channels.
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let message = self.decode_next(source)?;
+        channel_zero(message)
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let message = self.decode_next_eof(source)?;
+        channel_zero(message)
+    }
+}
+
+/// Counts a raw frame as decoded under the `metrics` feature.
+fn record_raw(channel: u16, body: Bytes) -> (u16, Bytes) {
+    #[cfg(feature = "metrics")]
+    MESSAGE_METRICS.record_decoded(body[0]);
+    (channel, body)
+}
+
+/// Returns the message of a frame on channel 0, the only one `MessageCodec` accepts.
+fn channel_zero<M>(message: Option<(u16, M)>) -> Result<Option<M>, std::io::Error> {
+    match message {
+        Some((0, message)) => Ok(Some(message)),
+        Some((channel, _)) => Err(PoolProtocolError::UnexpectedChannel(channel).into()),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{PrivateKey, Testnet3};
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_sequence_tracker_wraparound() {
+        let mut tracker = SequenceTracker {
+            expected: u32::MAX - 1,
+        };
+        assert_eq!(tracker.check(u32::MAX - 1), Ok(()));
+        assert_eq!(tracker.check(u32::MAX), Ok(()));
+        assert_eq!(tracker.check(0), Ok(()));
+        assert_eq!(tracker.check(1), Ok(()));
+        assert_eq!(tracker.expected(), 2);
+
+        // A gap across the wrap is still a gap, not a rewind.
+        let mut tracker = SequenceTracker { expected: u32::MAX };
+        assert_eq!(
+            tracker.check(1),
+            Err(PoolProtocolError::SequenceGap {
+                expected: u32::MAX,
+                received: 1
+            })
+        );
+        assert_eq!(
+            tracker.check(u32::MAX),
+            Err(PoolProtocolError::SequenceRewind {
+                expected: 2,
+                received: u32::MAX
+            })
+        );
+    }
+
+    #[test]
+    fn test_sequence_tracker_out_of_order() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.check(0), Ok(()));
+        assert_eq!(
+            tracker.check(2),
+            Err(PoolProtocolError::SequenceGap {
+                expected: 1,
+                received: 2
+            })
+        );
+        assert_eq!(
+            tracker.check(1),
+            Err(PoolProtocolError::SequenceRewind {
+                expected: 3,
+                received: 1
+            })
+        );
+        assert_eq!(
+            tracker.check(2),
+            Err(PoolProtocolError::SequenceRewind {
+                expected: 3,
+                received: 2
+            })
+        );
+        assert_eq!(tracker.check(3), Ok(()));
+    }
+
+    #[test]
+    fn test_codec_sequence_numbers() -> Result<()> {
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_SEQUENCE);
+        decoder.set_capabilities(CAP_SEQUENCE);
+
+        let mut frames = Vec::new();
+        for _ in 0..3 {
+            let mut buffer = BytesMut::new();
+            encoder.encode(PoolMessageCS::Ping, &mut buffer)?;
+            assert_eq!(buffer.len(), 4 + 4 + 1);
+            frames.push(buffer);
+        }
+        assert_eq!(&frames[2][4..8], &2u32.to_le_bytes());
+
+        // In order.
+        let mut buffer = frames[0].clone();
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageCS::Ping)
+        ));
+
+        // Skipping frame 1 is a gap, and the frame is consumed.
+        let mut buffer = frames[2].clone();
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::SequenceGap {
+                expected: 1,
+                received: 2
+            })
+        );
+        assert!(buffer.is_empty());
+
+        // Replaying frame 0 is a rewind.
+        let mut buffer = frames[0].clone();
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::SequenceRewind {
+                expected: 3,
+                received: 0
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_encode_shared() -> Result<()> {
+        use snarkvm::prelude::{EpochChallenge, Network};
+
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let notify = PoolMessageSC::Notify(7, 1_000, 1_000, epoch_challenge, vec![]);
+        let expected = notify.to_vec()?;
+
+        // One message, encoded by every connection's codec.
+        let mut codecs = vec![PoolCodecSC::<CurrentNetwork>::new(); 100];
+        for codec in &mut codecs {
+            let mut buffer = BytesMut::new();
+            codec.encode(&notify, &mut buffer)?;
+            assert_eq!(&buffer[..], &expected[..]);
+        }
+
+        let mut codec = PoolCodecCS::<CurrentNetwork>::new();
+        let mut buffer = BytesMut::new();
+        codec.encode(&PoolMessageCS::JobStatus(1, 2), &mut buffer)?;
+        assert_eq!(
+            &buffer[..],
+            &PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2).to_vec()?[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_encode_too_large() -> Result<()> {
+        use crate::message::tlv::{Tlv, TAG_MAX_SHARES};
+        use snarkvm::prelude::{EpochChallenge, Network};
+
+        let too_large = |error: anyhow::Error| match error.downcast::<PoolProtocolError>() {
+            Ok(PoolProtocolError::EncodeTooLarge {
+                name,
+                length,
+                limit,
+            }) => (name, length - limit),
+            error => panic!("unexpected error {:?}", error),
+        };
+
+        // A `Submit` one byte over `WORK_MESSAGE_LIMIT` leaves earlier frames in `dst` intact.
+        let submit = |solution_len| {
+            PoolMessageCS::<CurrentNetwork>::Submit(
+                1,
+                0,
+                7,
+                Data::Buffer(vec![0u8; solution_len].into()),
+                None,
+            )
+        };
+        let mut codec = PoolCodecCS::<CurrentNetwork>::new();
+        codec.set_capabilities(CAP_SEQUENCE);
+        let mut buffer = BytesMut::new();
+        codec.encode(PoolMessageCS::Ping, &mut buffer)?;
+        let before = buffer.clone();
+        let solution_len = WORK_MESSAGE_LIMIT - 4 - (1 + 17);
+        let error = codec
+            .encode(submit(solution_len + 1), &mut buffer)
+            .unwrap_err();
+        assert_eq!(too_large(error), ("Submit".to_string(), 1));
+        assert_eq!(buffer, before);
+        // The refused message used no sequence number.
+        codec.encode(submit(solution_len), &mut buffer)?;
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_SEQUENCE);
+        assert!(decoder.decode(&mut buffer)?.is_some());
+        assert!(decoder.decode(&mut buffer)?.is_some());
+
+        // A broadcast `Notify` against per-id limits, which bound the body.
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let notify = PoolMessageSC::Notify(
+            7,
+            1_000,
+            1_000,
+            epoch_challenge,
+            vec![Tlv::new(TAG_MAX_SHARES, vec![0; WORK_MESSAGE_LIMIT])],
+        );
+        let mut codec =
+            PoolCodecSC::<CurrentNetwork>::new().with_frame_limits(FrameLimits::default());
+        let mut buffer = BytesMut::new();
+        let error = codec.encode(&notify, &mut buffer).unwrap_err();
+        let overflow = notify.serialized_size() - WORK_MESSAGE_LIMIT;
+        assert_eq!(too_large(error), ("Notify".to_string(), overflow));
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_codec_truncated_eof() -> Result<()> {
+        use futures::StreamExt;
+        use snarkvm::prelude::{EpochChallenge, Network};
+        use tokio_util::codec::FramedRead;
+
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let notify = PoolMessageSC::Notify(7, 1_000, 1_000, epoch_challenge, vec![]).to_vec()?;
+        let mut stream = PoolMessageSC::<CurrentNetwork>::Pong(None).to_vec()?;
+        let complete = stream.len();
+        stream.extend_from_slice(&notify);
+
+        // Cut inside the length prefix, then inside the body.
+        for (end, length) in [(2, None), (20, Some(notify.len() - 4))] {
+            let mut reader = FramedRead::new(
+                &stream[..complete + end],
+                PoolCodecSC::<CurrentNetwork>::new(),
+            );
+            assert!(matches!(
+                reader.next().await,
+                Some(Ok(PoolMessageSC::Pong(None)))
+            ));
+            let error = reader.next().await.unwrap().unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+            assert_eq!(
+                PoolProtocolError::from_io_error(&error),
+                Some(&PoolProtocolError::TruncatedFrame {
+                    pending: end,
+                    length
+                })
+            );
+        }
+
+        // A stream ending on a frame boundary ends cleanly.
+        let mut reader = FramedRead::new(&stream[..], PoolCodecSC::<CurrentNetwork>::new());
+        assert_eq!(reader.by_ref().count().await, 2);
+
+        // The legacy decoder reports the same error.
+        let mut buffer = BytesMut::from(&notify[..20]);
+        let error = PoolMessageSC::<CurrentNetwork>::default()
+            .decode_eof(&mut buffer)
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_keepalive() -> Result<()> {
+        for capabilities in [0, CAP_SEQUENCE | CAP_VERSION_BYTE, CAP_VARINT_LENGTH] {
+            let codec = || {
+                let mut codec = PoolCodecSC::<CurrentNetwork>::new();
+                codec.set_capabilities(capabilities);
+                codec
+            };
+            let mut encoder = codec();
+            let mut buffer = BytesMut::new();
+            encoder.encode(PoolMessageSC::Pong(None), &mut buffer)?;
+            let frame_len = buffer.len();
+            encoder.encode_keepalive(&mut buffer);
+            assert!(buffer[frame_len..].iter().all(|byte| *byte == 0));
+            encoder.encode(PoolMessageSC::ShutDown, &mut buffer)?;
+
+            // A keepalive between two frames is consumed silently.
+            let mut decoder = codec();
+            assert!(matches!(
+                decoder.decode(&mut buffer)?,
+                Some(PoolMessageSC::Pong(None))
+            ));
+            assert!(matches!(
+                decoder.decode(&mut buffer)?,
+                Some(PoolMessageSC::ShutDown)
+            ));
+            assert!(buffer.is_empty());
+
+            // A stream of only keepalives yields nothing, nor fails at its end.
+            for _ in 0..3 {
+                encoder.encode_keepalive(&mut buffer);
+            }
+            assert!(decoder.decode(&mut buffer)?.is_none());
+            assert!(buffer.is_empty());
+            encoder.encode_keepalive(&mut buffer);
+            assert!(decoder.decode_eof(&mut buffer)?.is_none());
+            assert!(buffer.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_frame_error_policy() -> Result<()> {
+        let mut encoder = PoolCodecSC::<CurrentNetwork>::new();
+        let mut frames = BytesMut::new();
+        encoder.encode(PoolMessageSC::Throttle(500), &mut frames)?;
+        // A `JobHint` cut short.
+        frames.extend_from_slice(&[3, 0, 0, 0, 12, 1, 2]);
+        encoder.encode(PoolMessageSC::RequestProof(7), &mut frames)?;
+
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        decoder.set_frame_error_policy(FrameErrorPolicy::SkipFrame);
+        let mut buffer = frames.clone();
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageSC::RequestProof(7))
+        ));
+        assert!(buffer.is_empty());
+        assert_eq!(decoder.invalid_frames_skipped(), 1);
+
+        // Fatal by default.
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        let mut buffer = frames;
+        decoder.decode(&mut buffer)?;
+        assert!(decoder.decode(&mut buffer).is_err());
+        assert_eq!(decoder.invalid_frames_skipped(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_sequence_policy() -> Result<()> {
+        let mut encoder = PoolCodecSC::<CurrentNetwork>::new();
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_SEQUENCE);
+        decoder.set_capabilities(CAP_SEQUENCE);
+        decoder.set_sequence_policy(SequencePolicy::Report);
+        assert_eq!(decoder.last_sequence(), None);
+
+        let mut frames = Vec::new();
+        for job_id in 0..3 {
+            let mut buffer = BytesMut::new();
+            encoder.encode(PoolMessageSC::JobHint(job_id, 1), &mut buffer)?;
+            frames.push(buffer);
+        }
+
+        // A reordering proxy: 0, 2, 1. Every message is delivered and the anomalies counted.
+        for (index, expected) in [(0, 1), (2, 3), (1, 3)] {
+            let mut buffer = frames[index].clone();
+            assert!(matches!(
+                decoder.decode(&mut buffer)?,
+                Some(PoolMessageSC::JobHint(job_id, 1)) if job_id == index as u64
+            ));
+            assert_eq!(decoder.last_sequence(), Some(index as u32));
+            assert_eq!(decoder.expected_sequence(), expected);
+        }
+        assert_eq!(decoder.sequence_anomalies(), 2);
+
+        // Without sequence numbers nothing is tracked.
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        let mut buffer = BytesMut::new();
+        PoolCodecSC::<CurrentNetwork>::new().encode(PoolMessageSC::Throttle(1), &mut buffer)?;
+        decoder.decode(&mut buffer)?;
+        assert_eq!(decoder.last_sequence(), None);
+        Ok(())
+    }
+
+    fn authenticated_codecs(
+        capabilities: u32,
+    ) -> (PoolCodecSC<CurrentNetwork>, PoolCodecSC<CurrentNetwork>) {
+        let mut encoder = PoolCodecSC::new();
+        let mut decoder = PoolCodecSC::new();
+        for codec in [&mut encoder, &mut decoder] {
+            codec.set_capabilities(capabilities);
+            codec.set_session_key(SessionKey::from_connect_ack(1, "testsignature"));
+        }
+        (encoder, decoder)
+    }
+
+    #[test]
+    fn test_codec_hmac() -> Result<()> {
+        let (mut encoder, mut decoder) = authenticated_codecs(CAP_HMAC | CAP_SEQUENCE);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        assert_eq!(buffer.len(), 4 + 4 + 5 + AUTH_TAG_SIZE);
+        let frame = buffer.clone();
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+
+        // Tampered payload.
+        let (mut encoder, mut decoder) = authenticated_codecs(CAP_HMAC);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        buffer[5] ^= 1;
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::AuthenticationFailed)
+        );
+
+        // Tampered sequence number, which the tag covers too.
+        let (_, mut decoder) = authenticated_codecs(CAP_HMAC | CAP_SEQUENCE);
+        let mut buffer = frame.clone();
+        buffer[4] = 1;
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::AuthenticationFailed)
+        );
+
+        // Truncated tag, with the length prefix patched to match.
+        let (_, mut decoder) = authenticated_codecs(CAP_HMAC | CAP_SEQUENCE);
+        let mut buffer = frame.clone();
+        buffer.truncate(buffer.len() - 1);
+        let len = (buffer.len() - 4) as u32;
+        buffer[..4].copy_from_slice(&len.to_le_bytes());
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::AuthenticationFailed)
+        );
+
+        // Frame shorter than a tag.
+        let mut buffer = BytesMut::from(&[5u8, 0, 0, 0, 4, 1, 2, 3, 4][..]);
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::MissingAuthTag)
+        );
+
+        // A different session key.
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_HMAC | CAP_SEQUENCE);
+        decoder.set_session_key(SessionKey::from_connect_ack(2, "testsignature"));
+        let error = decoder.decode(&mut frame.clone()).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::AuthenticationFailed)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_hmac_fallback() -> Result<()> {
+        // Without the capability the key is ignored and frames stay plain.
+        let (mut encoder, mut decoder) = authenticated_codecs(0);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageSC::Pong(None), &mut buffer)?;
+        assert_eq!(&buffer[..], &[1, 0, 0, 0, 3]);
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageSC::Pong(None))
+        ));
+
+        // With the capability but no key, nothing is written.
+        let mut encoder = PoolCodecSC::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_HMAC);
+        let mut buffer = BytesMut::new();
+        assert!(encoder
+            .encode(PoolMessageSC::Pong(None), &mut buffer)
+            .is_err());
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_varint() -> Result<()> {
+        for (value, size) in [
+            (0, 1),
+            (127, 1),
+            (128, 2),
+            (16_383, 2),
+            (16_384, 3),
+            (u32::MAX, 5),
+        ] {
+            let mut buffer = BytesMut::new();
+            write_varint(value, &mut buffer);
+            assert_eq!(buffer.len(), size);
+            assert_eq!(read_varint(&buffer)?, Some((value, size)));
+            assert_eq!(read_varint(&buffer[..size - 1])?, None);
+        }
+        let mut buffer = BytesMut::new();
+        write_varint(300, &mut buffer);
+        assert_eq!(&buffer[..], &[0xac, 0x02]);
+
+        // Too long, overflowing, or not minimal.
+        for prefix in [
+            &[0x80, 0x80, 0x80, 0x80, 0x80][..],
+            &[0xff, 0xff, 0xff, 0xff, 0x10],
+            &[0x80, 0x00],
+            &[0x81, 0x80, 0x00],
+        ] {
+            assert_eq!(
+                read_varint(prefix),
+                Err(PoolProtocolError::InvalidLengthPrefix)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_varint_length() -> Result<()> {
+        let codec = |capabilities| {
+            let mut codec = PoolCodecSC::<CurrentNetwork>::new();
+            codec.set_capabilities(capabilities);
+            codec
+        };
+        let mut buffer = BytesMut::new();
+        codec(CAP_VARINT_LENGTH).encode(PoolMessageSC::Pong(None), &mut buffer)?;
+        assert_eq!(&buffer[..], &[1, 3]);
+
+        // Every optional field still works behind the shorter prefix.
+        for capabilities in [
+            CAP_VARINT_LENGTH,
+            CAP_VARINT_LENGTH | CAP_SEQUENCE | CAP_CHANNELS | CAP_CHECKSUM | CAP_VERSION_BYTE,
+        ] {
+            let mut encoder = codec(capabilities);
+            let mut decoder = codec(capabilities);
+            let mut buffer = BytesMut::new();
+            let messages = [
+                PoolMessageSC::Pong(None),
+                PoolMessageSC::ShareAckBatch(vec![(7, SHARE_ACCEPTED); 30]),
+                PoolMessageSC::ShutDown,
+            ];
+            for message in messages.clone() {
+                encoder.encode(message, &mut buffer)?;
+            }
+
+            // Fed a byte at a time, split inside the 2 byte prefix of the batch too.
+            let mut source = BytesMut::new();
+            let mut decoded = Vec::new();
+            for byte in buffer.iter() {
+                source.extend_from_slice(&[*byte]);
+                while let Some(message) = decoder.decode(&mut source)? {
+                    decoded.push(message.to_vec()?);
+                }
+            }
+            assert!(source.is_empty());
+            let expected = messages
+                .iter()
+                .map(|message| message.to_vec())
+                .collect::<Result<Vec<_>>>()?;
+            assert_eq!(decoded, expected);
+        }
+
+        let error = codec(CAP_VARINT_LENGTH)
+            .decode(&mut BytesMut::from(&[0x80, 0x00, 3][..]))
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::InvalidLengthPrefix)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_length_endianness() -> Result<()> {
+        let codec = |length_endianness| {
+            PoolCodecSC::<CurrentNetwork>::new().with_length_endianness(length_endianness)
+        };
+        let message = PoolMessageSC::ShareAckBatch(vec![(7, SHARE_ACCEPTED); 30]);
+        let body = message.to_vec()?.split_off(4);
+        for (length_endianness, prefix) in [
+            (LengthEndianness::Little, (body.len() as u32).to_le_bytes()),
+            (LengthEndianness::Big, (body.len() as u32).to_be_bytes()),
+        ] {
+            let mut buffer = BytesMut::new();
+            codec(length_endianness).encode(message.clone(), &mut buffer)?;
+            assert_eq!(&buffer[..4], &prefix);
+            assert_eq!(&buffer[4..], &body[..]);
+            let decoded = codec(length_endianness).decode(&mut buffer)?.unwrap();
+            assert_eq!(decoded.to_vec()?, message.to_vec()?);
+            assert!(buffer.is_empty());
+        }
+
+        // Big-endian frames are what `LengthDelimitedCodec` reads by default.
+        let mut buffer = BytesMut::new();
+        codec(LengthEndianness::Big).encode(message.clone(), &mut buffer)?;
+        let frame = tokio_util::codec::LengthDelimitedCodec::new()
+            .decode(&mut buffer)?
+            .unwrap();
+        assert_eq!(&frame[..], &body[..]);
+
+        // Mismatched ends misread the prefix.
+        let mut buffer = BytesMut::new();
+        codec(LengthEndianness::Little).encode(message, &mut buffer)?;
+        assert!(codec(LengthEndianness::Big).decode(&mut buffer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_version_byte() -> Result<()> {
+        let encode = |codec: &mut ProtocolCodec<PoolMessageSC<CurrentNetwork>>| -> Result<Vec<u8>> {
+            let mut buffer = BytesMut::new();
+            codec.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+            Ok(buffer.to_vec())
+        };
+        let codec = |capabilities| {
+            let mut codec = ProtocolCodec::<PoolMessageSC<CurrentNetwork>>::default();
+            codec.set_capabilities(capabilities);
+            codec
+        };
+
+        // The legacy layout is unchanged, and the new one stamps the negotiated version.
+        let legacy = [5, 0, 0, 0, 4, 0xf4, 1, 0, 0];
+        let v1 = [6, 0, 0, 0, 1, 4, 0xf4, 1, 0, 0];
+        let v2 = [6, 0, 0, 0, 2, 4, 0xf4, 1, 0, 0];
+        let v1_sequence = [10, 0, 0, 0, 1, 0, 0, 0, 0, 4, 0xf4, 1, 0, 0];
+        for (capabilities, golden) in [
+            (0, &legacy[..]),
+            (CAP_VERSION_BYTE, &v1[..]),
+            (CAP_VERSION_BYTE | CAP_PROTOCOL_V2, &v2[..]),
+            (CAP_VERSION_BYTE | CAP_SEQUENCE, &v1_sequence[..]),
+        ] {
+            assert_eq!(encode(&mut codec(capabilities))?, golden);
+            assert!(matches!(
+                codec(capabilities).decode(&mut golden.into())?,
+                Some(PoolMessageSC::Throttle(500))
+            ));
+        }
+
+        // Unknown versions, and known ones other than the negotiated one, are rejected.
+        for (capabilities, version) in [
+            (CAP_VERSION_BYTE, 0),
+            (CAP_VERSION_BYTE, 2),
+            (CAP_VERSION_BYTE, 9),
+            (CAP_VERSION_BYTE | CAP_PROTOCOL_V2, 1),
+        ] {
+            let mut buffer = BytesMut::from(&v1[..]);
+            buffer[4] = version;
+            let error = codec(capabilities).decode(&mut buffer).unwrap_err();
+            assert_eq!(
+                PoolProtocolError::from_io_error(&error),
+                Some(&PoolProtocolError::UnsupportedVersion(version))
+            );
+        }
+        // A frame of length 0 carries no version byte: it is a keepalive.
+        let mut buffer = BytesMut::from(&[0u8, 0, 0, 0][..]);
+        assert!(codec(CAP_VERSION_BYTE).decode(&mut buffer)?.is_none());
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_checksum() -> Result<()> {
+        let checksum_mismatch = |error: &std::io::Error| {
+            matches!(
+                PoolProtocolError::from_io_error(error),
+                Some(PoolProtocolError::ChecksumMismatch { .. })
+            )
+        };
+        let mut encoder = PoolCodecSC::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_CHECKSUM | CAP_SEQUENCE);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        assert_eq!(buffer.len(), 4 + 4 + 5 + CHECKSUM_SIZE);
+        assert_eq!(
+            buffer[buffer.len() - CHECKSUM_SIZE..],
+            crc32c(&buffer[4..buffer.len() - CHECKSUM_SIZE]).to_le_bytes()
+        );
+        let frame = buffer.clone();
+        let decoder = || {
+            let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+            decoder.set_capabilities(CAP_CHECKSUM | CAP_SEQUENCE);
+            decoder
+        };
+        assert!(matches!(
+            decoder().decode(&mut buffer)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+
+        // Every corrupted payload or checksum byte is caught before the body is read.
+        for index in 4..frame.len() {
+            let mut buffer = frame.clone();
+            buffer[index] ^= 0x10;
+            let error = decoder().decode(&mut buffer).unwrap_err();
+            assert!(checksum_mismatch(&error), "byte {}: {}", index, error);
+            assert_eq!(
+                PoolProtocolError::from_io_error(&error).unwrap().category(),
+                "checksum"
+            );
+        }
+
+        // Checked before the authentication tag, which would fail on the same corruption.
+        let (mut encoder, _) = authenticated_codecs(CAP_HMAC | CAP_CHECKSUM);
+        let (_, mut authenticated) = authenticated_codecs(CAP_HMAC | CAP_CHECKSUM);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        assert_eq!(buffer.len(), 4 + 5 + AUTH_TAG_SIZE + CHECKSUM_SIZE);
+        buffer[5] ^= 1;
+        assert!(checksum_mismatch(
+            &authenticated.decode(&mut buffer).unwrap_err()
+        ));
+
+        // A frame too short for its checksum, and a peer that did not agree on it.
+        let mut buffer = BytesMut::from(&[2u8, 0, 0, 0, 4, 1][..]);
+        assert!(decoder().decode(&mut buffer).is_err());
+        let mut buffer = frame.clone();
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_SEQUENCE);
+        assert!(decoder.decode(&mut buffer).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "compress-zstd")]
+    #[test]
+    fn test_codec_compression() -> Result<()> {
+        let codec = || {
+            let mut codec = PoolCodecCS::<CurrentNetwork>::new();
+            codec.set_capabilities(CAP_ZSTD | CAP_VERSION_BYTE | CAP_CHECKSUM);
+            codec
+        };
+        let connect = |address: String| {
+            PoolMessageCS::<CurrentNetwork>::Connect(
+                0,
+                1,
+                0,
+                1,
+                0,
+                "my_worker_1".to_string(),
+                address,
+                vec![],
+            )
+        };
+        let message = connect("215587407@qq.com".repeat(25));
+        let mut encoder = codec();
+        let mut buffer = BytesMut::new();
+        encoder.encode(message.clone(), &mut buffer)?;
+        assert_eq!(buffer[5], FLAG_COMPRESSED);
+        assert!(buffer.len() < message.to_vec()?.len());
+        let decoded = codec().decode(&mut buffer)?.unwrap();
+        assert_eq!(decoded.to_vec()?, message.to_vec()?);
+        assert!(buffer.is_empty());
+
+        // Bodies below the threshold are sent as is.
+        encoder.encode(PoolMessageCS::Ping, &mut buffer)?;
+        assert_eq!(buffer.len(), 4 + 2 + 1 + CHECKSUM_SIZE);
+        assert_eq!(buffer[5], 0);
+        assert!(matches!(
+            codec().decode(&mut buffer)?,
+            Some(PoolMessageCS::Ping)
+        ));
+        let mut encoder = codec().with_compression_threshold(1024);
+        encoder.encode(message.clone(), &mut buffer)?;
+        assert_eq!(buffer[5], 0);
+        let decoded = codec().decode(&mut buffer)?.unwrap();
+        assert_eq!(decoded.to_vec()?, message.to_vec()?);
+
+        // Decompressed bodies are held to the limit of their id, here from a peer with a larger one.
+        codec()
+            .with_max_frame_size(1024)
+            .encode(connect("215587407@qq.com".repeat(40)), &mut buffer)?;
+        assert_eq!(buffer[5], FLAG_COMPRESSED);
+        let error = codec().decode(&mut buffer).unwrap_err();
+        assert!(matches!(
+            PoolProtocolError::from_io_error(&error),
+            Some(PoolProtocolError::FrameTooLarge(..))
+        ));
+
+        // A bomb is cut off at the largest frame the decoder accepts.
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_ZSTD);
+        let bomb = CompressionAlgorithm::Zstd
+            .compress(&vec![0u8; 16 << 20])
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(&(1 + bomb.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&[FLAG_COMPRESSED]);
+        buffer.extend_from_slice(&bomb);
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::DecompressedTooLarge(WORK_MESSAGE_LIMIT))
+        );
+
+        // Unknown flags are rejected.
+        let mut buffer = BytesMut::from(&[2u8, 0, 0, 0, 0x02, 0][..]);
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::InvalidFrameFlags(0x02))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_without_sequence_matches_legacy_framing() -> Result<()> {
+        let mut buffer = BytesMut::new();
+        PoolCodecSC::<CurrentNetwork>::new().encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        let mut legacy = BytesMut::new();
+        PoolCodecSC::<CurrentNetwork>::new().encode(PoolMessageSC::Throttle(500), &mut legacy)?;
+        assert_eq!(buffer, legacy);
+
+        // A sequenced decoder rejects frames too short to carry the sequence number.
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_SEQUENCE);
+        let mut buffer = BytesMut::from(&[1u8, 0, 0, 0, 3][..]);
+        assert!(decoder.decode(&mut buffer).is_err());
+        Ok(())
+    }
+
+    fn large_connect() -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "a".repeat(2000),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_codec_chunked_transfer() -> Result<()> {
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        for codec in [&mut encoder, &mut decoder] {
+            codec.set_capabilities(CAP_CHUNKS | CAP_SEQUENCE | CAP_HMAC | CAP_CHANNELS);
+            codec.set_session_key(SessionKey::from_connect_ack(1, "testsignature"));
+        }
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageCS::Ping, &mut buffer)?;
+        encoder.encode(large_connect(), &mut buffer)?;
+        encoder.encode(PoolMessageCS::Ping, &mut buffer)?;
+
+        // Every frame fits the limit.
+        let mut frames = 0;
+        let mut rest = &buffer[..];
+        while !rest.is_empty() {
+            let length = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            assert!(length <= MAXIMUM_MESSAGE_SIZE);
+            rest = &rest[4 + length..];
+            frames += 1;
+        }
+        assert_eq!(frames, 2 + 5);
+
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageCS::Ping)
+        ));
+        match decoder.decode(&mut buffer)? {
+            Some(PoolMessageCS::Connect(.., address, _)) => assert_eq!(address, "a".repeat(2000)),
+            message => panic!("unexpected message {:?}", message),
+        }
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageCS::Ping)
+        ));
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_chunked_transfer_max_frame_size() -> Result<()> {
+        let new_codec = || {
+            let mut codec = PoolCodecCS::<CurrentNetwork>::new().with_max_frame_size(256);
+            codec.set_capabilities(CAP_CHUNKS | CAP_SEQUENCE | CAP_CHECKSUM);
+            codec
+        };
+        let (mut encoder, mut decoder) = (new_codec(), new_codec());
+        let mut buffer = BytesMut::new();
+        encoder.encode(large_connect(), &mut buffer)?;
+
+        // Chunks shrink to the lowered limit.
+        let mut frames = 0;
+        let mut rest = &buffer[..];
+        while !rest.is_empty() {
+            let length = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            assert!(length <= 256);
+            rest = &rest[4 + length..];
+            frames += 1;
+        }
+        assert!(frames > 5);
+
+        match decoder.decode(&mut buffer)? {
+            Some(PoolMessageCS::Connect(.., address, _)) => assert_eq!(address, "a".repeat(2000)),
+            message => panic!("unexpected message {:?}", message),
+        }
+        assert!(buffer.is_empty());
+
+        // A limit too small for any chunk frame writes nothing.
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new().with_max_frame_size(8);
+        encoder.set_capabilities(CAP_CHUNKS);
+        assert!(encoder.encode(large_connect(), &mut buffer).is_err());
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_signed_shares_need_capability() -> Result<()> {
+        let rng = &mut ::rand::thread_rng();
+        let signature = PrivateKey::<CurrentNetwork>::new(rng)?.sign_bytes(b"solution", rng)?;
+        let submit = |signature| {
+            PoolMessageCS::<CurrentNetwork>::Submit(
+                1,
+                0,
+                3,
+                Data::Buffer(vec![1; 8].into()),
+                signature,
+            )
+        };
+        let not_agreed = Some(&PoolProtocolError::CapabilityNotAgreed {
+            name: "Submit".to_string(),
+            capabilities: CAP_SIGNED_SHARES,
+        });
+
+        // The encoder writes nothing without the capability.
+        let mut buffer = BytesMut::new();
+        let error = PoolCodecCS::<CurrentNetwork>::new()
+            .encode(submit(Some(signature)), &mut buffer)
+            .unwrap_err();
+        assert_eq!(error.downcast_ref(), not_agreed);
+        assert!(buffer.is_empty());
+
+        // The decoder rejects a signed submit sent anyway.
+        let mut buffer = BytesMut::from(&submit(Some(signature)).to_vec()?[..]);
+        let error = PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert_eq!(PoolProtocolError::from_io_error(&error), not_agreed);
+
+        // Unsigned submits need nothing, signed ones go through once agreed.
+        let mut codec = PoolCodecCS::<CurrentNetwork>::new();
+        codec.encode(submit(None), &mut buffer)?;
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageCS::Submit(.., None))
+        ));
+        codec.set_capabilities(CAP_SIGNED_SHARES);
+        codec.encode(submit(Some(signature)), &mut buffer)?;
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageCS::Submit(.., Some(_)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_chunks_need_capability() -> Result<()> {
+        // Without the capability large messages cannot go out whole, and the encoder refuses them.
+        let mut buffer = BytesMut::new();
+        assert!(PoolCodecCS::<CurrentNetwork>::new()
+            .encode(large_connect(), &mut buffer)
+            .is_err());
+        assert!(buffer.is_empty());
+
+        // Chunks are handed over as they are.
+        let mut buffer = BytesMut::new();
+        PoolCodecCS::<CurrentNetwork>::new()
+            .encode(PoolMessageCS::Chunk(1, 0, 2, vec![131]), &mut buffer)?;
+        assert!(matches!(
+            PoolCodecCS::<CurrentNetwork>::new().decode(&mut buffer)?,
+            Some(PoolMessageCS::Chunk(1, 0, 2, _))
+        ));
+
+        // A chunked message may not be a chunk itself.
+        let mut inner = Vec::new();
+        PoolMessageCS::<CurrentNetwork>::Chunk(1, 0, 1, vec![131]).serialize_into(&mut inner)?;
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_CHUNKS);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageCS::Chunk(2, 0, 1, inner), &mut buffer)?;
+        assert!(decoder.decode(&mut buffer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_rejects_wrong_direction() -> Result<()> {
+        // A client message fed to the pool-to-client decoder.
+        let mut buffer = BytesMut::from(&PoolMessageCS::<CurrentNetwork>::Ping.to_vec()?[..]);
+        let error = PoolCodecSC::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::WrongDirection(131))
+        );
+        assert!(buffer.is_empty());
+
+        // And the other way around.
+        let mut buffer = BytesMut::from(&PoolMessageSC::<CurrentNetwork>::Pong(None).to_vec()?[..]);
+        let error = PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::WrongDirection(3))
+        );
+
+        // Unknown ids of the right direction are errors by default.
+        let mut buffer = BytesMut::from(&[1u8, 0, 0, 0, 126][..]);
+        let error = PoolCodecSC::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::UnknownMessageId(126))
+        );
+        Ok(())
+    }
+
+    /// A stream from a newer pool: Pong, a message with id 126 this build does not know, Throttle.
+    fn stream_with_unknown_id() -> Result<BytesMut> {
+        let mut encoder = PoolCodecSC::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_SEQUENCE);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageSC::Pong(None), &mut buffer)?;
+        // The Pong frame takes 9 bytes; give the unknown message a body and fix its length.
+        encoder.encode(PoolMessageSC::Unused(126), &mut buffer)?;
+        buffer.extend_from_slice(&[7, 7]);
+        let length = (buffer.len() - 9 - 4) as u32;
+        buffer[9..13].copy_from_slice(&length.to_le_bytes());
+        encoder.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn old_decoder(policy: UnknownIdPolicy) -> PoolCodecSC<CurrentNetwork> {
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_SEQUENCE);
+        decoder.set_unknown_id_policy(policy);
+        decoder
+    }
+
+    #[test]
+    fn test_unknown_id_policy() -> Result<()> {
+        let mut buffer = stream_with_unknown_id()?;
+        let mut decoder = old_decoder(UnknownIdPolicy::Error);
+        assert!(decoder.decode(&mut buffer)?.is_some());
+        assert!(decoder.decode(&mut buffer).is_err());
+
+        // Skipped frames are consumed and counted, and the sequence stays in step.
+        let mut buffer = stream_with_unknown_id()?;
+        let mut decoder = old_decoder(UnknownIdPolicy::Skip);
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageSC::Pong(None))
+        ));
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+        assert!(buffer.is_empty());
+        assert_eq!(decoder.unknown_ids_skipped(), 1);
+
+        let mut buffer = stream_with_unknown_id()?;
+        let mut decoder = old_decoder(UnknownIdPolicy::ReturnUnused);
+        let names = std::iter::from_fn(|| decoder.decode(&mut buffer).unwrap())
+            .map(|message| (message.name().to_string(), message.id()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                ("Pong".to_string(), 3),
+                ("Unused".to_string(), 126),
+                ("Throttle".to_string(), 4)
+            ]
+        );
+        assert_eq!(decoder.unknown_ids_skipped(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_max_frame_size() -> Result<()> {
+        // << length, 14, count(u16), 199 * 5 >> carries 998 bytes.
+        let message = || PoolMessageSC::<CurrentNetwork>::ShareAckBatch(vec![(1, 0); 199]);
+        let mut buffer = BytesMut::new();
+        PoolCodecSC::<CurrentNetwork>::new()
+            .with_max_frame_size(998)
+            .encode(message(), &mut buffer)?;
+        assert_eq!(buffer.len(), 4 + 998);
+
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new().with_max_frame_size(998);
+        assert_eq!(decoder.max_frame_size(), 998);
+        let decoded = decoder.decode(&mut buffer.clone())?.unwrap();
+        assert_eq!(decoded.to_vec()?, message().to_vec()?);
+
+        for mut decoder in [
+            PoolCodecSC::<CurrentNetwork>::new(),
+            PoolCodecSC::<CurrentNetwork>::new().with_max_frame_size(997),
+        ] {
+            let error = decoder.decode(&mut buffer.clone()).unwrap_err();
+            assert_eq!(
+                PoolProtocolError::from_io_error(&error),
+                Some(&PoolProtocolError::FrameTooLarge(998))
+            );
+        }
+
+        // Chunked transfer splits only what exceeds the limit.
+        let mut encoder = PoolCodecSC::<CurrentNetwork>::new().with_max_frame_size(998);
+        encoder.set_capabilities(CAP_CHUNKS);
+        let mut buffer = BytesMut::new();
+        encoder.encode(message(), &mut buffer)?;
+        assert_eq!(buffer.len(), 4 + 998);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_frame_limits() -> Result<()> {
+        // A 600 byte `Ping` and `Submit`, the `Ping` cut short after its id.
+        let mut ping = BytesMut::from(&600u32.to_le_bytes()[..]);
+        ping.extend_from_slice(&[131]);
+        let mut submit = BytesMut::from(&600u32.to_le_bytes()[..]);
+        submit.extend_from_slice(&[129]);
+        submit.extend_from_slice(&[0; 599]);
+
+        let mut decoder =
+            PoolCodecCS::<CurrentNetwork>::new().with_frame_limits(FrameLimits::default());
+        let error = decoder.decode(&mut ping.clone()).unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::MessageTooLarge {
+                id: 131,
+                length: 600
+            })
+        );
+        // The solution of a `Submit` is only read on demand.
+        assert!(matches!(
+            decoder.decode(&mut submit.clone())?,
+            Some(PoolMessageCS::Submit(0, 0, 0, ..))
+        ));
+
+        // Without the table the `Ping` hits the frame limit, while the `Submit` is a work message.
+        let error = PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut ping.clone())
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::FrameTooLarge(600))
+        );
+        assert!(matches!(
+            PoolCodecCS::<CurrentNetwork>::new().decode(&mut submit.clone())?,
+            Some(PoolMessageCS::Submit(0, 0, 0, ..))
+        ));
+
+        // Optional frame fields count towards neither, and a valid body decodes.
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        let mut decoder =
+            PoolCodecCS::<CurrentNetwork>::new().with_frame_limits(FrameLimits::default());
+        encoder.set_capabilities(CAP_SEQUENCE | CAP_CHANNELS);
+        decoder.set_capabilities(CAP_SEQUENCE | CAP_CHANNELS);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageCS::DisConnect(7), &mut buffer)?;
+        assert!(matches!(
+            decoder.decode(&mut buffer)?,
+            Some(PoolMessageCS::DisConnect(7))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_rejects_oversized_frame() {
+        // Above every default limit, rejected before the id arrives.
+        let length = WORK_MESSAGE_LIMIT as u32 + 1;
+        let mut decoder = PoolCodecSC::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_SEQUENCE);
+        let mut buffer = BytesMut::from(&length.to_le_bytes()[..]);
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        let error = PoolProtocolError::from_io_error(&error).unwrap();
+        assert_eq!(error, &PoolProtocolError::FrameTooLarge(length as usize));
+        assert_eq!(error.category(), "frame_size");
+
+        let mut buffer = BytesMut::from(&length.to_le_bytes()[..]);
+        let error = PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::FrameTooLarge(length as usize))
+        );
+
+        // Above `MAXIMUM_MESSAGE_SIZE`, rejected once the id shows it is no work message.
+        let length = MAXIMUM_MESSAGE_SIZE as u32 + 1;
+        let mut buffer = BytesMut::from(&length.to_le_bytes()[..]);
+        assert!(PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap()
+            .is_none());
+        buffer.extend_from_slice(&[131]);
+        let error = PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::FrameTooLarge(length as usize))
+        );
+    }
+}
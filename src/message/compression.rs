@@ -0,0 +1,198 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{capabilities::*, error::*};
+
+/// Frame flag set when the body is compressed with the agreed `CompressionAlgorithm`.
+pub const FLAG_COMPRESSED: u8 = 1 << 0;
+
+/// Every frame flag this library understands, the other bits must be zero.
+pub const KNOWN_FRAME_FLAGS: u8 = FLAG_COMPRESSED;
+
+/// Shorter bodies are sent as is, as the compression header would eat most of the savings.
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 128;
+
+/// The zstd level of compressed bodies, fast enough to compress every `Notify` of an epoch.
+pub const COMPRESSION_LEVEL: i32 = 3;
+
+/// The algorithm compressing frame bodies on a connection, picked from the agreed capabilities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    None,
+    /// lz4 block format, nearly free to decompress on low-power workers, see `CAP_LZ4`.
+    Lz4,
+    /// zstd at `COMPRESSION_LEVEL`, see `CAP_ZSTD`.
+    Zstd,
+}
+
+impl CompressionAlgorithm {
+    /// Returns the algorithm of a capability mask. zstd wins if both peers support both,
+    /// as `negotiate_capabilities` only keeps one of them.
+    pub fn from_capabilities(capabilities: u32) -> Self {
+        if capabilities & CAP_ZSTD != 0 {
+            Self::Zstd
+        } else if capabilities & CAP_LZ4 != 0 {
+            Self::Lz4
+        } else {
+            Self::None
+        }
+    }
+
+    /// Returns the capability advertising the algorithm, 0 for `None`.
+    pub fn capability(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Lz4 => CAP_LZ4,
+            Self::Zstd => CAP_ZSTD,
+        }
+    }
+
+    /// Returns `body` compressed, or `None` if that would not make it shorter or this build
+    /// lacks the algorithm's cargo feature.
+    pub fn compress(self, body: &[u8]) -> Option<Vec<u8>> {
+        let compressed = match self {
+            Self::None => None,
+            Self::Lz4 => compress_lz4(body),
+            Self::Zstd => compress_zstd(body),
+        }?;
+        (compressed.len() < body.len()).then_some(compressed)
+    }
+
+    /// Decompresses a body, failing as soon as it inflates past `limit` bytes so a small
+    /// frame cannot exhaust the receiver's memory.
+    pub fn decompress(self, compressed: &[u8], limit: usize) -> Result<Vec<u8>, PoolProtocolError> {
+        match self {
+            Self::None => Err(PoolProtocolError::InvalidFrameFlags(FLAG_COMPRESSED)),
+            Self::Lz4 => decompress_lz4(compressed, limit),
+            Self::Zstd => decompress_zstd(compressed, limit),
+        }
+    }
+}
+
+#[cfg(feature = "compress-lz4")]
+fn compress_lz4(body: &[u8]) -> Option<Vec<u8>> {
+    Some(lz4_flex::block::compress(body))
+}
+
+#[cfg(not(feature = "compress-lz4"))]
+fn compress_lz4(_body: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "compress-lz4")]
+fn decompress_lz4(compressed: &[u8], limit: usize) -> Result<Vec<u8>, PoolProtocolError> {
+    lz4_flex::block::decompress(compressed, limit).map_err(|error| match error {
+        lz4_flex::block::DecompressError::OutputTooSmall { .. } => {
+            PoolProtocolError::DecompressedTooLarge(limit)
+        }
+        _ => PoolProtocolError::InvalidCompression,
+    })
+}
+
+#[cfg(not(feature = "compress-lz4"))]
+fn decompress_lz4(_compressed: &[u8], _limit: usize) -> Result<Vec<u8>, PoolProtocolError> {
+    Err(PoolProtocolError::InvalidFrameFlags(FLAG_COMPRESSED))
+}
+
+#[cfg(feature = "compress-zstd")]
+fn compress_zstd(body: &[u8]) -> Option<Vec<u8>> {
+    zstd::bulk::compress(body, COMPRESSION_LEVEL).ok()
+}
+
+#[cfg(not(feature = "compress-zstd"))]
+fn compress_zstd(_body: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "compress-zstd")]
+fn decompress_zstd(compressed: &[u8], limit: usize) -> Result<Vec<u8>, PoolProtocolError> {
+    use std::io::Read;
+
+    let decoder = zstd::stream::read::Decoder::with_buffer(compressed)
+        .map_err(|_| PoolProtocolError::InvalidCompression)?;
+    let mut body = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|_| PoolProtocolError::InvalidCompression)?;
+    match body.len() > limit {
+        true => Err(PoolProtocolError::DecompressedTooLarge(limit)),
+        false => Ok(body),
+    }
+}
+
+#[cfg(not(feature = "compress-zstd"))]
+fn decompress_zstd(_compressed: &[u8], _limit: usize) -> Result<Vec<u8>, PoolProtocolError> {
+    Err(PoolProtocolError::InvalidFrameFlags(FLAG_COMPRESSED))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    fn check_compression(algorithm: CompressionAlgorithm, invalid: &[u8]) {
+        let body = [7u8; 1024];
+        let compressed = algorithm.compress(&body).unwrap();
+        assert!(compressed.len() < body.len());
+        assert_eq!(algorithm.decompress(&compressed, body.len()).unwrap(), body);
+
+        // Incompressible bodies are left alone.
+        assert_eq!(algorithm.compress(&[1, 2, 3, 4]), None);
+
+        // A bomb is cut off at the limit instead of inflated.
+        let bomb = algorithm.compress(&vec![0u8; 16 << 20]).unwrap();
+        assert!(bomb.len() < 128 << 10);
+        assert_eq!(
+            algorithm.decompress(&bomb, 64 << 10),
+            Err(PoolProtocolError::DecompressedTooLarge(64 << 10))
+        );
+        assert_eq!(
+            algorithm.decompress(invalid, body.len()),
+            Err(PoolProtocolError::InvalidCompression)
+        );
+    }
+
+    #[cfg(feature = "compress-zstd")]
+    #[test]
+    fn test_zstd() {
+        check_compression(CompressionAlgorithm::Zstd, &[0x28, 0xb5, 0x2f, 0xfd, 0xff]);
+    }
+
+    #[cfg(feature = "compress-lz4")]
+    #[test]
+    fn test_lz4() {
+        check_compression(CompressionAlgorithm::Lz4, &[0xff, 1, 2]);
+    }
+
+    #[test]
+    fn test_compression_algorithm() {
+        for algorithm in [
+            CompressionAlgorithm::None,
+            CompressionAlgorithm::Lz4,
+            CompressionAlgorithm::Zstd,
+        ] {
+            assert_eq!(
+                CompressionAlgorithm::from_capabilities(algorithm.capability() | CAP_SEQUENCE),
+                algorithm
+            );
+        }
+        assert_eq!(
+            CompressionAlgorithm::from_capabilities(CAP_LZ4 | CAP_ZSTD),
+            CompressionAlgorithm::Zstd
+        );
+        assert_eq!(CompressionAlgorithm::None.compress(&[0u8; 1024]), None);
+        assert_eq!(
+            CompressionAlgorithm::None.decompress(&[0u8; 16], 1024),
+            Err(PoolProtocolError::InvalidFrameFlags(FLAG_COMPRESSED))
+        );
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{poolmessage::*, tlv::*};
+use snarkvm::prelude::*;
+
+use std::{fmt, primitive::str};
+
+/// The share difficulty behind a raw `target`.
+/// A solution meets target `t` when `u64::MAX / sha256d(commitment) >= t`, which happens once in
+/// about `t` solutions, so the difficulty is the expected number of solutions per share.
+/// Keep targets in this type in client code; messages carry the raw u64.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Difficulty(u64);
+
+impl Difficulty {
+    pub const fn new(difficulty: u64) -> Self {
+        Self(difficulty)
+    }
+
+    /// Returns the difficulty of a raw target.
+    pub const fn from_target(target: u64) -> Self {
+        Self(target)
+    }
+
+    /// Returns the raw target sent on the wire.
+    pub const fn to_target(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the expected number of solutions per share.
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Difficulty {
+    /// Shows the difficulty with an SI suffix, e.g. `1.50M`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+        if self.0 < 1000 {
+            return write!(f, "{}", self.0);
+        }
+        let mut value = self.0 as f64 / 1000.0;
+        let mut unit = 0;
+        while value >= 1000.0 && unit < UNITS.len() - 1 {
+            value /= 1000.0;
+            unit += 1;
+        }
+        write!(f, "{:.2}{}", value, UNITS[unit])
+    }
+}
+
+/// Returns the `Notify` TLV capping the shares the pool accepts per epoch, 0 for unlimited.
+pub fn max_shares_tlv(max_shares: u32) -> Tlv {
+    Tlv::new(TAG_MAX_SHARES, max_shares.to_le_bytes().to_vec())
+}
+
+/// Returns the accepted `ConnectAck` TLV with the lowest solution target the pool accepts as a share.
+pub fn min_target_tlv(min_target: u64) -> Tlv {
+    Tlv::new(TAG_MIN_TARGET, min_target.to_le_bytes().to_vec())
+}
+
+impl<N: Network> PoolMessageSC<N> {
+    /// Returns a `Notify` for a job at the given block and share difficulties.
+    pub fn notify(
+        job_id: u64,
+        block_difficulty: Difficulty,
+        share_difficulty: Difficulty,
+        epoch_challenge: EpochChallenge<N>,
+    ) -> Self {
+        Self::Notify(
+            job_id,
+            block_difficulty.to_target(),
+            share_difficulty.to_target(),
+            epoch_challenge,
+            vec![],
+        )
+    }
+
+    /// Returns the share difficulty of a `Notify` or of the job in a `ConnectAck`.
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        match self {
+            Self::Notify(_, _, target, ..) | Self::ConnectAck(.., Some((_, target, _)), _) => {
+                Some(Difficulty::from_target(*target))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the block difficulty of a `Notify`, the threshold a share must also meet to be
+    /// proposed as a block.
+    pub fn block_difficulty(&self) -> Option<Difficulty> {
+        match self {
+            Self::Notify(_, target, ..) => Some(Difficulty::from_target(*target)),
+            _ => None,
+        }
+    }
+
+    /// Returns the shares a `Notify` allows per epoch, 0 (unlimited) if the pool sets no cap.
+    /// Clients may size their work queues from it and the target.
+    pub fn max_shares(&self) -> u32 {
+        match self {
+            Self::Notify(.., tlvs) => match find_tlv(tlvs, TAG_MAX_SHARES) {
+                Some(&[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]),
+                _ => 0,
+            },
+            _ => 0,
+        }
+    }
+
+    /// Returns the lowest solution target an accepted `ConnectAck` allows, 0 if the pool sets no floor.
+    /// Clients drop solutions below it before building a `Submit`.
+    pub fn min_target(&self) -> u64 {
+        match self {
+            Self::ConnectAck(true, .., tlvs) => match find_tlv(tlvs, TAG_MIN_TARGET) {
+                Some(&[a, b, c, d, e, f, g, h]) => u64::from_le_bytes([a, b, c, d, e, f, g, h]),
+                _ => 0,
+            },
+            _ => 0,
+        }
+    }
+
+    /// Returns the jobs := (job_id, share_target, epoch_challenge) of a `Notify`, a `NotifyBatch`
+    /// or the job in a `ConnectAck`. Clients replace their whole job set with the result, so miners
+    /// never see half of a batch.
+    pub fn jobs(&self) -> Vec<(u64, u64, &EpochChallenge<N>)> {
+        match self {
+            Self::Notify(job_id, _, target, epoch_challenge, _)
+            | Self::ConnectAck(.., Some((job_id, target, epoch_challenge)), _) => {
+                vec![(*job_id, *target, epoch_challenge)]
+            }
+            Self::NotifyBatch(jobs) => jobs
+                .iter()
+                .map(|(job_id, target, epoch_challenge)| (*job_id, *target, epoch_challenge))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_difficulty_target() -> anyhow::Result<()> {
+        let difficulty = Difficulty::new(1_500_000);
+        assert_eq!(Difficulty::from_target(difficulty.to_target()), difficulty);
+
+        let epoch_challenge = EpochChallenge::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let block_difficulty = Difficulty::new(u64::MAX / 2);
+        let message = PoolMessageSC::<CurrentNetwork>::notify(
+            7,
+            block_difficulty,
+            difficulty,
+            epoch_challenge,
+        );
+        assert!(matches!(
+            message,
+            PoolMessageSC::Notify(7, target, 1_500_000, ..) if target == u64::MAX / 2
+        ));
+        let bytes = message.to_vec()?;
+        let message = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(message.difficulty(), Some(difficulty));
+        assert_eq!(message.block_difficulty(), Some(block_difficulty));
+        assert_eq!(message.jobs()[0].1, 1_500_000);
+        assert_eq!(
+            PoolMessageSC::<CurrentNetwork>::Pong(None).difficulty(),
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_shares() -> anyhow::Result<()> {
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let message = PoolMessageSC::Notify(
+            7,
+            1_000,
+            1_000,
+            epoch_challenge.clone(),
+            vec![max_shares_tlv(64)],
+        );
+        let bytes = message.to_vec()?;
+        let message = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(message.max_shares(), 64);
+
+        // Older pools send no cap, and the frame is unchanged without one.
+        let message = PoolMessageSC::<CurrentNetwork>::notify(
+            7,
+            Difficulty::new(1_000),
+            Difficulty::new(1_000),
+            epoch_challenge,
+        );
+        let bytes = message.to_vec()?;
+        let message = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(message.max_shares(), 0);
+        assert_eq!(PoolMessageSC::<CurrentNetwork>::Pong(None).max_shares(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_target() -> anyhow::Result<()> {
+        let address = Address::<CurrentNetwork>::new(Group::generator());
+        let message = PoolMessageSC::<CurrentNetwork>::ConnectAck(
+            true,
+            address,
+            Some(7),
+            Some(String::from("testsignature")),
+            None,
+            vec![min_target_tlv(1_000)],
+        );
+        let bytes = message.to_vec()?;
+        let message = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(message.min_target(), 1_000);
+
+        // Older pools set no floor, and rejecting acks never carry one.
+        let message = PoolMessageSC::<CurrentNetwork>::ConnectAck(
+            true,
+            address,
+            Some(7),
+            Some(String::from("testsignature")),
+            None,
+            vec![],
+        );
+        assert_eq!(message.min_target(), 0);
+        let message = PoolMessageSC::<CurrentNetwork>::ConnectAck(
+            false,
+            address,
+            None,
+            None,
+            None,
+            vec![min_target_tlv(1_000)],
+        );
+        assert_eq!(message.min_target(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_jobs() -> anyhow::Result<()> {
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let message = PoolMessageSC::notify(
+            7,
+            Difficulty::new(u64::MAX),
+            Difficulty::new(1_000),
+            epoch_challenge.clone(),
+        );
+        assert_eq!(message.jobs(), vec![(7, 1_000, &epoch_challenge)]);
+        let message = PoolMessageSC::NotifyBatch(vec![
+            (8, 1_000, epoch_challenge.clone()),
+            (9, 2_000, epoch_challenge.clone()),
+        ]);
+        assert_eq!(
+            message.jobs(),
+            vec![(8, 1_000, &epoch_challenge), (9, 2_000, &epoch_challenge)]
+        );
+        assert!(PoolMessageSC::<CurrentNetwork>::Pong(None)
+            .jobs()
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_difficulty_display() {
+        assert_eq!(Difficulty::new(0).to_string(), "0");
+        assert_eq!(Difficulty::new(999).to_string(), "999");
+        assert_eq!(Difficulty::new(1_500).to_string(), "1.50K");
+        assert_eq!(Difficulty::new(1_500_000).to_string(), "1.50M");
+        assert_eq!(Difficulty::new(u64::MAX).to_string(), "18.45E");
+    }
+}
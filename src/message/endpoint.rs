@@ -0,0 +1,172 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{capabilities::*, poolmessage::*};
+use snarkvm::prelude::Network;
+
+use std::time::Duration;
+
+/// The round trip assumed for an endpoint that has not answered a `Ping` yet.
+pub const DEFAULT_ENDPOINT_RTT: Duration = Duration::from_millis(250);
+
+/// The score added per unit of load factor, so a saturated gateway (255) costs about 0.5 s.
+pub const LOAD_FACTOR_PENALTY: Duration = Duration::from_millis(2);
+
+/// The score added per consecutive failed connection attempt.
+pub const FAILURE_PENALTY: Duration = Duration::from_secs(5);
+
+impl<N: Network> PoolMessageSC<N> {
+    /// Returns a `Pong` reporting the gateway's load if the client negotiated `CAP_LOAD_FACTOR`.
+    pub fn pong(load_factor: u8, capabilities: u32) -> Self {
+        match capabilities & CAP_LOAD_FACTOR {
+            0 => Self::Pong(None),
+            _ => Self::Pong(Some(load_factor)),
+        }
+    }
+
+    /// Returns the load factor of a `Pong`, `None` for older pools.
+    pub fn load_factor(&self) -> Option<u8> {
+        match self {
+            Self::Pong(load_factor) => *load_factor,
+            _ => None,
+        }
+    }
+}
+
+/// What a client knows about one configured pool endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    pub address: String,
+    pub rtt: Option<Duration>,
+    pub load_factor: u8,
+    pub failures: u32,
+}
+
+impl Endpoint {
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            rtt: None,
+            load_factor: 0,
+            failures: 0,
+        }
+    }
+
+    /// Returns the endpoint's cost, lower is better:
+    /// rtt + load_factor * `LOAD_FACTOR_PENALTY` + failures * `FAILURE_PENALTY`.
+    pub fn score(&self) -> Duration {
+        self.rtt.unwrap_or(DEFAULT_ENDPOINT_RTT)
+            + LOAD_FACTOR_PENALTY * self.load_factor as u32
+            + FAILURE_PENALTY.saturating_mul(self.failures)
+    }
+}
+
+/// Client-side scoring of the configured endpoints, used to pick one on reconnect.
+#[derive(Clone, Debug, Default)]
+pub struct Endpoints {
+    endpoints: Vec<Endpoint>,
+}
+
+impl Endpoints {
+    pub fn new<I: IntoIterator<Item = String>>(addresses: I) -> Self {
+        Self {
+            endpoints: addresses.into_iter().map(Endpoint::new).collect(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Endpoint> {
+        self.endpoints.get(index)
+    }
+
+    /// Records the round trip of a `Ping` and the load factor of its `Pong`.
+    /// Pongs from older pools leave the last known load factor.
+    pub fn record_pong<N: Network>(
+        &mut self,
+        index: usize,
+        rtt: Duration,
+        pong: &PoolMessageSC<N>,
+    ) {
+        if let Some(endpoint) = self.endpoints.get_mut(index) {
+            endpoint.rtt = Some(rtt);
+            endpoint.failures = 0;
+            if let Some(load_factor) = pong.load_factor() {
+                endpoint.load_factor = load_factor;
+            }
+        }
+    }
+
+    /// Records a failed connection attempt.
+    pub fn record_failure(&mut self, index: usize) {
+        if let Some(endpoint) = self.endpoints.get_mut(index) {
+            endpoint.failures = endpoint.failures.saturating_add(1);
+        }
+    }
+
+    /// Returns the index of the endpoint to connect to, the lowest score first and the
+    /// configuration order on ties.
+    pub fn choose(&self) -> Option<usize> {
+        (0..self.endpoints.len()).min_by_key(|index| self.endpoints[*index].score())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    fn endpoints() -> Endpoints {
+        Endpoints::new(["pool-a:4040".to_string(), "pool-b:4040".to_string()])
+    }
+
+    #[test]
+    fn test_pong_load_factor() {
+        let pong = PoolMessageSC::<CurrentNetwork>::pong(200, CAP_LOAD_FACTOR);
+        assert_eq!(pong.load_factor(), Some(200));
+        let pong = PoolMessageSC::<CurrentNetwork>::pong(200, CAP_SEQUENCE);
+        assert_eq!(pong.load_factor(), None);
+    }
+
+    #[test]
+    fn test_load_factor_deprioritizes_endpoint() {
+        let mut endpoints = endpoints();
+        assert_eq!(endpoints.choose(), Some(0));
+
+        let loaded = PoolMessageSC::<CurrentNetwork>::Pong(Some(255));
+        let idle = PoolMessageSC::<CurrentNetwork>::Pong(Some(0));
+        endpoints.record_pong(0, Duration::from_millis(20), &loaded);
+        endpoints.record_pong(1, Duration::from_millis(80), &idle);
+        assert_eq!(endpoints.choose(), Some(1));
+
+        // Once the first gateway recovers its lower RTT wins again.
+        endpoints.record_pong(0, Duration::from_millis(20), &idle);
+        assert_eq!(endpoints.choose(), Some(0));
+
+        // Older pools keep the last known load.
+        let legacy = PoolMessageSC::<CurrentNetwork>::Pong(None);
+        endpoints.record_pong(1, Duration::from_millis(80), &legacy);
+        assert_eq!(endpoints.get(1).unwrap().load_factor, 0);
+    }
+
+    #[test]
+    fn test_failures_deprioritize_endpoint() {
+        let mut endpoints = endpoints();
+        endpoints.record_failure(0);
+        assert_eq!(endpoints.choose(), Some(1));
+        endpoints.record_pong(
+            0,
+            Duration::from_millis(20),
+            &PoolMessageSC::<CurrentNetwork>::Pong(None),
+        );
+        assert_eq!(endpoints.choose(), Some(0));
+        assert_eq!(Endpoints::default().choose(), None);
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::handshake::HandshakeState;
+use thiserror::Error;
+
+/// Protocol level errors callers may want to tell apart from plain I/O failures.
+/// Decoders wrap them in an `InvalidData` `std::io::Error`.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum PoolProtocolError {
+    /// One or more frames were skipped.
+    #[error("Sequence gap: expected {expected}, received {received}")]
+    SequenceGap { expected: u32, received: u32 },
+    /// A frame was repeated or replayed.
+    #[error("Sequence rewind: expected {expected}, received {received}")]
+    SequenceRewind { expected: u32, received: u32 },
+    /// An authenticated frame is too short to hold its tag.
+    #[error("Frame is missing its authentication tag")]
+    MissingAuthTag,
+    /// The authentication tag does not match the frame.
+    #[error("Frame authentication failed")]
+    AuthenticationFailed,
+    /// A frame's CRC32C checksum does not match, most likely corrupted on the line.
+    #[error("Frame checksum mismatch: expected {expected:#010x}, found {found:#010x}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// A frame is stamped with a protocol version other than the negotiated one,
+    /// e.g. by a newer peer, see `CAP_VERSION_BYTE`.
+    #[error("Unsupported protocol version {0} in frame header")]
+    UnsupportedVersion(u8),
+    /// A frame arrived on a channel the receiver does not handle.
+    #[error("Unexpected frame on channel {0}")]
+    UnexpectedChannel(u16),
+    /// A `Notify` carries an epoch challenge of the wrong size or degree.
+    #[error("Invalid epoch challenge: {0}")]
+    InvalidEpochChallenge(String),
+    /// A message id belongs to the other direction, see `Direction`.
+    #[error("Message ID {0} belongs to the other direction")]
+    WrongDirection(u8),
+    /// A resumed session's signature has expired.
+    #[error("Session of worker {0} has expired")]
+    SessionExpired(u32),
+    /// A resumed session's signature was already spent by an earlier `ResumeSession`.
+    #[error("Session of worker {0} was already resumed with this signature")]
+    SessionReplayed(u32),
+    /// A resumed session's signature was not issued by this pool for this worker.
+    #[error("Invalid session signature for worker {0}")]
+    InvalidSessionSignature(u32),
+    /// A `ConnectAck` is not an accepted, signed ack from the expected pool address.
+    #[error("Invalid ConnectAck")]
+    InvalidConnectAck,
+    /// A `Hello` or `HelloAck` sets capability bits this library does not define.
+    #[error("Reserved capability bits set: {0:#x}")]
+    ReservedCapabilities(u32),
+    /// A message needs capabilities the peers did not agree on, e.g. a signed `Submit`
+    /// without `CAP_SIGNED_SHARES`.
+    #[error("Message {name} needs capabilities {capabilities:#x}, which were not agreed")]
+    CapabilityNotAgreed { name: String, capabilities: u32 },
+    /// A `Submit` carries no signature but the pool requires one.
+    #[error("Submit is not signed")]
+    MissingSubmitSignature,
+    /// A `Submit` signature does not match the solution or the registered address.
+    #[error("Invalid submit signature")]
+    InvalidSubmitSignature,
+    /// A `Connect` with an Aleo address carries no address proof but the pool requires one.
+    #[error("Connect is not signed")]
+    MissingConnectSignature,
+    /// A `Connect` address proof was not made by the key behind its address.
+    #[error("Invalid Connect signature")]
+    InvalidConnectSignature,
+    /// A `Connect` address proof is older, or further ahead, than the allowed clock skew.
+    #[error("Connect timestamp {0} is outside the allowed skew")]
+    StaleConnectTimestamp(u64),
+    /// A `Connect` payout split is malformed or its weights do not sum to 10000.
+    #[error("Invalid payout split: {0}")]
+    InvalidPayoutSplit(String),
+    /// A `Connect` was made for another network than the pool's.
+    #[error("Network mismatch: expected {expected}, received {received}")]
+    NetworkMismatch { expected: u16, received: u16 },
+    /// A `Connect` string field is not valid UTF-8. Pools may answer with a rejecting
+    /// `ConnectAck`, see `PoolMessageSC::invalid_address_encoding`, before closing.
+    #[error("Invalid UTF-8 in {0}")]
+    InvalidUtf8(String),
+    /// A message id this build does not know, e.g. from a newer peer, see `UnknownIdPolicy`.
+    #[error("Invalid message ID {0}")]
+    UnknownMessageId(u8),
+    /// A frame's length prefix exceeds the decoder's limit, `MAXIMUM_MESSAGE_SIZE` by default,
+    /// see `default_frame_limit`.
+    #[error("Frame of length {0} is too large.")]
+    FrameTooLarge(usize),
+    /// A varint length prefix is longer than 5 bytes, overflows a u32 or is not minimal,
+    /// see `CAP_VARINT_LENGTH`.
+    #[error("Invalid varint length prefix")]
+    InvalidLengthPrefix,
+    /// A message body exceeds the limit of its id, see `FrameLimits`.
+    #[error("Message {id} of length {length} is too large")]
+    MessageTooLarge { id: u8, length: usize },
+    /// The encoder refused a message the peer's decoder would reject as too large,
+    /// its `length` measured like the decoder's `limit`.
+    #[error("Message {name} of length {length} exceeds the limit of {limit} by {}", .length - .limit)]
+    EncodeTooLarge {
+        name: String,
+        length: usize,
+        limit: usize,
+    },
+    /// A chunk is out of range or disagrees with the earlier chunks of its transfer.
+    #[error("Invalid chunk {index}/{total} of transfer {transfer_id}")]
+    InvalidChunk {
+        transfer_id: u32,
+        index: u16,
+        total: u16,
+    },
+    /// A chunk would open more transfers than the receiver keeps.
+    #[error("Too many concurrent chunked transfers")]
+    TooManyTransfers,
+    /// A chunked message exceeds `MAXIMUM_REASSEMBLED_SIZE`.
+    #[error("Chunked message of {0} bytes is too large")]
+    TransferTooLarge(usize),
+    /// A frame sets flag bits the receiver does not know, or compression this build cannot undo,
+    /// see `CAP_ZSTD`.
+    #[error("Invalid frame flags {0:#04x}")]
+    InvalidFrameFlags(u8),
+    /// A compressed body is not a valid zstd stream.
+    #[error("Invalid compressed frame")]
+    InvalidCompression,
+    /// A compressed body inflates past the decoder's limit.
+    #[error("Compressed frame inflates past {0} bytes")]
+    DecompressedTooLarge(usize),
+    /// The peer closed the connection in the middle of a frame, with `pending` bytes buffered.
+    /// `length` is the declared frame length if its prefix had arrived.
+    #[error(
+        "Connection closed with {pending} bytes of a frame pending, declared length {length:?}"
+    )]
+    TruncatedFrame {
+        pending: usize,
+        length: Option<usize>,
+    },
+}
+
+impl PoolProtocolError {
+    /// Returns the protocol error carried by a decoder error, if any.
+    pub fn from_io_error(error: &std::io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
+
+    /// Returns a short, stable name for the kind of error, e.g. to tag logs or metrics.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::SequenceGap { .. } | Self::SequenceRewind { .. } => "sequence",
+            Self::MissingAuthTag | Self::AuthenticationFailed => "authentication",
+            Self::ChecksumMismatch { .. } => "checksum",
+            Self::UnsupportedVersion(..) => "version",
+            Self::UnexpectedChannel(..) => "channel",
+            Self::InvalidEpochChallenge(..) => "epoch_challenge",
+            Self::WrongDirection(..) => "direction",
+            Self::SessionExpired(..)
+            | Self::SessionReplayed(..)
+            | Self::InvalidSessionSignature(..) => "session",
+            Self::InvalidConnectAck => "connect_ack",
+            Self::ReservedCapabilities(..) | Self::CapabilityNotAgreed { .. } => "capabilities",
+            Self::MissingSubmitSignature | Self::InvalidSubmitSignature => "submit_signature",
+            Self::MissingConnectSignature
+            | Self::InvalidConnectSignature
+            | Self::StaleConnectTimestamp(..) => "connect_signature",
+            Self::InvalidPayoutSplit(..) => "payout_split",
+            Self::NetworkMismatch { .. } => "network",
+            Self::InvalidUtf8(..) => "utf8",
+            Self::UnknownMessageId(..) => "unknown_id",
+            Self::FrameTooLarge(..)
+            | Self::InvalidLengthPrefix
+            | Self::MessageTooLarge { .. }
+            | Self::EncodeTooLarge { .. } => "frame_size",
+            Self::InvalidChunk { .. } | Self::TooManyTransfers | Self::TransferTooLarge(..) => {
+                "chunk"
+            }
+            Self::InvalidFrameFlags(..)
+            | Self::InvalidCompression
+            | Self::DecompressedTooLarge(..) => "compression",
+            Self::TruncatedFrame { .. } => "truncated",
+        }
+    }
+}
+
+/// Reports a frame that failed to decode, tagged with its error category: a `warn` event under
+/// the `tracing` feature and a count under `metrics`. Errors without a protocol cause, e.g. a
+/// truncated body, are `malformed`.
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+pub(crate) fn report_decode_error(error: &std::io::Error) {
+    let category =
+        PoolProtocolError::from_io_error(error).map_or("malformed", PoolProtocolError::category);
+    #[cfg(feature = "tracing")]
+    tracing::warn!(category, %error, "Failed to decode a pool message");
+    #[cfg(feature = "metrics")]
+    super::metrics::MESSAGE_METRICS.record_error(category);
+}
+
+/// A message that is valid on the wire but not at this point of the conversation.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum ProtocolViolation {
+    /// The message is not allowed in the current handshake state.
+    #[error("Unexpected '{message}' while {state:?}")]
+    UnexpectedMessage {
+        state: HandshakeState,
+        message: String,
+    },
+    /// The client sent a second `Connect`, `ResumeSession` or `Resume`.
+    #[error("Duplicate 'Connect'")]
+    DuplicateConnect,
+    /// The pool sent a second `ConnectAck`.
+    #[error("Duplicate 'ConnectAck'")]
+    DuplicateConnectAck,
+    /// A message followed `ShutDown`, `Kick`, `DisConnect`, `Bye` or a rejecting `ConnectAck`.
+    #[error("Unexpected '{0}' after the connection was closed")]
+    AfterClose(String),
+}
+
+/// Wraps a deserialization error for a decoder, keeping protocol errors typed.
+pub(crate) fn invalid_data(error: anyhow::Error) -> std::io::Error {
+    match error.downcast::<PoolProtocolError>() {
+        Ok(error) => error.into(),
+        Err(error) => std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+    }
+}
+
+impl From<PoolProtocolError> for std::io::Error {
+    fn from(error: PoolProtocolError) -> Self {
+        let kind = match error {
+            PoolProtocolError::TruncatedFrame { .. } => std::io::ErrorKind::UnexpectedEof,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
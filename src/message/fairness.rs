@@ -0,0 +1,157 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Far above the messages an ordinary peer has buffered at once, so only floods are cut short.
+pub const DEFAULT_MAX_ITEMS_PER_POLL: usize = 1024;
+
+/// A stream in front of `inner`, typically a `FramedRead` or `Framed`, that returns `Pending`
+/// after yielding `max_items_per_poll` items in a row, waking itself first. A peer flooding tiny
+/// frames then lets the other connections on the task run, while frames already buffered are
+/// still decoded on the next poll without waiting for more bytes from the peer.
+/// Sinks are passed through, so a wrapped `Framed` can still be written to.
+#[derive(Debug)]
+pub struct FairStream<S> {
+    inner: S,
+    max_items_per_poll: usize,
+    items_this_poll: usize,
+}
+
+impl<S> FairStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            max_items_per_poll: DEFAULT_MAX_ITEMS_PER_POLL,
+            items_this_poll: 0,
+        }
+    }
+
+    /// Sets how many items are yielded in a row before giving way to other tasks.
+    pub fn with_max_items_per_poll(mut self, max_items_per_poll: usize) -> Self {
+        self.max_items_per_poll = max_items_per_poll.max(1);
+        self
+    }
+
+    pub fn max_items_per_poll(&self) -> usize {
+        self.max_items_per_poll
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Stream + Unpin> Stream for FairStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.items_this_poll >= this.max_items_per_poll {
+            this.items_this_poll = 0;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let item = Pin::new(&mut this.inner).poll_next(cx);
+        match item {
+            Poll::Ready(Some(_)) => this.items_this_poll += 1,
+            _ => this.items_this_poll = 0,
+        }
+        item
+    }
+}
+
+impl<S: Sink<M> + Unpin, M> Sink<M> for FairStream<S> {
+    type Error = S::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: M) -> Result<(), Self::Error> {
+        Pin::new(&mut self.get_mut().inner).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{codec::*, poolmessage::*};
+    use anyhow::Result;
+    use futures::{FutureExt, StreamExt};
+    use snarkvm::prelude::Testnet3;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::{Encoder, FramedRead};
+    type CurrentNetwork = Testnet3;
+
+    #[tokio::test]
+    async fn test_buffered_frames_arrive_without_more_input() -> Result<()> {
+        let count = DEFAULT_MAX_ITEMS_PER_POLL + 6;
+        let mut frames = bytes::BytesMut::new();
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        for _ in 0..count {
+            encoder.encode(PoolMessageCS::Ping, &mut frames)?;
+        }
+        // The peer stays connected but idle once its frames are written.
+        let (mut writer, reader) = tokio::io::duplex(frames.len());
+        writer.write_all(&frames).await?;
+
+        let mut reader = FairStream::new(FramedRead::new(
+            reader,
+            PoolCodecCS::<CurrentNetwork>::new(),
+        ));
+        let mut decoded = 0;
+        while decoded < count {
+            let message = tokio::time::timeout(Duration::from_secs(5), reader.next()).await?;
+            assert!(matches!(message, Some(Ok(PoolMessageCS::Ping))));
+            decoded += 1;
+        }
+        drop(writer);
+        assert!(reader.next().await.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_yields_after_max_items() {
+        let mut stream = FairStream::new(futures::stream::iter(0..5)).with_max_items_per_poll(2);
+        let mut polls = vec![vec![]];
+        loop {
+            match stream.next().now_or_never() {
+                Some(Some(item)) => polls.last_mut().unwrap().push(item),
+                Some(None) => break,
+                None => polls.push(vec![]),
+            }
+        }
+        assert_eq!(polls, [vec![0, 1], vec![2, 3], vec![4]]);
+    }
+}
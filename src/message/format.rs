@@ -0,0 +1,361 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{capabilities::*, chunk::*, codec::*, error::*, version::*};
+
+use ::bytes::Bytes;
+use anyhow::Result;
+
+/// How message bodies are encoded inside frames.
+/// The handshake is always binary; `Json` applies once `CAP_JSON` is negotiated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    /// The binary layout := << id, data >>
+    #[default]
+    Binary,
+    /// A JSON object tagged by message name, with base64 for solution, challenge and chunk bytes.
+    Json,
+}
+
+impl WireFormat {
+    /// Returns the format selected by the negotiated capabilities.
+    pub fn from_capabilities(capabilities: u32) -> Self {
+        match capabilities & CAP_JSON != 0 {
+            true => Self::Json,
+            false => Self::Binary,
+        }
+    }
+
+    /// Serializes a message body.
+    pub fn serialize<M: WireMessage>(&self, message: &M, writer: &mut Vec<u8>) -> Result<()> {
+        self.serialize_versioned(ProtocolVersion::V1, message, writer)
+    }
+
+    /// Serializes a message body, binary bodies in the layout of `version`.
+    pub fn serialize_versioned<M: WireMessage>(
+        &self,
+        version: ProtocolVersion,
+        message: &M,
+        writer: &mut Vec<u8>,
+    ) -> Result<()> {
+        match self {
+            Self::Binary => message.serialize_versioned_into(version, writer),
+            Self::Json => {
+                writer.extend_from_slice(&message.to_json()?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Deserializes a message body. Binary ids of the other direction are rejected with
+    /// `WrongDirection`, JSON messages of the other direction are unknown types.
+    pub fn deserialize<M: WireMessage>(&self, buffer: &[u8]) -> Result<M> {
+        self.deserialize_versioned(ProtocolVersion::V1, buffer)
+    }
+
+    /// Deserializes a message body, binary bodies laid out in `version`.
+    pub fn deserialize_versioned<M: WireMessage>(
+        &self,
+        version: ProtocolVersion,
+        buffer: &[u8],
+    ) -> Result<M> {
+        match self {
+            Self::Binary => {
+                check_direction::<M>(buffer)?;
+                M::deserialize_versioned(version, buffer)
+            }
+            Self::Json => M::from_json(buffer),
+        }
+    }
+
+    /// Deserializes a message body like `deserialize_versioned`, letting binary messages keep
+    /// slices of `buffer`, see `WireMessage::deserialize_versioned_bytes`.
+    pub fn deserialize_versioned_bytes<M: WireMessage>(
+        &self,
+        version: ProtocolVersion,
+        buffer: Bytes,
+    ) -> Result<M> {
+        match self {
+            Self::Binary => {
+                check_direction::<M>(&buffer)?;
+                M::deserialize_versioned_bytes(version, buffer)
+            }
+            Self::Json => M::from_json(&buffer),
+        }
+    }
+
+    /// Returns the most message bytes one chunk carries in this format.
+    pub fn chunk_payload_size(&self) -> usize {
+        match self {
+            Self::Binary => CHUNK_PAYLOAD_SIZE,
+            Self::Json => JSON_CHUNK_PAYLOAD_SIZE,
+        }
+    }
+}
+
+/// Rejects a binary body whose id belongs to the other direction.
+pub(crate) fn check_direction<M: WireMessage>(buffer: &[u8]) -> Result<(), PoolProtocolError> {
+    match buffer.first() {
+        Some(&id) if Direction::of(id) != M::DIRECTION => {
+            Err(PoolProtocolError::WrongDirection(id))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{poolmessage::*, signature::*, tlv::*};
+    use ::bytes::BytesMut;
+    use ::rand::thread_rng;
+    use snarkvm::prelude::*;
+    use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+    use tokio_util::codec::{Decoder, Encoder};
+    type CurrentNetwork = Testnet3;
+
+    const FORMATS: [WireFormat; 2] = [WireFormat::Binary, WireFormat::Json];
+
+    fn sc_messages() -> Result<Vec<PoolMessageSC<CurrentNetwork>>> {
+        let address = Address::new(Uniform::rand(&mut thread_rng()));
+        let epoch_challenge = EpochChallenge::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        Ok(vec![
+            PoolMessageSC::ConnectAck(
+                true,
+                address,
+                Some(1),
+                Some(String::from("testsignature")),
+                None,
+                vec![Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0])],
+            ),
+            PoolMessageSC::ConnectAck(
+                true,
+                address,
+                Some(1),
+                Some(String::from("testsignature")),
+                Some((7, u64::MAX, epoch_challenge.clone())),
+                vec![Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0])],
+            ),
+            PoolMessageSC::ConnectAck(false, address, None, None, None, vec![]),
+            PoolMessageSC::Notify(7, u64::MAX, u64::MAX, epoch_challenge.clone(), vec![]),
+            PoolMessageSC::ShutDown,
+            PoolMessageSC::Pong(None),
+            PoolMessageSC::Pong(Some(200)),
+            PoolMessageSC::Throttle(500),
+            PoolMessageSC::Chunk(7, 1, 3, vec![1, 2, 3]),
+            PoolMessageSC::NonceRange(7, 0, 1 << 32),
+            PoolMessageSC::HelloAck(CAP_SEQUENCE | CAP_JSON),
+            PoolMessageSC::Pause(600),
+            PoolMessageSC::ResumeMining,
+            PoolMessageSC::Drain(30),
+            PoolMessageSC::UpgradeTlsAck(true),
+            PoolMessageSC::JobHint(7, 250_000),
+            PoolMessageSC::QueryInfo,
+            PoolMessageSC::ShareAckBatch(vec![(1, SHARE_ACCEPTED), (2, SHARE_STALE)]),
+            PoolMessageSC::NotifyBatch(vec![(8, 1_000, epoch_challenge)]),
+            PoolMessageSC::RequestProof(7),
+            PoolMessageSC::Kick(1, "invalid shares".to_string()),
+            PoolMessageSC::Bye,
+        ])
+    }
+
+    fn cs_messages() -> Result<Vec<PoolMessageCS<CurrentNetwork>>> {
+        let rng = &mut thread_rng();
+        let private_key = PrivateKey::new(rng)?;
+        let partial_solution = PartialSolution::new(
+            Address::try_from(&private_key)?,
+            u64::rand(rng),
+            KZGCommitment(rng.gen()),
+        );
+        let prover_solution = ProverSolution::new(
+            partial_solution,
+            KZGProof {
+                w: rng.gen(),
+                random_v: None,
+            },
+        );
+        let signature = sign_solution(&private_key, &prover_solution, rng)?;
+        Ok(vec![
+            PoolMessageCS::Connect(
+                0,
+                1,
+                0,
+                1,
+                0,
+                "my_worker_1".to_string(),
+                "215587407@qq.com".to_string(),
+                vec![Tlv::new(TAG_CAPABILITIES, vec![31, 0, 0, 0])],
+            ),
+            PoolMessageCS::Submit(1, 2, 3, Data::Object(prover_solution), None),
+            PoolMessageCS::Submit(1, 0, 3, Data::Object(prover_solution), Some(signature)),
+            PoolMessageCS::DisConnect(1),
+            PoolMessageCS::Ping,
+            PoolMessageCS::JobStatus(1, u64::MAX),
+            PoolMessageCS::RegisterWorker(3, "rig-03".to_string()),
+            PoolMessageCS::Chunk(7, 0, 1, vec![]),
+            PoolMessageCS::ResumeSession(1, "testaddress".to_string(), "testsignature".to_string()),
+            PoolMessageCS::Resume(u64::MAX),
+            PoolMessageCS::Hello(SUPPORTED_CAPABILITIES),
+            PoolMessageCS::UpgradeTls,
+            PoolMessageCS::InfoReport("zkwork-agent".to_string(), "1.2.0".to_string()),
+            PoolMessageCS::SetNotifyInterval(5_000),
+            PoolMessageCS::SubmitCommitment(7, 1, prover_solution.commitment()),
+            PoolMessageCS::PowerStats(7, 350, 1_200),
+            PoolMessageCS::NotifyAck(7, 1_700_000_000_000),
+            PoolMessageCS::SubmitChunk(7, 1, 0, 2, Bytes::from_static(&[1, 2, 3])),
+            PoolMessageCS::Bye,
+        ])
+    }
+
+    /// Encodes, decodes and re-encodes a message, comparing the bytes.
+    fn check_round_trip<M: WireMessage>(format: WireFormat, message: &M) -> Result<()> {
+        let mut bytes = Vec::new();
+        format.serialize(message, &mut bytes)?;
+        let decoded: M = format.deserialize(&bytes)?;
+        let mut bytes_2 = Vec::new();
+        format.serialize(&decoded, &mut bytes_2)?;
+        assert_eq!(bytes, bytes_2, "{:?} {}", format, message.name());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_bytes() -> Result<()> {
+        for message in cs_messages()? {
+            let bytes = Bytes::from(message.to_vec()?).slice(4..);
+            let copied = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes)?;
+            let sliced = PoolMessageCS::<CurrentNetwork>::deserialize_bytes(bytes.clone())?;
+            assert_eq!(sliced.to_vec()?, copied.to_vec()?, "{}", message.name());
+            // The solution is a view of the received frame.
+            if let PoolMessageCS::Submit(.., Data::Buffer(solution), _) = sliced {
+                let range = bytes.as_ptr_range();
+                assert!(range.contains(&solution.as_ptr()));
+                assert_eq!(solution.as_ptr_range().end, range.end);
+            }
+        }
+
+        // Through the decoders too, in either layout.
+        let messages = cs_messages()?;
+        let mut buffer = BytesMut::from(&messages[1].to_vec()?[..]);
+        let range = buffer.as_ptr_range();
+        match PoolCodecCS::<CurrentNetwork>::new().decode(&mut buffer)? {
+            Some(PoolMessageCS::Submit(.., Data::Buffer(solution), _)) => {
+                assert!(range.contains(&solution.as_ptr()))
+            }
+            message => panic!("Unexpected {:?}", message),
+        }
+        for capabilities in [
+            CAP_SIGNED_SHARES,
+            CAP_SIGNED_SHARES | CAP_PROTOCOL_V2 | CAP_SEQUENCE,
+        ] {
+            let mut encoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+            let mut decoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+            encoder.set_capabilities(capabilities);
+            decoder.set_capabilities(capabilities);
+            let mut buffer = BytesMut::new();
+            for message in &messages {
+                encoder.encode(message.clone(), &mut buffer)?;
+            }
+            let range = buffer.as_ptr_range();
+            for message in &messages {
+                let decoded = decoder.decode(&mut buffer)?.unwrap();
+                assert_eq!(decoded.to_vec()?, message.to_vec()?, "{}", message.name());
+                if let PoolMessageCS::Submit(.., Data::Buffer(solution), _) = decoded {
+                    assert!(range.contains(&solution.as_ptr()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_wire_format_round_trip() -> Result<()> {
+        for format in FORMATS {
+            for message in sc_messages()? {
+                check_round_trip(format, &message)?;
+            }
+            for message in cs_messages()? {
+                check_round_trip(format, &message)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_layout() -> Result<()> {
+        let mut bytes = Vec::new();
+        WireFormat::Json.serialize(
+            &PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2),
+            &mut bytes,
+        )?;
+        assert_eq!(
+            std::str::from_utf8(&bytes)?,
+            r#"{"type":"JobStatus","worker_id":1,"job_id":2}"#
+        );
+        let mut bytes = Vec::new();
+        WireFormat::Json.serialize(
+            &PoolMessageSC::<CurrentNetwork>::Chunk(1, 0, 1, vec![1, 2, 3]),
+            &mut bytes,
+        )?;
+        assert_eq!(
+            std::str::from_utf8(&bytes)?,
+            r#"{"type":"Chunk","transfer_id":1,"index":0,"total":1,"bytes":"AQID"}"#
+        );
+
+        // Older pongs keep their layout.
+        let mut bytes = Vec::new();
+        WireFormat::Json.serialize(&PoolMessageSC::<CurrentNetwork>::Pong(None), &mut bytes)?;
+        assert_eq!(std::str::from_utf8(&bytes)?, r#"{"type":"Pong"}"#);
+
+        // Types of the other direction are rejected.
+        assert!(WireFormat::Json
+            .deserialize::<PoolMessageSC<CurrentNetwork>>(br#"{"type":"Ping"}"#)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_switches_to_json() -> Result<()> {
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_SIGNED_SHARES);
+        decoder.set_capabilities(CAP_SIGNED_SHARES);
+        let mut buffer = BytesMut::new();
+        for message in cs_messages()? {
+            encoder.encode(message, &mut buffer)?;
+        }
+        let binary = buffer.len();
+        while decoder.decode(&mut buffer)?.is_some() {}
+
+        // After negotiation every message, chunked or not, travels as JSON.
+        let capabilities = CAP_JSON | CAP_CHUNKS | CAP_SEQUENCE | CAP_SIGNED_SHARES;
+        encoder.set_capabilities(capabilities);
+        decoder.set_capabilities(capabilities);
+        let messages = cs_messages()?
+            .into_iter()
+            .filter(|message| message.name() != "Chunk")
+            .collect::<Vec<_>>();
+        for message in messages.clone() {
+            encoder.encode(message, &mut buffer)?;
+        }
+        assert!(buffer.len() > binary);
+        for message in messages {
+            let decoded = decoder.decode(&mut buffer)?.unwrap();
+            assert_eq!(decoded.name(), message.name());
+            assert_eq!(decoded.to_json()?, message.to_json()?);
+        }
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::codec::*;
+#[cfg(feature = "json-codec")]
+use super::error::*;
+
+#[cfg(feature = "json-codec")]
+use ::bytes::BytesMut;
+#[cfg(feature = "json-codec")]
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Frames messages of type `M` on a byte stream, keeping message semantics apart from framing.
+/// `MessageCodec` is the length-prefixed binary framing peers speak; `JsonPoolCodec`, behind the
+/// `json-codec` feature, writes one JSON message per line for debugging.
+pub trait PoolCodec<M>:
+    Encoder<M, Error = anyhow::Error> + Decoder<Item = M, Error = std::io::Error>
+{
+}
+
+impl<M: WireMessage> PoolCodec<M> for MessageCodec<M> {}
+
+/// The longest line `JsonPoolCodec` accepts by default, enough for any message without chunks.
+#[cfg(feature = "json-codec")]
+pub const MAXIMUM_JSON_LINE_SIZE: usize = 64 * 1024;
+
+/// A newline-delimited JSON framing := << json, "\n" >>, each line the JSON form of one message
+/// as under `CAP_JSON`, so a session can be typed or read in a terminal.
+/// It has no capabilities and is not understood by pools; use it between debugging tools.
+#[cfg(feature = "json-codec")]
+#[derive(Clone, Debug)]
+pub struct JsonPoolCodec<M> {
+    max_line_size: usize,
+    _message: PhantomData<fn() -> M>,
+}
+
+#[cfg(feature = "json-codec")]
+impl<M> Default for JsonPoolCodec<M> {
+    fn default() -> Self {
+        Self {
+            max_line_size: MAXIMUM_JSON_LINE_SIZE,
+            _message: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "json-codec")]
+impl<M> JsonPoolCodec<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the longest line, newline excluded, the decoder accepts.
+    pub fn with_max_line_size(mut self, max_line_size: usize) -> Self {
+        self.max_line_size = max_line_size;
+        self
+    }
+
+    pub fn max_line_size(&self) -> usize {
+        self.max_line_size
+    }
+}
+
+#[cfg(feature = "json-codec")]
+impl<M: WireMessage> Encoder<M> for JsonPoolCodec<M> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: M, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        // Compact JSON escapes every newline inside strings.
+        dst.extend_from_slice(&message.to_json()?);
+        dst.extend_from_slice(b"\n");
+        Ok(())
+    }
+}
+
+#[cfg(feature = "json-codec")]
+impl<M: WireMessage> Decoder for JsonPoolCodec<M> {
+    type Error = std::io::Error;
+    type Item = M;
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let Some(end) = source.iter().position(|&byte| byte == b'\n') else {
+                if source.len() > self.max_line_size {
+                    return Err(PoolProtocolError::FrameTooLarge(source.len()).into());
+                }
+                return Ok(None);
+            };
+            if end > self.max_line_size {
+                return Err(PoolProtocolError::FrameTooLarge(end).into());
+            }
+            let line = source.split_to(end + 1);
+            let line = line[..end].strip_suffix(b"\r").unwrap_or(&line[..end]);
+            // Blank lines keep a typed session readable.
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return M::from_json(line).map(Some).map_err(invalid_data);
+        }
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(source)? {
+            None if !source.is_empty() => Err(PoolProtocolError::TruncatedFrame {
+                pending: source.len(),
+                length: None,
+            }
+            .into()),
+            message => Ok(message),
+        }
+    }
+}
+
+#[cfg(feature = "json-codec")]
+impl<M: WireMessage> PoolCodec<M> for JsonPoolCodec<M> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::poolmessage::*;
+    use ::bytes::BytesMut;
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    /// Round-trips messages through any framing.
+    fn round_trip<C: PoolCodec<PoolMessageSC<CurrentNetwork>>>(
+        mut codec: C,
+    ) -> anyhow::Result<BytesMut> {
+        let mut buffer = BytesMut::new();
+        codec.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        codec.encode(PoolMessageSC::Pong(None), &mut buffer)?;
+        let framed = buffer.clone();
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageSC::Pong(None))
+        ));
+        assert!(codec.decode(&mut buffer)?.is_none());
+        Ok(framed)
+    }
+
+    #[test]
+    fn test_binary_pool_codec() -> anyhow::Result<()> {
+        let framed = round_trip(PoolCodecSC::<CurrentNetwork>::new())?;
+        let mut expected = PoolMessageSC::<CurrentNetwork>::Throttle(500).to_vec()?;
+        expected.extend(PoolMessageSC::<CurrentNetwork>::Pong(None).to_vec()?);
+        assert_eq!(&framed[..], &expected[..]);
+        Ok(())
+    }
+
+    #[cfg(feature = "json-codec")]
+    #[test]
+    fn test_json_pool_codec() -> anyhow::Result<()> {
+        let framed = round_trip(JsonPoolCodec::<PoolMessageSC<CurrentNetwork>>::new())?;
+        let text = std::str::from_utf8(&framed)?;
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.ends_with("{\"type\":\"Pong\"}\n"));
+
+        // Blank lines and CRLF endings are accepted, a partial line waits for its newline.
+        let mut codec = JsonPoolCodec::<PoolMessageSC<CurrentNetwork>>::new();
+        let mut buffer = BytesMut::from(&b"\r\n{\"type\":\"Pong\"}\r\n{\"type\""[..]);
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageSC::Pong(None))
+        ));
+        assert!(codec.decode(&mut buffer)?.is_none());
+        assert_eq!(&buffer[..], b"{\"type\"");
+
+        // Lines above the limit and messages of the other direction are rejected.
+        let mut codec = JsonPoolCodec::<PoolMessageSC<CurrentNetwork>>::new().with_max_line_size(8);
+        let error = codec
+            .decode(&mut BytesMut::from(&b"{\"type\":\"Pong\"}\n"[..]))
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::FrameTooLarge(15))
+        );
+        let mut codec = JsonPoolCodec::<PoolMessageSC<CurrentNetwork>>::new();
+        assert!(codec
+            .decode(&mut BytesMut::from(&b"{\"type\":\"Ping\"}\n"[..]))
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{error::*, poolmessage::*};
+use snarkvm::prelude::Network;
+
+/// Where a connection is in the Connect → ConnectAck handshake.
+/// Both sides drive it with every message they send or receive, in order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HandshakeState {
+    /// Nothing was exchanged yet, the client must start with `Connect`,
+    /// optionally preceded by `UpgradeTls` → `UpgradeTlsAck` and `Hello` → `HelloAck` exchanges.
+    #[default]
+    AwaitingConnect,
+    /// The client sent `Connect` and waits for the pool's `ConnectAck`.
+    AwaitingConnectAck,
+    /// The pool accepted the worker, jobs and solutions may flow.
+    Established,
+    /// The connection was rejected, shut down or disconnected.
+    Closed,
+}
+
+/// The effect of a valid message on a `HandshakeState`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// The state is unchanged.
+    Stay,
+    /// The connection moves to the given state.
+    To(HandshakeState),
+}
+
+/// A message the handshake state machine can validate.
+pub trait HandshakeMessage {
+    /// Returns the transition this message causes in `state`.
+    fn transition(&self, state: HandshakeState) -> Result<Transition, ProtocolViolation>;
+}
+
+fn unexpected(state: HandshakeState, name: &str) -> ProtocolViolation {
+    match state {
+        HandshakeState::Closed => ProtocolViolation::AfterClose(name.to_string()),
+        state => ProtocolViolation::UnexpectedMessage {
+            state,
+            message: name.to_string(),
+        },
+    }
+}
+
+impl<N: Network> HandshakeMessage for PoolMessageCS<N> {
+    fn transition(&self, state: HandshakeState) -> Result<Transition, ProtocolViolation> {
+        use HandshakeState::*;
+        match (state, self) {
+            (AwaitingConnect, Self::Hello(..) | Self::UpgradeTls) => Ok(Transition::Stay),
+            (AwaitingConnect, Self::Connect(..) | Self::ResumeSession(..) | Self::Resume(..)) => {
+                Ok(Transition::To(AwaitingConnectAck))
+            }
+            (
+                AwaitingConnectAck | Established,
+                Self::Connect(..) | Self::ResumeSession(..) | Self::Resume(..),
+            ) => Err(ProtocolViolation::DuplicateConnect),
+            (
+                AwaitingConnect | AwaitingConnectAck | Established,
+                Self::DisConnect(..) | Self::Bye,
+            ) => Ok(Transition::To(Closed)),
+            (Established, Self::Hello(..) | Self::UpgradeTls) => {
+                Err(unexpected(state, self.name()))
+            }
+            (Established, _) => Ok(Transition::Stay),
+            (state, message) => Err(unexpected(state, message.name())),
+        }
+    }
+}
+
+impl<N: Network> HandshakeMessage for PoolMessageSC<N> {
+    fn transition(&self, state: HandshakeState) -> Result<Transition, ProtocolViolation> {
+        use HandshakeState::*;
+        match (state, self) {
+            (AwaitingConnect, Self::HelloAck(..) | Self::UpgradeTlsAck(..)) => Ok(Transition::Stay),
+            (AwaitingConnectAck, Self::ConnectAck(true, ..)) => Ok(Transition::To(Established)),
+            (AwaitingConnectAck, Self::ConnectAck(false, ..)) => Ok(Transition::To(Closed)),
+            (Established, Self::ConnectAck(..)) => Err(ProtocolViolation::DuplicateConnectAck),
+            (Established, Self::HelloAck(..) | Self::UpgradeTlsAck(..)) => {
+                Err(unexpected(state, self.name()))
+            }
+            (AwaitingConnect | AwaitingConnectAck | Established, Self::ShutDown | Self::Bye) => {
+                Ok(Transition::To(Closed))
+            }
+            (Established, Self::Kick(..)) => Ok(Transition::To(Closed)),
+            (Established, _) => Ok(Transition::Stay),
+            (state, message) => Err(unexpected(state, message.name())),
+        }
+    }
+}
+
+impl HandshakeState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks that `message` may be sent or received in this state.
+    pub fn validate<M: HandshakeMessage>(
+        &self,
+        message: &M,
+    ) -> Result<Transition, ProtocolViolation> {
+        message.transition(*self)
+    }
+
+    /// Validates `message` and applies its transition.
+    /// The state is left unchanged on a violation.
+    pub fn advance<M: HandshakeMessage>(
+        &mut self,
+        message: &M,
+    ) -> Result<Transition, ProtocolViolation> {
+        let transition = self.validate(message)?;
+        if let Transition::To(state) = transition {
+            *self = state;
+        }
+        Ok(transition)
+    }
+
+    /// Returns true once solutions and jobs may be exchanged.
+    pub fn is_established(&self) -> bool {
+        *self == Self::Established
+    }
+
+    /// Returns true once the connection should be dropped.
+    pub fn is_closed(&self) -> bool {
+        *self == Self::Closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::capabilities::*;
+    use ::rand::thread_rng;
+    use anyhow::Result;
+    use snarkvm::prelude::{Address, EpochChallenge, Testnet3, Uniform};
+    use HandshakeState::*;
+    type CurrentNetwork = Testnet3;
+
+    const STATES: [HandshakeState; 4] = [AwaitingConnect, AwaitingConnectAck, Established, Closed];
+
+    fn connect() -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            vec![],
+        )
+    }
+
+    fn connect_ack(is_accept: bool) -> PoolMessageSC<CurrentNetwork> {
+        let address = Address::new(Uniform::rand(&mut thread_rng()));
+        match is_accept {
+            true => PoolMessageSC::ConnectAck(
+                true,
+                address,
+                Some(1),
+                Some(String::from("testsignature")),
+                None,
+                vec![],
+            ),
+            false => PoolMessageSC::ConnectAck(false, address, None, None, None, vec![]),
+        }
+    }
+
+    fn cs_messages() -> Vec<PoolMessageCS<CurrentNetwork>> {
+        vec![
+            connect(),
+            PoolMessageCS::Submit(1, 0, 1, Data::Buffer(vec![0u8; 8].into()), None),
+            PoolMessageCS::DisConnect(1),
+            PoolMessageCS::Ping,
+            PoolMessageCS::JobStatus(1, 1),
+            PoolMessageCS::RegisterWorker(1, "rig-01".to_string()),
+            PoolMessageCS::Chunk(1, 0, 2, vec![]),
+            PoolMessageCS::ResumeSession(1, "testaddress".to_string(), "testsignature".to_string()),
+            PoolMessageCS::Resume(1),
+            PoolMessageCS::Hello(SUPPORTED_CAPABILITIES),
+            PoolMessageCS::UpgradeTls,
+            PoolMessageCS::InfoReport("zkwork-agent".to_string(), "1.2.0".to_string()),
+            PoolMessageCS::SetNotifyInterval(5_000),
+            PoolMessageCS::SubmitCommitment(1, 1, Default::default()),
+            PoolMessageCS::PowerStats(1, 350, 1_200),
+            PoolMessageCS::NotifyAck(1, 1_700_000_000_000),
+            PoolMessageCS::SubmitChunk(1, 1, 0, 2, vec![1, 2, 3].into()),
+            PoolMessageCS::Bye,
+        ]
+    }
+
+    fn sc_messages() -> Result<Vec<PoolMessageSC<CurrentNetwork>>> {
+        let epoch_challenge = EpochChallenge::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        Ok(vec![
+            connect_ack(true),
+            connect_ack(false),
+            PoolMessageSC::Notify(1, 100000, 100000, epoch_challenge.clone(), vec![]),
+            PoolMessageSC::ShutDown,
+            PoolMessageSC::Pong(None),
+            PoolMessageSC::Throttle(500),
+            PoolMessageSC::Chunk(1, 0, 2, vec![]),
+            PoolMessageSC::NonceRange(1, 0, 1 << 32),
+            PoolMessageSC::HelloAck(CAP_SEQUENCE),
+            PoolMessageSC::Pause(600),
+            PoolMessageSC::ResumeMining,
+            PoolMessageSC::Drain(30),
+            PoolMessageSC::UpgradeTlsAck(true),
+            PoolMessageSC::JobHint(7, 250_000),
+            PoolMessageSC::QueryInfo,
+            PoolMessageSC::ShareAckBatch(vec![(1, SHARE_ACCEPTED), (2, SHARE_STALE)]),
+            PoolMessageSC::NotifyBatch(vec![(8, 1_000, epoch_challenge)]),
+            PoolMessageSC::RequestProof(7),
+            PoolMessageSC::Kick(1, "invalid shares".to_string()),
+            PoolMessageSC::Bye,
+        ])
+    }
+
+    /// The expected transition of every (state, message) pair, by message name.
+    fn expected_cs(state: HandshakeState, name: &str) -> Option<Transition> {
+        match (state, name) {
+            (AwaitingConnect, "Hello" | "UpgradeTls") => Some(Transition::Stay),
+            (_, "Hello" | "UpgradeTls") => None,
+            (AwaitingConnect, "Connect" | "ResumeSession" | "Resume") => {
+                Some(Transition::To(AwaitingConnectAck))
+            }
+            (Closed, _) | (_, "Connect" | "ResumeSession" | "Resume") => None,
+            (_, "Disconnect" | "Bye") => Some(Transition::To(Closed)),
+            (Established, _) => Some(Transition::Stay),
+            _ => None,
+        }
+    }
+
+    fn expected_sc(
+        state: HandshakeState,
+        message: &PoolMessageSC<CurrentNetwork>,
+    ) -> Option<Transition> {
+        match (state, message) {
+            (AwaitingConnect, PoolMessageSC::HelloAck(..) | PoolMessageSC::UpgradeTlsAck(..)) => {
+                Some(Transition::Stay)
+            }
+            (_, PoolMessageSC::HelloAck(..) | PoolMessageSC::UpgradeTlsAck(..)) => None,
+            (AwaitingConnectAck, PoolMessageSC::ConnectAck(true, ..)) => {
+                Some(Transition::To(Established))
+            }
+            (AwaitingConnectAck, PoolMessageSC::ConnectAck(false, ..)) => {
+                Some(Transition::To(Closed))
+            }
+            (Closed, _) | (_, PoolMessageSC::ConnectAck(..)) => None,
+            (_, PoolMessageSC::ShutDown | PoolMessageSC::Bye) => Some(Transition::To(Closed)),
+            (Established, PoolMessageSC::Kick(..)) => Some(Transition::To(Closed)),
+            (Established, _) => Some(Transition::Stay),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_handshake_exhaustive() -> Result<()> {
+        for state in STATES {
+            for message in cs_messages() {
+                assert_eq!(
+                    state.validate(&message).ok(),
+                    expected_cs(state, message.name()),
+                    "{:?} {}",
+                    state,
+                    message.name()
+                );
+            }
+            for message in sc_messages()? {
+                assert_eq!(
+                    state.validate(&message).ok(),
+                    expected_sc(state, &message),
+                    "{:?} {}",
+                    state,
+                    message.name()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_handshake_flow() {
+        let mut state = HandshakeState::new();
+        assert_eq!(
+            state.advance(&PoolMessageCS::<CurrentNetwork>::Ping),
+            Err(ProtocolViolation::UnexpectedMessage {
+                state: AwaitingConnect,
+                message: "Ping".to_string()
+            })
+        );
+        assert_eq!(state, AwaitingConnect);
+
+        assert_eq!(
+            state.advance(&connect()),
+            Ok(Transition::To(AwaitingConnectAck))
+        );
+        assert_eq!(
+            state.advance(&PoolMessageCS::<CurrentNetwork>::Submit(
+                1,
+                0,
+                1,
+                Data::Buffer(vec![].into()),
+                None
+            )),
+            Err(ProtocolViolation::UnexpectedMessage {
+                state: AwaitingConnectAck,
+                message: "Submit".to_string()
+            })
+        );
+        assert_eq!(
+            state.advance(&connect()),
+            Err(ProtocolViolation::DuplicateConnect)
+        );
+        assert_eq!(
+            state.advance(&connect_ack(true)),
+            Ok(Transition::To(Established))
+        );
+        assert!(state.is_established());
+        assert_eq!(
+            state.advance(&connect()),
+            Err(ProtocolViolation::DuplicateConnect)
+        );
+        assert_eq!(
+            state.advance(&connect_ack(true)),
+            Err(ProtocolViolation::DuplicateConnectAck)
+        );
+        assert_eq!(
+            state.advance(&PoolMessageCS::<CurrentNetwork>::Ping),
+            Ok(Transition::Stay)
+        );
+        assert_eq!(
+            state.advance(&PoolMessageSC::<CurrentNetwork>::ShutDown),
+            Ok(Transition::To(Closed))
+        );
+        assert!(state.is_closed());
+        assert_eq!(
+            state.advance(&PoolMessageSC::<CurrentNetwork>::Pong(None)),
+            Err(ProtocolViolation::AfterClose("Pong".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_handshake_rejected() {
+        let mut state = HandshakeState::new();
+        state.advance(&connect()).unwrap();
+        assert_eq!(
+            state.advance(&connect_ack(false)),
+            Ok(Transition::To(Closed))
+        );
+        assert!(state.advance(&connect()).is_err());
+    }
+}
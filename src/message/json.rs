@@ -0,0 +1,628 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{poolmessage::*, tlv::*};
+use snarkvm::prelude::*;
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::{primitive::str, str::FromStr};
+
+/// JSON form of a `Tlv` := { tag, value(base64) }
+#[derive(Serialize, Deserialize)]
+struct JsonTlv {
+    tag: u8,
+    value: String,
+}
+
+fn tlvs_to_json(tlvs: &[Tlv]) -> Vec<JsonTlv> {
+    tlvs.iter()
+        .map(|tlv| JsonTlv {
+            tag: tlv.tag,
+            value: BASE64.encode(&tlv.value),
+        })
+        .collect()
+}
+
+fn tlvs_from_json(tlvs: Vec<JsonTlv>, known_tags: &[u8]) -> Result<Vec<Tlv>> {
+    let mut result = Vec::new();
+    for tlv in tlvs {
+        let value = BASE64.decode(tlv.value)?;
+        if value.len() > u16::MAX as usize {
+            return Err(anyhow!(
+                "TLV value for tag {} is too long: {}",
+                tlv.tag,
+                value.len()
+            ));
+        }
+        if known_tags.contains(&tlv.tag) {
+            result.push(Tlv::new(tlv.tag, value));
+        }
+    }
+    Ok(result)
+}
+
+/// JSON form of the initial job in `ConnectAck` := { job_id, target, epoch_challenge(base64) }
+#[derive(Serialize, Deserialize)]
+struct JsonJob {
+    job_id: u64,
+    target: u64,
+    epoch_challenge: String,
+}
+
+fn epoch_challenge_to_json<N: Network>(epoch_challenge: &EpochChallenge<N>) -> Result<String> {
+    Ok(BASE64.encode(epoch_challenge.to_bytes_le()?))
+}
+
+fn epoch_challenge_from_json<N: Network>(epoch_challenge: String) -> Result<EpochChallenge<N>> {
+    let bytes = BASE64.decode(epoch_challenge)?;
+    let mut reader = &bytes[..];
+    let epoch_challenge = read_epoch_challenge(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(anyhow!("Invalid epoch challenge: {:?}", bytes));
+    }
+    Ok(epoch_challenge)
+}
+
+/// JSON form of `PoolMessageSC`, tagged by message name.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum JsonMessageSC {
+    ConnectAck {
+        is_accept: bool,
+        address: String,
+        worker_id: Option<u32>,
+        signature: Option<String>,
+        #[serde(default)]
+        initial_job: Option<JsonJob>,
+        tlvs: Vec<JsonTlv>,
+    },
+    Notify {
+        job_id: u64,
+        target: u64,
+        share_target: u64,
+        epoch_challenge: String,
+        tlvs: Vec<JsonTlv>,
+    },
+    ShutDown,
+    Pong {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        load_factor: Option<u8>,
+    },
+    Throttle {
+        min_submit_interval_ms: u32,
+    },
+    Chunk {
+        transfer_id: u32,
+        index: u16,
+        total: u16,
+        bytes: String,
+    },
+    NonceRange {
+        job_id: u64,
+        start: u64,
+        end: u64,
+    },
+    HelloAck {
+        capabilities: u32,
+    },
+    Pause {
+        max_duration_secs: u32,
+    },
+    ResumeMining,
+    Drain {
+        grace_period_secs: u32,
+    },
+    UpgradeTlsAck {
+        is_accept: bool,
+    },
+    JobHint {
+        job_id: u64,
+        suggested_target_per_device: u64,
+    },
+    QueryInfo,
+    ShareAckBatch {
+        acks: Vec<(u32, u8)>,
+    },
+    NotifyBatch {
+        jobs: Vec<JsonJob>,
+    },
+    RequestProof {
+        work_id: u32,
+    },
+    Kick {
+        worker_id: u32,
+        reason: String,
+    },
+    Bye,
+}
+
+/// JSON form of `PoolMessageCS`, tagged by message name.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum JsonMessageCS {
+    Connect {
+        worker_type: u8,
+        address_type: u8,
+        v_major: u8,
+        v_minor: u8,
+        v_patch: u8,
+        name: String,
+        address: String,
+        tlvs: Vec<JsonTlv>,
+    },
+    Submit {
+        worker_id: u32,
+        sub_worker_id: u32,
+        job_id: u64,
+        prover_solution: String,
+        signature: Option<String>,
+    },
+    DisConnect {
+        worker_id: u32,
+    },
+    Ping,
+    JobStatus {
+        worker_id: u32,
+        job_id: u64,
+    },
+    RegisterWorker {
+        sub_worker_id: u32,
+        name: String,
+    },
+    Chunk {
+        transfer_id: u32,
+        index: u16,
+        total: u16,
+        bytes: String,
+    },
+    ResumeSession {
+        worker_id: u32,
+        address: String,
+        signature: String,
+    },
+    Resume {
+        resume_token: u64,
+    },
+    Hello {
+        capabilities: u32,
+    },
+    UpgradeTls,
+    InfoReport {
+        agent_name: String,
+        version: String,
+    },
+    SetNotifyInterval {
+        min_notify_interval_ms: u32,
+    },
+    SubmitCommitment {
+        work_id: u32,
+        job_id: u64,
+        commitment: String,
+    },
+    PowerStats {
+        worker_id: u32,
+        power_watts: u32,
+        efficiency_mj_per_proof: u32,
+    },
+    NotifyAck {
+        job_id: u64,
+        received_at_ms: u64,
+    },
+    SubmitChunk {
+        work_id: u32,
+        job_id: u64,
+        index: u16,
+        total: u16,
+        bytes: String,
+    },
+    Bye,
+}
+
+impl<N: Network> PoolMessageSC<N> {
+    /// Serializes the message as JSON, with base64 for binary fields.
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        let message = match self {
+            Self::ConnectAck(is_accept, address, worker_id, signature, initial_job, tlvs) => {
+                JsonMessageSC::ConnectAck {
+                    is_accept: *is_accept,
+                    address: address.to_string(),
+                    worker_id: *worker_id,
+                    signature: signature.clone(),
+                    initial_job: match initial_job {
+                        Some((job_id, target, epoch_challenge)) => Some(JsonJob {
+                            job_id: *job_id,
+                            target: *target,
+                            epoch_challenge: epoch_challenge_to_json(epoch_challenge)?,
+                        }),
+                        None => None,
+                    },
+                    tlvs: tlvs_to_json(tlvs),
+                }
+            }
+            Self::Notify(job_id, target, share_target, epoch_challenge, tlvs) => {
+                JsonMessageSC::Notify {
+                    job_id: *job_id,
+                    target: *target,
+                    share_target: *share_target,
+                    epoch_challenge: epoch_challenge_to_json(epoch_challenge)?,
+                    tlvs: tlvs_to_json(tlvs),
+                }
+            }
+            Self::ShutDown => JsonMessageSC::ShutDown,
+            Self::Pong(load_factor) => JsonMessageSC::Pong {
+                load_factor: *load_factor,
+            },
+            Self::Throttle(min_submit_interval_ms) => JsonMessageSC::Throttle {
+                min_submit_interval_ms: *min_submit_interval_ms,
+            },
+            Self::Chunk(transfer_id, index, total, bytes) => JsonMessageSC::Chunk {
+                transfer_id: *transfer_id,
+                index: *index,
+                total: *total,
+                bytes: BASE64.encode(bytes),
+            },
+            Self::NonceRange(job_id, start, end) => JsonMessageSC::NonceRange {
+                job_id: *job_id,
+                start: *start,
+                end: *end,
+            },
+            Self::HelloAck(capabilities) => JsonMessageSC::HelloAck {
+                capabilities: *capabilities,
+            },
+            Self::Pause(max_duration_secs) => JsonMessageSC::Pause {
+                max_duration_secs: *max_duration_secs,
+            },
+            Self::ResumeMining => JsonMessageSC::ResumeMining,
+            Self::Drain(grace_period_secs) => JsonMessageSC::Drain {
+                grace_period_secs: *grace_period_secs,
+            },
+            Self::UpgradeTlsAck(is_accept) => JsonMessageSC::UpgradeTlsAck {
+                is_accept: *is_accept,
+            },
+            Self::JobHint(job_id, suggested_target_per_device) => JsonMessageSC::JobHint {
+                job_id: *job_id,
+                suggested_target_per_device: *suggested_target_per_device,
+            },
+            Self::QueryInfo => JsonMessageSC::QueryInfo,
+            Self::ShareAckBatch(acks) => JsonMessageSC::ShareAckBatch { acks: acks.clone() },
+            Self::NotifyBatch(jobs) => JsonMessageSC::NotifyBatch {
+                jobs: jobs
+                    .iter()
+                    .map(|(job_id, target, epoch_challenge)| {
+                        Ok(JsonJob {
+                            job_id: *job_id,
+                            target: *target,
+                            epoch_challenge: epoch_challenge_to_json(epoch_challenge)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            Self::RequestProof(work_id) => JsonMessageSC::RequestProof { work_id: *work_id },
+            Self::Kick(worker_id, reason) => {
+                if reason.len() > MAXIMUM_KICK_REASON_LENGTH {
+                    return Err(anyhow!("Kick: reason is too long: {}", reason.len()));
+                }
+                JsonMessageSC::Kick {
+                    worker_id: *worker_id,
+                    reason: reason.clone(),
+                }
+            }
+            Self::Bye => JsonMessageSC::Bye,
+            Self::Unused(..) => return Err(anyhow!("Unused message can not be serialized")),
+        };
+        Ok(serde_json::to_vec(&message)?)
+    }
+
+    /// Deserializes a message from its JSON form.
+    pub fn from_json(buffer: &[u8]) -> Result<Self> {
+        let message = match serde_json::from_slice(buffer)? {
+            JsonMessageSC::ConnectAck {
+                is_accept,
+                address,
+                worker_id,
+                signature,
+                initial_job,
+                tlvs,
+            } => {
+                if is_accept && (worker_id.is_none() || signature.is_none()) {
+                    return Err(anyhow!("ConnectAck: Invalid id"));
+                }
+                let initial_job = match initial_job {
+                    Some(JsonJob {
+                        job_id,
+                        target,
+                        epoch_challenge,
+                    }) => Some((job_id, target, epoch_challenge_from_json(epoch_challenge)?)),
+                    None => None,
+                };
+                Self::ConnectAck(
+                    is_accept,
+                    Address::from_str(&address)?,
+                    worker_id,
+                    signature,
+                    initial_job,
+                    tlvs_from_json(tlvs, CONNECT_ACK_TAGS)?,
+                )
+            }
+            JsonMessageSC::Notify {
+                job_id,
+                target,
+                share_target,
+                epoch_challenge,
+                tlvs,
+            } => Self::Notify(
+                job_id,
+                target,
+                share_target,
+                epoch_challenge_from_json(epoch_challenge)?,
+                tlvs_from_json(tlvs, NOTIFY_TAGS)?,
+            ),
+            JsonMessageSC::ShutDown => Self::ShutDown,
+            JsonMessageSC::Pong { load_factor } => Self::Pong(load_factor),
+            JsonMessageSC::Throttle {
+                min_submit_interval_ms,
+            } => Self::Throttle(min_submit_interval_ms),
+            JsonMessageSC::Chunk {
+                transfer_id,
+                index,
+                total,
+                bytes,
+            } => Self::Chunk(transfer_id, index, total, BASE64.decode(bytes)?),
+            JsonMessageSC::NonceRange { job_id, start, end } => {
+                Self::NonceRange(job_id, start, end)
+            }
+            JsonMessageSC::HelloAck { capabilities } => Self::HelloAck(capabilities),
+            JsonMessageSC::Pause { max_duration_secs } => Self::Pause(max_duration_secs),
+            JsonMessageSC::ResumeMining => Self::ResumeMining,
+            JsonMessageSC::Drain { grace_period_secs } => Self::Drain(grace_period_secs),
+            JsonMessageSC::UpgradeTlsAck { is_accept } => Self::UpgradeTlsAck(is_accept),
+            JsonMessageSC::JobHint {
+                job_id,
+                suggested_target_per_device,
+            } => Self::JobHint(job_id, suggested_target_per_device),
+            JsonMessageSC::QueryInfo => Self::QueryInfo,
+            JsonMessageSC::ShareAckBatch { acks } => Self::ShareAckBatch(acks),
+            JsonMessageSC::RequestProof { work_id } => Self::RequestProof(work_id),
+            JsonMessageSC::Kick { worker_id, reason } => {
+                match reason.len() <= MAXIMUM_KICK_REASON_LENGTH {
+                    true => Self::Kick(worker_id, reason),
+                    false => return Err(anyhow!("Invalid 'Kick' message: {:?}", reason)),
+                }
+            }
+            JsonMessageSC::NotifyBatch { jobs } => {
+                if jobs.is_empty() || jobs.len() > MAXIMUM_NOTIFY_BATCH_JOBS {
+                    return Err(anyhow!("NotifyBatch: invalid job count: {}", jobs.len()));
+                }
+                Self::NotifyBatch(
+                    jobs.into_iter()
+                        .map(|job| {
+                            Ok((
+                                job.job_id,
+                                job.target,
+                                epoch_challenge_from_json(job.epoch_challenge)?,
+                            ))
+                        })
+                        .collect::<Result<_>>()?,
+                )
+            }
+            JsonMessageSC::Bye => Self::Bye,
+        };
+        Ok(message)
+    }
+}
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Serializes the message as JSON, with base64 for binary fields.
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        let message = match self {
+            Self::Connect(
+                worker_type,
+                address_type,
+                v_major,
+                v_minor,
+                v_patch,
+                name,
+                address,
+                tlvs,
+            ) => JsonMessageCS::Connect {
+                worker_type: *worker_type,
+                address_type: *address_type,
+                v_major: *v_major,
+                v_minor: *v_minor,
+                v_patch: *v_patch,
+                name: name.clone(),
+                address: address.clone(),
+                tlvs: tlvs_to_json(tlvs),
+            },
+            Self::Submit(worker_id, sub_worker_id, job_id, prover_solution, signature) => {
+                let mut bytes = Vec::new();
+                prover_solution.serialize_blocking_into(&mut bytes)?;
+                JsonMessageCS::Submit {
+                    worker_id: *worker_id,
+                    sub_worker_id: *sub_worker_id,
+                    job_id: *job_id,
+                    prover_solution: BASE64.encode(bytes),
+                    signature: signature.as_ref().map(|signature| signature.to_string()),
+                }
+            }
+            Self::DisConnect(worker_id) => JsonMessageCS::DisConnect {
+                worker_id: *worker_id,
+            },
+            Self::Ping => JsonMessageCS::Ping,
+            Self::JobStatus(worker_id, job_id) => JsonMessageCS::JobStatus {
+                worker_id: *worker_id,
+                job_id: *job_id,
+            },
+            Self::RegisterWorker(sub_worker_id, name) => {
+                if name.len() > MAXIMUM_WORKER_NAME_LENGTH {
+                    return Err(anyhow!("RegisterWorker: name is too long: {}", name.len()));
+                }
+                JsonMessageCS::RegisterWorker {
+                    sub_worker_id: *sub_worker_id,
+                    name: name.clone(),
+                }
+            }
+            Self::Chunk(transfer_id, index, total, bytes) => JsonMessageCS::Chunk {
+                transfer_id: *transfer_id,
+                index: *index,
+                total: *total,
+                bytes: BASE64.encode(bytes),
+            },
+            Self::ResumeSession(worker_id, address, signature) => JsonMessageCS::ResumeSession {
+                worker_id: *worker_id,
+                address: address.clone(),
+                signature: signature.clone(),
+            },
+            Self::Resume(resume_token) => JsonMessageCS::Resume {
+                resume_token: *resume_token,
+            },
+            Self::Hello(capabilities) => JsonMessageCS::Hello {
+                capabilities: *capabilities,
+            },
+            Self::UpgradeTls => JsonMessageCS::UpgradeTls,
+            Self::InfoReport(agent_name, version) => JsonMessageCS::InfoReport {
+                agent_name: agent_name.clone(),
+                version: version.clone(),
+            },
+            Self::SetNotifyInterval(min_notify_interval_ms) => JsonMessageCS::SetNotifyInterval {
+                min_notify_interval_ms: *min_notify_interval_ms,
+            },
+            Self::SubmitCommitment(work_id, job_id, commitment) => {
+                JsonMessageCS::SubmitCommitment {
+                    work_id: *work_id,
+                    job_id: *job_id,
+                    commitment: BASE64.encode(commitment.to_bytes_le()?),
+                }
+            }
+            Self::PowerStats(worker_id, power_watts, efficiency_mj_per_proof) => {
+                JsonMessageCS::PowerStats {
+                    worker_id: *worker_id,
+                    power_watts: *power_watts,
+                    efficiency_mj_per_proof: *efficiency_mj_per_proof,
+                }
+            }
+            Self::NotifyAck(job_id, received_at_ms) => JsonMessageCS::NotifyAck {
+                job_id: *job_id,
+                received_at_ms: *received_at_ms,
+            },
+            Self::SubmitChunk(work_id, job_id, index, total, bytes) => JsonMessageCS::SubmitChunk {
+                work_id: *work_id,
+                job_id: *job_id,
+                index: *index,
+                total: *total,
+                bytes: BASE64.encode(bytes),
+            },
+            Self::Bye => JsonMessageCS::Bye,
+            Self::Unused(..) => return Err(anyhow!("Unused message can not be serialized")),
+        };
+        Ok(serde_json::to_vec(&message)?)
+    }
+
+    /// Deserializes a message from its JSON form.
+    pub fn from_json(buffer: &[u8]) -> Result<Self> {
+        let message = match serde_json::from_slice(buffer)? {
+            JsonMessageCS::Connect {
+                worker_type,
+                address_type,
+                v_major,
+                v_minor,
+                v_patch,
+                name,
+                address,
+                tlvs,
+            } => Self::Connect(
+                worker_type,
+                address_type,
+                v_major,
+                v_minor,
+                v_patch,
+                name,
+                address,
+                tlvs_from_json(tlvs, CONNECT_TAGS)?,
+            ),
+            JsonMessageCS::Submit {
+                worker_id,
+                sub_worker_id,
+                job_id,
+                prover_solution,
+                signature,
+            } => Self::Submit(
+                worker_id,
+                sub_worker_id,
+                job_id,
+                Data::Buffer(BASE64.decode(prover_solution)?.into()),
+                signature
+                    .map(|signature| Signature::from_str(&signature))
+                    .transpose()?,
+            ),
+            JsonMessageCS::DisConnect { worker_id } => Self::DisConnect(worker_id),
+            JsonMessageCS::Ping => Self::Ping,
+            JsonMessageCS::JobStatus { worker_id, job_id } => Self::JobStatus(worker_id, job_id),
+            JsonMessageCS::RegisterWorker {
+                sub_worker_id,
+                name,
+            } => match name.len() <= MAXIMUM_WORKER_NAME_LENGTH {
+                true => Self::RegisterWorker(sub_worker_id, name),
+                false => return Err(anyhow!("Invalid 'RegisterWorker' message: {:?}", name)),
+            },
+            JsonMessageCS::Chunk {
+                transfer_id,
+                index,
+                total,
+                bytes,
+            } => Self::Chunk(transfer_id, index, total, BASE64.decode(bytes)?),
+            JsonMessageCS::ResumeSession {
+                worker_id,
+                address,
+                signature,
+            } => Self::ResumeSession(worker_id, address, signature),
+            JsonMessageCS::Resume { resume_token } => Self::Resume(resume_token),
+            JsonMessageCS::Hello { capabilities } => Self::Hello(capabilities),
+            JsonMessageCS::UpgradeTls => Self::UpgradeTls,
+            JsonMessageCS::InfoReport {
+                agent_name,
+                version,
+            } => Self::InfoReport(agent_name, version),
+            JsonMessageCS::SetNotifyInterval {
+                min_notify_interval_ms,
+            } => Self::SetNotifyInterval(min_notify_interval_ms),
+            JsonMessageCS::SubmitCommitment {
+                work_id,
+                job_id,
+                commitment,
+            } => Self::SubmitCommitment(
+                work_id,
+                job_id,
+                PuzzleCommitment::from_bytes_le(&BASE64.decode(commitment)?)?,
+            ),
+            JsonMessageCS::PowerStats {
+                worker_id,
+                power_watts,
+                efficiency_mj_per_proof,
+            } => Self::PowerStats(worker_id, power_watts, efficiency_mj_per_proof),
+            JsonMessageCS::NotifyAck {
+                job_id,
+                received_at_ms,
+            } => Self::NotifyAck(job_id, received_at_ms),
+            JsonMessageCS::SubmitChunk {
+                work_id,
+                job_id,
+                index,
+                total,
+                bytes,
+            } => Self::SubmitChunk(work_id, job_id, index, total, BASE64.decode(bytes)?.into()),
+            JsonMessageCS::Bye => Self::Bye,
+        };
+        Ok(message)
+    }
+}
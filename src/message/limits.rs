@@ -0,0 +1,151 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::message_id::*;
+
+use std::collections::HashMap;
+
+/// The default limit of control messages, whose bodies are a few fixed fields.
+pub const CONTROL_MESSAGE_LIMIT: usize = 16;
+/// The default limit of `Connect` and `ConnectAck`.
+pub const HANDSHAKE_MESSAGE_LIMIT: usize = 1024;
+/// The default limit of `Submit` and `Notify`, which carry solutions and epoch challenges.
+pub const WORK_MESSAGE_LIMIT: usize = 4096;
+
+/// Returns the limit of a frame with the given id for decoders without `FrameLimits`.
+/// `Notify`, `NotifyBatch` and `Submit` may grow to `WORK_MESSAGE_LIMIT`, as their size follows
+/// the network's challenge and solution encodings; every other id is bound by `max_frame_size`.
+pub fn default_frame_limit(id: u8, max_frame_size: usize) -> usize {
+    match id {
+        sc::NOTIFY | sc::NOTIFY_BATCH | cs::SUBMIT => max_frame_size.max(WORK_MESSAGE_LIMIT),
+        _ => max_frame_size,
+    }
+}
+
+/// Per-message-id limits on the binary body := << id, data >> of a frame, see
+/// `MessageCodec::with_frame_limits`. The decoder checks them as soon as the id arrives,
+/// so an oversized `Ping` is rejected without buffering it. Ids without a limit are bound
+/// by the codec's `max_frame_size`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameLimits {
+    limits: HashMap<u8, usize>,
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        let mut limits = Self::empty();
+        for id in [
+            sc::SHUT_DOWN,
+            sc::PONG,
+            sc::THROTTLE,
+            sc::HELLO_ACK,
+            sc::PAUSE,
+            sc::RESUME_MINING,
+            sc::DRAIN,
+            sc::UPGRADE_TLS_ACK,
+            sc::QUERY_INFO,
+            sc::REQUEST_PROOF,
+            sc::BYE,
+            cs::DISCONNECT,
+            cs::PING,
+            cs::JOB_STATUS,
+            cs::RESUME,
+            cs::HELLO,
+            cs::UPGRADE_TLS,
+            cs::SET_NOTIFY_INTERVAL,
+            cs::POWER_STATS,
+            cs::BYE,
+        ] {
+            limits.set(id, CONTROL_MESSAGE_LIMIT);
+        }
+        for id in [sc::NONCE_RANGE, sc::JOB_HINT, cs::NOTIFY_ACK] {
+            limits.set(id, 2 * CONTROL_MESSAGE_LIMIT);
+        }
+        limits.set(cs::SUBMIT_COMMITMENT, 4 * CONTROL_MESSAGE_LIMIT);
+        for id in [sc::CONNECT_ACK, cs::CONNECT] {
+            limits.set(id, HANDSHAKE_MESSAGE_LIMIT);
+        }
+        for id in [sc::NOTIFY, sc::NOTIFY_BATCH, cs::SUBMIT, cs::SUBMIT_CHUNK] {
+            limits.set(id, WORK_MESSAGE_LIMIT);
+        }
+        limits
+    }
+}
+
+impl FrameLimits {
+    /// Returns a table without limits.
+    pub fn empty() -> Self {
+        Self {
+            limits: HashMap::new(),
+        }
+    }
+
+    /// Returns the limit of a message id, if it has one.
+    pub fn get(&self, id: u8) -> Option<usize> {
+        self.limits.get(&id).copied()
+    }
+
+    /// Sets the limit of a message id.
+    pub fn set(&mut self, id: u8, limit: usize) {
+        self.limits.insert(id, limit);
+    }
+
+    /// Removes the limit of a message id, leaving it to the codec's `max_frame_size`.
+    pub fn remove(&mut self, id: u8) {
+        self.limits.remove(&id);
+    }
+
+    /// Returns the table with the limit of a message id set.
+    pub fn with_limit(mut self, id: u8, limit: usize) -> Self {
+        self.set(id, limit);
+        self
+    }
+
+    /// Returns the limit of a body with the given id, `max_frame_size` if the id has none.
+    pub fn limit(&self, id: u8, max_frame_size: usize) -> usize {
+        self.get(id).unwrap_or(max_frame_size)
+    }
+
+    /// Returns the largest body any id may have.
+    pub fn max(&self, max_frame_size: usize) -> usize {
+        self.limits
+            .values()
+            .copied()
+            .fold(max_frame_size, usize::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MAXIMUM_MESSAGE_SIZE;
+
+    #[test]
+    fn test_frame_limits() {
+        let limits = FrameLimits::default();
+        assert_eq!(limits.get(cs::PING), Some(CONTROL_MESSAGE_LIMIT));
+        assert_eq!(limits.get(cs::SUBMIT), Some(WORK_MESSAGE_LIMIT));
+        assert_eq!(
+            limits.limit(cs::CHUNK, MAXIMUM_MESSAGE_SIZE),
+            MAXIMUM_MESSAGE_SIZE
+        );
+        assert_eq!(limits.max(MAXIMUM_MESSAGE_SIZE), WORK_MESSAGE_LIMIT);
+
+        let mut limits = limits.with_limit(cs::SUBMIT, 8192);
+        assert_eq!(limits.max(MAXIMUM_MESSAGE_SIZE), 8192);
+        limits.remove(cs::SUBMIT);
+        assert_eq!(limits.get(cs::SUBMIT), None);
+        assert_eq!(
+            FrameLimits::empty().max(MAXIMUM_MESSAGE_SIZE),
+            MAXIMUM_MESSAGE_SIZE
+        );
+    }
+}
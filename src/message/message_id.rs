@@ -0,0 +1,146 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use std::ops::RangeInclusive;
+
+/// Every pool-to-client id. Each direction is split into a core range for the messages
+/// defined in this crate, a reserved range for future protocol messages, a vendor range
+/// for pool- or client-specific extensions, and the `Unused` default as its last id.
+pub const SC_IDS: RangeInclusive<u8> = 0..=127;
+/// Pool-to-client ids of the messages defined in this crate.
+pub const SC_CORE_IDS: RangeInclusive<u8> = 0..=63;
+/// Pool-to-client ids kept for future protocol messages.
+pub const SC_RESERVED_IDS: RangeInclusive<u8> = 64..=111;
+/// Pool-to-client ids free for vendor extensions, handled by peers without them through `UnknownIdPolicy`.
+pub const SC_VENDOR_IDS: RangeInclusive<u8> = 112..=126;
+
+/// Every client-to-pool id.
+pub const CS_IDS: RangeInclusive<u8> = 128..=255;
+/// Client-to-pool ids of the messages defined in this crate.
+pub const CS_CORE_IDS: RangeInclusive<u8> = 128..=191;
+/// Client-to-pool ids kept for future protocol messages.
+pub const CS_RESERVED_IDS: RangeInclusive<u8> = 192..=239;
+/// Client-to-pool ids free for vendor extensions, handled by peers without them through `UnknownIdPolicy`.
+pub const CS_VENDOR_IDS: RangeInclusive<u8> = 240..=254;
+
+/// Pool-to-client message ids, see `PoolMessageSC`.
+pub mod sc {
+    pub const CONNECT_ACK: u8 = 0;
+    pub const NOTIFY: u8 = 1;
+    pub const SHUT_DOWN: u8 = 2;
+    pub const PONG: u8 = 3;
+    pub const THROTTLE: u8 = 4;
+    pub const CHUNK: u8 = 5;
+    pub const NONCE_RANGE: u8 = 6;
+    pub const HELLO_ACK: u8 = 7;
+    pub const PAUSE: u8 = 8;
+    pub const RESUME_MINING: u8 = 9;
+    pub const DRAIN: u8 = 10;
+    pub const UPGRADE_TLS_ACK: u8 = 11;
+    pub const JOB_HINT: u8 = 12;
+    pub const QUERY_INFO: u8 = 13;
+    pub const SHARE_ACK_BATCH: u8 = 14;
+    pub const NOTIFY_BATCH: u8 = 15;
+    pub const REQUEST_PROOF: u8 = 16;
+    pub const KICK: u8 = 17;
+    pub const BYE: u8 = 18;
+    /// The id of the default `Unused` message.
+    pub const UNUSED: u8 = 127;
+}
+
+/// Client-to-pool message ids, see `PoolMessageCS`.
+pub mod cs {
+    pub const CONNECT: u8 = 128;
+    pub const SUBMIT: u8 = 129;
+    pub const DISCONNECT: u8 = 130;
+    pub const PING: u8 = 131;
+    pub const JOB_STATUS: u8 = 132;
+    pub const REGISTER_WORKER: u8 = 133;
+    pub const CHUNK: u8 = 134;
+    pub const RESUME_SESSION: u8 = 135;
+    pub const RESUME: u8 = 136;
+    pub const HELLO: u8 = 137;
+    pub const UPGRADE_TLS: u8 = 138;
+    pub const INFO_REPORT: u8 = 139;
+    pub const SET_NOTIFY_INTERVAL: u8 = 140;
+    pub const SUBMIT_COMMITMENT: u8 = 141;
+    pub const POWER_STATS: u8 = 142;
+    pub const NOTIFY_ACK: u8 = 143;
+    pub const SUBMIT_CHUNK: u8 = 144;
+    pub const BYE: u8 = 145;
+    /// The id of the default `Unused` message.
+    pub const UNUSED: u8 = 255;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{PoolMessageCS, PoolMessageSC, PoolProtocolError};
+    use snarkvm::prelude::Testnet3;
+    use std::collections::HashSet;
+
+    type CurrentNetwork = Testnet3;
+
+    fn is_unknown(result: anyhow::Result<impl Sized>) -> bool {
+        matches!(
+            result
+                .err()
+                .and_then(|e| e.downcast::<PoolProtocolError>().ok()),
+            Some(PoolProtocolError::UnknownMessageId(_))
+        )
+    }
+
+    #[test]
+    fn test_message_ids() {
+        let sc_ids = PoolMessageSC::<CurrentNetwork>::all_ids();
+        let cs_ids = PoolMessageCS::<CurrentNetwork>::all_ids();
+        let unique = sc_ids.iter().chain(cs_ids).collect::<HashSet<_>>();
+        assert_eq!(unique.len(), sc_ids.len() + cs_ids.len());
+        assert!(sc_ids.iter().all(|id| SC_CORE_IDS.contains(id)));
+        assert!(cs_ids.iter().all(|id| CS_CORE_IDS.contains(id)));
+
+        // `deserialize` knows exactly the registered ids of its direction.
+        for id in SC_IDS {
+            let buffer = [id];
+            let known = sc_ids.contains(&id);
+            assert_eq!(
+                is_unknown(PoolMessageSC::<CurrentNetwork>::deserialize(&buffer)),
+                !known
+            );
+        }
+        for id in CS_IDS {
+            let buffer = [id];
+            let known = cs_ids.contains(&id);
+            assert_eq!(
+                is_unknown(PoolMessageCS::<CurrentNetwork>::deserialize(&buffer)),
+                !known
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_id_ranges() {
+        for (all, ranges) in [
+            (SC_IDS, [SC_CORE_IDS, SC_RESERVED_IDS, SC_VENDOR_IDS]),
+            (CS_IDS, [CS_CORE_IDS, CS_RESERVED_IDS, CS_VENDOR_IDS]),
+        ] {
+            // The ranges tile the direction, leaving only its last id for `Unused`.
+            let mut next = *all.start();
+            for range in ranges {
+                assert_eq!(*range.start(), next);
+                next = range.end() + 1;
+            }
+            assert_eq!(next, *all.end());
+        }
+        assert_eq!(PoolMessageSC::<CurrentNetwork>::default().id(), sc::UNUSED);
+        assert_eq!(PoolMessageCS::<CurrentNetwork>::default().id(), cs::UNUSED);
+    }
+}
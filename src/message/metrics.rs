@@ -0,0 +1,159 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::codec::Direction;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// The counters of every `MessageCodec` in the process.
+pub static MESSAGE_METRICS: MessageMetrics = MessageMetrics::new();
+
+/// Counts the messages codecs encode and decode by message id, and the frames that failed to
+/// decode by error category, see `PoolProtocolError::category`.
+#[derive(Debug)]
+pub struct MessageMetrics {
+    encoded: [AtomicU64; 256],
+    decoded: [AtomicU64; 256],
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl MessageMetrics {
+    const fn new() -> Self {
+        Self {
+            encoded: [const { AtomicU64::new(0) }; 256],
+            decoded: [const { AtomicU64::new(0) }; 256],
+            errors: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the number of messages with `id` encoded so far.
+    pub fn encoded(&self, id: u8) -> u64 {
+        self.encoded[id as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of messages with `id` decoded so far.
+    pub fn decoded(&self, id: u8) -> u64 {
+        self.decoded[id as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of frames that failed to decode with an error of `category`.
+    pub fn errors(&self, category: &str) -> u64 {
+        let errors = self.errors.lock().expect("metrics lock is not poisoned");
+        errors.get(category).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn record_encoded(&self, id: u8) {
+        self.encoded[id as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_decoded(&self, id: u8) {
+        self.decoded[id as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_error(&self, category: &'static str) {
+        let mut errors = self.errors.lock().expect("metrics lock is not poisoned");
+        *errors.entry(category).or_default() += 1;
+    }
+
+    /// Renders the counters in the Prometheus text exposition format, leaving out ids never seen.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, help, counters) in [
+            (
+                "zkwork_messages_encoded_total",
+                "Pool messages encoded, by message id.",
+                &self.encoded,
+            ),
+            (
+                "zkwork_messages_decoded_total",
+                "Pool messages decoded, by message id.",
+                &self.decoded,
+            ),
+        ] {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} counter", name);
+            for (id, counter) in counters.iter().enumerate() {
+                let count = counter.load(Ordering::Relaxed);
+                if count == 0 {
+                    continue;
+                }
+                let direction = match Direction::of(id as u8) {
+                    Direction::PoolToClient => "pool_to_client",
+                    Direction::ClientToPool => "client_to_pool",
+                };
+                let _ = writeln!(
+                    output,
+                    "{}{{id=\"{}\",direction=\"{}\"}} {}",
+                    name, id, direction, count
+                );
+            }
+        }
+        let name = "zkwork_decode_errors_total";
+        let _ = writeln!(
+            output,
+            "# HELP {} Frames that failed to decode, by error category.",
+            name
+        );
+        let _ = writeln!(output, "# TYPE {} counter", name);
+        let errors = self.errors.lock().expect("metrics lock is not poisoned");
+        for (category, count) in errors.iter() {
+            let _ = writeln!(output, "{}{{category=\"{}\"}} {}", name, category, count);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{codec::*, message_id::*, poolmessage::*};
+    use ::bytes::BytesMut;
+    use snarkvm::prelude::Testnet3;
+    use tokio_util::codec::{Decoder, Encoder};
+    type CurrentNetwork = Testnet3;
+
+    // The counters are shared by every test of the process, so only their growth is checked.
+    #[test]
+    fn test_message_metrics() -> anyhow::Result<()> {
+        let (encoded, decoded) = (
+            MESSAGE_METRICS.encoded(cs::JOB_STATUS),
+            MESSAGE_METRICS.decoded(cs::JOB_STATUS),
+        );
+        let errors = MESSAGE_METRICS.errors("direction");
+
+        let mut codec = PoolCodecCS::<CurrentNetwork>::new();
+        let mut buffer = BytesMut::new();
+        for _ in 0..3 {
+            codec.encode(PoolMessageCS::JobStatus(1, 2), &mut buffer)?;
+        }
+        while codec.decode(&mut buffer)?.is_some() {}
+        assert!(MESSAGE_METRICS.encoded(cs::JOB_STATUS) >= encoded + 3);
+        assert!(MESSAGE_METRICS.decoded(cs::JOB_STATUS) >= decoded + 3);
+
+        PoolCodecSC::<CurrentNetwork>::new().encode(PoolMessageSC::ShutDown, &mut buffer)?;
+        assert!(codec.decode(&mut buffer).is_err());
+        assert!(MESSAGE_METRICS.errors("direction") > errors);
+
+        let output = MESSAGE_METRICS.render();
+        assert!(output.contains("# TYPE zkwork_messages_decoded_total counter\n"));
+        assert!(output
+            .contains("zkwork_messages_encoded_total{id=\"132\",direction=\"client_to_pool\"} "));
+        assert!(output.contains("zkwork_decode_errors_total{category=\"direction\"} "));
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::poolmessage::*;
+use snarkvm::prelude::Network;
+
+use std::time::{Duration, Instant};
+
+/// Whether a worker's provers should run, driven by the pool messages the client receives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MiningState {
+    /// Provers work on the latest job.
+    #[default]
+    Mining,
+    /// Provers are parked until `ResumeMining` or the given deadline.
+    Paused(Instant),
+}
+
+/// A change of `MiningState` the provers must act on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MiningTransition {
+    /// Park the prover threads for at most the given duration.
+    Pause(Duration),
+    /// Wake the prover threads.
+    Resume,
+}
+
+impl MiningState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a message from the pool, returning the transition it causes.
+    /// Messages other than `Pause` and `ResumeMining` leave the state unchanged; a `Notify`
+    /// received while paused only replaces the job the provers pick up on resume.
+    pub fn advance<N: Network>(&mut self, message: &PoolMessageSC<N>) -> Option<MiningTransition> {
+        self.advance_at(message, Instant::now())
+    }
+
+    /// Applies a message from the pool as of `now`.
+    pub fn advance_at<N: Network>(
+        &mut self,
+        message: &PoolMessageSC<N>,
+        now: Instant,
+    ) -> Option<MiningTransition> {
+        match message {
+            PoolMessageSC::Pause(max_duration_secs) => {
+                let duration = Duration::from_secs(*max_duration_secs as u64);
+                *self = Self::Paused(now + duration);
+                Some(MiningTransition::Pause(duration))
+            }
+            PoolMessageSC::ResumeMining => {
+                let was_paused = matches!(self, Self::Paused(..));
+                *self = Self::Mining;
+                was_paused.then_some(MiningTransition::Resume)
+            }
+            _ => None,
+        }
+    }
+
+    /// Ends a pause whose duration elapsed without a `ResumeMining`.
+    /// Clients call it from their timer, e.g. when the `Pause` duration fires.
+    pub fn expire_at(&mut self, now: Instant) -> Option<MiningTransition> {
+        match *self {
+            Self::Paused(until) if now >= until => {
+                *self = Self::Mining;
+                Some(MiningTransition::Resume)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true while the provers should stay parked.
+    pub fn is_paused_at(&self, now: Instant) -> bool {
+        matches!(*self, Self::Paused(until) if now < until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_pause_and_resume() {
+        let now = Instant::now();
+        let mut state = MiningState::new();
+        assert_eq!(
+            state.advance_at(&PoolMessageSC::<CurrentNetwork>::Pong(None), now),
+            None
+        );
+        assert_eq!(
+            state.advance_at(&PoolMessageSC::<CurrentNetwork>::Pause(60), now),
+            Some(MiningTransition::Pause(Duration::from_secs(60)))
+        );
+        assert!(state.is_paused_at(now));
+        assert_eq!(state.expire_at(now), None);
+        assert_eq!(
+            state.advance_at(&PoolMessageSC::<CurrentNetwork>::ResumeMining, now),
+            Some(MiningTransition::Resume)
+        );
+        assert_eq!(state, MiningState::Mining);
+
+        // A stray ResumeMining while mining changes nothing.
+        assert_eq!(
+            state.advance_at(&PoolMessageSC::<CurrentNetwork>::ResumeMining, now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pause_expires() {
+        let now = Instant::now();
+        let mut state = MiningState::new();
+        state.advance_at(&PoolMessageSC::<CurrentNetwork>::Pause(60), now);
+        let deadline = now + Duration::from_secs(60);
+        assert!(!state.is_paused_at(deadline));
+        assert_eq!(state.expire_at(deadline), Some(MiningTransition::Resume));
+        assert_eq!(state.expire_at(deadline), None);
+
+        // A ResumeMining after the expiry does not wake the provers twice.
+        assert_eq!(
+            state.advance_at(&PoolMessageSC::<CurrentNetwork>::ResumeMining, deadline),
+            None
+        );
+    }
+}
@@ -11,3 +11,125 @@
 
 pub mod poolmessage;
 pub use poolmessage::*;
+
+pub mod tlv;
+pub use tlv::*;
+
+pub mod message_id;
+
+pub mod auth;
+pub use auth::*;
+
+pub mod checksum;
+pub use checksum::*;
+
+pub mod compression;
+pub use compression::*;
+
+pub mod chunk;
+pub use chunk::*;
+
+pub mod submit_chunk;
+pub use submit_chunk::*;
+
+pub mod channel;
+pub use channel::*;
+
+pub mod capabilities;
+pub use capabilities::*;
+
+pub mod format;
+pub use format::*;
+
+pub mod framing;
+pub use framing::*;
+
+pub mod bidirectional;
+pub use bidirectional::*;
+
+pub mod json;
+
+pub mod raw;
+pub use raw::*;
+
+pub mod codec;
+pub use codec::*;
+
+pub mod limits;
+pub use limits::*;
+
+pub mod version;
+pub use version::*;
+
+pub mod v2;
+pub use v2::*;
+
+pub mod replay;
+pub use replay::*;
+
+pub mod batching;
+pub use batching::*;
+
+pub mod bandwidth;
+pub use bandwidth::*;
+
+pub mod backpressure;
+pub use backpressure::*;
+
+pub mod fairness;
+pub use fairness::*;
+
+pub mod error;
+pub use error::*;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+
+pub mod handshake;
+pub use handshake::*;
+
+pub mod mining;
+pub use mining::*;
+
+pub mod pacing;
+pub use pacing::*;
+
+pub mod signature;
+pub use signature::*;
+
+pub mod redact;
+pub use redact::*;
+
+pub mod network;
+pub use network::*;
+
+pub mod client_version;
+pub use client_version::*;
+
+pub mod validation;
+pub use validation::*;
+
+pub mod difficulty;
+pub use difficulty::*;
+
+pub mod agent;
+pub use agent::*;
+
+pub mod payout;
+pub use payout::*;
+
+pub mod session;
+pub use session::*;
+
+pub mod endpoint;
+pub use endpoint::*;
+
+pub mod priority;
+pub use priority::*;
+
+pub mod workers;
+pub use workers::*;
+
+pub mod vectors;
@@ -0,0 +1,177 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{error::*, poolmessage::*, tlv::*};
+use snarkvm::prelude::*;
+
+use std::primitive::str;
+
+/// The reason of a `ConnectAck` rejecting a `Connect` for another network.
+pub const NETWORK_MISMATCH: &str = "network mismatch";
+
+/// The reason of a `ConnectAck` rejecting a `Connect` whose address is not valid UTF-8.
+pub const INVALID_ADDRESS_ENCODING: &str = "invalid address encoding";
+
+/// Returns the `Connect` TLV tagging the client's network with snarkVM's `Network::ID`.
+pub fn network_id_tlv<N: Network>() -> Tlv {
+    Tlv::new(TAG_NETWORK_ID, N::ID.to_le_bytes().to_vec())
+}
+
+/// Returns the TLV explaining why a `ConnectAck` rejects the client.
+pub fn reject_reason_tlv(reason: &str) -> Tlv {
+    Tlv::new(TAG_REJECT_REASON, reason.as_bytes().to_vec())
+}
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Returns the network id of a `Connect`, `None` for clients predating the tag.
+    pub fn network_id(&self) -> Option<u16> {
+        match self {
+            Self::Connect(.., tlvs) => match find_tlv(tlvs, TAG_NETWORK_ID)? {
+                &[a, b] => Some(u16::from_le_bytes([a, b])),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Checks that a `Connect` was made for the pool's network `N`.
+    /// Clients that send no network id are accepted.
+    pub fn check_network(&self) -> Result<(), PoolProtocolError> {
+        match self.network_id() {
+            Some(received) if received != N::ID => Err(PoolProtocolError::NetworkMismatch {
+                expected: N::ID,
+                received,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<N: Network> PoolMessageSC<N> {
+    /// Returns the `ConnectAck` rejecting a client of another network.
+    pub fn network_mismatch(address: Address<N>) -> Self {
+        Self::rejected(address, NETWORK_MISMATCH)
+    }
+
+    /// Returns the `ConnectAck` rejecting a client whose address failed with `InvalidUtf8`.
+    pub fn invalid_address_encoding(address: Address<N>) -> Self {
+        Self::rejected(address, INVALID_ADDRESS_ENCODING)
+    }
+
+    /// Returns a `ConnectAck` rejecting the client for `reason`.
+    pub fn rejected(address: Address<N>, reason: &str) -> Self {
+        Self::ConnectAck(
+            false,
+            address,
+            None,
+            None,
+            None,
+            vec![reject_reason_tlv(reason)],
+        )
+    }
+
+    /// Returns the reason given by a rejecting `ConnectAck`, if any.
+    pub fn reject_reason(&self) -> Option<String> {
+        match self {
+            Self::ConnectAck(false, .., tlvs) => find_tlv(tlvs, TAG_REJECT_REASON)
+                .and_then(|value| String::from_utf8(value.to_vec()).ok()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::PoolCodecCS;
+    use ::bytes::BytesMut;
+    use ::rand::thread_rng;
+    use anyhow::Result;
+    use tokio_util::codec::Decoder;
+    type CurrentNetwork = Testnet3;
+
+    fn connect(tlvs: Vec<Tlv>) -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            ADDRESS_TYPE_EMAIL,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            tlvs,
+        )
+    }
+
+    #[test]
+    fn test_network_id() -> Result<()> {
+        let bytes = connect(vec![network_id_tlv::<CurrentNetwork>()]).to_vec()?;
+        let message = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(message.network_id(), Some(CurrentNetwork::ID));
+        assert_eq!(message.check_network(), Ok(()));
+
+        // Older clients are not checked.
+        assert_eq!(connect(vec![]).network_id(), None);
+        assert_eq!(connect(vec![]).check_network(), Ok(()));
+
+        let message = connect(vec![Tlv::new(TAG_NETWORK_ID, 1u16.to_le_bytes().to_vec())]);
+        assert_eq!(
+            message.check_network(),
+            Err(PoolProtocolError::NetworkMismatch {
+                expected: CurrentNetwork::ID,
+                received: 1,
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_mismatch_ack() -> Result<()> {
+        let address = Address::try_from(PrivateKey::<CurrentNetwork>::new(&mut thread_rng())?)?;
+        let bytes = PoolMessageSC::network_mismatch(address).to_vec()?;
+        let message = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert!(matches!(message, PoolMessageSC::ConnectAck(false, ..)));
+        assert_eq!(message.reject_reason().as_deref(), Some(NETWORK_MISMATCH));
+
+        let message = PoolMessageSC::ConnectAck(false, address, None, None, None, vec![]);
+        assert_eq!(message.reject_reason(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_address_encoding() -> Result<()> {
+        let mut bytes = connect(vec![]).to_vec()?;
+        let at = bytes.len() - 3;
+        bytes[at] = 0xff;
+        let error = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..]).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PoolProtocolError>(),
+            Some(&PoolProtocolError::InvalidUtf8("address".to_string()))
+        );
+
+        // The decoder keeps the error typed, so the pool can still answer.
+        let error = PoolCodecCS::<CurrentNetwork>::new()
+            .decode(&mut BytesMut::from(&bytes[..]))
+            .unwrap_err();
+        assert_eq!(
+            PoolProtocolError::from_io_error(&error),
+            Some(&PoolProtocolError::InvalidUtf8("address".to_string()))
+        );
+
+        let address = Address::try_from(PrivateKey::<CurrentNetwork>::new(&mut thread_rng())?)?;
+        let message = PoolMessageSC::invalid_address_encoding(address);
+        assert_eq!(
+            message.reject_reason().as_deref(),
+            Some(INVALID_ADDRESS_ENCODING)
+        );
+        Ok(())
+    }
+}
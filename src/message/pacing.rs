@@ -0,0 +1,111 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::poolmessage::*;
+use snarkvm::prelude::Network;
+
+use std::time::{Duration, Instant};
+
+/// The longest interval a pool honors from `SetNotifyInterval`, so a worker cannot fall minutes behind.
+pub const MAXIMUM_NOTIFY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The pool's per-connection record of a worker's `SetNotifyInterval`.
+/// The interval is a floor between `Notify`s within an epoch; the first job of a new epoch is
+/// always sent at once, as work on the old epoch is wasted. Jobs held back are not queued:
+/// the pool sends the latest one once `next_at` passes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NotifyPacer {
+    interval: Duration,
+    last: Option<(u32, Instant)>,
+}
+
+impl NotifyPacer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the interval in force, capped at `MAXIMUM_NOTIFY_INTERVAL`.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Applies a message from the worker, returning true if it was a `SetNotifyInterval`.
+    pub fn apply<N: Network>(&mut self, message: &PoolMessageCS<N>) -> bool {
+        match message {
+            PoolMessageCS::SetNotifyInterval(min_notify_interval_ms) => {
+                self.interval = Duration::from_millis(*min_notify_interval_ms as u64)
+                    .min(MAXIMUM_NOTIFY_INTERVAL);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns when a job of `epoch_number` may next be sent, `None` if it may be sent now.
+    pub fn next_at(&self, epoch_number: u32, now: Instant) -> Option<Instant> {
+        match self.last {
+            Some((last_epoch, sent_at)) if last_epoch == epoch_number => {
+                let next = sent_at + self.interval;
+                (now < next).then_some(next)
+            }
+            _ => None,
+        }
+    }
+
+    /// Records a `Notify` for a job of `epoch_number` if it may be sent at `now`,
+    /// returning false if it must be held back.
+    pub fn try_send_at(&mut self, epoch_number: u32, now: Instant) -> bool {
+        if self.next_at(epoch_number, now).is_some() {
+            return false;
+        }
+        self.last = Some((epoch_number, now));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_notify_pacer() {
+        let start = Instant::now();
+        let mut pacer = NotifyPacer::new();
+        assert!(pacer.try_send_at(1, start));
+        assert!(pacer.try_send_at(1, start));
+
+        assert!(pacer.apply(&PoolMessageCS::<CurrentNetwork>::SetNotifyInterval(5_000)));
+        assert!(!pacer.apply(&PoolMessageCS::<CurrentNetwork>::Ping));
+        assert_eq!(pacer.interval(), Duration::from_secs(5));
+        let one_second = start + Duration::from_secs(1);
+        assert!(!pacer.try_send_at(1, one_second));
+        assert_eq!(
+            pacer.next_at(1, one_second),
+            Some(start + Duration::from_secs(5))
+        );
+        assert!(pacer.try_send_at(1, start + Duration::from_secs(5)));
+
+        // A new epoch goes out at once and restarts the interval.
+        let six_seconds = start + Duration::from_secs(6);
+        assert!(pacer.try_send_at(2, six_seconds));
+        assert!(!pacer.try_send_at(2, six_seconds + Duration::from_secs(4)));
+
+        // Intervals are capped, and 0 lifts the floor.
+        pacer.apply(&PoolMessageCS::<CurrentNetwork>::SetNotifyInterval(
+            u32::MAX,
+        ));
+        assert_eq!(pacer.interval(), MAXIMUM_NOTIFY_INTERVAL);
+        pacer.apply(&PoolMessageCS::<CurrentNetwork>::SetNotifyInterval(0));
+        assert!(pacer.try_send_at(2, six_seconds));
+    }
+}
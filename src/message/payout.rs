@@ -0,0 +1,226 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{error::*, poolmessage::*, tlv::*};
+use snarkvm::prelude::*;
+
+/// The most payout addresses a `Connect` may split rewards between.
+pub const MAXIMUM_PAYOUT_SPLITS: usize = 4;
+/// The sum of the weights of a payout split, in basis points.
+pub const TOTAL_WEIGHT_BPS: u16 = 10000;
+
+/// Payout addresses sharing a worker's rewards := [(address, weight_bps)]
+/// Carried in the `Connect` TLV section, the pool attributes shares to each address by weight.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayoutSplit<N: Network>(Vec<(Address<N>, u16)>);
+
+fn invalid(reason: impl Into<String>) -> PoolProtocolError {
+    PoolProtocolError::InvalidPayoutSplit(reason.into())
+}
+
+impl<N: Network> PayoutSplit<N> {
+    /// Checks that there are 1 to `MAXIMUM_PAYOUT_SPLITS` distinct addresses with non-zero
+    /// weights summing to `TOTAL_WEIGHT_BPS`.
+    pub fn new(splits: Vec<(Address<N>, u16)>) -> Result<Self, PoolProtocolError> {
+        if splits.is_empty() || splits.len() > MAXIMUM_PAYOUT_SPLITS {
+            return Err(invalid(format!("{} addresses", splits.len())));
+        }
+        if splits.iter().any(|(_, weight)| *weight == 0) {
+            return Err(invalid("zero weight"));
+        }
+        for (i, (address, _)) in splits.iter().enumerate() {
+            if splits[..i].iter().any(|(other, _)| other == address) {
+                return Err(invalid(format!("duplicate address {}", address)));
+            }
+        }
+        let total: u32 = splits.iter().map(|(_, weight)| *weight as u32).sum();
+        if total != TOTAL_WEIGHT_BPS as u32 {
+            return Err(invalid(format!("weights sum to {}", total)));
+        }
+        Ok(Self(splits))
+    }
+
+    /// Returns the split paying everything to one address.
+    pub fn single(address: Address<N>) -> Self {
+        Self(vec![(address, TOTAL_WEIGHT_BPS)])
+    }
+
+    pub fn splits(&self) -> &[(Address<N>, u16)] {
+        &self.0
+    }
+
+    /// Divides `amount` (shares or rewards) by weight, rounding down.
+    /// The rounding remainder goes to the first address so nothing is lost.
+    pub fn attribute(&self, amount: u64) -> Vec<(Address<N>, u64)> {
+        let mut attributed: Vec<_> = self
+            .0
+            .iter()
+            .map(|(address, weight)| {
+                let part = amount as u128 * *weight as u128 / TOTAL_WEIGHT_BPS as u128;
+                (*address, part as u64)
+            })
+            .collect();
+        let remainder = amount - attributed.iter().map(|(_, part)| part).sum::<u64>();
+        attributed[0].1 += remainder;
+        attributed
+    }
+
+    /// Returns the `Connect` TLV carrying this split.
+    pub fn to_tlv(&self) -> anyhow::Result<Tlv> {
+        let mut value = vec![self.0.len() as u8];
+        for (address, weight) in &self.0 {
+            address.write_le(&mut value)?;
+            value.extend_from_slice(&weight.to_le_bytes());
+        }
+        Ok(Tlv::new(TAG_PAYOUT_SPLIT, value))
+    }
+
+    /// Reads a split from its TLV value, checking the count against the length.
+    pub fn from_tlv_value(value: &[u8]) -> Result<Self, PoolProtocolError> {
+        const ENTRY_SIZE: usize = 32 + 2;
+        let count = *value.first().ok_or_else(|| invalid("empty"))? as usize;
+        if count > MAXIMUM_PAYOUT_SPLITS || value.len() != 1 + count * ENTRY_SIZE {
+            return Err(invalid(format!(
+                "{} addresses in {} bytes",
+                count,
+                value.len()
+            )));
+        }
+        let splits = value[1..]
+            .chunks(ENTRY_SIZE)
+            .map(|entry| {
+                let address = Address::read_le(&entry[..32])
+                    .map_err(|_| invalid("address is not on the curve"))?;
+                Ok((address, u16::from_le_bytes([entry[32], entry[33]])))
+            })
+            .collect::<Result<Vec<_>, PoolProtocolError>>()?;
+        Self::new(splits)
+    }
+}
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Returns the payout split of a `Connect`, `None` when the address in `Connect` gets everything.
+    pub fn payout_split(&self) -> Result<Option<PayoutSplit<N>>, PoolProtocolError> {
+        match self {
+            Self::Connect(.., tlvs) => find_tlv(tlvs, TAG_PAYOUT_SPLIT)
+                .map(PayoutSplit::from_tlv_value)
+                .transpose(),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::thread_rng;
+    type CurrentNetwork = Testnet3;
+
+    fn sample_addresses(count: usize) -> Vec<Address<CurrentNetwork>> {
+        let rng = &mut thread_rng();
+        (0..count)
+            .map(|_| Address::new(Uniform::rand(rng)))
+            .collect()
+    }
+
+    fn sample_connect(tlvs: Vec<Tlv>) -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            ADDRESS_TYPE_EMAIL,
+            0,
+            1,
+            0,
+            "rig-01".to_string(),
+            "215587407@qq.com".to_string(),
+            tlvs,
+        )
+    }
+
+    #[test]
+    fn test_payout_split() -> anyhow::Result<()> {
+        let addresses = sample_addresses(3);
+        let split = PayoutSplit::new(vec![
+            (addresses[0], 5000),
+            (addresses[1], 3000),
+            (addresses[2], 2000),
+        ])?;
+        let connect = sample_connect(vec![split.to_tlv()?]);
+        let bytes = connect.to_vec()?;
+        let connect = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(connect.payout_split()?, Some(split.clone()));
+        assert_eq!(sample_connect(vec![]).payout_split()?, None);
+
+        assert_eq!(
+            split.attribute(101),
+            vec![(addresses[0], 51), (addresses[1], 30), (addresses[2], 20)]
+        );
+        assert_eq!(
+            split
+                .attribute(u64::MAX)
+                .iter()
+                .map(|(_, part)| *part as u128)
+                .sum::<u128>(),
+            u64::MAX as u128
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_payout_split_single() -> anyhow::Result<()> {
+        let address = sample_addresses(1)[0];
+        let split = PayoutSplit::single(address);
+        assert_eq!(PayoutSplit::new(vec![(address, TOTAL_WEIGHT_BPS)])?, split);
+        let tlv = split.to_tlv()?;
+        assert_eq!(tlv.value.len(), 1 + 34);
+        assert_eq!(PayoutSplit::from_tlv_value(&tlv.value)?, split);
+        assert_eq!(split.attribute(7), vec![(address, 7)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_payout_split_invalid() -> anyhow::Result<()> {
+        let addresses = sample_addresses(5);
+        let weights = |weights: &[u16]| {
+            PayoutSplit::<CurrentNetwork>::new(
+                addresses
+                    .iter()
+                    .copied()
+                    .zip(weights.iter().copied())
+                    .collect(),
+            )
+        };
+        assert!(weights(&[]).is_err());
+        assert!(weights(&[5000, 4999]).is_err());
+        assert!(weights(&[10000, 0]).is_err());
+        assert!(weights(&[2000; 5]).is_err());
+        assert!(PayoutSplit::new(vec![(addresses[0], 5000), (addresses[0], 5000)]).is_err());
+
+        let tlv = weights(&[2500; 4])?.to_tlv()?;
+        assert!(PayoutSplit::<CurrentNetwork>::from_tlv_value(&[]).is_err());
+        assert!(
+            PayoutSplit::<CurrentNetwork>::from_tlv_value(&tlv.value[..tlv.value.len() - 1])
+                .is_err()
+        );
+        let mut value = tlv.value.clone();
+        value[0] = 5;
+        assert!(PayoutSplit::<CurrentNetwork>::from_tlv_value(&value).is_err());
+        let mut value = tlv.value;
+        value[0] = 3;
+        assert!(PayoutSplit::<CurrentNetwork>::from_tlv_value(&value).is_err());
+
+        let connect = sample_connect(vec![Tlv::new(TAG_PAYOUT_SPLIT, vec![1])]);
+        assert!(matches!(
+            connect.payout_split(),
+            Err(PoolProtocolError::InvalidPayoutSplit(..))
+        ));
+        Ok(())
+    }
+}
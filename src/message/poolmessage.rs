@@ -14,17 +14,103 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
+use super::{
+    capabilities::*,
+    chunk::*,
+    codec::{PoolCodecCS, PoolCodecSC},
+    error::*,
+    limits::*,
+    message_id::{cs, sc},
+    submit_chunk::*,
+    tlv::*,
+};
 use snarkvm::prelude::*;
 
-use ::bytes::{Buf, BufMut, BytesMut};
+use ::bytes::BytesMut;
 use anyhow::{anyhow, Result};
-use std::{default::Default, io::Write};
-use tokio_util::codec::{Decoder, Encoder};
+use std::{
+    default::Default,
+    io::{self, Read, Write},
+};
+// snarkvm's prelude glob-exports two `str` modules, which makes a bare `&str` ambiguous.
+use std::primitive::str;
+use tokio_util::{
+    codec::{Decoder, Encoder},
+    sync::CancellationToken,
+};
 
 use ::bytes::Bytes;
-use tokio::task;
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    task,
+};
 
-const MAXIMUM_MESSAGE_SIZE: usize = 512;
+/// The largest frame, length prefix excluded, decoders accept by default, except for work
+/// messages, see `default_frame_limit` and `MessageCodec::with_max_frame_size`.
+pub const MAXIMUM_MESSAGE_SIZE: usize = 512;
+/// The serialized size of an `EpochChallenge` := (epoch_number(u32), epoch_block_hash, degree(u32))
+pub const EPOCH_CHALLENGE_SIZE: usize = 4 + 32 + 4;
+/// The longest name a V1 `Connect` may carry, in bytes, as its length is a u8.
+pub const MAXIMUM_CONNECT_NAME_LENGTH: usize = u8::MAX as usize;
+/// The longest name or address a V2 `Connect` may carry, in bytes, as their lengths are u16.
+pub const MAXIMUM_CONNECT_FIELD_LENGTH_V2: usize = u16::MAX as usize;
+/// The longest name a `RegisterWorker` may carry, in bytes.
+pub const MAXIMUM_WORKER_NAME_LENGTH: usize = 64;
+/// The longest agent name or version an `InfoReport` may carry, in bytes.
+pub const MAXIMUM_INFO_REPORT_LENGTH: usize = 128;
+/// The longest reason a `Kick` may carry, in bytes.
+pub const MAXIMUM_KICK_REASON_LENGTH: usize = 128;
+
+/// The most jobs a `NotifyBatch` may carry, so a full batch fits the default frame.
+pub const MAXIMUM_NOTIFY_BATCH_JOBS: usize = 8;
+/// The serialized size of a `NotifyBatch` job := (job_id, target, epoch_challenge)
+pub const NOTIFY_BATCH_JOB_SIZE: usize = 8 + 8 + EPOCH_CHALLENGE_SIZE;
+
+/// The serialized size of a `PuzzleCommitment`, a compressed KZG commitment.
+pub const PUZZLE_COMMITMENT_SIZE: usize = 48;
+/// The serialized size of a `ProverSolution` := (address, nonce(u64), commitment, proof), its
+/// proof := (w, has_random_v) without `random_v`, which adds `PROVER_SOLUTION_RANDOM_V_SIZE`.
+pub const PROVER_SOLUTION_SIZE: usize = 32 + 8 + PUZZLE_COMMITMENT_SIZE + 48 + 1;
+pub const PROVER_SOLUTION_RANDOM_V_SIZE: usize = 32;
+/// The serialized size of a `Signature`.
+pub const SIGNATURE_SIZE: usize = 128;
+
+/// `ShareAckBatch` result codes. Pools may define more from `SHARE_OTHER` on.
+pub const SHARE_ACCEPTED: u8 = 0;
+pub const SHARE_REJECTED: u8 = 1;
+pub const SHARE_STALE: u8 = 2;
+pub const SHARE_DUPLICATE: u8 = 3;
+pub const SHARE_OTHER: u8 = 4;
+
+/// Frame sizes := length(u32) + id + data of the fixed-size messages, see `encoded_len`.
+pub const PING_FRAME_SIZE: usize = 4 + 1;
+pub const SHUT_DOWN_FRAME_SIZE: usize = 4 + 1;
+pub const PONG_FRAME_SIZE: usize = 4 + 1;
+/// The frame size of a `Pong` carrying the load factor.
+pub const LOADED_PONG_FRAME_SIZE: usize = 4 + 1 + 1;
+pub const DISCONNECT_FRAME_SIZE: usize = 4 + 1 + 4;
+
+/// `Connect` address type of an Aleo payout address.
+pub const ADDRESS_TYPE_ALEO: u8 = 0;
+/// `Connect` address type of an email account.
+pub const ADDRESS_TYPE_EMAIL: u8 = 1;
+
+/// Deprecated: use `PoolMessageSC::peek_id` or `PoolMessageCS::peek_id`, which also check the
+/// direction of the id. Kept for one release, sharing their logic; returns `None` where they fail
+/// for lack of bytes, whatever the id.
+pub fn peek_id(buffer: &[u8]) -> Option<u8> {
+    first_frame_id(buffer)
+}
+
+/// Returns the message ID of the first frame := << length(u32), id, data >> in `buffer`, or `None`
+/// until it is present. Only valid for frames without negotiated fields. Leading
+/// keepalives := << 0u32 >> are skipped like decoders do, so the id is the next frame's.
+fn first_frame_id(mut buffer: &[u8]) -> Option<u8> {
+    while let Some(rest) = buffer.strip_prefix(&[0; 4]) {
+        buffer = rest;
+    }
+    buffer.get(4).copied()
+}
 
 /// This object enables deferred deserialization / ahead-of-time serialization for objects that
 /// take a while to deserialize / serialize, in order to allow these operations to be non-blocking.
@@ -35,13 +121,31 @@ pub enum Data<T: FromBytes + ToBytes + Send + 'static> {
 }
 
 impl<T: FromBytes + ToBytes + Send + 'static> Data<T> {
+    /// Deserializes the buffer on the blocking pool.
+    /// If the returned future is dropped before the blocking task starts, the task is skipped.
     pub async fn deserialize(self) -> Result<T> {
+        self.deserialize_with_token(CancellationToken::new()).await
+    }
+
+    /// Deserializes the buffer on the blocking pool, giving up once `token` is cancelled.
+    /// A task that already started runs to completion, but its result is discarded.
+    pub async fn deserialize_with_token(self, token: CancellationToken) -> Result<T> {
         match self {
             Self::Object(x) => Ok(x),
             Self::Buffer(bytes) => {
-                match task::spawn_blocking(move || T::from_bytes_le(&bytes)).await {
-                    Ok(x) => x,
-                    Err(err) => Err(err.into()),
+                // Cancelled when this future is dropped, so queued work is abandoned.
+                let task_token = token.child_token();
+                let _guard = task_token.clone().drop_guard();
+                let task = task::spawn_blocking(move || match task_token.is_cancelled() {
+                    true => Err(anyhow!("Deserialization cancelled")),
+                    false => T::from_bytes_le(&bytes),
+                });
+                tokio::select! {
+                    result = task => match result {
+                        Ok(x) => x,
+                        Err(err) => Err(err.into()),
+                    },
+                    _ = token.cancelled() => Err(anyhow!("Deserialization cancelled")),
                 }
             }
         }
@@ -64,6 +168,15 @@ impl<T: FromBytes + ToBytes + Send + 'static> Data<T> {
         }
     }
 
+    /// Returns a `Buffer` copy for fan-out, without requiring `T: Clone`.
+    /// An `Object` is serialized once; a `Buffer` shares its bytes.
+    pub fn clone_buffer(&self) -> Result<Data<T>> {
+        match self {
+            Self::Object(x) => Ok(Self::Buffer(x.to_bytes_le()?.into())),
+            Self::Buffer(bytes) => Ok(Self::Buffer(bytes.clone())),
+        }
+    }
+
     pub fn serialize_blocking_into<W: Write>(&self, writer: &mut W) -> Result<()> {
         match self {
             Self::Object(x) => {
@@ -75,23 +188,92 @@ impl<T: FromBytes + ToBytes + Send + 'static> Data<T> {
     }
 }
 
+impl<N: Network> Data<ProverSolution<N>> {
+    /// Returns the size `serialize_blocking_into` writes, without serializing an object.
+    pub fn serialized_size(&self) -> usize {
+        match self {
+            Self::Object(prover_solution) => match prover_solution.proof().random_v {
+                Some(_) => PROVER_SOLUTION_SIZE + PROVER_SOLUTION_RANDOM_V_SIZE,
+                None => PROVER_SOLUTION_SIZE,
+            },
+            Self::Buffer(bytes) => bytes.len(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum PoolMessageSC<N: Network> {
-    /// ConnectAck := (is_accecpt, address, [id], [signature])
-    ConnectAck(bool, Address<N>, Option<u32>, Option<String>),
-    /// Notify := (job_id, target, epoch_challenge)
-    Notify(u64, u64, EpochChallenge<N>),
+    /// ConnectAck := (is_accecpt, address, [id], [signature], [initial_job], tlvs)
+    /// An accepted ack may carry the first job as (job_id, target, epoch_challenge), saving the `Notify`.
+    ConnectAck(
+        bool,
+        Address<N>,
+        Option<u32>,
+        Option<String>,
+        Option<(u64, u64, EpochChallenge<N>)>,
+        Vec<Tlv>,
+    ),
+    /// Notify := (job_id, target, share_target, epoch_challenge, tlvs)
+    /// `target` is the block threshold and `share_target` the easier one clients submit against.
+    /// The pool acks a `Submit` as `SHARE_ACCEPTED` once it meets `share_target`, and also
+    /// proposes it as a block when it meets `target`.
+    /// On the wire `share_target` is the `TAG_SHARE_TARGET` TLV, only sent when it differs from
+    /// `target`, so the body keeps its original layout and older frames mean `share_target == target`.
+    Notify(u64, u64, u64, EpochChallenge<N>, Vec<Tlv>),
     /// ShutDown := ()
     ShutDown,
-    /// Pong
-    Pong,
-    /// Unused
+    /// Pong := ([load_factor])
+    /// The gateway's load from 0 (idle) to 255 (saturated), only sent with `CAP_LOAD_FACTOR`.
+    Pong(Option<u8>),
+    /// Throttle := (min_submit_interval_ms)
+    Throttle(u32),
+    /// Chunk := (transfer_id, index, total, bytes)
+    Chunk(u32, u16, u16, Vec<u8>),
+    /// NonceRange := (job_id, start, end)
+    /// The worker only iterates nonces in [start, end) for the job; without one the whole space is open.
+    NonceRange(u64, u64, u64),
+    /// HelloAck := (capabilities), the intersection of the `Hello` mask and the pool's own
+    HelloAck(u32),
+    /// Pause := (max_duration_secs)
+    /// Workers stop proving, keeping the connection, until `ResumeMining` or the duration elapses.
+    Pause(u32),
+    /// ResumeMining, ends a `Pause`
+    ResumeMining,
+    /// Drain := (grace_period_secs)
+    /// The pool sends no new `Notify` but still accepts `Submit` for the grace period, then sends `ShutDown`.
+    Drain(u32),
+    /// UpgradeTlsAck := (is_accept), answers `UpgradeTls`
+    /// After an accepted ack the client starts the TLS handshake on the same connection.
+    UpgradeTlsAck(bool),
+    /// JobHint := (job_id, suggested_target_per_device)
+    /// Guides an agent serving many rigs on one connection, see `device_difficulties`.
+    JobHint(u64, u64),
+    /// QueryInfo, asks the client for an `InfoReport`
+    QueryInfo,
+    /// ShareAckBatch := (count * (work_id, result_code))
+    /// Acknowledges many `Submit`s in one frame, so the pool can coalesce acks on a short timer.
+    ShareAckBatch(Vec<(u32, u8)>),
+    /// NotifyBatch := (count * (job_id, target, epoch_challenge))
+    /// Replaces the whole job set at once, e.g. at an epoch boundary or when several priorities
+    /// or networks are served. Carries 1 to `MAXIMUM_NOTIFY_BATCH_JOBS` jobs.
+    NotifyBatch(Vec<(u64, u64, EpochChallenge<N>)>),
+    /// RequestProof := (work_id), asks for the full `Submit` of a share sent as `SubmitCommitment`
+    RequestProof(u32),
+    /// Kick := (worker_id, reason), disconnects one worker with a human-readable reason
+    /// The client surfaces the reason to the operator, then closes the connection.
+    Kick(u32, String),
+    /// Bye := (), sent just before the pool closes the connection on purpose, e.g. for a restart.
+    /// Unlike a dropped connection, the client should wait before reconnecting.
+    Bye,
+    /// Unused := (id), the default, or a message id this build does not know,
+    /// see `UnknownIdPolicy::ReturnUnused`
     #[allow(unused)]
-    Unused,
+    Unused(u8),
 }
+
 impl<N: Network> Default for PoolMessageSC<N> {
     fn default() -> Self {
-        Self::Unused
+        Self::Unused(sc::UNUSED)
     }
 }
 
@@ -104,8 +286,23 @@ impl<N: Network> PoolMessageSC<N> {
             Self::ConnectAck(..) => "ConnectAck",
             Self::Notify(..) => "Notify",
             Self::ShutDown => "Shutdown",
-            Self::Pong => "Pong",
-            Self::Unused => "Unused",
+            Self::Pong(..) => "Pong",
+            Self::Throttle(..) => "Throttle",
+            Self::Chunk(..) => "Chunk",
+            Self::NonceRange(..) => "NonceRange",
+            Self::HelloAck(..) => "HelloAck",
+            Self::Pause(..) => "Pause",
+            Self::ResumeMining => "ResumeMining",
+            Self::Drain(..) => "Drain",
+            Self::UpgradeTlsAck(..) => "UpgradeTlsAck",
+            Self::JobHint(..) => "JobHint",
+            Self::QueryInfo => "QueryInfo",
+            Self::ShareAckBatch(..) => "ShareAckBatch",
+            Self::NotifyBatch(..) => "NotifyBatch",
+            Self::RequestProof(..) => "RequestProof",
+            Self::Kick(..) => "Kick",
+            Self::Bye => "Bye",
+            Self::Unused(..) => "Unused",
         }
     }
 
@@ -113,56 +310,304 @@ impl<N: Network> PoolMessageSC<N> {
     #[inline]
     pub fn id(&self) -> u8 {
         match self {
-            Self::ConnectAck(..) => 0,
-            Self::Notify(..) => 1,
-            Self::ShutDown => 2,
-            Self::Pong => 3,
-            Self::Unused => 127,
+            Self::ConnectAck(..) => sc::CONNECT_ACK,
+            Self::Notify(..) => sc::NOTIFY,
+            Self::ShutDown => sc::SHUT_DOWN,
+            Self::Pong(..) => sc::PONG,
+            Self::Throttle(..) => sc::THROTTLE,
+            Self::Chunk(..) => sc::CHUNK,
+            Self::NonceRange(..) => sc::NONCE_RANGE,
+            Self::HelloAck(..) => sc::HELLO_ACK,
+            Self::Pause(..) => sc::PAUSE,
+            Self::ResumeMining => sc::RESUME_MINING,
+            Self::Drain(..) => sc::DRAIN,
+            Self::UpgradeTlsAck(..) => sc::UPGRADE_TLS_ACK,
+            Self::JobHint(..) => sc::JOB_HINT,
+            Self::QueryInfo => sc::QUERY_INFO,
+            Self::ShareAckBatch(..) => sc::SHARE_ACK_BATCH,
+            Self::NotifyBatch(..) => sc::NOTIFY_BATCH,
+            Self::RequestProof(..) => sc::REQUEST_PROOF,
+            Self::Kick(..) => sc::KICK,
+            Self::Bye => sc::BYE,
+            Self::Unused(id) => *id,
         }
     }
 
+    /// Returns the id of every message except `Unused`.
+    pub fn all_ids() -> &'static [u8] {
+        &[
+            sc::CONNECT_ACK,
+            sc::NOTIFY,
+            sc::SHUT_DOWN,
+            sc::PONG,
+            sc::THROTTLE,
+            sc::CHUNK,
+            sc::NONCE_RANGE,
+            sc::HELLO_ACK,
+            sc::PAUSE,
+            sc::RESUME_MINING,
+            sc::DRAIN,
+            sc::UPGRADE_TLS_ACK,
+            sc::JOB_HINT,
+            sc::QUERY_INFO,
+            sc::SHARE_ACK_BATCH,
+            sc::NOTIFY_BATCH,
+            sc::REQUEST_PROOF,
+            sc::KICK,
+            sc::BYE,
+        ]
+    }
+
     /// Returns the message data as bytes.
     #[inline]
     pub fn serialize_data_into<W: Write>(&self, writer: &mut W) -> Result<()> {
         match self {
-            Self::ConnectAck(is_accept, address, id, signature) => match is_accept {
-                true => match (id, signature) {
-                    (Some(id), Some(signature)) => {
-                        writer.write_all(&[1u8])?;
-                        bincode::serialize_into(&mut *writer, address)?;
-                        writer.write_all(&id.to_le_bytes())?;
-                        writer.write_all(signature.as_bytes())?;
-                        Ok(())
+            Self::ConnectAck(is_accept, address, id, signature, initial_job, tlvs) => {
+                match is_accept {
+                    true => match (id, signature) {
+                        (Some(id), Some(signature)) => {
+                            writer.write_all(&[1u8])?;
+                            address.write_le(&mut *writer)?;
+                            writer.write_all(&id.to_le_bytes())?;
+                            match initial_job {
+                                Some((job_id, target, epoch_challenge)) => {
+                                    writer.write_all(&[1u8])?;
+                                    writer.write_all(&job_id.to_le_bytes())?;
+                                    writer.write_all(&target.to_le_bytes())?;
+                                    writer.write_all(&epoch_challenge.to_bytes_le()?)?;
+                                }
+                                None => writer.write_all(&[0u8])?,
+                            }
+                            writer.write_all(signature.as_bytes())?;
+                            if !tlvs.is_empty() {
+                                writer.write_all(&[TLV_SEPARATOR])?;
+                                write_tlvs(writer, tlvs)?;
+                            }
+                            Ok(())
+                        }
+                        _ => Err(anyhow!("ConnectAck: Invalid id")),
+                    },
+                    false => {
+                        writer.write_all(&[0u8])?;
+                        address.write_le(&mut *writer)?;
+                        write_tlvs(writer, tlvs)
                     }
-                    _ => Err(anyhow!("ConnectAck: Invalid id")),
-                },
-                false => {
-                    writer.write_all(&[0u8])?;
-                    bincode::serialize_into(&mut *writer, address)?;
-                    Ok(())
                 }
-            },
-            Self::Notify(job_id, target, epoch_challenge) => {
-                bincode::serialize_into(&mut *writer, job_id)?;
-                bincode::serialize_into(&mut *writer, target)?;
+            }
+            Self::Notify(job_id, target, share_target, epoch_challenge, tlvs) => {
+                if find_tlv(tlvs, TAG_SHARE_TARGET).is_some() {
+                    return Err(anyhow!(
+                        "'Notify' carries its share target as a field, not a TLV"
+                    ));
+                }
+                writer.write_all(&job_id.to_le_bytes())?;
+                writer.write_all(&target.to_le_bytes())?;
                 writer.write_all(&epoch_challenge.to_bytes_le()?)?;
-                Ok(())
+                write_tlvs(writer, tlvs)?;
+                match share_target != target {
+                    true => write_tlv(writer, TAG_SHARE_TARGET, &share_target.to_le_bytes()),
+                    false => Ok(()),
+                }
             }
             Self::ShutDown => Ok(()),
-            Self::Pong => Ok(()),
-            Self::Unused => Ok(()),
+            Self::Pong(load_factor) => match load_factor {
+                Some(load_factor) => Ok(writer.write_all(&[*load_factor])?),
+                None => Ok(()),
+            },
+            Self::Throttle(min_submit_interval_ms) => {
+                writer.write_all(&min_submit_interval_ms.to_le_bytes())?;
+                Ok(())
+            }
+            Self::Chunk(transfer_id, index, total, bytes) => {
+                write_chunk(writer, *transfer_id, *index, *total, bytes)
+            }
+            Self::NonceRange(job_id, start, end) => {
+                writer.write_all(&job_id.to_le_bytes())?;
+                writer.write_all(&start.to_le_bytes())?;
+                writer.write_all(&end.to_le_bytes())?;
+                Ok(())
+            }
+            Self::HelloAck(capabilities) => {
+                writer.write_all(&capabilities.to_le_bytes())?;
+                Ok(())
+            }
+            Self::Pause(max_duration_secs) => {
+                writer.write_all(&max_duration_secs.to_le_bytes())?;
+                Ok(())
+            }
+            Self::ResumeMining => Ok(()),
+            Self::Drain(grace_period_secs) => {
+                writer.write_all(&grace_period_secs.to_le_bytes())?;
+                Ok(())
+            }
+            Self::UpgradeTlsAck(is_accept) => {
+                writer.write_all(&[*is_accept as u8])?;
+                Ok(())
+            }
+            Self::JobHint(job_id, suggested_target_per_device) => {
+                writer.write_all(&job_id.to_le_bytes())?;
+                writer.write_all(&suggested_target_per_device.to_le_bytes())?;
+                Ok(())
+            }
+            Self::QueryInfo => Ok(()),
+            Self::ShareAckBatch(acks) => {
+                let count = u16::try_from(acks.len())
+                    .map_err(|_| anyhow!("ShareAckBatch: too many acks: {}", acks.len()))?;
+                writer.write_all(&count.to_le_bytes())?;
+                for (work_id, result_code) in acks {
+                    writer.write_all(&work_id.to_le_bytes())?;
+                    writer.write_all(&[*result_code])?;
+                }
+                Ok(())
+            }
+            Self::NotifyBatch(jobs) => {
+                if jobs.is_empty() || jobs.len() > MAXIMUM_NOTIFY_BATCH_JOBS {
+                    return Err(anyhow!("NotifyBatch: invalid job count: {}", jobs.len()));
+                }
+                writer.write_all(&[jobs.len() as u8])?;
+                for (job_id, target, epoch_challenge) in jobs {
+                    writer.write_all(&job_id.to_le_bytes())?;
+                    writer.write_all(&target.to_le_bytes())?;
+                    writer.write_all(&epoch_challenge.to_bytes_le()?)?;
+                }
+                Ok(())
+            }
+            Self::RequestProof(work_id) => {
+                writer.write_all(&work_id.to_le_bytes())?;
+                Ok(())
+            }
+            Self::Kick(worker_id, reason) => {
+                if reason.len() > MAXIMUM_KICK_REASON_LENGTH {
+                    return Err(anyhow!("Kick: reason is too long: {}", reason.len()));
+                }
+                writer.write_all(&worker_id.to_le_bytes())?;
+                writer.write_all(&[reason.len() as u8])?;
+                writer.write_all(reason.as_bytes())?;
+                Ok(())
+            }
+            Self::Bye | Self::Unused(..) => Ok(()),
         }
     }
 
     /// Serializes the given message into bytes.
     #[inline]
     pub fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<()> {
-        bincode::serialize_into(&mut *writer, &self.id())?;
+        writer.write_all(&[self.id()])?;
         self.serialize_data_into(writer)
     }
 
+    /// Returns the size `serialize_into` writes, id included, without serializing anything.
+    /// Messages `serialize_into` rejects, e.g. with a reason over `MAXIMUM_KICK_REASON_LENGTH`,
+    /// report the size of their fields as they are.
+    pub fn serialized_size(&self) -> usize {
+        1 + match self {
+            Self::ConnectAck(true, _, _, signature, initial_job, tlvs) => {
+                let job = match initial_job {
+                    Some(_) => 8 + 8 + EPOCH_CHALLENGE_SIZE,
+                    None => 0,
+                };
+                let tlvs = match tlvs.is_empty() {
+                    true => 0,
+                    false => 1 + tlvs_size(tlvs),
+                };
+                1 + Address::<N>::size_in_bytes()
+                    + 4
+                    + 1
+                    + job
+                    + signature.as_ref().map_or(0, String::len)
+                    + tlvs
+            }
+            Self::ConnectAck(false, .., tlvs) => {
+                1 + Address::<N>::size_in_bytes() + tlvs_size(tlvs)
+            }
+            Self::Notify(_, target, share_target, _, tlvs) => {
+                let share_target_size = if share_target != target { 1 + 2 + 8 } else { 0 };
+                8 + 8 + EPOCH_CHALLENGE_SIZE + tlvs_size(tlvs) + share_target_size
+            }
+            Self::ShutDown
+            | Self::ResumeMining
+            | Self::QueryInfo
+            | Self::Bye
+            | Self::Unused(..) => 0,
+            Self::Pong(load_factor) => load_factor.map_or(0, |_| 1),
+            Self::Throttle(..)
+            | Self::HelloAck(..)
+            | Self::Pause(..)
+            | Self::Drain(..)
+            | Self::RequestProof(..) => 4,
+            Self::Chunk(.., bytes) => 4 + 2 + 2 + bytes.len(),
+            Self::NonceRange(..) => 8 + 8 + 8,
+            Self::UpgradeTlsAck(..) => 1,
+            Self::JobHint(..) => 8 + 8,
+            Self::ShareAckBatch(acks) => 2 + acks.len() * (4 + 1),
+            Self::NotifyBatch(jobs) => 1 + jobs.len() * NOTIFY_BATCH_JOB_SIZE,
+            Self::Kick(_, reason) => 4 + 1 + reason.len(),
+        }
+    }
+
+    /// Serializes the given message into a frame := << length(u32), message >>,
+    /// the same bytes the `Encoder` produces without negotiated capabilities.
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::with_capacity(4 + self.serialized_size());
+        buffer.extend_from_slice(&[0u8; 4]);
+        self.serialize_into(&mut buffer)?;
+        let len_slice = ((buffer.len() - 4) as u32).to_le_bytes();
+        buffer[..4].copy_from_slice(&len_slice);
+        Ok(buffer)
+    }
+
+    /// Returns the length of the frame `to_vec` produces, without allocating it.
+    pub fn encoded_len(&self) -> Result<usize> {
+        match self {
+            Self::ShutDown => Ok(SHUT_DOWN_FRAME_SIZE),
+            Self::Pong(None) => Ok(PONG_FRAME_SIZE),
+            Self::Pong(Some(_)) => Ok(LOADED_PONG_FRAME_SIZE),
+            _ => {
+                let mut counter = ByteCounter(4);
+                self.serialize_into(&mut counter)?;
+                Ok(counter.0)
+            }
+        }
+    }
+
+    /// Writes the frame produced by `to_vec` to `writer` and flushes it.
+    pub async fn write_framed<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.to_vec()?).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Returns the frame produced by `to_vec`, like `PoolMessageCS::encode_async`.
+    /// Pool messages hold no `Data`, so every field is written inline.
+    pub async fn encode_async(self) -> Result<Bytes> {
+        self.to_vec().map(Bytes::from)
+    }
+
+    /// Reads a single frame produced by `to_vec` from `reader` without a runtime, e.g. in offline
+    /// tools. Returns `None` if `reader` is at its end.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Option<Self>> {
+        read_frame(reader)?
+            .map(|buffer| Self::deserialize(&buffer))
+            .transpose()
+    }
+
+    /// Returns the message id of a frame produced by `to_vec` without deserializing it, skipping
+    /// leading keepalives. Fails until the id has arrived, or if it is not a message of this
+    /// direction.
+    pub fn peek_id(frame: &[u8]) -> Result<u8> {
+        peek_frame_id(frame, Self::all_ids(), PoolMessageCS::<N>::all_ids())
+    }
+
     /// Deserializes the given buffer into a message.
     #[inline]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            skip_all,
+            fields(id = buffer.first(), len = buffer.len(), name = tracing::field::Empty)
+        )
+    )]
     pub fn deserialize(buffer: &[u8]) -> Result<Self> {
         if buffer.is_empty() {
             return Err(anyhow!("Invalid message buffer"));
@@ -171,31 +616,83 @@ impl<N: Network> PoolMessageSC<N> {
         let (id, data) = (buffer[0], &buffer[1..]);
 
         let message = match id {
-            0 => match data.is_empty() {
-                true => return Err(anyhow!("Invalid message buffer")),
-                false => match data[0] {
-                    0 => Self::ConnectAck(false, bincode::deserialize(&data[1..=32])?, None, None),
-                    1 => Self::ConnectAck(
-                        true,
-                        bincode::deserialize(&data[1..=32])?,
-                        Some(u32::from_le_bytes([data[33], data[34], data[35], data[36]])),
-                        Some(String::from_utf8(data[37..].to_vec())?),
+            sc::CONNECT_ACK => {
+                let invalid = || anyhow!("Invalid 'ConnectAck' message: {:?} {:?}", buffer, data);
+                let truncated =
+                    || anyhow!("Truncated 'ConnectAck' message: {:?} {:?}", buffer, data);
+                let (&is_accept, rest) = data.split_first().ok_or_else(truncated)?;
+                let (address, rest) = split_address::<N>(rest).ok_or_else(truncated)?;
+                let address = Address::read_le(address)?;
+                match is_accept {
+                    0 => Self::ConnectAck(
+                        false,
+                        address,
+                        None,
+                        None,
+                        None,
+                        read_tlvs(rest, CONNECT_ACK_TAGS)?,
                     ),
-                    _ => {
-                        return Err(anyhow!(
-                            "Invalid 'ConnectAck' message: {:?} {:?}",
-                            buffer,
-                            data
-                        ))
+                    1 => {
+                        let id = rest.get(..4).ok_or_else(truncated)?;
+                        let id = u32::from_le_bytes([id[0], id[1], id[2], id[3]]);
+                        let (&has_job, mut reader) =
+                            rest[4..].split_first().ok_or_else(truncated)?;
+                        let initial_job = match has_job {
+                            0 => None,
+                            1 => {
+                                let job = reader.get(..16).ok_or_else(truncated)?;
+                                let (job_id, target) = (
+                                    u64::from_le_bytes(job[0..8].try_into()?),
+                                    u64::from_le_bytes(job[8..16].try_into()?),
+                                );
+                                reader = &reader[16..];
+                                Some((job_id, target, read_epoch_challenge(&mut reader)?))
+                            }
+                            _ => return Err(invalid()),
+                        };
+                        // V1 signatures run up to the TLV separator, see `PoolMessageSCv2` for
+                        // the length-prefixed layout. An accepted ack is always signed, so an
+                        // empty signature means the frame was cut short.
+                        let (signature, tlvs) = split_at_separator(reader);
+                        if signature.is_empty() {
+                            return Err(truncated());
+                        }
+                        Self::ConnectAck(
+                            true,
+                            address,
+                            Some(id),
+                            Some(String::from_utf8(signature.to_vec())?),
+                            initial_job,
+                            read_tlvs(tlvs, CONNECT_ACK_TAGS)?,
+                        )
                     }
-                },
-            },
-            1 => Self::Notify(
-                bincode::deserialize(&data[0..8])?,
-                bincode::deserialize(&data[8..16])?,
-                EpochChallenge::read_le(&data[16..])?,
-            ),
-            2 => match data.is_empty() {
+                    _ => return Err(invalid()),
+                }
+            }
+            sc::NOTIFY => {
+                if data.len() < 16 {
+                    return Err(anyhow!("Invalid 'Notify' message: {:?} {:?}", buffer, data));
+                }
+                let mut reader = &data[16..];
+                let epoch_challenge = read_epoch_challenge(&mut reader)?;
+                let target = u64::from_le_bytes(data[8..16].try_into()?);
+                let mut tlvs = read_tlvs(reader, NOTIFY_TAGS)?;
+                let share_target = match find_tlv(&tlvs, TAG_SHARE_TARGET) {
+                    Some(value) => u64::from_le_bytes(value.try_into().map_err(|_| {
+                        anyhow!("Invalid 'Notify' message: {:?} {:?}", buffer, data)
+                    })?),
+                    None => target,
+                };
+                tlvs.retain(|tlv| tlv.tag != TAG_SHARE_TARGET);
+                Self::Notify(
+                    u64::from_le_bytes(data[0..8].try_into()?),
+                    target,
+                    share_target,
+                    epoch_challenge,
+                    tlvs,
+                )
+            }
+            sc::SHUT_DOWN => match data.is_empty() {
                 true => Self::ShutDown,
                 false => {
                     return Err(anyhow!(
@@ -205,94 +702,376 @@ impl<N: Network> PoolMessageSC<N> {
                     ))
                 }
             },
-            3 => match data.is_empty() {
-                true => Self::Pong,
-                false => return Err(anyhow!("Invalid 'Pong' message: {:?} {:?}", buffer, data)),
+            sc::PONG => match data {
+                [] => Self::Pong(None),
+                [load_factor] => Self::Pong(Some(*load_factor)),
+                _ => return Err(anyhow!("Invalid 'Pong' message: {:?} {:?}", buffer, data)),
+            },
+            sc::THROTTLE => match data.len() {
+                4 => Self::Throttle(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'Throttle' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::CHUNK => match read_chunk(data) {
+                Some((transfer_id, index, total, bytes)) => {
+                    Self::Chunk(transfer_id, index, total, bytes)
+                }
+                None => return Err(anyhow!("Invalid 'Chunk' message: {:?} {:?}", buffer, data)),
+            },
+            sc::NONCE_RANGE => match data.len() {
+                24 => Self::NonceRange(
+                    u64::from_le_bytes(data[0..8].try_into()?),
+                    u64::from_le_bytes(data[8..16].try_into()?),
+                    u64::from_le_bytes(data[16..24].try_into()?),
+                ),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'NonceRange' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::HELLO_ACK => match data.len() {
+                4 => Self::HelloAck(read_capability_mask(data)?),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'HelloAck' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::PAUSE => match data.len() {
+                4 => Self::Pause(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                _ => return Err(anyhow!("Invalid 'Pause' message: {:?} {:?}", buffer, data)),
+            },
+            sc::RESUME_MINING => match data.is_empty() {
+                true => Self::ResumeMining,
+                false => {
+                    return Err(anyhow!(
+                        "Invalid 'ResumeMining' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::DRAIN => match data.len() {
+                4 => Self::Drain(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                _ => return Err(anyhow!("Invalid 'Drain' message: {:?} {:?}", buffer, data)),
+            },
+            sc::UPGRADE_TLS_ACK => match data {
+                [0] => Self::UpgradeTlsAck(false),
+                [1] => Self::UpgradeTlsAck(true),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'UpgradeTlsAck' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::JOB_HINT => match data.len() {
+                16 => Self::JobHint(
+                    u64::from_le_bytes(data[0..8].try_into()?),
+                    u64::from_le_bytes(data[8..16].try_into()?),
+                ),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'JobHint' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::QUERY_INFO => match data.is_empty() {
+                true => Self::QueryInfo,
+                false => {
+                    return Err(anyhow!(
+                        "Invalid 'QueryInfo' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::SHARE_ACK_BATCH => match data.len() >= 2
+                && data.len() - 2 == u16::from_le_bytes([data[0], data[1]]) as usize * 5
+            {
+                true => Self::ShareAckBatch(
+                    data[2..]
+                        .chunks_exact(5)
+                        .map(|entry| {
+                            (
+                                u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]),
+                                entry[4],
+                            )
+                        })
+                        .collect(),
+                ),
+                false => {
+                    return Err(anyhow!(
+                        "Invalid 'ShareAckBatch' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::NOTIFY_BATCH => match data.first() {
+                Some(&count)
+                    if (1..=MAXIMUM_NOTIFY_BATCH_JOBS).contains(&(count as usize))
+                        && data.len() == 1 + count as usize * NOTIFY_BATCH_JOB_SIZE =>
+                {
+                    let mut jobs = Vec::with_capacity(count as usize);
+                    for job in data[1..].chunks_exact(NOTIFY_BATCH_JOB_SIZE) {
+                        let mut reader = &job[16..];
+                        jobs.push((
+                            u64::from_le_bytes(job[0..8].try_into()?),
+                            u64::from_le_bytes(job[8..16].try_into()?),
+                            read_epoch_challenge(&mut reader)?,
+                        ));
+                    }
+                    Self::NotifyBatch(jobs)
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'NotifyBatch' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::REQUEST_PROOF => match data.len() {
+                4 => Self::RequestProof(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'RequestProof' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            sc::KICK => match data.len() >= 5
+                && data.len() == 5 + data[4] as usize
+                && data[4] as usize <= MAXIMUM_KICK_REASON_LENGTH
+            {
+                true => Self::Kick(
+                    u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+                    String::from_utf8(data[5..].to_vec())?,
+                ),
+                false => return Err(anyhow!("Invalid 'Kick' message: {:?} {:?}", buffer, data)),
             },
-            _ => return Err(anyhow!("Invalid message ID {}", id)),
+            sc::BYE => match data.is_empty() {
+                true => Self::Bye,
+                false => return Err(anyhow!("Invalid 'Bye' message: {:?} {:?}", buffer, data)),
+            },
+            _ => return Err(PoolProtocolError::UnknownMessageId(id).into()),
         };
 
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("name", message.name());
         Ok(message)
     }
 }
 
+/// Checks the length prefix of a frame against the default limit of its id, see
+/// `default_frame_limit`. Returns `false` while a frame above `MAXIMUM_MESSAGE_SIZE` lacks its id.
+fn check_frame_size(source: &[u8], length: usize) -> Result<bool, PoolProtocolError> {
+    match source.get(4) {
+        _ if length <= MAXIMUM_MESSAGE_SIZE => Ok(true),
+        None if length <= WORK_MESSAGE_LIMIT => Ok(false),
+        Some(&id) if length <= default_frame_limit(id, MAXIMUM_MESSAGE_SIZE) => Ok(true),
+        _ => Err(PoolProtocolError::FrameTooLarge(length)),
+    }
+}
+
+/// Returns the id of a frame := << length(u32), id, data >> if it is one of `ids`,
+/// `other_ids` being the ids of the other direction.
+fn peek_frame_id(frame: &[u8], ids: &[u8], other_ids: &[u8]) -> Result<u8> {
+    let id = first_frame_id(frame).ok_or_else(|| anyhow!("Invalid message buffer"))?;
+    if ids.contains(&id) {
+        Ok(id)
+    } else if other_ids.contains(&id) {
+        Err(PoolProtocolError::WrongDirection(id).into())
+    } else {
+        Err(PoolProtocolError::UnknownMessageId(id).into())
+    }
+}
+
+/// Reads one frame := << length(u32), message >> from `reader` and returns the message bytes,
+/// `None` if the input ends before the frame starts. The length is checked like the decoders do.
+fn read_frame<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut frame = vec![0u8; 4];
+    let mut read = 0;
+    while read < 4 {
+        match reader.read(&mut frame[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(n) => read += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+    let length = u32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]) as usize;
+    if length > MAXIMUM_MESSAGE_SIZE {
+        // The limit of a larger frame depends on its id.
+        frame.push(0);
+        reader.read_exact(&mut frame[4..])?;
+    }
+    check_frame_size(&frame, length)?;
+    let start = frame.len();
+    frame.resize(4 + length, 0);
+    reader.read_exact(&mut frame[start..])?;
+    frame.drain(..4);
+    Ok(Some(frame))
+}
+
+/// Splits the bytes of an address off the start of `data`, or returns `None` if `data` is shorter.
+pub(crate) fn split_address<N: Network>(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let size = Address::<N>::size_in_bytes();
+    data.get(..size).map(|address| (address, &data[size..]))
+}
+
+/// Reads an `EpochChallenge`, checking its size and degree before the costly construction.
+pub(crate) fn read_epoch_challenge<N: Network>(reader: &mut &[u8]) -> Result<EpochChallenge<N>> {
+    if reader.len() < EPOCH_CHALLENGE_SIZE {
+        return Err(PoolProtocolError::InvalidEpochChallenge(format!(
+            "expected {} bytes, found {}",
+            EPOCH_CHALLENGE_SIZE,
+            reader.len()
+        ))
+        .into());
+    }
+    let degree = u32::from_le_bytes([reader[36], reader[37], reader[38], reader[39]]);
+    if degree != N::COINBASE_PUZZLE_DEGREE {
+        return Err(PoolProtocolError::InvalidEpochChallenge(format!(
+            "expected degree {}, found {}",
+            N::COINBASE_PUZZLE_DEGREE,
+            degree
+        ))
+        .into());
+    }
+    Ok(EpochChallenge::read_le(reader)?)
+}
+
+/// Deprecated: encode with `PoolCodecSC`, which carries the codec configuration.
+/// Kept for one release as a thin wrapper around `PoolCodecSC::new()`.
 impl<N: Network> Encoder<PoolMessageSC<N>> for PoolMessageSC<N> {
     type Error = anyhow::Error;
 
     fn encode(&mut self, message: PoolMessageSC<N>, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        dst.extend_from_slice(&0u32.to_le_bytes());
-        message.serialize_into(&mut dst.writer())?;
-        let len_slice = (dst[4..].len() as u32).to_le_bytes();
-        dst[..4].copy_from_slice(&len_slice);
-        Ok(())
+        PoolCodecSC::new().encode(message, dst)
     }
 }
 
+/// Deprecated: decode with `PoolCodecSC`, which carries the codec configuration.
+/// Kept for one release as a thin wrapper around `PoolCodecSC::new()`.
 impl<N: Network> Decoder for PoolMessageSC<N> {
     type Error = std::io::Error;
     type Item = PoolMessageSC<N>;
 
     fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if source.len() < 4 {
-            return Ok(None);
-        }
-        let mut length_bytes = [0u8; 4];
-        length_bytes.copy_from_slice(&source[..4]);
-        let length = u32::from_le_bytes(length_bytes) as usize;
-        // Check that the length is not too large to avoid a denial of
-        // service attack where the node server runs out of memory.
-        if length > MAXIMUM_MESSAGE_SIZE {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Frame of length {} is too large.", length),
-            ));
-        }
-
-        if source.len() < 4 + length {
-            // The full message has not yet arrived.
-            //
-            // We reserve more space in the buffer. This is not strictly
-            // necessary, but is a good idea performance-wise.
-            source.reserve(4 + length - source.len());
-
-            // We inform `Framed` that we need more bytes to form the next frame.
-            return Ok(None);
-        }
-
-        // Convert the buffer to a message, or fail if it is not valid.
-        let message = match PoolMessageSC::deserialize(&source[4..][..length]) {
-            Ok(message) => Ok(Some(message)),
-            Err(error) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error)),
-        };
-
-        // Use `advance` to modify the source such that it no longer contains this frame.
-        source.advance(4 + length);
+        PoolCodecSC::new().decode(source)
+    }
 
-        message
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        PoolCodecSC::new().decode_eof(source)
     }
 }
 #[derive(Clone, Debug)]
 pub enum PoolMessageCS<N: Network> {
-    /// Connect := (type, address_type, version(major, minor, patch), name, address)
-    Connect(u8, u8, u8, u8, u8, String, String),
-    /// submit := (work_id, job_id, address, prover_solution)
-    Submit(u32, u64, Data<ProverSolution<N>>),
+    /// Connect := (type, address_type, version(major, minor, patch), name, address, tlvs)
+    Connect(u8, u8, u8, u8, u8, String, String, Vec<Tlv>),
+    /// submit := (work_id, sub_worker_id, job_id, prover_solution, [signature])
+    /// sub_worker_id is 0 for the connection's own worker, otherwise an id from `RegisterWorker`.
+    /// The optional signature is the worker's signature of the solution commitment, see `sign_solution`.
+    Submit(u32, u32, u64, Data<ProverSolution<N>>, Option<Signature<N>>),
     /// DisConnect := (id)
     DisConnect(u32),
     /// Ping
     Ping,
-    // Unused
+    /// JobStatus := (worker_id, job_id)
+    JobStatus(u32, u64),
+    /// RegisterWorker := (sub_worker_id, name)
+    RegisterWorker(u32, String),
+    /// Chunk := (transfer_id, index, total, bytes)
+    Chunk(u32, u16, u16, Vec<u8>),
+    /// ResumeSession := (worker_id, address, signature), from an earlier `Connect` and its accepted `ConnectAck`
+    ResumeSession(u32, String, String),
+    /// Resume := (resume_token), from the last accepted `ConnectAck`
+    Resume(u64),
+    /// Hello := (capabilities), sent before `Connect` to advertise the client's features
+    Hello(u32),
+    /// UpgradeTls, asks the pool to switch the connection to TLS before `Hello` or `Connect`
+    UpgradeTls,
+    /// InfoReport := (agent_name, version), answers `QueryInfo` for diagnostics
+    InfoReport(String, String),
+    /// SetNotifyInterval := (min_notify_interval_ms), the least time the pool should leave between
+    /// `Notify`s for a new job of the same epoch, e.g. for low-power devices; 0 for no floor.
+    /// A new epoch is still notified at once, see `NotifyPacer`.
+    SetNotifyInterval(u32),
+    /// SubmitCommitment := (work_id, job_id, commitment), a share sent as the solution's KZG
+    /// commitment alone. The pool checks the commitment against the target and answers
+    /// `RequestProof` for the shares it needs in full, e.g. block candidates, which the client
+    /// then sends as a `Submit` with the same work_id. Clients keep the solutions until the job ends.
+    SubmitCommitment(u32, u64, PuzzleCommitment<N>),
+    /// PowerStats := (worker_id, power_watts, efficiency_mj_per_proof), the draw of a worker's rig
+    /// for fleet power reporting; 0 where the rig does not measure it.
+    PowerStats(u32, u32, u32),
+    /// NotifyAck := (job_id, received_at_ms), acknowledges the `Notify` of a job, stamped in
+    /// milliseconds since the Unix epoch on the client's clock, so the pool can measure how long
+    /// jobs take to reach each worker.
+    NotifyAck(u64, u64),
+    /// SubmitChunk := (work_id, job_id, index, total, bytes), a part of a solution too large for a
+    /// single `Submit` frame, see `split_submit`. The pool rebuilds the solution with a
+    /// `SubmitReassembler` once all `total` chunks arrive.
+    SubmitChunk(u32, u64, u16, u16, Bytes),
+    /// Bye := (), sent just before the client closes the connection on purpose, e.g. on shutdown.
+    /// Unlike a dropped connection, the pool need not keep the session around for a quick resume.
+    Bye,
+    /// Unused := (id), the default, or a message id this build does not know,
+    /// see `UnknownIdPolicy::ReturnUnused`
     #[allow(unused)]
-    Unused,
+    Unused(u8),
 }
 
 impl<N: Network> Default for PoolMessageCS<N> {
     fn default() -> Self {
-        Self::Unused
+        Self::Unused(cs::UNUSED)
     }
 }
 
 impl<N: Network> PoolMessageCS<N> {
+    /// Returns a V1 `Connect`, failing if the name is longer than `MAXIMUM_CONNECT_NAME_LENGTH`.
+    /// Use `PoolMessageCSv2::connect` for longer names.
+    pub fn connect(
+        worker_type: u8,
+        address_type: u8,
+        version: (u8, u8, u8),
+        custom_name: String,
+        address: String,
+        tlvs: Vec<Tlv>,
+    ) -> Result<Self> {
+        if custom_name.len() > MAXIMUM_CONNECT_NAME_LENGTH {
+            return Err(anyhow!("Connect: name is too long: {}", custom_name.len()));
+        }
+        let (v_major, v_minor, v_patch) = version;
+        Ok(Self::Connect(
+            worker_type,
+            address_type,
+            v_major,
+            v_minor,
+            v_patch,
+            custom_name,
+            address,
+            tlvs,
+        ))
+    }
+
     /// Returns the messge name
     #[inline]
     #[allow(dead_code)]
@@ -302,21 +1081,73 @@ impl<N: Network> PoolMessageCS<N> {
             Self::Submit(..) => "Submit",
             Self::DisConnect(..) => "Disconnect",
             Self::Ping => "Ping",
-            Self::Unused => "Unused",
+            Self::JobStatus(..) => "JobStatus",
+            Self::RegisterWorker(..) => "RegisterWorker",
+            Self::Chunk(..) => "Chunk",
+            Self::ResumeSession(..) => "ResumeSession",
+            Self::Resume(..) => "Resume",
+            Self::Hello(..) => "Hello",
+            Self::UpgradeTls => "UpgradeTls",
+            Self::InfoReport(..) => "InfoReport",
+            Self::SetNotifyInterval(..) => "SetNotifyInterval",
+            Self::SubmitCommitment(..) => "SubmitCommitment",
+            Self::PowerStats(..) => "PowerStats",
+            Self::NotifyAck(..) => "NotifyAck",
+            Self::SubmitChunk(..) => "SubmitChunk",
+            Self::Bye => "Bye",
+            Self::Unused(..) => "Unused",
         }
     }
 
     /// Returns the message ID.
     pub fn id(&self) -> u8 {
         match self {
-            Self::Connect(..) => 128,
-            Self::Submit(..) => 129,
-            Self::DisConnect(..) => 130,
-            Self::Ping => 131,
-            Self::Unused => 255,
+            Self::Connect(..) => cs::CONNECT,
+            Self::Submit(..) => cs::SUBMIT,
+            Self::DisConnect(..) => cs::DISCONNECT,
+            Self::Ping => cs::PING,
+            Self::JobStatus(..) => cs::JOB_STATUS,
+            Self::RegisterWorker(..) => cs::REGISTER_WORKER,
+            Self::Chunk(..) => cs::CHUNK,
+            Self::ResumeSession(..) => cs::RESUME_SESSION,
+            Self::Resume(..) => cs::RESUME,
+            Self::Hello(..) => cs::HELLO,
+            Self::UpgradeTls => cs::UPGRADE_TLS,
+            Self::InfoReport(..) => cs::INFO_REPORT,
+            Self::SetNotifyInterval(..) => cs::SET_NOTIFY_INTERVAL,
+            Self::SubmitCommitment(..) => cs::SUBMIT_COMMITMENT,
+            Self::PowerStats(..) => cs::POWER_STATS,
+            Self::NotifyAck(..) => cs::NOTIFY_ACK,
+            Self::SubmitChunk(..) => cs::SUBMIT_CHUNK,
+            Self::Bye => cs::BYE,
+            Self::Unused(id) => *id,
         }
     }
 
+    /// Returns the id of every message except `Unused`.
+    pub fn all_ids() -> &'static [u8] {
+        &[
+            cs::CONNECT,
+            cs::SUBMIT,
+            cs::DISCONNECT,
+            cs::PING,
+            cs::JOB_STATUS,
+            cs::REGISTER_WORKER,
+            cs::CHUNK,
+            cs::RESUME_SESSION,
+            cs::RESUME,
+            cs::HELLO,
+            cs::UPGRADE_TLS,
+            cs::INFO_REPORT,
+            cs::SET_NOTIFY_INTERVAL,
+            cs::SUBMIT_COMMITMENT,
+            cs::POWER_STATS,
+            cs::NOTIFY_ACK,
+            cs::SUBMIT_CHUNK,
+            cs::BYE,
+        ]
+    }
+
     /// Returns the message data as bytes.
     #[inline]
     pub fn serialize_data_into<W: Write>(&self, writer: &mut W) -> Result<()> {
@@ -329,44 +1160,257 @@ impl<N: Network> PoolMessageCS<N> {
                 v_patch,
                 custom_name,
                 address,
+                tlvs,
             ) => {
                 writer.write_all(&[*worker_type])?;
                 writer.write_all(&[*address_type])?;
                 writer.write_all(&[*v_major])?;
                 writer.write_all(&[*v_minor])?;
                 writer.write_all(&[*v_patch])?;
-                let len = custom_name.len() as u8;
+                let len = u8::try_from(custom_name.len())
+                    .map_err(|_| anyhow!("Connect: name is too long: {}", custom_name.len()))?;
                 writer.write_all(&[len])?;
                 writer.write_all(custom_name.as_bytes())?;
-                //bincode::serialize_into(&mut *writer, custom_name)?;
-                //bincode::serialize_into(&mut *writer, address)?;
                 writer.write_all(address.as_bytes())?;
+                if !tlvs.is_empty() {
+                    writer.write_all(&[TLV_SEPARATOR])?;
+                    write_tlvs(writer, tlvs)?;
+                }
                 Ok(())
             }
-            Self::Submit(worker_id, job_id, prover_solution) => {
-                bincode::serialize_into(&mut *writer, worker_id)?;
-                bincode::serialize_into(&mut *writer, job_id)?;
+            Self::Submit(worker_id, sub_worker_id, job_id, prover_solution, signature) => {
+                writer.write_all(&worker_id.to_le_bytes())?;
+                writer.write_all(&sub_worker_id.to_le_bytes())?;
+                writer.write_all(&job_id.to_le_bytes())?;
+                match signature {
+                    Some(signature) => {
+                        writer.write_all(&[1u8])?;
+                        writer.write_all(&signature.to_bytes_le()?)?;
+                    }
+                    None => writer.write_all(&[0u8])?,
+                }
                 prover_solution.serialize_blocking_into(writer)
             }
             Self::DisConnect(id) => {
-                bincode::serialize_into(&mut *writer, id)?;
+                writer.write_all(&id.to_le_bytes())?;
                 Ok(())
             }
             Self::Ping => Ok(()),
-            Self::Unused => Ok(()),
+            Self::JobStatus(worker_id, job_id) => {
+                writer.write_all(&worker_id.to_le_bytes())?;
+                writer.write_all(&job_id.to_le_bytes())?;
+                Ok(())
+            }
+            Self::RegisterWorker(sub_worker_id, name) => {
+                if name.len() > MAXIMUM_WORKER_NAME_LENGTH {
+                    return Err(anyhow!("RegisterWorker: name is too long: {}", name.len()));
+                }
+                writer.write_all(&sub_worker_id.to_le_bytes())?;
+                writer.write_all(&[name.len() as u8])?;
+                writer.write_all(name.as_bytes())?;
+                Ok(())
+            }
+            Self::Chunk(transfer_id, index, total, bytes) => {
+                write_chunk(writer, *transfer_id, *index, *total, bytes)
+            }
+            Self::ResumeSession(worker_id, address, signature) => {
+                let address_length = u8::try_from(address.len())
+                    .map_err(|_| anyhow!("Address is too long: {}", address.len()))?;
+                writer.write_all(&worker_id.to_le_bytes())?;
+                writer.write_all(&[address_length])?;
+                writer.write_all(address.as_bytes())?;
+                writer.write_all(signature.as_bytes())?;
+                Ok(())
+            }
+            Self::Resume(resume_token) => {
+                writer.write_all(&resume_token.to_le_bytes())?;
+                Ok(())
+            }
+            Self::Hello(capabilities) => {
+                writer.write_all(&capabilities.to_le_bytes())?;
+                Ok(())
+            }
+            Self::UpgradeTls => Ok(()),
+            Self::InfoReport(agent_name, version) => {
+                for field in [agent_name, version] {
+                    if field.len() > MAXIMUM_INFO_REPORT_LENGTH {
+                        return Err(anyhow!("InfoReport: field is too long: {}", field.len()));
+                    }
+                    writer.write_all(&[field.len() as u8])?;
+                    writer.write_all(field.as_bytes())?;
+                }
+                Ok(())
+            }
+            Self::SetNotifyInterval(min_notify_interval_ms) => {
+                writer.write_all(&min_notify_interval_ms.to_le_bytes())?;
+                Ok(())
+            }
+            Self::SubmitCommitment(work_id, job_id, commitment) => {
+                writer.write_all(&work_id.to_le_bytes())?;
+                writer.write_all(&job_id.to_le_bytes())?;
+                writer.write_all(&commitment.to_bytes_le()?)?;
+                Ok(())
+            }
+            Self::PowerStats(worker_id, power_watts, efficiency_mj_per_proof) => {
+                writer.write_all(&worker_id.to_le_bytes())?;
+                writer.write_all(&power_watts.to_le_bytes())?;
+                writer.write_all(&efficiency_mj_per_proof.to_le_bytes())?;
+                Ok(())
+            }
+            Self::NotifyAck(job_id, received_at_ms) => {
+                writer.write_all(&job_id.to_le_bytes())?;
+                writer.write_all(&received_at_ms.to_le_bytes())?;
+                Ok(())
+            }
+            Self::SubmitChunk(work_id, job_id, index, total, bytes) => {
+                writer.write_all(&work_id.to_le_bytes())?;
+                writer.write_all(&job_id.to_le_bytes())?;
+                writer.write_all(&index.to_le_bytes())?;
+                writer.write_all(&total.to_le_bytes())?;
+                writer.write_all(bytes)?;
+                Ok(())
+            }
+            Self::Bye | Self::Unused(..) => Ok(()),
         }
     }
 
     /// Serializes the given message into bytes.
     #[inline]
     pub fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<()> {
-        bincode::serialize_into(&mut *writer, &self.id())?;
+        writer.write_all(&[self.id()])?;
         self.serialize_data_into(writer)
     }
 
+    /// Returns the size `serialize_into` writes, id included, without serializing anything,
+    /// like `PoolMessageSC::serialized_size`.
+    pub fn serialized_size(&self) -> usize {
+        1 + match self {
+            Self::Connect(.., custom_name, address, tlvs) => {
+                let tlvs = match tlvs.is_empty() {
+                    true => 0,
+                    false => 1 + tlvs_size(tlvs),
+                };
+                5 + 1 + custom_name.len() + address.len() + tlvs
+            }
+            Self::Submit(.., prover_solution, signature) => {
+                let signature = signature.as_ref().map_or(0, |_| SIGNATURE_SIZE);
+                4 + 4 + 8 + 1 + signature + prover_solution.serialized_size()
+            }
+            Self::Ping | Self::UpgradeTls | Self::Bye | Self::Unused(..) => 0,
+            Self::DisConnect(..) | Self::Hello(..) | Self::SetNotifyInterval(..) => 4,
+            Self::JobStatus(..) => 4 + 8,
+            Self::RegisterWorker(_, name) => 4 + 1 + name.len(),
+            Self::Chunk(.., bytes) => 4 + 2 + 2 + bytes.len(),
+            Self::ResumeSession(_, address, signature) => 4 + 1 + address.len() + signature.len(),
+            Self::Resume(..) => 8,
+            Self::InfoReport(agent_name, version) => 1 + agent_name.len() + 1 + version.len(),
+            Self::SubmitCommitment(..) => 4 + 8 + PUZZLE_COMMITMENT_SIZE,
+            Self::PowerStats(..) => 4 + 4 + 4,
+            Self::NotifyAck(..) => 8 + 8,
+            Self::SubmitChunk(.., bytes) => 4 + 8 + 2 + 2 + bytes.len(),
+        }
+    }
+
+    /// Serializes the given message into a frame := << length(u32), message >>,
+    /// the same bytes the `Encoder` produces without negotiated capabilities.
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::with_capacity(4 + self.serialized_size());
+        buffer.extend_from_slice(&[0u8; 4]);
+        self.serialize_into(&mut buffer)?;
+        let len_slice = ((buffer.len() - 4) as u32).to_le_bytes();
+        buffer[..4].copy_from_slice(&len_slice);
+        Ok(buffer)
+    }
+
+    /// Returns the length of the frame `to_vec` produces, without allocating it.
+    pub fn encoded_len(&self) -> Result<usize> {
+        match self {
+            Self::Ping => Ok(PING_FRAME_SIZE),
+            Self::DisConnect(..) => Ok(DISCONNECT_FRAME_SIZE),
+            _ => {
+                let mut counter = ByteCounter(4);
+                self.serialize_into(&mut counter)?;
+                Ok(counter.0)
+            }
+        }
+    }
+
+    /// Writes the frame produced by `encode_async` to `writer` and flushes it.
+    pub async fn write_framed<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> Result<()> {
+        let frame = self.clone().encode_async().await?;
+        writer.write_all(&frame).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Serializes the solution of a `Submit` holding a `Data::Object` into a `Data::Buffer` on
+    /// the blocking pool, and returns other messages as they are. Prepare submits before handing
+    /// them to a codec, which serializes inline and would otherwise block the reactor thread.
+    /// `encode_async` and `write_framed` go through it too, so this is the only place a solution
+    /// is serialized off the reactor.
+    pub async fn prepare(self) -> Result<Self> {
+        match self {
+            Self::Submit(worker_id, sub_worker_id, job_id, Data::Object(solution), signature) => {
+                let solution = Data::<ProverSolution<N>>::Object(solution)
+                    .serialize()
+                    .await?;
+                Ok(Self::Submit(
+                    worker_id,
+                    sub_worker_id,
+                    job_id,
+                    Data::Buffer(solution),
+                    signature,
+                ))
+            }
+            message => Ok(message),
+        }
+    }
+
+    /// Returns the frame produced by `to_vec`, after `prepare`, so a large object stays off the
+    /// reactor thread.
+    pub async fn encode_async(self) -> Result<Bytes> {
+        self.prepare().await?.to_vec().map(Bytes::from)
+    }
+
+    /// Reads a single frame produced by `to_vec` from `reader` without a runtime, e.g. in offline
+    /// tools. Returns `None` if `reader` is at its end.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Option<Self>> {
+        read_frame(reader)?
+            .map(|buffer| Self::deserialize(&buffer))
+            .transpose()
+    }
+
+    /// Returns the message id of a frame produced by `to_vec` without deserializing it,
+    /// like `PoolMessageSC::peek_id`.
+    pub fn peek_id(frame: &[u8]) -> Result<u8> {
+        peek_frame_id(frame, Self::all_ids(), PoolMessageSC::<N>::all_ids())
+    }
+
     /// Deserializes the given buffer into a message.
     #[inline]
     pub fn deserialize(buffer: &[u8]) -> Result<Self> {
+        Self::deserialize_with(buffer, Bytes::copy_from_slice)
+    }
+
+    /// Deserializes the given buffer into a message like `deserialize`, keeping the solution
+    /// of a `Submit`, or the bytes of a `SubmitChunk`, as a slice of `buffer` instead of copying it.
+    #[inline]
+    pub fn deserialize_bytes(buffer: Bytes) -> Result<Self> {
+        Self::deserialize_with(&buffer, |solution| buffer.slice_ref(solution))
+    }
+
+    /// Deserializes a message, turning the solution of a `Submit` or the bytes of a `SubmitChunk`
+    /// into `Bytes` with `solution`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            name = "deserialize",
+            skip_all,
+            fields(id = buffer.first(), len = buffer.len(), name = tracing::field::Empty)
+        )
+    )]
+    fn deserialize_with(buffer: &[u8], solution: impl FnOnce(&[u8]) -> Bytes) -> Result<Self> {
         if buffer.is_empty() {
             return Err(anyhow!("Invalid message buffer"));
         }
@@ -374,94 +1418,281 @@ impl<N: Network> PoolMessageCS<N> {
         let (id, data) = (buffer[0], &buffer[1..]);
 
         let message = match id {
-            128 => {
-                let name_end = (6 + data[5]) as usize;
+            cs::CONNECT => {
+                if data.len() < 6 || data.len() < 6 + data[5] as usize {
+                    return Err(anyhow!(
+                        "Invalid 'Connect' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ));
+                }
+                let name_end = 6 + data[5] as usize;
+                let (address, tlvs) = split_at_separator(&data[name_end..]);
                 Self::Connect(
                     data[0],
                     data[1],
                     data[2],
                     data[3],
                     data[4],
-                    String::from_utf8((data[6..name_end]).to_vec())?,
-                    String::from_utf8((data[name_end..]).to_vec())?,
+                    String::from_utf8((data[6..name_end]).to_vec())
+                        .map_err(|_| PoolProtocolError::InvalidUtf8("worker name".to_string()))?,
+                    String::from_utf8(address.to_vec())
+                        .map_err(|_| PoolProtocolError::InvalidUtf8("address".to_string()))?,
+                    read_tlvs(tlvs, CONNECT_TAGS)?,
                 )
             }
-            129 => Self::Submit(
-                bincode::deserialize(&data[0..4])?,
-                bincode::deserialize(&data[4..12])?,
-                Data::Buffer(data[12..].to_vec().into()),
-            ),
-            130 => Self::DisConnect(bincode::deserialize(data)?),
-            131 => match data.is_empty() {
-                true => Self::Ping,
+            cs::SUBMIT => {
+                if data.len() < 17 {
+                    return Err(anyhow!("Invalid 'Submit' message: {:?} {:?}", buffer, data));
+                }
+                let mut reader = &data[17..];
+                let signature = match data[16] {
+                    0 => None,
+                    1 => Some(Signature::read_le(&mut reader)?),
+                    _ => return Err(anyhow!("Invalid 'Submit' message: {:?} {:?}", buffer, data)),
+                };
+                Self::Submit(
+                    u32::from_le_bytes(data[0..4].try_into()?),
+                    u32::from_le_bytes(data[4..8].try_into()?),
+                    u64::from_le_bytes(data[8..16].try_into()?),
+                    Data::Buffer(solution(reader)),
+                    signature,
+                )
+            }
+            cs::DISCONNECT => match data.len() {
+                4 => Self::DisConnect(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'DisConnect' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::PING => match data.is_empty() {
+                true => Self::Ping,
                 false => return Err(anyhow!("Invalid 'Ping' message: {:?} {:?}", buffer, data)),
             },
-            _ => return Err(anyhow!("Invalid message ID {}", id)),
+            cs::JOB_STATUS => match data.len() {
+                12 => Self::JobStatus(
+                    u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+                    u64::from_le_bytes([
+                        data[4], data[5], data[6], data[7], data[8], data[9], data[10], data[11],
+                    ]),
+                ),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'JobStatus' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::REGISTER_WORKER => match data.len() >= 5
+                && data.len() == 5 + data[4] as usize
+                && data[4] as usize <= MAXIMUM_WORKER_NAME_LENGTH
+            {
+                true => Self::RegisterWorker(
+                    u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+                    String::from_utf8(data[5..].to_vec())?,
+                ),
+                false => {
+                    return Err(anyhow!(
+                        "Invalid 'RegisterWorker' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::CHUNK => match read_chunk(data) {
+                Some((transfer_id, index, total, bytes)) => {
+                    Self::Chunk(transfer_id, index, total, bytes)
+                }
+                None => return Err(anyhow!("Invalid 'Chunk' message: {:?} {:?}", buffer, data)),
+            },
+            cs::RESUME_SESSION => match data.len() > 5 && data.len() > 5 + data[4] as usize {
+                true => Self::ResumeSession(
+                    u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+                    String::from_utf8(data[5..5 + data[4] as usize].to_vec())?,
+                    String::from_utf8(data[5 + data[4] as usize..].to_vec())?,
+                ),
+                false => {
+                    return Err(anyhow!(
+                        "Invalid 'ResumeSession' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::RESUME => match data.len() {
+                8 => Self::Resume(u64::from_le_bytes([
+                    data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7],
+                ])),
+                _ => return Err(anyhow!("Invalid 'Resume' message: {:?} {:?}", buffer, data)),
+            },
+            cs::HELLO => match data.len() {
+                4 => Self::Hello(read_capability_mask(data)?),
+                _ => return Err(anyhow!("Invalid 'Hello' message: {:?} {:?}", buffer, data)),
+            },
+            cs::UPGRADE_TLS => match data.is_empty() {
+                true => Self::UpgradeTls,
+                false => {
+                    return Err(anyhow!(
+                        "Invalid 'UpgradeTls' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::INFO_REPORT => match read_info_report(data) {
+                Some((agent_name, version)) => Self::InfoReport(agent_name, version),
+                None => {
+                    return Err(anyhow!(
+                        "Invalid 'InfoReport' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::SET_NOTIFY_INTERVAL => match data.len() {
+                4 => Self::SetNotifyInterval(u32::from_le_bytes([
+                    data[0], data[1], data[2], data[3],
+                ])),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'SetNotifyInterval' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::SUBMIT_COMMITMENT => match data.len() == 12 + PUZZLE_COMMITMENT_SIZE {
+                true => Self::SubmitCommitment(
+                    u32::from_le_bytes(data[0..4].try_into()?),
+                    u64::from_le_bytes(data[4..12].try_into()?),
+                    PuzzleCommitment::read_le(&data[12..])?,
+                ),
+                false => {
+                    return Err(anyhow!(
+                        "Invalid 'SubmitCommitment' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::POWER_STATS => match data.len() {
+                12 => Self::PowerStats(
+                    u32::from_le_bytes(data[0..4].try_into()?),
+                    u32::from_le_bytes(data[4..8].try_into()?),
+                    u32::from_le_bytes(data[8..12].try_into()?),
+                ),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'PowerStats' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::NOTIFY_ACK => match data.len() {
+                16 => Self::NotifyAck(
+                    u64::from_le_bytes(data[0..8].try_into()?),
+                    u64::from_le_bytes(data[8..16].try_into()?),
+                ),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid 'NotifyAck' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::SUBMIT_CHUNK => match read_submit_chunk(data) {
+                Some((work_id, job_id, index, total, bytes)) => {
+                    Self::SubmitChunk(work_id, job_id, index, total, solution(bytes))
+                }
+                None => {
+                    return Err(anyhow!(
+                        "Invalid 'SubmitChunk' message: {:?} {:?}",
+                        buffer,
+                        data
+                    ))
+                }
+            },
+            cs::BYE => match data.is_empty() {
+                true => Self::Bye,
+                false => return Err(anyhow!("Invalid 'Bye' message: {:?} {:?}", buffer, data)),
+            },
+            _ => return Err(PoolProtocolError::UnknownMessageId(id).into()),
         };
 
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("name", message.name());
         Ok(message)
     }
 }
 
+/// A writer that only counts the bytes written to it.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads an `InfoReport` := (name_length, agent_name, version_length, version).
+fn read_info_report(data: &[u8]) -> Option<(String, String)> {
+    let mut fields = Vec::with_capacity(2);
+    let mut data = data;
+    for _ in 0..2 {
+        let (&length, rest) = data.split_first()?;
+        let length = length as usize;
+        if length > MAXIMUM_INFO_REPORT_LENGTH || rest.len() < length {
+            return None;
+        }
+        fields.push(String::from_utf8(rest[..length].to_vec()).ok()?);
+        data = &rest[length..];
+    }
+    let version = fields.pop()?;
+    let agent_name = fields.pop()?;
+    data.is_empty().then_some((agent_name, version))
+}
+
+/// Deprecated: encode with `PoolCodecCS`, which carries the codec configuration.
+/// Kept for one release as a thin wrapper around `PoolCodecCS::new()`.
 impl<N: Network> Encoder<PoolMessageCS<N>> for PoolMessageCS<N> {
     type Error = anyhow::Error;
 
     fn encode(&mut self, message: PoolMessageCS<N>, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        dst.extend_from_slice(&0u32.to_le_bytes());
-        message.serialize_into(&mut dst.writer())?;
-        let len_slice = (dst[4..].len() as u32).to_le_bytes();
-        dst[..4].copy_from_slice(&len_slice);
-        Ok(())
+        PoolCodecCS::new().encode(message, dst)
     }
 }
 
+/// Deprecated: decode with `PoolCodecCS`, which carries the codec configuration.
+/// Kept for one release as a thin wrapper around `PoolCodecCS::new()`.
 impl<N: Network> Decoder for PoolMessageCS<N> {
     type Error = std::io::Error;
     type Item = PoolMessageCS<N>;
 
     fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if source.len() < 4 {
-            return Ok(None);
-        }
-        let mut length_bytes = [0u8; 4];
-        length_bytes.copy_from_slice(&source[..4]);
-        let length = u32::from_le_bytes(length_bytes) as usize;
-        // Check that the length is not too large to avoid a denial of
-        // service attack where the node server runs out of memory.
-        if length > MAXIMUM_MESSAGE_SIZE {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Frame of length {} is too large.", length),
-            ));
-        }
-
-        if source.len() < 4 + length {
-            // The full message has not yet arrived.
-            //
-            // We reserve more space in the buffer. This is not strictly
-            // necessary, but is a good idea performance-wise.
-            source.reserve(4 + length - source.len());
-
-            // We inform `Framed` that we need more bytes to form the next frame.
-            return Ok(None);
-        }
-
-        // Convert the buffer to a message, or fail if it is not valid.
-        let message = match PoolMessageCS::deserialize(&source[4..][..length]) {
-            Ok(message) => Ok(Some(message)),
-            Err(error) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error)),
-        };
-
-        // Use `advance` to modify the source such that it no longer contains this frame.
-        source.advance(4 + length);
+        PoolCodecCS::new().decode(source)
+    }
 
-        message
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        PoolCodecCS::new().decode_eof(source)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::message::signature::sign_solution;
     use ::rand::thread_rng;
     use snarkvm::prelude::Testnet3;
     type CurrentNetwork = Testnet3;
@@ -471,31 +1702,39 @@ mod tests {
 
     fn check_pool_message_sc(message: PoolMessageSC<CurrentNetwork>) {
         println!("{:?}", message);
+        let bytes = message.to_vec().unwrap();
         let mut buffer = BytesMut::new();
-        let _ = PoolMessageSC::<CurrentNetwork>::default().encode(message, &mut buffer);
+        let _ = PoolCodecSC::<CurrentNetwork>::new().encode(message, &mut buffer);
+        assert_eq!(&buffer[..], &bytes[..]);
         println!("{:?}", buffer);
-        let message1 = PoolMessageSC::<CurrentNetwork>::default()
+        let message1 = PoolCodecSC::<CurrentNetwork>::new()
             .decode(&mut buffer.clone())
             .unwrap()
             .unwrap();
         println!("{:?}", message1);
         let mut buffer_2 = BytesMut::new();
-        let _ = PoolMessageSC::<CurrentNetwork>::default().encode(message1, &mut buffer_2);
+        let _ = PoolCodecSC::<CurrentNetwork>::new().encode(message1, &mut buffer_2);
         assert_eq!(buffer, buffer_2);
     }
 
+    /// Returns a codec that agreed on `CAP_SIGNED_SHARES`, which signed `Submit`s need.
+    fn signed_codec() -> PoolCodecCS<CurrentNetwork> {
+        let mut codec = PoolCodecCS::<CurrentNetwork>::new();
+        codec.set_capabilities(CAP_SIGNED_SHARES);
+        codec
+    }
+
     fn check_pool_message_cs(message: PoolMessageCS<CurrentNetwork>) {
         println!("message: {:?}", message);
+        let bytes = message.to_vec().unwrap();
         let mut buffer = BytesMut::new();
-        let _ = PoolMessageCS::<CurrentNetwork>::default().encode(message, &mut buffer);
+        let _ = signed_codec().encode(message, &mut buffer);
+        assert_eq!(&buffer[..], &bytes[..]);
         println!("buffer: {:?}", buffer);
-        let message1 = PoolMessageCS::<CurrentNetwork>::default()
-            .decode(&mut buffer.clone())
-            .unwrap()
-            .unwrap();
+        let message1 = signed_codec().decode(&mut buffer.clone()).unwrap().unwrap();
         println!("message: {:?}", message1);
         let mut buffer_2 = BytesMut::new();
-        let _ = PoolMessageCS::default().encode(message1, &mut buffer_2);
+        let _ = signed_codec().encode(message1, &mut buffer_2);
         println!("buffer: {:?}", buffer_2);
         assert_eq!(buffer, buffer_2);
     }
@@ -512,6 +1751,8 @@ mod tests {
             address,
             Some(1),
             Some(String::from("testsignature")),
+            None,
+            vec![],
         );
         check_pool_message_sc(message);
 
@@ -520,14 +1761,78 @@ mod tests {
             CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
             CurrentNetwork::COINBASE_PUZZLE_DEGREE,
         )?;
-        let message = PoolMessageSC::Notify::<CurrentNetwork>(0, 100000, epoch_challenge);
+        let message =
+            PoolMessageSC::Notify::<CurrentNetwork>(0, 100000, 100000, epoch_challenge, vec![]);
         check_pool_message_sc(message);
 
         let message = PoolMessageSC::ShutDown;
         check_pool_message_sc(message);
 
-        let message = PoolMessageSC::Pong;
+        let message = PoolMessageSC::Pong(None);
+        check_pool_message_sc(message);
+
+        let message = PoolMessageSC::Pong(Some(200));
+        check_pool_message_sc(message);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[3, 1, 2]).is_err());
+
+        let message = PoolMessageSC::Throttle(500);
+        check_pool_message_sc(message);
+
+        let message = PoolMessageSC::Chunk(7, 1, 3, vec![1, 2, 3]);
+        check_pool_message_sc(message);
+
+        let message = PoolMessageSC::NonceRange(7, 0, u64::MAX / 2);
+        check_pool_message_sc(message);
+        let bytes = PoolMessageSC::<CurrentNetwork>::NonceRange(7, 0, 1).to_vec()?;
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..bytes.len() - 1]).is_err());
+        assert!(
+            PoolMessageSC::<CurrentNetwork>::deserialize(&[&bytes[4..], &[0]].concat()).is_err()
+        );
+
+        let message = PoolMessageSC::HelloAck(CAP_SEQUENCE | CAP_SIGNED_SHARES);
+        check_pool_message_sc(message);
+
+        let message = PoolMessageSC::Pause(600);
+        check_pool_message_sc(message);
+
+        let message = PoolMessageSC::ResumeMining;
+        check_pool_message_sc(message);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[8, 1, 0, 0]).is_err());
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[9, 0]).is_err());
+
+        let message = PoolMessageSC::Drain(30);
+        check_pool_message_sc(message);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[10, 30, 0, 0, 0, 0]).is_err());
+
+        let message = PoolMessageSC::UpgradeTlsAck(true);
+        check_pool_message_sc(message);
+        let message = PoolMessageSC::UpgradeTlsAck(false);
+        check_pool_message_sc(message);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[11, 2]).is_err());
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[11]).is_err());
+
+        let message = PoolMessageSC::JobHint(7, 250_000);
+        check_pool_message_sc(message);
+
+        let bytes = PoolMessageSC::<CurrentNetwork>::JobHint(7, 250_000).to_vec()?;
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..bytes.len() - 1]).is_err());
+        assert!(
+            PoolMessageSC::<CurrentNetwork>::deserialize(&[&bytes[4..], &[0]].concat()).is_err()
+        );
+
+        let message = PoolMessageSC::QueryInfo;
+        check_pool_message_sc(message);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[13, 0]).is_err());
+
+        let message = PoolMessageSC::RequestProof(7);
+        assert_eq!(message.to_vec()?, [5, 0, 0, 0, 16, 7, 0, 0, 0]);
+        check_pool_message_sc(message);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[16, 7, 0, 0]).is_err());
+
+        let message = PoolMessageSC::Bye;
+        assert_eq!(message.to_vec()?, [1, 0, 0, 0, sc::BYE]);
         check_pool_message_sc(message);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[sc::BYE, 0]).is_err());
 
         Ok(())
     }
@@ -542,6 +1847,7 @@ mod tests {
             0,
             "my_worker_1".to_string(),
             "215587407@qq.com".to_string(),
+            vec![],
         );
         check_pool_message_cs(message);
 
@@ -557,7 +1863,22 @@ mod tests {
                 random_v: None,
             },
         );
-        let message = PoolMessageCS::Submit::<CurrentNetwork>(0, 0, Data::Object(prover_solution));
+        let message =
+            PoolMessageCS::Submit::<CurrentNetwork>(0, 0, 0, Data::Object(prover_solution), None);
+        check_pool_message_cs(message.clone());
+
+        let message =
+            PoolMessageCS::Submit::<CurrentNetwork>(0, 3, 0, Data::Object(prover_solution), None);
+        check_pool_message_cs(message);
+
+        let signature = sign_solution(&PrivateKey::new(rng)?, &prover_solution, rng)?;
+        let message = PoolMessageCS::Submit::<CurrentNetwork>(
+            0,
+            0,
+            0,
+            Data::Object(prover_solution),
+            Some(signature),
+        );
         check_pool_message_cs(message);
 
         let message = PoolMessageCS::DisConnect::<CurrentNetwork>(1);
@@ -565,6 +1886,935 @@ mod tests {
 
         let message = PoolMessageCS::Ping;
         check_pool_message_cs(message);
+
+        let message = PoolMessageCS::JobStatus::<CurrentNetwork>(1, u64::MAX - 1);
+        check_pool_message_cs(message);
+
+        let message = PoolMessageCS::RegisterWorker::<CurrentNetwork>(3, "rig-03".to_string());
+        check_pool_message_cs(message);
+
+        let message = PoolMessageCS::Chunk::<CurrentNetwork>(7, 0, 1, vec![]);
+        check_pool_message_cs(message);
+
+        let message = PoolMessageCS::ResumeSession::<CurrentNetwork>(
+            1,
+            String::from("testaddress"),
+            String::from("testsignature"),
+        );
+        check_pool_message_cs(message);
+
+        let message = PoolMessageCS::Resume::<CurrentNetwork>(u64::MAX);
+        check_pool_message_cs(message);
+
+        let message = PoolMessageCS::Hello::<CurrentNetwork>(SUPPORTED_CAPABILITIES);
+        check_pool_message_cs(message);
+
+        let message = PoolMessageCS::UpgradeTls::<CurrentNetwork>;
+        check_pool_message_cs(message);
+
+        let message = PoolMessageCS::InfoReport::<CurrentNetwork>(
+            "zkwork-agent".to_string(),
+            "1.2.0 (rustc 1.66, cuda 11.8)".to_string(),
+        );
+        check_pool_message_cs(message);
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&[138, 0]).is_err());
+
+        let message = PoolMessageCS::SubmitCommitment::<CurrentNetwork>(
+            7,
+            u64::MAX,
+            prover_solution.commitment(),
+        );
+        assert_eq!(message.to_vec()?.len(), 4 + 1 + 12 + PUZZLE_COMMITMENT_SIZE);
+        check_pool_message_cs(message);
+        let bytes =
+            PoolMessageCS::SubmitCommitment::<CurrentNetwork>(7, 0, prover_solution.commitment())
+                .to_vec()?;
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..bytes.len() - 1]).is_err());
+        let mut invalid = bytes[4..].to_vec();
+        invalid[13..].fill(0xff);
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&invalid).is_err());
+
+        let message = PoolMessageCS::SetNotifyInterval::<CurrentNetwork>(5_000);
+        assert_eq!(message.to_vec()?, [5, 0, 0, 0, 140, 0x88, 0x13, 0, 0]);
+        check_pool_message_cs(message);
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&[140, 0x88, 0x13, 0]).is_err());
+
+        let message = PoolMessageCS::Bye::<CurrentNetwork>;
+        assert_eq!(message.to_vec()?, [1, 0, 0, 0, cs::BYE]);
+        check_pool_message_cs(message);
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&[cs::BYE, 0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_submit_chunk() -> Result<()> {
+        let message = PoolMessageCS::SubmitChunk::<CurrentNetwork>(
+            7,
+            1,
+            0,
+            2,
+            Bytes::from_static(&[1, 2, 3]),
+        );
+        assert_eq!(message.serialized_size() + 4, message.to_vec()?.len());
+        check_pool_message_cs(message);
+
+        let frame = |index: u16, total: u16, size: usize| {
+            PoolMessageCS::SubmitChunk::<CurrentNetwork>(7, 1, index, total, vec![0u8; size].into())
+                .to_vec()
+                .unwrap()
+        };
+        let valid = frame(
+            MAXIMUM_SUBMIT_CHUNKS - 1,
+            MAXIMUM_SUBMIT_CHUNKS,
+            CHUNK_PAYLOAD_SIZE,
+        );
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&valid[4..]).is_ok());
+        for invalid in [
+            frame(2, 2, 1),
+            frame(0, 0, 0),
+            frame(0, MAXIMUM_SUBMIT_CHUNKS + 1, 1),
+            frame(0, 1, CHUNK_PAYLOAD_SIZE + 1),
+        ] {
+            assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&invalid[4..]).is_err());
+        }
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&valid[4..19]).is_err());
+
+        // The chunks of a solution reassemble into the same `Buffer`.
+        let solution = Bytes::from(vec![9u8; 1000]);
+        let mut reassembler = SubmitReassembler::<CurrentNetwork>::new();
+        let now = std::time::Instant::now();
+        let mut reassembled = None;
+        for message in split_submit(
+            3,
+            1,
+            &Data::<ProverSolution<CurrentNetwork>>::Buffer(solution.clone()),
+        )? {
+            let bytes = message.to_vec()?;
+            if let PoolMessageCS::SubmitChunk(work_id, job_id, index, total, bytes) =
+                PoolMessageCS::<CurrentNetwork>::deserialize_bytes(Bytes::from(bytes).slice(4..))?
+            {
+                reassembled = reassembler.insert(work_id, job_id, index, total, &bytes, now)?;
+            }
+        }
+        assert!(matches!(reassembled, Some(Data::Buffer(bytes)) if bytes == solution));
+        Ok(())
+    }
+
+    #[test]
+    fn test_power_stats() -> Result<()> {
+        let message = PoolMessageCS::PowerStats::<CurrentNetwork>(7, 350, 1_200);
+        assert_eq!(
+            message.to_vec()?,
+            [13, 0, 0, 0, 142, 7, 0, 0, 0, 0x5e, 0x01, 0, 0, 0xb0, 0x04, 0, 0]
+        );
+        check_pool_message_cs(message);
+        let bytes =
+            PoolMessageCS::PowerStats::<CurrentNetwork>(u32::MAX, u32::MAX, u32::MAX).to_vec()?;
+        assert!(matches!(
+            PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?,
+            PoolMessageCS::PowerStats(u32::MAX, u32::MAX, u32::MAX)
+        ));
+        // Fixed width: truncated or padded bodies are rejected.
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..bytes.len() - 1]).is_err());
+        let mut padded = bytes[4..].to_vec();
+        padded.push(0);
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&padded).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_notify_ack() -> Result<()> {
+        let message = PoolMessageCS::NotifyAck::<CurrentNetwork>(7, 1_700_000_000_000);
+        assert_eq!(
+            message.to_vec()?,
+            [17, 0, 0, 0, 143, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0x68, 0xe5, 0xcf, 0x8b, 0x01, 0, 0]
+        );
+        check_pool_message_cs(message);
+        assert!(
+            PoolMessageCS::<CurrentNetwork>::deserialize(&[143, 7, 0, 0, 0, 0, 0, 0, 0]).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_notify_share_target_tlv() -> Result<()> {
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let message = PoolMessageSC::Notify(0, 200000, 100000, epoch_challenge.clone(), vec![]);
+        let bytes = message.to_vec()?;
+        assert_eq!(bytes.len(), 4 + message.serialized_size());
+
+        // The field owns the tag.
+        let tlvs = vec![Tlv::new(TAG_SHARE_TARGET, 100000u64.to_le_bytes().to_vec())];
+        let message = PoolMessageSC::Notify(0, 200000, 200000, epoch_challenge, tlvs);
+        assert!(message.to_vec().is_err());
+        let mut body = Vec::new();
+        assert!(message.serialize_data_into(&mut body).is_err());
+        assert!(body.is_empty());
+
+        // A share target that is not a u64.
+        let mut message = bytes[4..bytes.len() - 1].to_vec();
+        let len = message.len();
+        message[len - 9] = 7;
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&message).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_notify_invalid_epoch_challenge() -> Result<()> {
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let bytes = PoolMessageSC::Notify(0, 100000, 100000, epoch_challenge, vec![]).to_vec()?;
+        let message = &bytes[4..];
+        assert_eq!(message.len(), 1 + 16 + EPOCH_CHALLENGE_SIZE);
+
+        let invalid_epoch_challenge = |message: &[u8]| {
+            let error = PoolMessageSC::<CurrentNetwork>::deserialize(message).unwrap_err();
+            matches!(
+                error.downcast_ref(),
+                Some(PoolProtocolError::InvalidEpochChallenge(_))
+            )
+        };
+        // Truncated.
+        assert!(invalid_epoch_challenge(&message[..message.len() - 1]));
+        assert!(invalid_epoch_challenge(&message[..17]));
+        // Another degree.
+        let mut message = message.to_vec();
+        message[1 + 16 + 36] ^= 1;
+        assert!(invalid_epoch_challenge(&message));
+        // Too short for the job id and target.
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[1, 0, 0]).is_err());
+
+        // Decoders keep the error typed.
+        let mut buffer = BytesMut::from(&bytes[..bytes.len() - 1]);
+        buffer[0] -= 1;
+        let error = PoolCodecSC::<CurrentNetwork>::new()
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert!(matches!(
+            PoolProtocolError::from_io_error(&error),
+            Some(PoolProtocolError::InvalidEpochChallenge(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_framed() -> Result<()> {
+        let (mut writer, mut reader) = tokio::io::duplex(1024);
+        PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2)
+            .write_framed(&mut writer)
+            .await?;
+        PoolMessageSC::<CurrentNetwork>::Throttle(500)
+            .write_framed(&mut writer)
+            .await?;
+        drop(writer);
+
+        let mut buffer = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut buffer).await?;
+        let mut buffer = BytesMut::from(&buffer[..]);
+        assert!(matches!(
+            PoolCodecCS::<CurrentNetwork>::new().decode(&mut buffer)?,
+            Some(PoolMessageCS::JobStatus(1, 2))
+        ));
+        assert!(matches!(
+            PoolCodecSC::<CurrentNetwork>::new().decode(&mut buffer)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_from() -> Result<()> {
+        let mut input = PoolMessageSC::<CurrentNetwork>::Throttle(500).to_vec()?;
+        input.extend(PoolMessageSC::<CurrentNetwork>::Pong(None).to_vec()?);
+        let mut reader = &input[..];
+        assert!(matches!(
+            PoolMessageSC::<CurrentNetwork>::read_from(&mut reader)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+        assert!(matches!(
+            PoolMessageSC::<CurrentNetwork>::read_from(&mut reader)?,
+            Some(PoolMessageSC::Pong(None))
+        ));
+        assert!(PoolMessageSC::<CurrentNetwork>::read_from(&mut reader)?.is_none());
+
+        // A solution past `MAXIMUM_MESSAGE_SIZE` is read like the decoder accepts it.
+        let submit = PoolMessageCS::<CurrentNetwork>::Submit(
+            1,
+            0,
+            7,
+            Data::Buffer(Bytes::from(vec![0; MAXIMUM_MESSAGE_SIZE])),
+            None,
+        );
+        let input = submit.to_vec()?;
+        assert!(matches!(
+            PoolMessageCS::<CurrentNetwork>::read_from(&mut &input[..])?,
+            Some(PoolMessageCS::Submit(1, 0, 7, Data::Buffer(solution), None))
+                if solution.len() == MAXIMUM_MESSAGE_SIZE
+        ));
+
+        // Input ending inside a frame is an error, as is a frame above the limit.
+        for end in [2, input.len() - 1] {
+            assert!(PoolMessageCS::<CurrentNetwork>::read_from(&mut &input[..end]).is_err());
+        }
+        let input = ((MAXIMUM_MESSAGE_SIZE + 1) as u32).to_le_bytes();
+        let mut input = input.to_vec();
+        input.push(cs::PING);
+        let error = PoolMessageCS::<CurrentNetwork>::read_from(&mut &input[..]).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PoolProtocolError>(),
+            Some(&PoolProtocolError::FrameTooLarge(MAXIMUM_MESSAGE_SIZE + 1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_connect_ack_initial_job() -> Result<()> {
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(&mut thread_rng()));
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        for initial_job in [None, Some((3, 100000, epoch_challenge.clone()))] {
+            for tlvs in [vec![], vec![Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0])]] {
+                let message = PoolMessageSC::ConnectAck(
+                    true,
+                    address,
+                    Some(7),
+                    Some(String::from("testsignature")),
+                    initial_job.clone(),
+                    tlvs.clone(),
+                );
+                let bytes = message.to_vec()?;
+                match PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])? {
+                    PoolMessageSC::ConnectAck(true, _, Some(7), Some(signature), job, tlvs1) => {
+                        assert_eq!(signature, "testsignature");
+                        assert_eq!(job, initial_job);
+                        assert_eq!(tlvs1, tlvs);
+                    }
+                    message => panic!("unexpected message {:?}", message),
+                }
+                check_pool_message_sc(message);
+            }
+        }
+
+        // A truncated job or an unknown presence byte is rejected.
+        let message = PoolMessageSC::<CurrentNetwork>::ConnectAck(
+            true,
+            address,
+            Some(7),
+            Some(String::from("testsignature")),
+            Some((3, 100000, epoch_challenge)),
+            vec![],
+        );
+        let bytes = message.to_vec()?;
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..50]).is_err());
+        let mut bytes = bytes[4..].to_vec();
+        bytes[38] = 2;
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&bytes).is_err());
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[..30]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_connect_ack_truncated() -> Result<()> {
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(&mut thread_rng()));
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        for initial_job in [None, Some((3, 100000, epoch_challenge))] {
+            let message = PoolMessageSC::<CurrentNetwork>::ConnectAck(
+                true,
+                address,
+                Some(7),
+                Some(String::from("testsignature")),
+                initial_job,
+                vec![],
+            );
+            let bytes = message.to_vec()?;
+            let signature_start = bytes.len() - "testsignature".len();
+            // Every cut before the signature is an error rather than a panic.
+            for end in 5..=signature_start {
+                assert!(
+                    PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..end]).is_err(),
+                    "cut at {}",
+                    end
+                );
+            }
+            // Payloads ending before the address or within the worker id.
+            for len in [0, 5, 33, 36] {
+                let error =
+                    PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..5 + len]).unwrap_err();
+                assert!(
+                    error
+                        .to_string()
+                        .starts_with("Truncated 'ConnectAck' message"),
+                    "{} bytes: {}",
+                    len,
+                    error
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_byte_layout() -> Result<()> {
+        let rng = &mut thread_rng();
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(rng));
+        let message = PoolMessageSC::<CurrentNetwork>::ConnectAck(
+            true,
+            address,
+            Some(0x0403_0201),
+            Some("s".to_string()),
+            None,
+            vec![],
+        );
+        let mut expected = vec![40, 0, 0, 0, 0, 1];
+        expected.extend_from_slice(&address.to_bytes_le()?);
+        expected.extend_from_slice(&[1, 2, 3, 4, 0, b's']);
+        assert_eq!(message.to_vec()?, expected);
+
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let bytes =
+            PoolMessageSC::Notify(0x0807_0605_0403_0201, 0x20, 0x10, epoch_challenge, vec![])
+                .to_vec()?;
+        assert_eq!(
+            &bytes[..21],
+            &[68, 0, 0, 0, 1, 1, 2, 3, 4, 5, 6, 7, 8, 0x20, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            &bytes[bytes.len() - 11..],
+            &[TAG_SHARE_TARGET, 8, 0, 0x10, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        let message = PoolMessageCS::<CurrentNetwork>::Submit(
+            0x0403_0201,
+            0x0807_0605,
+            0x10,
+            Data::Buffer(Bytes::from_static(&[0xaa])),
+            None,
+        );
+        assert_eq!(
+            message.to_vec()?,
+            vec![19, 0, 0, 0, 129, 1, 2, 3, 4, 5, 6, 7, 8, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0xaa]
+        );
+        assert_eq!(
+            PoolMessageCS::<CurrentNetwork>::DisConnect(0x0403_0201).to_vec()?,
+            vec![5, 0, 0, 0, 130, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2).to_vec()?,
+            vec![13, 0, 0, 0, 132, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&[130, 1, 2, 3]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_appends() -> Result<()> {
+        let mut buffer = BytesMut::new();
+        let mut codec = PoolMessageSC::<CurrentNetwork>::default();
+        codec.encode(PoolMessageSC::Throttle(500), &mut buffer)?;
+        codec.encode(PoolMessageSC::RequestProof(7), &mut buffer)?;
+        // A message that fails to serialize leaves the earlier frames intact.
+        assert!(codec
+            .encode(PoolMessageSC::NotifyBatch(vec![]), &mut buffer)
+            .is_err());
+        assert_eq!(buffer.len(), 9 + 9);
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageSC::Throttle(500))
+        ));
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageSC::RequestProof(7))
+        ));
+        assert!(buffer.is_empty());
+
+        let mut codec = PoolMessageCS::<CurrentNetwork>::default();
+        codec.encode(PoolMessageCS::Ping, &mut buffer)?;
+        codec.encode(PoolMessageCS::JobStatus(1, 2), &mut buffer)?;
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageCS::Ping)
+        ));
+        assert!(matches!(
+            codec.decode(&mut buffer)?,
+            Some(PoolMessageCS::JobStatus(1, 2))
+        ));
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_peek_id() -> Result<()> {
+        let bytes = PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2).to_vec()?;
+        assert_eq!(peek_id(&bytes), Some(132));
+        assert_eq!(peek_id(&bytes[..5]), Some(132));
+        assert_eq!(peek_id(&bytes[..4]), None);
+        assert_eq!(peek_id(&[]), None);
+        assert_eq!(
+            peek_id(&PoolMessageSC::<CurrentNetwork>::Pong(None).to_vec()?),
+            Some(3)
+        );
+
+        // A keepalive before a frame is not mistaken for it.
+        let mut buffer = BytesMut::new();
+        let codec = PoolCodecCS::<CurrentNetwork>::new();
+        codec.encode_keepalive(&mut buffer);
+        codec.encode_keepalive(&mut buffer);
+        buffer.extend_from_slice(&bytes);
+        assert_eq!(peek_id(&buffer), Some(132));
+        assert_eq!(peek_id(&buffer[..8]), None);
+        assert_eq!(PoolMessageCS::<CurrentNetwork>::peek_id(&buffer)?, 132);
+        assert!(PoolMessageCS::<CurrentNetwork>::peek_id(&buffer[..8]).is_err());
+        assert!(PoolMessageSC::<CurrentNetwork>::peek_id(&buffer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_worker_bounds() {
+        let name = "x".repeat(MAXIMUM_WORKER_NAME_LENGTH + 1);
+        let message = PoolMessageCS::RegisterWorker::<CurrentNetwork>(1, name);
+        assert!(message.to_vec().is_err());
+
+        // Declared name length disagrees with the payload.
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&[133, 1, 0, 0, 0, 3, b'a']).is_err());
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&[133, 1, 0, 0]).is_err());
+        let mut buffer = vec![133, 1, 0, 0, 0, 65];
+        buffer.extend_from_slice(&[b'x'; 65]);
+        assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&buffer).is_err());
+    }
+
+    #[test]
+    fn test_kick_bounds() -> Result<()> {
+        let message = PoolMessageSC::<CurrentNetwork>::Kick(7, "invalid shares".to_string());
+        let bytes = message.to_vec()?;
+        assert!(matches!(
+            PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?,
+            PoolMessageSC::Kick(7, reason) if reason == "invalid shares"
+        ));
+
+        let reason = "x".repeat(MAXIMUM_KICK_REASON_LENGTH + 1);
+        assert!(PoolMessageSC::<CurrentNetwork>::Kick(7, reason)
+            .to_vec()
+            .is_err());
+
+        // Declared reason length disagrees with the payload, or exceeds the limit.
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[17, 7, 0, 0, 0, 3, b'a']).is_err());
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&[17, 7, 0, 0]).is_err());
+        let mut buffer = vec![17, 7, 0, 0, 0, 129];
+        buffer.extend_from_slice(&[b'x'; 129]);
+        assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&buffer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_frame_sizes() -> Result<()> {
+        for (message, size) in [
+            (
+                PoolMessageSC::<CurrentNetwork>::ShutDown,
+                SHUT_DOWN_FRAME_SIZE,
+            ),
+            (PoolMessageSC::Pong(None), PONG_FRAME_SIZE),
+            (PoolMessageSC::Pong(Some(7)), LOADED_PONG_FRAME_SIZE),
+        ] {
+            assert_eq!(message.to_vec()?.len(), size);
+            assert_eq!(message.encoded_len()?, size);
+        }
+        for (message, size) in [
+            (PoolMessageCS::<CurrentNetwork>::Ping, PING_FRAME_SIZE),
+            (PoolMessageCS::DisConnect(7), DISCONNECT_FRAME_SIZE),
+        ] {
+            assert_eq!(message.to_vec()?.len(), size);
+            assert_eq!(message.encoded_len()?, size);
+        }
+
+        // Other messages are measured by serializing them.
+        let message = PoolMessageSC::<CurrentNetwork>::ShareAckBatch(vec![(1, SHARE_ACCEPTED); 3]);
+        assert_eq!(message.encoded_len()?, message.to_vec()?.len());
+        let message = PoolMessageCS::<CurrentNetwork>::InfoReport("a".into(), "1.0".into());
+        assert_eq!(message.encoded_len()?, message.to_vec()?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_submit_frame_size() -> Result<()> {
+        let rng = &mut thread_rng();
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(rng));
+        let partial_solution =
+            PartialSolution::new(address, u64::rand(rng), KZGCommitment(rng.gen()));
+        let prover_solution = ProverSolution::new(
+            partial_solution,
+            KZGProof {
+                w: rng.gen(),
+                random_v: Some(rng.gen()),
+            },
+        );
+        let signature = sign_solution(&PrivateKey::new(rng)?, &prover_solution, rng)?;
+        let submit = |solution: Data<ProverSolution<CurrentNetwork>>| {
+            PoolMessageCS::Submit::<CurrentNetwork>(1, 2, 3, solution, Some(signature))
+        };
+
+        // A real solution, and one padded past `MAXIMUM_MESSAGE_SIZE` as larger networks produce,
+        // both decode end to end through the message decoder and the codec.
+        let padded = [
+            prover_solution.to_bytes_le()?,
+            vec![0; MAXIMUM_MESSAGE_SIZE],
+        ]
+        .concat();
+        for solution in [Data::Object(prover_solution), Data::Buffer(padded.into())] {
+            let bytes = submit(solution.clone()).to_vec()?;
+            assert_eq!(4 + submit(solution.clone()).serialized_size(), bytes.len());
+            let mut buffer = BytesMut::new();
+            signed_codec().encode(submit(solution), &mut buffer)?;
+            let decoded = signed_codec().decode(&mut buffer)?;
+            assert_eq!(decoded.unwrap().to_vec()?, bytes);
+        }
+        let bytes = submit(Data::Object(prover_solution)).to_vec()?;
+        let decoded = signed_codec().decode(&mut bytes[..].into())?;
+        match decoded {
+            Some(PoolMessageCS::Submit(1, 2, 3, solution, Some(_))) => {
+                assert_eq!(solution.deserialize_blocking()?, prover_solution)
+            }
+            message => panic!("Unexpected {:?}", message),
+        }
+
+        // Work messages are bound by `WORK_MESSAGE_LIMIT` instead.
+        let oversized = [prover_solution.to_bytes_le()?, vec![0; WORK_MESSAGE_LIMIT]].concat();
+        let bytes = submit(Data::Buffer(oversized.into())).to_vec()?;
+        assert!(signed_codec().decode(&mut bytes[..].into()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_notify_batch() -> Result<()> {
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let jobs = (0..MAXIMUM_NOTIFY_BATCH_JOBS as u64)
+            .map(|job_id| (job_id, 1_000 << job_id, epoch_challenge.clone()))
+            .collect::<Vec<_>>();
+        for count in [1, MAXIMUM_NOTIFY_BATCH_JOBS] {
+            let message = PoolMessageSC::NotifyBatch(jobs[..count].to_vec());
+            let bytes = message.to_vec()?;
+            assert_eq!(bytes.len(), 4 + 2 + count * NOTIFY_BATCH_JOB_SIZE);
+            assert!(bytes.len() - 4 <= MAXIMUM_MESSAGE_SIZE);
+            match PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])? {
+                PoolMessageSC::NotifyBatch(decoded) => {
+                    assert_eq!(decoded.len(), count);
+                    for ((job_id, target, challenge), expected) in decoded.iter().zip(&jobs) {
+                        assert_eq!((*job_id, *target), (expected.0, expected.1));
+                        assert_eq!(challenge.to_bytes_le()?, expected.2.to_bytes_le()?);
+                    }
+                }
+                message => panic!("Unexpected {:?}", message),
+            }
+        }
+
+        // Empty and oversized batches, and counts that disagree with the jobs, are rejected.
+        assert!(PoolMessageSC::<CurrentNetwork>::NotifyBatch(vec![])
+            .to_vec()
+            .is_err());
+        let mut too_many = jobs.clone();
+        too_many.push((8, 1, epoch_challenge));
+        assert!(PoolMessageSC::NotifyBatch(too_many).to_vec().is_err());
+        let bytes = PoolMessageSC::NotifyBatch(jobs[..2].to_vec()).to_vec()?;
+        for buffer in [
+            vec![15],
+            vec![15, 0],
+            [&[15, 1], &bytes[6..]].concat(),
+            [&[15, 3], &bytes[6..]].concat(),
+            bytes[4..bytes.len() - 1].to_vec(),
+            [
+                &[15, 9],
+                &bytes[6..],
+                &bytes[6..],
+                &bytes[6..],
+                &bytes[6..],
+                &bytes[6..],
+            ]
+            .concat(),
+        ] {
+            assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&buffer).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_share_ack_batch() -> Result<()> {
+        for acks in [
+            vec![],
+            vec![(0x0403_0201, SHARE_STALE)],
+            (0..1000)
+                .map(|work_id| (work_id, (work_id % 5) as u8))
+                .collect(),
+        ] {
+            let bytes = PoolMessageSC::<CurrentNetwork>::ShareAckBatch(acks.clone()).to_vec()?;
+            assert_eq!(bytes.len(), 4 + 3 + acks.len() * 5);
+            match PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])? {
+                PoolMessageSC::ShareAckBatch(decoded) => assert_eq!(decoded, acks),
+                message => panic!("Unexpected {:?}", message),
+            }
+        }
+        let bytes =
+            PoolMessageSC::<CurrentNetwork>::ShareAckBatch(vec![(1, SHARE_ACCEPTED)]).to_vec()?;
+        assert_eq!(&bytes[4..], &[14, 1, 0, 1, 0, 0, 0, 0]);
+
+        // The count must match the entries that follow.
+        for buffer in [
+            vec![14],
+            vec![14, 1],
+            vec![14, 1, 0],
+            vec![14, 2, 0, 1, 0, 0, 0, 0],
+            vec![14, 0, 0, 1, 0, 0, 0, 0],
+            vec![14, 1, 0, 1, 0, 0, 0, 0, 0],
+        ] {
+            assert!(PoolMessageSC::<CurrentNetwork>::deserialize(&buffer).is_err());
+        }
+        let acks = vec![(0, SHARE_ACCEPTED); u16::MAX as usize + 1];
+        assert!(PoolMessageSC::<CurrentNetwork>::ShareAckBatch(acks)
+            .to_vec()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_report_bounds() -> Result<()> {
+        let long = "x".repeat(MAXIMUM_INFO_REPORT_LENGTH + 1);
+        let message = PoolMessageCS::InfoReport::<CurrentNetwork>(long.clone(), String::new());
+        assert!(message.to_vec().is_err());
+        let message = PoolMessageCS::InfoReport::<CurrentNetwork>(String::new(), long);
+        assert!(message.to_vec().is_err());
+
+        let bytes =
+            PoolMessageCS::InfoReport::<CurrentNetwork>(String::new(), String::new()).to_vec()?;
+        assert_eq!(&bytes[4..], &[139, 0, 0]);
+        // Missing, truncated, trailing or oversized fields.
+        for buffer in [
+            vec![139],
+            vec![139, 0],
+            vec![139, 1, b'a', 2, b'b'],
+            vec![139, 0, 0, 0],
+            [vec![139, 129], vec![b'x'; 129], vec![0]].concat(),
+        ] {
+            assert!(PoolMessageCS::<CurrentNetwork>::deserialize(&buffer).is_err());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encode_async() -> Result<()> {
+        let rng = &mut thread_rng();
+        let prover_solution = ProverSolution::new(
+            PartialSolution::new(
+                Address::<CurrentNetwork>::new(Uniform::rand(rng)),
+                u64::rand(rng),
+                KZGCommitment(rng.gen()),
+            ),
+            KZGProof {
+                w: rng.gen(),
+                random_v: None,
+            },
+        );
+        let signature = sign_solution(&PrivateKey::new(rng)?, &prover_solution, rng)?;
+        for message in [
+            PoolMessageCS::Submit(1, 2, 3, Data::Object(prover_solution), Some(signature)),
+            PoolMessageCS::Submit(1, 0, 3, Data::Object(prover_solution), None),
+            PoolMessageCS::Submit(
+                1,
+                0,
+                3,
+                Data::Buffer(prover_solution.to_bytes_le()?.into()),
+                None,
+            ),
+            PoolMessageCS::JobStatus(1, 2),
+        ] {
+            let expected = message.to_vec()?;
+            assert_eq!(&message.encode_async().await?[..], &expected[..]);
+        }
+
+        let message = PoolMessageSC::<CurrentNetwork>::Throttle(500);
+        assert_eq!(
+            &message.clone().encode_async().await?[..],
+            &message.to_vec()?[..]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prepare() -> Result<()> {
+        let rng = &mut thread_rng();
+        let prover_solution = ProverSolution::new(
+            PartialSolution::new(
+                Address::<CurrentNetwork>::new(Uniform::rand(rng)),
+                u64::rand(rng),
+                KZGCommitment(rng.gen()),
+            ),
+            KZGProof {
+                w: rng.gen(),
+                random_v: None,
+            },
+        );
+        let message = PoolMessageCS::Submit(1, 2, 3, Data::Object(prover_solution), None);
+        let expected = message.to_vec()?;
+
+        // A prepared submit only holds bytes, which the codec copies without serializing.
+        let prepared = message.prepare().await?;
+        match &prepared {
+            PoolMessageCS::Submit(1, 2, 3, Data::Buffer(bytes), None) => {
+                assert_eq!(&bytes[..], &prover_solution.to_bytes_le()?[..])
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+        let mut buffer = BytesMut::new();
+        PoolCodecCS::<CurrentNetwork>::new().encode(prepared, &mut buffer)?;
+        assert_eq!(&buffer[..], &expected[..]);
+
+        let mut writer = Vec::new();
+        PoolMessageCS::Submit(1, 2, 3, Data::Object(prover_solution), None)
+            .write_framed(&mut writer)
+            .await?;
+        assert_eq!(writer, expected);
+
+        let message = PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2);
+        assert!(matches!(
+            message.prepare().await?,
+            PoolMessageCS::JobStatus(1, 2)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_data_deserialize_with_token() -> Result<()> {
+        let data = Data::<u64>::Buffer(42u64.to_bytes_le()?.into());
+        assert_eq!(data.clone().deserialize().await?, 42);
+        assert_eq!(
+            data.clone()
+                .deserialize_with_token(CancellationToken::new())
+                .await?,
+            42
+        );
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(data.deserialize_with_token(token.clone()).await.is_err());
+
+        // Objects never hit the blocking pool.
+        assert_eq!(Data::Object(7u64).deserialize_with_token(token).await?, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_clone_buffer() -> Result<()> {
+        let data = Data::Object(42u64).clone_buffer()?;
+        assert_eq!(data, Data::Buffer(42u64.to_bytes_le()?.into()));
+
+        let copy = data.clone_buffer()?;
+        match (&data, &copy) {
+            (Data::Buffer(a), Data::Buffer(b)) => assert_eq!(a.as_ptr(), b.as_ptr()),
+            _ => panic!("clone_buffer must return a Buffer"),
+        }
+        assert_eq!(copy.deserialize_blocking()?, 42);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_message_unknown_tlvs() -> Result<()> {
+        let tlvs = vec![
+            Tlv::new(250, b"from the future".to_vec()),
+            Tlv::new(251, vec![]),
+        ];
+
+        let message = PoolMessageCS::Connect::<CurrentNetwork>(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            tlvs.clone(),
+        );
+        let mut buffer = BytesMut::new();
+        PoolCodecCS::<CurrentNetwork>::new().encode(message, &mut buffer)?;
+        match PoolCodecCS::<CurrentNetwork>::new().decode(&mut buffer)? {
+            Some(PoolMessageCS::Connect(_, _, _, _, _, name, address, tlvs)) => {
+                assert_eq!(name, "my_worker_1");
+                assert_eq!(address, "215587407@qq.com");
+                assert!(tlvs.is_empty());
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+
+        let rng = &mut thread_rng();
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(rng));
+        for is_accept in [true, false] {
+            let (id, signature) = match is_accept {
+                true => (Some(7), Some(String::from("testsignature"))),
+                false => (None, None),
+            };
+            let message = PoolMessageSC::ConnectAck::<CurrentNetwork>(
+                is_accept,
+                address,
+                id,
+                signature.clone(),
+                None,
+                tlvs.clone(),
+            );
+            let mut buffer = BytesMut::new();
+            PoolCodecSC::<CurrentNetwork>::new().encode(message, &mut buffer)?;
+            match PoolCodecSC::<CurrentNetwork>::new().decode(&mut buffer)? {
+                Some(PoolMessageSC::ConnectAck(accept, address1, id1, signature1, None, tlvs)) => {
+                    assert_eq!(accept, is_accept);
+                    assert_eq!(address1, address);
+                    assert_eq!(id1, id);
+                    assert_eq!(signature1, signature);
+                    assert!(tlvs.is_empty());
+                }
+                message => panic!("unexpected message {:?}", message),
+            }
+        }
+
+        let epoch_challenge = EpochChallenge::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let message = PoolMessageSC::Notify::<CurrentNetwork>(
+            3,
+            200000,
+            100000,
+            epoch_challenge.clone(),
+            tlvs,
+        );
+        let mut buffer = BytesMut::new();
+        PoolCodecSC::<CurrentNetwork>::new().encode(message, &mut buffer)?;
+        match PoolCodecSC::<CurrentNetwork>::new().decode(&mut buffer)? {
+            Some(PoolMessageSC::Notify(job_id, target, share_target, epoch_challenge1, tlvs)) => {
+                assert_eq!((job_id, target, share_target), (3, 200000, 100000));
+                assert_eq!(epoch_challenge1, epoch_challenge);
+                assert!(tlvs.is_empty());
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
         Ok(())
     }
 }
@@ -0,0 +1,233 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::poolmessage::*;
+use snarkvm::prelude::Network;
+
+use futures_sink::Sink;
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+/// Messages that may overtake queued bulk traffic.
+pub trait Prioritized {
+    /// Returns true for control messages, which are written before any queued normal message.
+    fn is_high_priority(&self) -> bool;
+}
+
+impl<N: Network> Prioritized for PoolMessageSC<N> {
+    fn is_high_priority(&self) -> bool {
+        matches!(self, Self::Pong(..) | Self::ShutDown | Self::Kick(..))
+    }
+}
+
+impl<N: Network> Prioritized for PoolMessageCS<N> {
+    fn is_high_priority(&self) -> bool {
+        matches!(self, Self::Ping | Self::DisConnect(..))
+    }
+}
+
+/// The number of messages a `PrioritizedSink` queues by default.
+pub const DEFAULT_PRIORITY_QUEUE_CAPACITY: usize = 64;
+
+/// A sink with a high-priority and a normal lane in front of `inner`, typically a `FramedWrite`.
+/// Queued high-priority messages are always handed to `inner` first, so a `Ping` is not stuck
+/// behind `Submit`s waiting for the socket. Messages within a lane keep their order.
+#[derive(Debug)]
+pub struct PrioritizedSink<S, M> {
+    inner: S,
+    high: VecDeque<M>,
+    normal: VecDeque<M>,
+    capacity: usize,
+}
+
+impl<S, M> PrioritizedSink<S, M> {
+    pub fn new(inner: S) -> Self {
+        Self::with_capacity(inner, DEFAULT_PRIORITY_QUEUE_CAPACITY)
+    }
+
+    /// Queues at most `capacity` messages before applying backpressure.
+    pub fn with_capacity(inner: S, capacity: usize) -> Self {
+        Self {
+            inner,
+            high: VecDeque::new(),
+            normal: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Returns the number of queued messages.
+    pub fn len(&self) -> usize {
+        self.high.len() + self.normal.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.high.is_empty() && self.normal.is_empty()
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Returns the inner sink, dropping the queued messages.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink<M> + Unpin, M> PrioritizedSink<S, M> {
+    /// Hands one queued message to `inner`, high-priority first.
+    fn poll_send_one(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        ready!(Pin::new(&mut self.inner).poll_ready(cx))?;
+        let message = match self.high.pop_front() {
+            Some(message) => message,
+            None => match self.normal.pop_front() {
+                Some(message) => message,
+                None => return Poll::Ready(Ok(())),
+            },
+        };
+        Poll::Ready(Pin::new(&mut self.inner).start_send(message))
+    }
+
+    /// Hands every queued message to `inner`.
+    fn poll_send_all(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        while !self.is_empty() {
+            ready!(self.poll_send_one(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: Sink<M> + Unpin, M: Prioritized + Unpin> Sink<M> for PrioritizedSink<S, M> {
+    type Error = S::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        while this.len() >= this.capacity {
+            ready!(this.poll_send_one(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: M) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        match message.is_high_priority() {
+            true => this.high.push_back(message),
+            false => this.normal.push_back(message),
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_send_all(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_send_all(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::codec::*;
+    use anyhow::Result;
+    use futures::{SinkExt, StreamExt};
+    use snarkvm::prelude::Testnet3;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+    type CurrentNetwork = Testnet3;
+
+    fn submit(job_id: u64) -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Submit(1, 0, job_id, Data::Buffer(vec![0u8; 300].into()), None)
+    }
+
+    #[tokio::test]
+    async fn test_ping_overtakes_queued_submit() -> Result<()> {
+        let mut sink = PrioritizedSink::new(Vec::new());
+        sink.feed(submit(1)).await?;
+        sink.feed(submit(2)).await?;
+        sink.feed(PoolMessageCS::Ping).await?;
+        sink.feed(PoolMessageCS::DisConnect(1)).await?;
+        assert_eq!(sink.len(), 4);
+        sink.flush().await?;
+
+        let names = sink
+            .get_ref()
+            .iter()
+            .map(|message| message.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Ping", "Disconnect", "Submit", "Submit"]);
+        assert!(matches!(
+            sink.get_ref()[2],
+            PoolMessageCS::Submit(1, 0, 1, _, None)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ping_overtakes_submit_over_framed_write() -> Result<()> {
+        // The Submits are still queued when the Ping arrives, so it reaches the socket first.
+        let (writer, reader) = tokio::io::duplex(256);
+        let mut writer = FramedWrite::new(writer, PoolCodecCS::<CurrentNetwork>::new());
+        writer.set_backpressure_boundary(1);
+        let mut sink = PrioritizedSink::with_capacity(writer, 8);
+        let task = tokio::spawn(async move {
+            for job_id in 0..4 {
+                sink.feed(submit(job_id)).await?;
+            }
+            sink.feed(PoolMessageCS::Ping).await?;
+            sink.flush().await?;
+            anyhow::Ok(())
+        });
+
+        let mut reader = FramedRead::new(reader, PoolCodecCS::<CurrentNetwork>::new());
+        let mut names = Vec::new();
+        while let Some(message) = reader.next().await {
+            names.push(message?.name().to_string());
+            if names.len() == 5 {
+                break;
+            }
+        }
+        task.await??;
+        assert_eq!(names, ["Ping", "Submit", "Submit", "Submit", "Submit"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_capacity_backpressure() -> Result<()> {
+        let mut sink = PrioritizedSink::with_capacity(Vec::new(), 2);
+        sink.feed(PoolMessageSC::<CurrentNetwork>::Throttle(1))
+            .await?;
+        sink.feed(PoolMessageSC::Throttle(2)).await?;
+        // A full queue hands its oldest high-priority, then normal, message to the inner sink.
+        sink.feed(PoolMessageSC::Pong(None)).await?;
+        assert_eq!(sink.len(), 2);
+        assert!(matches!(sink.get_ref()[..], [PoolMessageSC::Throttle(1)]));
+        sink.close().await?;
+        let names = sink
+            .get_ref()
+            .iter()
+            .map(|message| message.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Throttle", "Pong", "Throttle"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{codec::*, error::*, format::*, version::*};
+
+use ::bytes::{Bytes, BytesMut};
+use anyhow::Result;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// A frame whose body has not been deserialized yet, yielded by `RawCodec`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawFrame {
+    /// The message id, the first byte of `payload`.
+    pub id: u8,
+    /// The frame body := << id, data >>, decompressed and reassembled.
+    pub payload: Bytes,
+    /// The protocol version `payload` is laid out in.
+    pub version: ProtocolVersion,
+}
+
+impl RawFrame {
+    /// Deserializes the payload into a message, e.g. once the frame reached the task handling its id.
+    pub fn deserialize<M: WireMessage>(self) -> Result<M> {
+        WireFormat::Binary.deserialize_versioned_bytes(self.version, self.payload)
+    }
+}
+
+/// Wraps a codec whose `Decoder` yields `RawFrame`s instead of messages, so a router can
+/// dispatch frames by id and leave deserialization to the receiving task. Frames pass every
+/// check of the inner codec except the deserialization of their body, and unknown ids are
+/// returned as they are. Requires `WireFormat::Binary`.
+#[derive(Clone, Debug)]
+pub struct RawCodec<M> {
+    inner: MessageCodec<M>,
+}
+
+impl<M> Default for RawCodec<M> {
+    fn default() -> Self {
+        Self::new(MessageCodec::new())
+    }
+}
+
+impl<M> RawCodec<M> {
+    /// Wraps a codec, yielding raw frames from now on.
+    pub fn new(inner: MessageCodec<M>) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &MessageCodec<M> {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut MessageCodec<M> {
+        &mut self.inner
+    }
+
+    fn raw_frame(&self, frame: Option<(u16, Bytes)>) -> Result<Option<RawFrame>, std::io::Error> {
+        match frame {
+            Some((0, payload)) => Ok(Some(RawFrame {
+                id: payload[0],
+                payload,
+                version: self.inner.protocol_version(),
+            })),
+            Some((channel, _)) => Err(PoolProtocolError::UnexpectedChannel(channel).into()),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<M: WireMessage> Encoder<M> for RawCodec<M> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: M, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(message, dst)
+    }
+}
+
+impl<M: WireMessage> Decoder for RawCodec<M> {
+    type Error = std::io::Error;
+    type Item = RawFrame;
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let frame = self.inner.decode_next_raw(source)?;
+        self.raw_frame(frame)
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let frame = self.inner.decode_next_raw_eof(source)?;
+        self.raw_frame(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{capabilities::*, message_id::cs, poolmessage::*};
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_raw_codec() -> Result<()> {
+        let connect = PoolMessageCS::<CurrentNetwork>::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "a".repeat(2000),
+            vec![],
+        );
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_CHUNKS | CAP_SEQUENCE);
+        let mut buffer = BytesMut::new();
+        encoder.encode(PoolMessageCS::Ping, &mut buffer)?;
+        encoder.encode(connect, &mut buffer)?;
+        encoder.encode(PoolMessageCS::JobStatus(1, 2), &mut buffer)?;
+
+        let mut decoder = RawCodec::new(PoolCodecCS::<CurrentNetwork>::new());
+        decoder
+            .inner_mut()
+            .set_capabilities(CAP_CHUNKS | CAP_SEQUENCE);
+        let mut ids = Vec::new();
+        while let Some(frame) = decoder.decode(&mut buffer)? {
+            ids.push(frame.id);
+            // The chunked Connect is reassembled before it is yielded.
+            if frame.id == cs::CONNECT {
+                match frame.deserialize::<PoolMessageCS<CurrentNetwork>>()? {
+                    PoolMessageCS::Connect(.., address, _) => {
+                        assert_eq!(address, "a".repeat(2000))
+                    }
+                    message => panic!("unexpected message {:?}", message),
+                }
+            }
+        }
+        assert_eq!(ids, [cs::PING, cs::CONNECT, cs::JOB_STATUS]);
+
+        // Raw frames still belong to one direction.
+        PoolCodecSC::<CurrentNetwork>::new().encode(PoolMessageSC::ShutDown, &mut buffer)?;
+        assert!(decoder.decode(&mut buffer).is_err());
+        Ok(())
+    }
+}
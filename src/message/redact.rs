@@ -0,0 +1,184 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{poolmessage::*, tlv::*};
+use snarkvm::prelude::*;
+
+use std::primitive::str;
+
+/// Stands in for a masked value in `PoolMessageCS::redacted`.
+pub const REDACTED: &str = "<redacted>";
+
+/// TLVs whose values identify the miner or prove its keys.
+const REDACTED_TAGS: &[u8] = &[TAG_RESUME_TOKEN, TAG_ADDRESS_PROOF, TAG_PAYOUT_SPLIT];
+
+/// Masks an address, keeping the last 4 characters of an Aleo address to tell miners apart.
+/// Anything else, e.g. an email, is masked whole.
+fn redact_address(address: &str) -> String {
+    match address.strip_prefix("aleo1") {
+        Some(rest) if address.is_ascii() && rest.len() > 4 => {
+            format!("\"aleo1…{}\"", &rest[rest.len() - 4..])
+        }
+        _ => REDACTED.to_string(),
+    }
+}
+
+fn redact_tlvs(tlvs: &[Tlv]) -> String {
+    let tlvs = tlvs
+        .iter()
+        .map(|tlv| match REDACTED_TAGS.contains(&tlv.tag) {
+            true => format!("Tlv {{ tag: {}, value: {} }}", tlv.tag, REDACTED),
+            false => format!("{:?}", tlv),
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", tlvs.join(", "))
+}
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Returns a `Debug`-like string with addresses, signatures, tokens and solutions masked,
+    /// for logs that must not hold them in plaintext. Use it instead of `{:?}`.
+    pub fn redacted(&self) -> String {
+        match self {
+            Self::Connect(typ, address_type, major, minor, patch, name, address, tlvs) => format!(
+                "Connect({}, {}, {}, {}, {}, {:?}, {}, {})",
+                typ,
+                address_type,
+                major,
+                minor,
+                patch,
+                name,
+                redact_address(address),
+                redact_tlvs(tlvs)
+            ),
+            Self::Submit(worker_id, sub_worker_id, job_id, _, signature) => format!(
+                "Submit({}, {}, {}, {}, {})",
+                worker_id,
+                sub_worker_id,
+                job_id,
+                REDACTED,
+                match signature {
+                    Some(_) => format!("Some({})", REDACTED),
+                    None => "None".to_string(),
+                }
+            ),
+            Self::Chunk(transfer_id, index, total, bytes) => format!(
+                "Chunk({}, {}, {}, <{} bytes>)",
+                transfer_id,
+                index,
+                total,
+                bytes.len()
+            ),
+            Self::SubmitChunk(work_id, job_id, index, total, bytes) => format!(
+                "SubmitChunk({}, {}, {}, {}, <{} bytes>)",
+                work_id,
+                job_id,
+                index,
+                total,
+                bytes.len()
+            ),
+            Self::ResumeSession(worker_id, address, _) => format!(
+                "ResumeSession({}, {}, {})",
+                worker_id,
+                redact_address(address),
+                REDACTED
+            ),
+            Self::Resume(_) => format!("Resume({})", REDACTED),
+            Self::SubmitCommitment(work_id, job_id, _) => {
+                format!("SubmitCommitment({}, {}, {})", work_id, job_id, REDACTED)
+            }
+            Self::DisConnect(..)
+            | Self::Ping
+            | Self::JobStatus(..)
+            | Self::RegisterWorker(..)
+            | Self::Hello(..)
+            | Self::UpgradeTls
+            | Self::InfoReport(..)
+            | Self::SetNotifyInterval(..)
+            | Self::PowerStats(..)
+            | Self::NotifyAck(..)
+            | Self::Bye
+            | Self::Unused(..) => format!("{:?}", self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::thread_rng;
+    use snarkvm::prelude::Testnet3;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_redacted() -> anyhow::Result<()> {
+        let rng = &mut thread_rng();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng)?;
+        let address = Address::try_from(&private_key)?.to_string();
+        let message = PoolMessageCS::<CurrentNetwork>::Connect(
+            0,
+            0,
+            0,
+            3,
+            6,
+            "rig-01".to_string(),
+            address.clone(),
+            vec![
+                Tlv::new(TAG_NONCE, 7u64.to_le_bytes().to_vec()),
+                Tlv::new(TAG_ADDRESS_PROOF, b"secret proof".to_vec()),
+            ],
+        );
+        let redacted = message.redacted();
+        assert_eq!(
+            redacted,
+            format!(
+                "Connect(0, 0, 0, 3, 6, \"rig-01\", \"aleo1…{}\", [Tlv {{ tag: 3, value: [7, 0, 0, 0, 0, 0, 0, 0] }}, Tlv {{ tag: 4, value: <redacted> }}])",
+                &address[address.len() - 4..]
+            )
+        );
+        assert!(!redacted.contains(&address));
+
+        let message = PoolMessageCS::<CurrentNetwork>::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            vec![],
+        );
+        assert_eq!(
+            message.redacted(),
+            "Connect(0, 1, 0, 1, 0, \"my_worker_1\", <redacted>, [])"
+        );
+
+        let message = PoolMessageCS::<CurrentNetwork>::ResumeSession(
+            1,
+            address.clone(),
+            "testsignature".to_string(),
+        );
+        assert!(!message.redacted().contains(&address));
+        assert!(!message.redacted().contains("testsignature"));
+        assert_eq!(
+            PoolMessageCS::<CurrentNetwork>::Resume(0xdead_beef).redacted(),
+            "Resume(<redacted>)"
+        );
+        assert_eq!(
+            PoolMessageCS::<CurrentNetwork>::Chunk(7, 0, 2, vec![1; 448]).redacted(),
+            "Chunk(7, 0, 2, <448 bytes>)"
+        );
+        assert_eq!(
+            PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2).redacted(),
+            "JobStatus(1, 2)"
+        );
+        Ok(())
+    }
+}
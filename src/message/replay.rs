@@ -0,0 +1,224 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{codec::LengthEndianness, error::PoolProtocolError, poolmessage::MAXIMUM_MESSAGE_SIZE};
+
+use ::bytes::BytesMut;
+use std::io::{self, Read};
+use tokio_util::codec::Decoder;
+
+/// Reads captured traffic := << length(u32), frame >>* back one frame at a time,
+/// e.g. from a file written during a postmortem capture.
+/// Each frame keeps its length prefix so it can be fed to any codec's `decode`.
+pub struct FrameReader<R> {
+    reader: R,
+    max_frame_size: usize,
+    length_endianness: LengthEndianness,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            max_frame_size: MAXIMUM_MESSAGE_SIZE,
+            length_endianness: LengthEndianness::Little,
+        }
+    }
+
+    /// Sets the largest frame accepted, which should match the live codec's limit.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Sets the byte order of the length prefixes, which should match the live codec's.
+    pub fn with_length_endianness(mut self, length_endianness: LengthEndianness) -> Self {
+        self.length_endianness = length_endianness;
+        self
+    }
+
+    /// Returns the next frame, `None` at the end of the capture.
+    /// A capture ending in the middle of a frame is an `UnexpectedEof` error.
+    pub fn next_frame(&mut self) -> io::Result<Option<BytesMut>> {
+        let mut length_bytes = [0u8; 4];
+        let mut read = 0;
+        while read < 4 {
+            match self.reader.read(&mut length_bytes[read..])? {
+                0 if read == 0 => return Ok(None),
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => read += n,
+            }
+        }
+        let length = self.length_endianness.from_bytes(length_bytes) as usize;
+        if length > self.max_frame_size {
+            return Err(PoolProtocolError::FrameTooLarge(length).into());
+        }
+        let mut frame = BytesMut::zeroed(4 + length);
+        frame[..4].copy_from_slice(&length_bytes);
+        self.reader.read_exact(&mut frame[4..])?;
+        Ok(Some(frame))
+    }
+
+    /// Replays the capture through `decoder`, yielding the messages it reconstructs.
+    /// The decoder must be set up like the live one, e.g. with the negotiated capabilities.
+    pub fn decode<D: Decoder>(self, decoder: D) -> Replay<R, D> {
+        Replay {
+            frames: self,
+            decoder,
+        }
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = io::Result<BytesMut>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+/// The messages decoded from a `FrameReader`, see `FrameReader::decode`.
+pub struct Replay<R, D> {
+    frames: FrameReader<R>,
+    decoder: D,
+}
+
+impl<R: Read, D: Decoder> Replay<R, D> {
+    /// Returns the decoder, e.g. to adjust its capabilities after a replayed handshake.
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+}
+
+impl<R: Read, D: Decoder> Iterator for Replay<R, D> {
+    type Item = Result<D::Item, D::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // A frame may only complete a message, e.g. a chunk of a larger transfer.
+        loop {
+            let mut frame = match self.frames.next_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error.into())),
+            };
+            match self.decoder.decode(&mut frame) {
+                Ok(Some(message)) => return Some(Ok(message)),
+                Ok(None) => continue,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{capabilities::*, codec::*, poolmessage::*};
+    use anyhow::Result;
+    use snarkvm::prelude::Testnet3;
+    use tokio_util::codec::Encoder;
+    type CurrentNetwork = Testnet3;
+
+    fn large_connect() -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "a".repeat(2000),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_replay_capture() -> Result<()> {
+        let mut encoder = PoolCodecCS::<CurrentNetwork>::new();
+        encoder.set_capabilities(CAP_CHUNKS | CAP_SEQUENCE);
+        let mut capture = BytesMut::new();
+        encoder.encode(PoolMessageCS::Ping, &mut capture)?;
+        encoder.encode(large_connect(), &mut capture)?;
+        encoder.encode(PoolMessageCS::JobStatus(1, 2), &mut capture)?;
+
+        assert_eq!(FrameReader::new(&capture[..]).count(), 2 + 5);
+
+        let mut decoder = PoolCodecCS::<CurrentNetwork>::new();
+        decoder.set_capabilities(CAP_CHUNKS | CAP_SEQUENCE);
+        let names = FrameReader::new(&capture[..])
+            .decode(decoder)
+            .map(|message| message.map(|message| message.name().to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(names, ["Ping", "Connect", "JobStatus"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_legacy_decoder() -> Result<()> {
+        let mut capture = Vec::new();
+        capture.extend_from_slice(&PoolMessageSC::<CurrentNetwork>::Pong(None).to_vec()?);
+        capture.extend_from_slice(&PoolMessageSC::<CurrentNetwork>::Throttle(500).to_vec()?);
+        let mut replay =
+            FrameReader::new(&capture[..]).decode(PoolCodecSC::<CurrentNetwork>::new());
+        assert!(matches!(replay.next(), Some(Ok(PoolMessageSC::Pong(None)))));
+        assert!(matches!(
+            replay.next(),
+            Some(Ok(PoolMessageSC::<CurrentNetwork>::Throttle(500)))
+        ));
+        assert!(replay.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_big_endian() -> Result<()> {
+        let mut encoder =
+            PoolCodecSC::<CurrentNetwork>::new().with_length_endianness(LengthEndianness::Big);
+        let mut capture = BytesMut::new();
+        encoder.encode(PoolMessageSC::Throttle(500), &mut capture)?;
+        let decoder =
+            PoolCodecSC::<CurrentNetwork>::new().with_length_endianness(LengthEndianness::Big);
+        let mut replay = FrameReader::new(&capture[..])
+            .with_length_endianness(LengthEndianness::Big)
+            .decode(decoder);
+        assert!(matches!(
+            replay.next(),
+            Some(Ok(PoolMessageSC::<CurrentNetwork>::Throttle(500)))
+        ));
+        assert!(replay.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_truncated() -> Result<()> {
+        let capture = PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2).to_vec()?;
+        assert!(FrameReader::new(&capture[..0]).next().is_none());
+        for end in [2, capture.len() - 1] {
+            let error = FrameReader::new(&capture[..end])
+                .next()
+                .unwrap()
+                .unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        let capture = ((MAXIMUM_MESSAGE_SIZE + 1) as u32).to_le_bytes();
+        let error = FrameReader::new(&capture[..]).next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // A larger limit reads the frame, here only to its missing body.
+        let error = FrameReader::new(&capture[..])
+            .with_max_frame_size(MAXIMUM_MESSAGE_SIZE + 1)
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+}
@@ -0,0 +1,775 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{auth::*, error::*, poolmessage::*, tlv::*};
+use snarkvm::prelude::*;
+
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashMap,
+    fmt,
+    primitive::str,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const SESSION_SIGNATURE_CONTEXT: &[u8] = b"zkwork-aleo-session";
+
+/// How long a `ConnectAck` signature can be used to resume its session by default.
+pub const DEFAULT_SESSION_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a resume token stays valid by default.
+/// Tokens only bridge brief network drops; after that the client resumes with its signature.
+pub const DEFAULT_RESUME_TOKEN_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// The signed message := (context, worker_id, nonce, expires_at, address)
+fn session_message(worker_id: u32, nonce: u64, expires_at: u64, address: &str) -> Vec<u8> {
+    let mut message = SESSION_SIGNATURE_CONTEXT.to_vec();
+    message.extend_from_slice(&worker_id.to_le_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&expires_at.to_le_bytes());
+    message.extend_from_slice(address.as_bytes());
+    message
+}
+
+/// Splits a session signature := "expires_at.nonce.aleo_signature"
+fn parse_session_signature<N: Network>(signature: &str) -> Option<(u64, u64, Signature<N>)> {
+    let mut parts = signature.splitn(3, '.');
+    let expires_at = parts.next()?.parse().ok()?;
+    let nonce = parts.next()?.parse().ok()?;
+    let signature = Signature::from_str(parts.next()?).ok()?;
+    Some((expires_at, nonce, signature))
+}
+
+/// Returns the TLV carrying the client nonce in `Connect`.
+pub fn nonce_tlv(nonce: u64) -> Tlv {
+    Tlv::new(TAG_NONCE, nonce.to_le_bytes().to_vec())
+}
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Returns the nonce of a `Connect`, `None` for older clients.
+    pub fn nonce(&self) -> Option<u64> {
+        match self {
+            Self::Connect(.., tlvs) => match find_tlv(tlvs, TAG_NONCE) {
+                Some(&[a, b, c, d, e, f, g, h]) => {
+                    Some(u64::from_le_bytes([a, b, c, d, e, f, g, h]))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Pool-side issuer of the signatures returned in an accepted `ConnectAck`.
+/// A signature := "expires_at.nonce.aleo_signature", where the Aleo signature by the pool's key
+/// covers the worker id, the client's `Connect` nonce and address, and the expiry.
+/// The client checks it with `verify_connect_ack`, and can later prove it owns the worker id
+/// with `ResumeSession`. Each signature resumes a session once: the signer remembers the spent
+/// ones until they expire, and `resume_ack` answers with a fresh signature for the next resume.
+pub struct SessionSigner<N: Network> {
+    private_key: PrivateKey<N>,
+    address: Address<N>,
+    lifetime: Duration,
+    /// Spent signatures := aleo_signature => expires_at
+    spent: HashMap<String, u64>,
+}
+
+impl<N: Network> SessionSigner<N> {
+    pub fn new(private_key: PrivateKey<N>, lifetime: Duration) -> Result<Self> {
+        let address = Address::try_from(&private_key)?;
+        Ok(Self {
+            private_key,
+            address,
+            lifetime,
+            spent: HashMap::new(),
+        })
+    }
+
+    /// Returns the pool address the signatures verify against.
+    pub fn address(&self) -> Address<N> {
+        self.address
+    }
+
+    /// Signs a session for `worker_id`, valid for the signer's lifetime.
+    /// `nonce` and `address` come from the client's `Connect`; older clients send no nonce, use 0.
+    pub fn sign_session<R: Rng + CryptoRng>(
+        &self,
+        worker_id: u32,
+        nonce: u64,
+        address: &str,
+        rng: &mut R,
+    ) -> Result<String> {
+        self.sign_session_at(worker_id, nonce, address, unix_now(), rng)
+    }
+
+    /// Signs a session as of `now`, in seconds since the Unix epoch.
+    pub fn sign_session_at<R: Rng + CryptoRng>(
+        &self,
+        worker_id: u32,
+        nonce: u64,
+        address: &str,
+        now: u64,
+        rng: &mut R,
+    ) -> Result<String> {
+        let expires_at = now.saturating_add(self.lifetime.as_secs());
+        let signature = self
+            .private_key
+            .sign_bytes(&session_message(worker_id, nonce, expires_at, address), rng)?;
+        Ok(format!("{}.{}.{}", expires_at, nonce, signature))
+    }
+
+    /// Returns the accepted `ConnectAck` answering `connect`, with a fresh session signature.
+    pub fn connect_ack<R: Rng + CryptoRng>(
+        &self,
+        worker_id: u32,
+        connect: &PoolMessageCS<N>,
+        tlvs: Vec<Tlv>,
+        rng: &mut R,
+    ) -> Result<PoolMessageSC<N>> {
+        let address = match connect {
+            PoolMessageCS::Connect(.., address, _) => address,
+            message => return Err(anyhow!("Expected 'Connect', found '{}'", message.name())),
+        };
+        let nonce = connect.nonce().unwrap_or_default();
+        let signature = self.sign_session(worker_id, nonce, address, rng)?;
+        Ok(PoolMessageSC::ConnectAck(
+            true,
+            self.address,
+            Some(worker_id),
+            Some(signature),
+            None,
+            tlvs,
+        ))
+    }
+
+    /// Checks the signature of a `ResumeSession` and spends it, so a captured `ResumeSession`
+    /// cannot be replayed. On success the pool restores the session and answers with
+    /// `resume_ack`. Spent signatures are kept in memory only, until they expire.
+    pub fn verify_session(
+        &mut self,
+        worker_id: u32,
+        address: &str,
+        signature: &str,
+    ) -> Result<(), PoolProtocolError> {
+        self.verify_session_at(worker_id, address, signature, unix_now())
+    }
+
+    /// Checks and spends the signature of a `ResumeSession` as of `now`.
+    pub fn verify_session_at(
+        &mut self,
+        worker_id: u32,
+        address: &str,
+        signature: &str,
+        now: u64,
+    ) -> Result<(), PoolProtocolError> {
+        let invalid = PoolProtocolError::InvalidSessionSignature(worker_id);
+        let (expires_at, nonce, signature) =
+            parse_session_signature::<N>(signature).ok_or(invalid.clone())?;
+        let message = session_message(worker_id, nonce, expires_at, address);
+        if !signature.verify_bytes(&self.address, &message) {
+            return Err(invalid);
+        }
+        if now >= expires_at {
+            return Err(PoolProtocolError::SessionExpired(worker_id));
+        }
+        self.spent
+            .retain(|_, spent_expires_at| now < *spent_expires_at);
+        match self.spent.insert(signature.to_string(), expires_at) {
+            None => Ok(()),
+            Some(_) => Err(PoolProtocolError::SessionReplayed(worker_id)),
+        }
+    }
+
+    /// Returns the accepted `ConnectAck` answering a `ResumeSession` that passed `verify_session`,
+    /// with a fresh signature over the nonce of the spent one.
+    pub fn resume_ack<R: Rng + CryptoRng>(
+        &self,
+        resume: &PoolMessageCS<N>,
+        tlvs: Vec<Tlv>,
+        rng: &mut R,
+    ) -> Result<PoolMessageSC<N>> {
+        let (worker_id, address, signature) = match resume {
+            PoolMessageCS::ResumeSession(worker_id, address, signature) => {
+                (*worker_id, address, signature)
+            }
+            message => {
+                return Err(anyhow!(
+                    "Expected 'ResumeSession', found '{}'",
+                    message.name()
+                ))
+            }
+        };
+        let (_, nonce, _) = parse_session_signature::<N>(signature)
+            .ok_or(PoolProtocolError::InvalidSessionSignature(worker_id))?;
+        let signature = self.sign_session(worker_id, nonce, address, rng)?;
+        Ok(PoolMessageSC::ConnectAck(
+            true,
+            self.address,
+            Some(worker_id),
+            Some(signature),
+            None,
+            tlvs,
+        ))
+    }
+
+    /// Returns the number of spent signatures that have not expired yet.
+    pub fn spent_sessions(&self) -> usize {
+        self.spent.len()
+    }
+}
+
+/// Checks on the client side that `ack` accepts the `Connect` sent with `nonce` and `address`,
+/// and was signed by the pool's published address. A replayed ack fails the nonce check.
+pub fn verify_connect_ack<N: Network>(
+    ack: &PoolMessageSC<N>,
+    pool_address: &Address<N>,
+    nonce: u64,
+    address: &str,
+) -> Result<(), PoolProtocolError> {
+    let (worker_id, signature) = match ack {
+        PoolMessageSC::ConnectAck(true, ack_address, Some(worker_id), Some(signature), ..)
+            if ack_address == pool_address =>
+        {
+            (*worker_id, signature)
+        }
+        _ => return Err(PoolProtocolError::InvalidConnectAck),
+    };
+    let invalid = PoolProtocolError::InvalidSessionSignature(worker_id);
+    let (expires_at, signed_nonce, signature) =
+        parse_session_signature::<N>(signature).ok_or(invalid.clone())?;
+    let message = session_message(worker_id, nonce, expires_at, address);
+    match signed_nonce == nonce && signature.verify_bytes(pool_address, &message) {
+        true => Ok(()),
+        false => Err(invalid),
+    }
+}
+
+/// Returns the TLV carrying a resume token in `ConnectAck`.
+pub fn resume_token_tlv(resume_token: u64) -> Tlv {
+    Tlv::new(TAG_RESUME_TOKEN, resume_token.to_le_bytes().to_vec())
+}
+
+impl<N: Network> PoolMessageSC<N> {
+    /// Returns the resume token issued by an accepted `ConnectAck`, if any.
+    pub fn resume_token(&self) -> Option<u64> {
+        match self {
+            Self::ConnectAck(true, .., tlvs) => match find_tlv(tlvs, TAG_RESUME_TOKEN) {
+                Some(&[a, b, c, d, e, f, g, h]) => {
+                    Some(u64::from_le_bytes([a, b, c, d, e, f, g, h]))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the pool software name and version reported by an accepted `ConnectAck`, if any.
+    pub fn server_info(&self) -> Option<String> {
+        match self {
+            Self::ConnectAck(true, .., tlvs) => find_tlv(tlvs, TAG_SERVER_INFO)
+                .and_then(|value| String::from_utf8(value.to_vec()).ok()),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the TLV reporting the pool software in `ConnectAck`, e.g. "zkwork-pool/1.4.2".
+pub fn server_info_tlv(server_info: &str) -> Tlv {
+    Tlv::new(TAG_SERVER_INFO, server_info.as_bytes().to_vec())
+}
+
+/// Pool-side table of the resume tokens issued in `ConnectAck`.
+/// A token is valid once, for the table's lifetime; redeeming it with `Resume` restores the worker
+/// id without checking the session signature, and the pool issues a fresh token in its reply.
+#[derive(Clone, Debug)]
+pub struct ResumeTokens {
+    tokens: HashMap<u64, (u32, Instant)>,
+    lifetime: Duration,
+}
+
+impl Default for ResumeTokens {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESUME_TOKEN_LIFETIME)
+    }
+}
+
+impl ResumeTokens {
+    pub fn new(lifetime: Duration) -> Self {
+        Self {
+            tokens: HashMap::new(),
+            lifetime,
+        }
+    }
+
+    /// Issues a token for `worker_id`.
+    pub fn issue<R: Rng>(&mut self, worker_id: u32, rng: &mut R) -> u64 {
+        self.issue_at(worker_id, Instant::now(), rng)
+    }
+
+    /// Issues a token for `worker_id` as of `now`.
+    pub fn issue_at<R: Rng>(&mut self, worker_id: u32, now: Instant, rng: &mut R) -> u64 {
+        self.collect_garbage(now);
+        loop {
+            let resume_token = rng.gen();
+            if !self.tokens.contains_key(&resume_token) {
+                self.tokens
+                    .insert(resume_token, (worker_id, now + self.lifetime));
+                return resume_token;
+            }
+        }
+    }
+
+    /// Consumes a token, returning its worker id if it is still valid.
+    pub fn redeem(&mut self, resume_token: u64) -> Option<u32> {
+        self.redeem_at(resume_token, Instant::now())
+    }
+
+    /// Consumes a token as of `now`.
+    pub fn redeem_at(&mut self, resume_token: u64, now: Instant) -> Option<u32> {
+        match self.tokens.remove(&resume_token) {
+            Some((worker_id, expires_at)) if now < expires_at => Some(worker_id),
+            _ => None,
+        }
+    }
+
+    /// Invalidates every token of `worker_id`, e.g. after an explicit `DisConnect`.
+    pub fn revoke(&mut self, worker_id: u32) {
+        self.tokens.retain(|_, (id, _)| *id != worker_id);
+    }
+
+    /// Drops the expired tokens.
+    pub fn collect_garbage(&mut self, now: Instant) {
+        self.tokens.retain(|_, (_, expires_at)| now < *expires_at);
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+/// Client-side record of an accepted session, kept to resume it after a reconnect.
+/// It is stored as "worker_id:address:signature"; the short-lived resume token and the
+/// server info are kept in memory only.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SessionTicket {
+    pub worker_id: u32,
+    pub address: String,
+    pub signature: String,
+    pub resume_token: Option<u64>,
+    pub server_info: Option<String>,
+}
+
+impl SessionTicket {
+    pub fn new(worker_id: u32, address: String, signature: String) -> Self {
+        Self {
+            worker_id,
+            address,
+            signature,
+            resume_token: None,
+            server_info: None,
+        }
+    }
+
+    /// Returns the ticket issued by an accepted `ConnectAck` to the `Connect` sent with `address`.
+    pub fn from_connect_ack<N: Network>(message: &PoolMessageSC<N>, address: &str) -> Option<Self> {
+        match message {
+            PoolMessageSC::ConnectAck(true, _, Some(worker_id), Some(signature), ..) => {
+                Some(Self {
+                    resume_token: message.resume_token(),
+                    server_info: message.server_info(),
+                    ..Self::new(*worker_id, address.to_string(), signature.clone())
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the message resuming this session on a new connection.
+    pub fn resume_message<N: Network>(&self) -> PoolMessageCS<N> {
+        PoolMessageCS::ResumeSession(self.worker_id, self.address.clone(), self.signature.clone())
+    }
+
+    /// Returns the cheaper `Resume` message when the pool issued a resume token.
+    /// Fall back to `resume_message` if the pool rejects it.
+    pub fn resume_token_message<N: Network>(&self) -> Option<PoolMessageCS<N>> {
+        self.resume_token.map(PoolMessageCS::Resume)
+    }
+
+    /// Returns the key authenticating frames of this session.
+    pub fn session_key(&self) -> SessionKey {
+        SessionKey::from_connect_ack(self.worker_id, &self.signature)
+    }
+}
+
+impl fmt::Display for SessionTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.worker_id, self.address, self.signature)
+    }
+}
+
+impl fmt::Debug for SessionTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SessionTicket({}, {}, ..)", self.worker_id, self.address)
+    }
+}
+
+impl FromStr for SessionTicket {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.splitn(3, ':');
+        let (worker_id, address, signature) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(worker_id), Some(address), Some(signature)) => (worker_id, address, signature),
+            _ => return Err(anyhow!("Invalid session ticket")),
+        };
+        Ok(Self::new(
+            worker_id.parse()?,
+            address.to_string(),
+            signature.to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::handshake::*;
+    use ::rand::thread_rng;
+    type CurrentNetwork = Testnet3;
+
+    fn new_signer() -> Result<SessionSigner<CurrentNetwork>> {
+        SessionSigner::new(
+            PrivateKey::new(&mut thread_rng())?,
+            DEFAULT_SESSION_LIFETIME,
+        )
+    }
+
+    fn new_connect(address: &str, nonce: u64) -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            0,
+            0,
+            1,
+            0,
+            "test-worker".to_string(),
+            address.to_string(),
+            vec![nonce_tlv(nonce)],
+        )
+    }
+
+    #[test]
+    fn test_session_resume() -> Result<()> {
+        let rng = &mut thread_rng();
+        let mut signer = new_signer()?;
+        let nonce = rng.gen();
+        let connect = new_connect("aleo1client", nonce);
+        let bytes = connect.to_vec()?;
+        let connect = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(connect.nonce(), Some(nonce));
+        let ack = signer.connect_ack(7, &connect, vec![], rng)?;
+        verify_connect_ack(&ack, &signer.address(), nonce, "aleo1client")?;
+
+        // The client stores the ticket and resumes on a new connection.
+        let ticket = SessionTicket::from_connect_ack(&ack, "aleo1client").unwrap();
+        let stored = ticket.to_string();
+        let ticket = stored.parse::<SessionTicket>()?;
+        assert_eq!(ticket.worker_id, 7);
+        assert_eq!(ticket.address, "aleo1client");
+        let resume = ticket.resume_message::<CurrentNetwork>();
+        let mut state = HandshakeState::new();
+        state.advance(&resume)?;
+
+        // The pool restores the session with the same id and a fresh signature.
+        let resume = PoolMessageCS::<CurrentNetwork>::deserialize(&resume.to_vec()?[4..])?;
+        let (worker_id, address, signature) = match &resume {
+            PoolMessageCS::ResumeSession(worker_id, address, signature) => {
+                (*worker_id, address.clone(), signature.clone())
+            }
+            message => panic!("unexpected message {:?}", message),
+        };
+        signer.verify_session(worker_id, &address, &signature)?;
+        let ack = signer.resume_ack(&resume, vec![], rng)?;
+        verify_connect_ack(&ack, &signer.address(), nonce, "aleo1client")?;
+        state.advance(&ack)?;
+        assert!(state.is_established());
+        let resumed = SessionTicket::from_connect_ack(&ack, &address).unwrap();
+        assert_eq!(resumed.worker_id, 7);
+        assert_ne!(resumed.signature, ticket.signature);
+
+        // The next resume uses the fresh signature.
+        let resume = resumed.resume_message::<CurrentNetwork>();
+        signer.verify_session(7, "aleo1client", &resumed.signature)?;
+        assert!(signer.resume_ack(&resume, vec![], rng).is_ok());
+        assert!(signer
+            .resume_ack(&PoolMessageCS::Ping, vec![], rng)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_replayed() -> Result<()> {
+        let rng = &mut thread_rng();
+        let mut signer = new_signer()?;
+        let ack = signer.connect_ack(7, &new_connect("aleo1client", 1), vec![], rng)?;
+        let ticket = SessionTicket::from_connect_ack(&ack, "aleo1client").unwrap();
+
+        // An eavesdropper replays the client's ResumeSession after it was accepted.
+        let captured = ticket.resume_message::<CurrentNetwork>().to_vec()?;
+        for expected in [Ok(()), Err(PoolProtocolError::SessionReplayed(7))] {
+            let (worker_id, address, signature) =
+                match PoolMessageCS::<CurrentNetwork>::deserialize(&captured[4..])? {
+                    PoolMessageCS::ResumeSession(worker_id, address, signature) => {
+                        (worker_id, address, signature)
+                    }
+                    message => panic!("unexpected message {:?}", message),
+                };
+            assert_eq!(
+                signer.verify_session(worker_id, &address, &signature),
+                expected
+            );
+        }
+        assert_eq!(signer.spent_sessions(), 1);
+
+        // Spent signatures are forgotten once they expire.
+        let signature = signer.sign_session_at(7, 2, "aleo1client", 1000, rng)?;
+        let expires_at = 1000 + DEFAULT_SESSION_LIFETIME.as_secs();
+        signer.verify_session_at(7, "aleo1client", &signature, expires_at - 1)?;
+        let signature = signer.sign_session_at(8, 3, "aleo1client", expires_at, rng)?;
+        signer.verify_session_at(8, "aleo1client", &signature, expires_at)?;
+        assert_eq!(signer.spent_sessions(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_without_nonce() -> Result<()> {
+        let rng = &mut thread_rng();
+        let signer = new_signer()?;
+        // Older clients send no nonce; the pool signs 0.
+        let connect = PoolMessageCS::Connect(
+            0,
+            0,
+            0,
+            1,
+            0,
+            "test-worker".to_string(),
+            "aleo1client".to_string(),
+            vec![],
+        );
+        assert_eq!(connect.nonce(), None);
+        let ack = signer.connect_ack(7, &connect, vec![], rng)?;
+        verify_connect_ack(&ack, &signer.address(), 0, "aleo1client")?;
+        assert!(signer
+            .connect_ack(7, &PoolMessageCS::Ping, vec![], rng)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_connect_ack() -> Result<()> {
+        let rng = &mut thread_rng();
+        let signer = new_signer()?;
+        let nonce = rng.gen();
+        let ack = signer.connect_ack(7, &new_connect("aleo1client", nonce), vec![], rng)?;
+        let forged = Err(PoolProtocolError::InvalidSessionSignature(7));
+        assert_eq!(
+            verify_connect_ack(&ack, &signer.address(), nonce, "aleo1client"),
+            Ok(())
+        );
+
+        // A replayed ack answers another nonce.
+        assert_eq!(
+            verify_connect_ack(
+                &ack,
+                &signer.address(),
+                nonce.wrapping_add(1),
+                "aleo1client"
+            ),
+            forged
+        );
+        // An ack for another client address.
+        assert_eq!(
+            verify_connect_ack(&ack, &signer.address(), nonce, "aleo1other"),
+            forged
+        );
+        // An ack from another pool.
+        let other = new_signer()?;
+        assert_eq!(
+            verify_connect_ack(&ack, &other.address(), nonce, "aleo1client"),
+            Err(PoolProtocolError::InvalidConnectAck)
+        );
+        // Another pool's signature under this pool's address.
+        let other_ack = other.connect_ack(7, &new_connect("aleo1client", nonce), vec![], rng)?;
+        let signature = match other_ack {
+            PoolMessageSC::ConnectAck(_, _, _, signature, ..) => signature,
+            message => panic!("unexpected message {:?}", message),
+        };
+        let ack =
+            PoolMessageSC::ConnectAck(true, signer.address(), Some(7), signature, None, vec![]);
+        assert_eq!(
+            verify_connect_ack(&ack, &signer.address(), nonce, "aleo1client"),
+            forged
+        );
+        // A rejected ack.
+        let ack = PoolMessageSC::ConnectAck(false, signer.address(), None, None, None, vec![]);
+        assert_eq!(
+            verify_connect_ack(&ack, &signer.address(), nonce, "aleo1client"),
+            Err(PoolProtocolError::InvalidConnectAck)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_expired() -> Result<()> {
+        let mut signer = new_signer()?;
+        let signature = signer.sign_session_at(7, 1, "aleo1client", 1000, &mut thread_rng())?;
+        let expires_at = 1000 + DEFAULT_SESSION_LIFETIME.as_secs();
+        assert_eq!(
+            signer.verify_session_at(7, "aleo1client", &signature, expires_at - 1),
+            Ok(())
+        );
+        assert_eq!(
+            signer.verify_session_at(7, "aleo1client", &signature, expires_at),
+            Err(PoolProtocolError::SessionExpired(7))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_forged() -> Result<()> {
+        let rng = &mut thread_rng();
+        let mut signer = new_signer()?;
+        let signature = signer.sign_session(7, 1, "aleo1client", rng)?;
+        let forged = Err(PoolProtocolError::InvalidSessionSignature(7));
+
+        // Another worker id.
+        assert_eq!(
+            signer.verify_session(8, "aleo1client", &signature),
+            Err(PoolProtocolError::InvalidSessionSignature(8))
+        );
+        // Another client address.
+        assert_eq!(signer.verify_session(7, "aleo1other", &signature), forged);
+        // An extended expiry.
+        let (expires_at, rest) = signature.split_once('.').unwrap();
+        let extended = format!("{}.{}", expires_at.parse::<u64>()? + 1, rest);
+        assert_eq!(signer.verify_session(7, "aleo1client", &extended), forged);
+        // Another nonce.
+        let (nonce, aleo_signature) = rest.split_once('.').unwrap();
+        let renonced = format!(
+            "{}.{}.{}",
+            expires_at,
+            nonce.parse::<u64>()? + 1,
+            aleo_signature
+        );
+        assert_eq!(signer.verify_session(7, "aleo1client", &renonced), forged);
+        // Another pool's key.
+        let other = new_signer()?.sign_session(7, 1, "aleo1client", rng)?;
+        assert_eq!(signer.verify_session(7, "aleo1client", &other), forged);
+        // Garbage.
+        assert_eq!(
+            signer.verify_session(7, "aleo1client", "testsignature"),
+            forged
+        );
+        assert_eq!(
+            signer.verify_session(7, "aleo1client", "1.2.testsignature"),
+            forged
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_token() -> Result<()> {
+        let rng = &mut thread_rng();
+        let signer = new_signer()?;
+        let mut tokens = ResumeTokens::default();
+        let connect = new_connect("aleo1client", 1);
+        let tlvs = vec![resume_token_tlv(tokens.issue(7, rng))];
+        let ack = signer.connect_ack(7, &connect, tlvs, rng)?;
+        let bytes = ack.to_vec()?;
+        let ack = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+
+        let ticket = SessionTicket::from_connect_ack(&ack, "aleo1client").unwrap();
+        let resume = match ticket.resume_token_message::<CurrentNetwork>() {
+            Some(PoolMessageCS::Resume(resume_token)) => resume_token,
+            message => panic!("unexpected message {:?}", message),
+        };
+        assert_eq!(tokens.redeem(resume), Some(7));
+        // Tokens are single use.
+        assert_eq!(tokens.redeem(resume), None);
+        assert!(tokens.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_info() -> Result<()> {
+        let rng = &mut thread_rng();
+        let signer = new_signer()?;
+        let connect = new_connect("aleo1client", 1);
+        let tlvs = vec![server_info_tlv("zkwork-pool/1.4.2")];
+        let ack = signer.connect_ack(7, &connect, tlvs, rng)?;
+        let bytes = ack.to_vec()?;
+        let ack = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(ack.server_info().as_deref(), Some("zkwork-pool/1.4.2"));
+        let ticket = SessionTicket::from_connect_ack(&ack, "aleo1client").unwrap();
+        assert_eq!(ticket.server_info.as_deref(), Some("zkwork-pool/1.4.2"));
+
+        // Clients predating the tag skip it, and older pools send none.
+        let tlvs = match ack {
+            PoolMessageSC::ConnectAck(.., tlvs) => tlvs,
+            message => panic!("unexpected message {:?}", message),
+        };
+        let mut section = Vec::new();
+        write_tlvs(&mut section, &tlvs)?;
+        assert!(read_tlvs(&section, &[TAG_CAPABILITIES, TAG_RESUME_TOKEN])?.is_empty());
+        let ack = signer.connect_ack(7, &connect, vec![], rng)?;
+        assert_eq!(ack.server_info(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_token_lifetime() {
+        let rng = &mut thread_rng();
+        let now = Instant::now();
+        let mut tokens = ResumeTokens::default();
+        let first = tokens.issue_at(1, now, rng);
+        let second = tokens.issue_at(2, now, rng);
+        let third = tokens.issue_at(2, now, rng);
+        assert_eq!(
+            tokens.redeem_at(first, now + DEFAULT_RESUME_TOKEN_LIFETIME),
+            None
+        );
+
+        tokens.revoke(2);
+        assert_eq!(tokens.redeem_at(second, now), None);
+        assert_eq!(tokens.redeem_at(third, now), None);
+
+        tokens.issue_at(3, now, rng);
+        tokens.collect_garbage(now + DEFAULT_RESUME_TOKEN_LIFETIME);
+        assert!(tokens.is_empty());
+
+        // A rejected ConnectAck carries no token.
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(rng));
+        let ack =
+            PoolMessageSC::ConnectAck(false, address, None, None, None, vec![resume_token_tlv(1)]);
+        assert_eq!(ack.resume_token(), None);
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{error::*, poolmessage::*, session::unix_now, tlv::*};
+use snarkvm::prelude::*;
+
+use anyhow::Result;
+use std::{primitive::str, time::Duration};
+
+/// How far a `Connect` timestamp may be from the pool's clock by default.
+pub const DEFAULT_CONNECT_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// Signs the commitment of a solution with the worker's account key, for `Submit`.
+pub fn sign_solution<N: Network, R: Rng + CryptoRng>(
+    private_key: &PrivateKey<N>,
+    solution: &ProverSolution<N>,
+    rng: &mut R,
+) -> Result<Signature<N>> {
+    private_key.sign_bytes(&solution.commitment().to_bytes_le()?, rng)
+}
+
+/// Checks that a `Submit` signature was made by `address` over the solution's commitment.
+/// Pools that require signed shares pass the address registered for the worker.
+pub fn verify_solution_signature<N: Network>(
+    address: &Address<N>,
+    solution: &ProverSolution<N>,
+    signature: Option<&Signature<N>>,
+) -> Result<(), PoolProtocolError> {
+    let signature = signature.ok_or(PoolProtocolError::MissingSubmitSignature)?;
+    let commitment = solution
+        .commitment()
+        .to_bytes_le()
+        .map_err(|_| PoolProtocolError::InvalidSubmitSignature)?;
+    match signature.verify_bytes(address, &commitment) {
+        true => Ok(()),
+        false => Err(PoolProtocolError::InvalidSubmitSignature),
+    }
+}
+
+/// The signed message of an address proof := (timestamp, name)
+fn connect_message(timestamp: u64, name: &str) -> Vec<u8> {
+    let mut message = timestamp.to_le_bytes().to_vec();
+    message.extend_from_slice(name.as_bytes());
+    message
+}
+
+/// Returns the `Connect` TLV proving that the worker `name` holds the key behind its payout address.
+/// `timestamp` is in seconds since the Unix epoch.
+pub fn sign_connect<N: Network, R: Rng + CryptoRng>(
+    private_key: &PrivateKey<N>,
+    name: &str,
+    timestamp: u64,
+    rng: &mut R,
+) -> Result<Tlv> {
+    let signature = private_key.sign_bytes(&connect_message(timestamp, name), rng)?;
+    let mut value = timestamp.to_le_bytes().to_vec();
+    signature.write_le(&mut value)?;
+    Ok(Tlv::new(TAG_ADDRESS_PROOF, value))
+}
+
+impl<N: Network> PoolMessageCS<N> {
+    /// Returns the (timestamp, signature) address proof of a `Connect`, `None` for older clients.
+    pub fn address_proof(&self) -> Option<(u64, Signature<N>)> {
+        match self {
+            Self::Connect(.., tlvs) => {
+                let value = find_tlv(tlvs, TAG_ADDRESS_PROOF)?;
+                if value.len() < 8 {
+                    return None;
+                }
+                let timestamp = u64::from_le_bytes(value[..8].try_into().ok()?);
+                let signature = Signature::from_bytes_le(&value[8..]).ok()?;
+                Some((timestamp, signature))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Checks that a `Connect` was signed by the key behind its payout address within `max_skew`
+/// of the pool's clock. Email accounts have no key and always pass.
+pub fn verify_connect<N: Network>(
+    connect: &PoolMessageCS<N>,
+    max_skew: Duration,
+) -> Result<(), PoolProtocolError> {
+    verify_connect_at(connect, max_skew, unix_now())
+}
+
+/// Checks a `Connect` address proof as of `now`, in seconds since the Unix epoch.
+pub fn verify_connect_at<N: Network>(
+    connect: &PoolMessageCS<N>,
+    max_skew: Duration,
+    now: u64,
+) -> Result<(), PoolProtocolError> {
+    let (name, address) = match connect {
+        PoolMessageCS::Connect(_, ADDRESS_TYPE_EMAIL, ..) => return Ok(()),
+        PoolMessageCS::Connect(.., name, address, _) => (name, address),
+        _ => return Err(PoolProtocolError::MissingConnectSignature),
+    };
+    let (timestamp, signature) = connect
+        .address_proof()
+        .ok_or(PoolProtocolError::MissingConnectSignature)?;
+    if now.abs_diff(timestamp) > max_skew.as_secs() {
+        return Err(PoolProtocolError::StaleConnectTimestamp(timestamp));
+    }
+    let address =
+        Address::<N>::from_str(address).map_err(|_| PoolProtocolError::InvalidConnectSignature)?;
+    match signature.verify_bytes(&address, &connect_message(timestamp, name)) {
+        true => Ok(()),
+        false => Err(PoolProtocolError::InvalidConnectSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::thread_rng;
+    use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+    type CurrentNetwork = Testnet3;
+
+    fn sample_prover_solution(address: Address<CurrentNetwork>) -> ProverSolution<CurrentNetwork> {
+        let rng = &mut thread_rng();
+        let partial_solution =
+            PartialSolution::new(address, u64::rand(rng), KZGCommitment(rng.gen()));
+        ProverSolution::new(
+            partial_solution,
+            KZGProof {
+                w: rng.gen(),
+                random_v: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_solution_signature() -> Result<()> {
+        let rng = &mut thread_rng();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng)?;
+        let address = Address::try_from(&private_key)?;
+        let solution = sample_prover_solution(address);
+        let signature = sign_solution(&private_key, &solution, rng)?;
+        assert_eq!(
+            verify_solution_signature(&address, &solution, Some(&signature)),
+            Ok(())
+        );
+
+        // A share stolen by another worker or altered on the way.
+        let other = Address::try_from(&PrivateKey::<CurrentNetwork>::new(rng)?)?;
+        assert_eq!(
+            verify_solution_signature(&other, &solution, Some(&signature)),
+            Err(PoolProtocolError::InvalidSubmitSignature)
+        );
+        let altered = sample_prover_solution(address);
+        assert_eq!(
+            verify_solution_signature(&address, &altered, Some(&signature)),
+            Err(PoolProtocolError::InvalidSubmitSignature)
+        );
+        assert_eq!(
+            verify_solution_signature(&address, &solution, None),
+            Err(PoolProtocolError::MissingSubmitSignature)
+        );
+        Ok(())
+    }
+
+    fn sample_connect(
+        address_type: u8,
+        name: &str,
+        address: &str,
+        tlvs: Vec<Tlv>,
+    ) -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            address_type,
+            0,
+            1,
+            0,
+            name.to_string(),
+            address.to_string(),
+            tlvs,
+        )
+    }
+
+    #[test]
+    fn test_connect_signature() -> Result<()> {
+        let rng = &mut thread_rng();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng)?;
+        let address = Address::try_from(&private_key)?.to_string();
+        let proof = sign_connect(&private_key, "rig-01", 1000, rng)?;
+        let connect = sample_connect(ADDRESS_TYPE_ALEO, "rig-01", &address, vec![proof.clone()]);
+        let bytes = connect.to_vec()?;
+        let connect = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+        assert_eq!(
+            connect.address_proof().map(|(timestamp, _)| timestamp),
+            Some(1000)
+        );
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1000),
+            Ok(())
+        );
+
+        let skew = DEFAULT_CONNECT_SKEW.as_secs();
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1000 + skew),
+            Ok(())
+        );
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1001 + skew),
+            Err(PoolProtocolError::StaleConnectTimestamp(1000))
+        );
+        assert_eq!(
+            verify_connect_at(&connect, Duration::from_secs(10), 989),
+            Err(PoolProtocolError::StaleConnectTimestamp(1000))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_connect_signature_forged() -> Result<()> {
+        let rng = &mut thread_rng();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng)?;
+        let victim = Address::try_from(&PrivateKey::<CurrentNetwork>::new(rng)?)?.to_string();
+        let forged = Err(PoolProtocolError::InvalidConnectSignature);
+
+        // Someone else's address, signed with the wrong key.
+        let proof = sign_connect(&private_key, "rig-01", 1000, rng)?;
+        let connect = sample_connect(ADDRESS_TYPE_ALEO, "rig-01", &victim, vec![proof.clone()]);
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1000),
+            forged
+        );
+        // A proof replayed under another name.
+        let address = Address::try_from(&private_key)?.to_string();
+        let connect = sample_connect(ADDRESS_TYPE_ALEO, "rig-02", &address, vec![proof]);
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1000),
+            forged
+        );
+        // No proof, or a garbled one.
+        let connect = sample_connect(ADDRESS_TYPE_ALEO, "rig-01", &address, vec![]);
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1000),
+            Err(PoolProtocolError::MissingConnectSignature)
+        );
+        let garbled = Tlv::new(TAG_ADDRESS_PROOF, vec![0; 12]);
+        let connect = sample_connect(ADDRESS_TYPE_ALEO, "rig-01", &address, vec![garbled]);
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1000),
+            Err(PoolProtocolError::MissingConnectSignature)
+        );
+
+        // Email accounts skip the check.
+        let connect = sample_connect(ADDRESS_TYPE_EMAIL, "rig-01", "215587407@qq.com", vec![]);
+        assert_eq!(
+            verify_connect_at(&connect, DEFAULT_CONNECT_SKEW, 1000),
+            Ok(())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{chunk::*, error::*, poolmessage::*};
+use ::bytes::Bytes;
+use anyhow::{anyhow, Result};
+use snarkvm::prelude::*;
+use std::{marker::PhantomData, time::Instant};
+
+/// The most chunks a `SubmitChunk` transfer may have.
+pub const MAXIMUM_SUBMIT_CHUNKS: u16 = 128;
+/// The most `SubmitChunk` transfers the pool keeps open per connection.
+pub const MAXIMUM_SUBMIT_TRANSFERS: usize = 4;
+
+/// Reads the body of a `SubmitChunk` := << work_id(u32), job_id(u64), index(u16), total(u16), bytes >>,
+/// checking the index and total against `MAXIMUM_SUBMIT_CHUNKS` and the bytes against `CHUNK_PAYLOAD_SIZE`.
+pub(crate) fn read_submit_chunk(data: &[u8]) -> Option<(u32, u64, u16, u16, &[u8])> {
+    if data.len() < 16 {
+        return None;
+    }
+    let index = u16::from_le_bytes([data[12], data[13]]);
+    let total = u16::from_le_bytes([data[14], data[15]]);
+    let bytes = &data[16..];
+    match index < total && total <= MAXIMUM_SUBMIT_CHUNKS && bytes.len() <= CHUNK_PAYLOAD_SIZE {
+        true => Some((
+            u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+            u64::from_le_bytes([
+                data[4], data[5], data[6], data[7], data[8], data[9], data[10], data[11],
+            ]),
+            index,
+            total,
+            bytes,
+        )),
+        false => None,
+    }
+}
+
+/// Splits a solution into `SubmitChunk` messages of at most `CHUNK_PAYLOAD_SIZE` bytes,
+/// for solutions too large for a single `Submit` frame.
+pub fn split_submit<N: Network>(
+    work_id: u32,
+    job_id: u64,
+    prover_solution: &Data<ProverSolution<N>>,
+) -> Result<Vec<PoolMessageCS<N>>> {
+    let mut solution = Vec::with_capacity(prover_solution.serialized_size());
+    prover_solution.serialize_blocking_into(&mut solution)?;
+    let solution = Bytes::from(solution);
+    let total = solution.len().div_ceil(CHUNK_PAYLOAD_SIZE).max(1);
+    if total > MAXIMUM_SUBMIT_CHUNKS as usize {
+        return Err(anyhow!(PoolProtocolError::TransferTooLarge(solution.len())));
+    }
+    Ok((0..total)
+        .map(|index| {
+            let start = index * CHUNK_PAYLOAD_SIZE;
+            let end = (start + CHUNK_PAYLOAD_SIZE).min(solution.len());
+            PoolMessageCS::SubmitChunk(
+                work_id,
+                job_id,
+                index as u16,
+                total as u16,
+                solution.slice(start..end),
+            )
+        })
+        .collect())
+}
+
+/// Collects the `SubmitChunk`s of a connection into solutions.
+/// Transfers are keyed by work id and job id, chunks may arrive in any order,
+/// repeated chunks are ignored, and a transfer that waits longer than `TRANSFER_TIMEOUT`
+/// for its next chunk is dropped.
+#[derive(Clone, Debug)]
+pub struct SubmitReassembler<N: Network> {
+    transfers: Transfers<(u32, u64)>,
+    _network: PhantomData<N>,
+}
+
+impl<N: Network> Default for SubmitReassembler<N> {
+    fn default() -> Self {
+        Self {
+            transfers: Transfers::new(MAXIMUM_SUBMIT_TRANSFERS),
+            _network: PhantomData,
+        }
+    }
+}
+
+impl<N: Network> SubmitReassembler<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk and returns the solution as a `Data::Buffer` once its last missing chunk arrives.
+    /// A chunk that breaks the limits or disagrees with earlier chunks drops its transfer.
+    pub fn insert(
+        &mut self,
+        work_id: u32,
+        job_id: u64,
+        index: u16,
+        total: u16,
+        bytes: &[u8],
+        now: Instant,
+    ) -> Result<Option<Data<ProverSolution<N>>>, PoolProtocolError> {
+        let invalid = PoolProtocolError::InvalidChunk {
+            transfer_id: work_id,
+            index,
+            total,
+        };
+        if total > MAXIMUM_SUBMIT_CHUNKS {
+            self.transfers.remove((work_id, job_id));
+            return Err(invalid);
+        }
+        let solution = self.transfers.insert(
+            (work_id, job_id),
+            index,
+            total,
+            bytes.to_vec(),
+            now,
+            invalid,
+        )?;
+        Ok(solution.map(|solution| Data::Buffer(solution.into())))
+    }
+
+    /// Drops the transfers that have not received a chunk within `TRANSFER_TIMEOUT`.
+    pub fn collect_garbage(&mut self, now: Instant) {
+        self.transfers.collect_garbage(now)
+    }
+
+    /// Returns the number of incomplete transfers.
+    pub fn len(&self) -> usize {
+        self.transfers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::chunk::TRANSFER_TIMEOUT;
+    use snarkvm::prelude::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    fn parts(solution: &[u8]) -> Vec<(u32, u64, u16, u16, Bytes)> {
+        split_submit::<CurrentNetwork>(3, 9, &Data::Buffer(Bytes::copy_from_slice(solution)))
+            .unwrap()
+            .into_iter()
+            .map(|message| match message {
+                PoolMessageCS::SubmitChunk(work_id, job_id, index, total, bytes) => {
+                    (work_id, job_id, index, total, bytes)
+                }
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_submit_reassembler() -> Result<()> {
+        let solution = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut parts = parts(&solution);
+        assert_eq!(parts.len(), 3);
+        parts.reverse();
+
+        let now = Instant::now();
+        let mut reassembler = SubmitReassembler::<CurrentNetwork>::new();
+        let (work_id, job_id, index, total, bytes) = parts.pop().unwrap();
+        for (work_id, job_id, index, total, bytes) in parts {
+            assert!(reassembler
+                .insert(work_id, job_id, index, total, &bytes, now)?
+                .is_none());
+        }
+        assert_eq!(reassembler.len(), 1);
+        match reassembler.insert(work_id, job_id, index, total, &bytes, now)? {
+            Some(Data::Buffer(bytes)) => assert_eq!(bytes, solution),
+            _ => panic!("expected a reassembled buffer"),
+        }
+        assert!(reassembler.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_submit_reassembler_limits() {
+        let now = Instant::now();
+        let mut reassembler = SubmitReassembler::<CurrentNetwork>::new();
+        assert!(reassembler
+            .insert(1, 1, 0, MAXIMUM_SUBMIT_CHUNKS + 1, &[0], now)
+            .is_err());
+        assert!(reassembler.insert(1, 1, 2, 2, &[0], now).is_err());
+
+        for work_id in 0..MAXIMUM_SUBMIT_TRANSFERS as u32 {
+            assert!(reassembler.insert(work_id, 1, 0, 2, &[0], now).is_ok());
+        }
+        assert!(matches!(
+            reassembler.insert(9, 1, 0, 2, &[0], now),
+            Err(PoolProtocolError::TooManyTransfers)
+        ));
+        // Stale transfers are dropped to make room.
+        let later = now + TRANSFER_TIMEOUT;
+        assert!(reassembler.insert(9, 1, 0, 2, &[0], later).is_ok());
+        assert_eq!(reassembler.len(), 1);
+    }
+
+    #[test]
+    fn test_split_submit_too_large() {
+        let solution = vec![0u8; CHUNK_PAYLOAD_SIZE * MAXIMUM_SUBMIT_CHUNKS as usize + 1];
+        assert!(split_submit::<CurrentNetwork>(1, 1, &Data::Buffer(solution.into())).is_err());
+    }
+}
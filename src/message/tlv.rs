@@ -0,0 +1,192 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{anyhow, Result};
+use std::io::Write;
+
+/// Separates a variable-length string field from the TLV section that follows it.
+/// Address, email and signature strings never contain a NUL byte.
+pub const TLV_SEPARATOR: u8 = 0;
+
+/// Capability mask := u32
+pub const TAG_CAPABILITIES: u8 = 1;
+
+/// Resume token := u64, see `ResumeTokens`
+pub const TAG_RESUME_TOKEN: u8 = 2;
+
+/// Client nonce := u64, signed back in the `ConnectAck` session signature
+pub const TAG_NONCE: u8 = 3;
+
+/// Address proof := (timestamp(u64), signature), see `sign_connect`
+pub const TAG_ADDRESS_PROOF: u8 = 4;
+
+/// Payout split := (count(u8), count * (address, weight_bps(u16))), see `PayoutSplit`
+pub const TAG_PAYOUT_SPLIT: u8 = 5;
+
+/// Server info := UTF-8 "name/version" of the pool software, e.g. "zkwork-pool/1.4.2"
+pub const TAG_SERVER_INFO: u8 = 6;
+
+/// Network id := u16, snarkVM's `Network::ID` of the client, see `network_id_tlv`
+pub const TAG_NETWORK_ID: u8 = 7;
+
+/// Reject reason := UTF-8 text of a rejecting `ConnectAck`, e.g. "network mismatch"
+pub const TAG_REJECT_REASON: u8 = 8;
+
+/// Max shares := u32, the shares the pool accepts per epoch, 0 for unlimited, see `max_shares_tlv`
+pub const TAG_MAX_SHARES: u8 = 9;
+
+/// Min target := u64, the lowest solution target the pool accepts as a share, see `min_target_tlv`
+pub const TAG_MIN_TARGET: u8 = 10;
+
+/// Share target := u64, the `Notify` share target when it differs from the block target
+pub const TAG_SHARE_TARGET: u8 = 11;
+
+/// Tags understood in the `Connect` TLV section.
+pub const CONNECT_TAGS: &[u8] = &[
+    TAG_CAPABILITIES,
+    TAG_NONCE,
+    TAG_ADDRESS_PROOF,
+    TAG_PAYOUT_SPLIT,
+    TAG_NETWORK_ID,
+];
+/// Tags understood in the `ConnectAck` TLV section.
+pub const CONNECT_ACK_TAGS: &[u8] = &[
+    TAG_CAPABILITIES,
+    TAG_RESUME_TOKEN,
+    TAG_SERVER_INFO,
+    TAG_REJECT_REASON,
+    TAG_MIN_TARGET,
+];
+/// Tags understood in the `Notify` TLV section.
+pub const NOTIFY_TAGS: &[u8] = &[TAG_MAX_SHARES, TAG_SHARE_TARGET];
+
+/// A single extension field := (tag, value)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tlv {
+    pub tag: u8,
+    pub value: Vec<u8>,
+}
+
+impl Tlv {
+    pub fn new(tag: u8, value: Vec<u8>) -> Self {
+        Self { tag, value }
+    }
+}
+
+/// Writes a single TLV as << tag, length(u16), value >>.
+pub fn write_tlv<W: Write>(writer: &mut W, tag: u8, value: &[u8]) -> Result<()> {
+    let len = u16::try_from(value.len())
+        .map_err(|_| anyhow!("TLV value for tag {} is too long: {}", tag, value.len()))?;
+    writer.write_all(&[tag])?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(value)?;
+    Ok(())
+}
+
+/// Returns the serialized size of a list of TLVs, see `write_tlvs`.
+pub fn tlvs_size(tlvs: &[Tlv]) -> usize {
+    tlvs.iter().map(|tlv| 1 + 2 + tlv.value.len()).sum()
+}
+
+/// Writes a list of TLVs back to back.
+pub fn write_tlvs<W: Write>(writer: &mut W, tlvs: &[Tlv]) -> Result<()> {
+    for tlv in tlvs {
+        write_tlv(writer, tlv.tag, &tlv.value)?;
+    }
+    Ok(())
+}
+
+/// Reads a TLV section, keeping the tags in `known_tags` and skipping everything else.
+/// An empty section yields no TLVs.
+pub fn read_tlvs(mut data: &[u8], known_tags: &[u8]) -> Result<Vec<Tlv>> {
+    let mut tlvs = Vec::new();
+    while !data.is_empty() {
+        if data.len() < 3 {
+            return Err(anyhow!("Truncated TLV header: {:?}", data));
+        }
+        let tag = data[0];
+        let len = u16::from_le_bytes([data[1], data[2]]) as usize;
+        if data.len() < 3 + len {
+            return Err(anyhow!("Truncated TLV value for tag {}: {:?}", tag, data));
+        }
+        if known_tags.contains(&tag) {
+            tlvs.push(Tlv::new(tag, data[3..3 + len].to_vec()));
+        }
+        data = &data[3 + len..];
+    }
+    Ok(tlvs)
+}
+
+/// Returns the value of the first TLV with the given tag.
+pub fn find_tlv(tlvs: &[Tlv], tag: u8) -> Option<&[u8]> {
+    tlvs.iter()
+        .find(|tlv| tlv.tag == tag)
+        .map(|tlv| tlv.value.as_slice())
+}
+
+/// Splits a "string then optional TLV section" payload at the separator.
+pub(crate) fn split_at_separator(data: &[u8]) -> (&[u8], &[u8]) {
+    match data.iter().position(|b| *b == TLV_SEPARATOR) {
+        Some(pos) => (&data[..pos], &data[pos + 1..]),
+        None => (data, &[]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tlv_known_and_unknown_tags() -> Result<()> {
+        let mut buffer = Vec::new();
+        write_tlv(&mut buffer, 1, b"known")?;
+        write_tlv(&mut buffer, 200, &[0xff; 300])?;
+        write_tlv(&mut buffer, 2, &[])?;
+        write_tlv(&mut buffer, 201, b"unknown")?;
+
+        let tlvs = read_tlvs(&buffer, &[1, 2])?;
+        assert_eq!(
+            tlvs,
+            vec![Tlv::new(1, b"known".to_vec()), Tlv::new(2, vec![])]
+        );
+        assert_eq!(find_tlv(&tlvs, 1), Some(&b"known"[..]));
+        assert_eq!(find_tlv(&tlvs, 200), None);
+
+        // A peer that knows nothing still walks the whole section.
+        assert!(read_tlvs(&buffer, &[])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tlv_empty_and_truncated() -> Result<()> {
+        assert!(read_tlvs(&[], &[1])?.is_empty());
+
+        let mut buffer = Vec::new();
+        write_tlv(&mut buffer, 1, b"value")?;
+        assert!(read_tlvs(&buffer[..2], &[1]).is_err());
+        assert!(read_tlvs(&buffer[..buffer.len() - 1], &[1]).is_err());
+
+        assert!(write_tlv(&mut Vec::new(), 1, &vec![0u8; u16::MAX as usize + 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_at_separator() {
+        assert_eq!(
+            split_at_separator(b"aleo1abc"),
+            (&b"aleo1abc"[..], &b""[..])
+        );
+        assert_eq!(
+            split_at_separator(b"aleo1abc\0\x01"),
+            (&b"aleo1abc"[..], &b"\x01"[..])
+        );
+    }
+}
@@ -0,0 +1,446 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{message_id::*, poolmessage::*, tlv::*};
+use snarkvm::prelude::*;
+
+use ::bytes::Bytes;
+use anyhow::{anyhow, Result};
+use std::io::Write;
+
+/// Pool-to-client messages in the V2 layout, see `ProtocolVersion`.
+/// Only `ConnectAck` changed; every other message keeps its V1 layout.
+#[derive(Clone, Debug)]
+pub enum PoolMessageSCv2<N: Network> {
+    /// ConnectAck := (is_accept, address, [id], [signature], [initial_job], tlvs)
+    /// The signature is prefixed by its length(u16), so the TLV section needs no separator.
+    ConnectAck(
+        bool,
+        Address<N>,
+        Option<u32>,
+        Option<String>,
+        Option<(u64, u64, EpochChallenge<N>)>,
+        Vec<Tlv>,
+    ),
+    /// Any other message, in its V1 layout
+    V1(PoolMessageSC<N>),
+}
+
+impl<N: Network> PoolMessageSCv2<N> {
+    /// Serializes a message in the V2 layout, converting only the messages whose layout changed.
+    pub fn serialize_message_into<W: Write>(
+        message: &PoolMessageSC<N>,
+        writer: &mut W,
+    ) -> Result<()> {
+        match message {
+            PoolMessageSC::ConnectAck(..) => {
+                Self::try_from(message.clone())?.serialize_into(writer)
+            }
+            message => message.serialize_into(writer),
+        }
+    }
+
+    /// Serializes the message into the buffer, id included.
+    pub fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self {
+            Self::ConnectAck(is_accept, address, id, signature, initial_job, tlvs) => {
+                writer.write_all(&[sc::CONNECT_ACK, *is_accept as u8])?;
+                address.write_le(&mut *writer)?;
+                if *is_accept {
+                    let (Some(id), Some(signature)) = (id, signature) else {
+                        return Err(anyhow!("ConnectAck: Invalid id"));
+                    };
+                    writer.write_all(&id.to_le_bytes())?;
+                    match initial_job {
+                        Some((job_id, target, epoch_challenge)) => {
+                            writer.write_all(&[1u8])?;
+                            writer.write_all(&job_id.to_le_bytes())?;
+                            writer.write_all(&target.to_le_bytes())?;
+                            writer.write_all(&epoch_challenge.to_bytes_le()?)?;
+                        }
+                        None => writer.write_all(&[0u8])?,
+                    }
+                    let len = u16::try_from(signature.len())
+                        .map_err(|_| anyhow!("ConnectAck: signature is too long"))?;
+                    writer.write_all(&len.to_le_bytes())?;
+                    writer.write_all(signature.as_bytes())?;
+                }
+                write_tlvs(writer, tlvs)
+            }
+            Self::V1(message) => message.serialize_into(writer),
+        }
+    }
+
+    /// Deserializes the given buffer into a message.
+    pub fn deserialize(buffer: &[u8]) -> Result<Self> {
+        match buffer.split_first() {
+            Some((&sc::CONNECT_ACK, data)) => read_connect_ack(buffer, data),
+            _ => Ok(Self::V1(PoolMessageSC::deserialize(buffer)?)),
+        }
+    }
+}
+
+/// Reads the data of a V2 `ConnectAck`.
+fn read_connect_ack<N: Network>(buffer: &[u8], data: &[u8]) -> Result<PoolMessageSCv2<N>> {
+    let invalid = || anyhow!("Invalid 'ConnectAck' message: {:?} {:?}", buffer, data);
+    let (&is_accept, rest) = data.split_first().ok_or_else(invalid)?;
+    let (address, mut reader) = split_address::<N>(rest).ok_or_else(invalid)?;
+    let address = Address::read_le(address)?;
+    match is_accept {
+        0 => {
+            let tlvs = read_tlvs(reader, CONNECT_ACK_TAGS)?;
+            return Ok(PoolMessageSCv2::ConnectAck(
+                false, address, None, None, None, tlvs,
+            ));
+        }
+        1 if reader.len() >= 5 => {}
+        _ => return Err(invalid()),
+    }
+    let id = u32::from_le_bytes(reader[0..4].try_into()?);
+    let initial_job = match reader[4] {
+        0 => {
+            reader = &reader[5..];
+            None
+        }
+        1 if reader.len() >= 21 => {
+            let job_id = u64::from_le_bytes(reader[5..13].try_into()?);
+            let target = u64::from_le_bytes(reader[13..21].try_into()?);
+            reader = &reader[21..];
+            Some((job_id, target, read_epoch_challenge(&mut reader)?))
+        }
+        _ => return Err(invalid()),
+    };
+    let len = match reader {
+        [a, b, ..] => u16::from_le_bytes([*a, *b]) as usize,
+        _ => return Err(invalid()),
+    };
+    if reader.len() < 2 + len {
+        return Err(invalid());
+    }
+    let (signature, tlvs) = reader[2..].split_at(len);
+    Ok(PoolMessageSCv2::ConnectAck(
+        true,
+        address,
+        Some(id),
+        Some(String::from_utf8(signature.to_vec())?),
+        initial_job,
+        read_tlvs(tlvs, CONNECT_ACK_TAGS)?,
+    ))
+}
+
+impl<N: Network> TryFrom<PoolMessageSC<N>> for PoolMessageSCv2<N> {
+    type Error = anyhow::Error;
+
+    fn try_from(message: PoolMessageSC<N>) -> Result<Self> {
+        match message {
+            PoolMessageSC::ConnectAck(is_accept, address, id, signature, initial_job, tlvs) => {
+                if signature.as_ref().map_or(0, String::len) > u16::MAX as usize {
+                    return Err(anyhow!("ConnectAck: signature is too long"));
+                }
+                Ok(Self::ConnectAck(
+                    is_accept,
+                    address,
+                    id,
+                    signature,
+                    initial_job,
+                    tlvs,
+                ))
+            }
+            message => Ok(Self::V1(message)),
+        }
+    }
+}
+
+impl<N: Network> From<PoolMessageSCv2<N>> for PoolMessageSC<N> {
+    fn from(message: PoolMessageSCv2<N>) -> Self {
+        match message {
+            PoolMessageSCv2::ConnectAck(is_accept, address, id, signature, initial_job, tlvs) => {
+                Self::ConnectAck(is_accept, address, id, signature, initial_job, tlvs)
+            }
+            PoolMessageSCv2::V1(message) => message,
+        }
+    }
+}
+
+/// Client-to-pool messages in the V2 layout, see `ProtocolVersion`.
+/// Only `Connect` changed; every other message keeps its V1 layout.
+#[derive(Clone, Debug)]
+pub enum PoolMessageCSv2<N: Network> {
+    /// Connect := (type, address_type, version(major, minor, patch), name, address, tlvs)
+    /// The name and address are prefixed by their length(u16), so names may exceed 255 bytes
+    /// and the TLV section needs no separator.
+    Connect(u8, u8, u8, u8, u8, String, String, Vec<Tlv>),
+    /// Any other message, in its V1 layout
+    V1(PoolMessageCS<N>),
+}
+
+impl<N: Network> PoolMessageCSv2<N> {
+    /// Returns a V2 `Connect`, failing if the name or address is longer than
+    /// `MAXIMUM_CONNECT_FIELD_LENGTH_V2`.
+    pub fn connect(
+        worker_type: u8,
+        address_type: u8,
+        version: (u8, u8, u8),
+        custom_name: String,
+        address: String,
+        tlvs: Vec<Tlv>,
+    ) -> Result<Self> {
+        let (v_major, v_minor, v_patch) = version;
+        Self::try_from(PoolMessageCS::Connect(
+            worker_type,
+            address_type,
+            v_major,
+            v_minor,
+            v_patch,
+            custom_name,
+            address,
+            tlvs,
+        ))
+    }
+
+    /// Serializes a message in the V2 layout, converting only the messages whose layout changed.
+    pub fn serialize_message_into<W: Write>(
+        message: &PoolMessageCS<N>,
+        writer: &mut W,
+    ) -> Result<()> {
+        match message {
+            PoolMessageCS::Connect(..) => Self::try_from(message.clone())?.serialize_into(writer),
+            message => message.serialize_into(writer),
+        }
+    }
+
+    /// Serializes the message into the buffer, id included.
+    pub fn serialize_into<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self {
+            Self::Connect(
+                worker_type,
+                address_type,
+                v_major,
+                v_minor,
+                v_patch,
+                custom_name,
+                address,
+                tlvs,
+            ) => {
+                writer.write_all(&[
+                    cs::CONNECT,
+                    *worker_type,
+                    *address_type,
+                    *v_major,
+                    *v_minor,
+                    *v_patch,
+                ])?;
+                for field in [custom_name, address] {
+                    let len = u16::try_from(field.len())
+                        .map_err(|_| anyhow!("Connect: field is too long: {}", field.len()))?;
+                    writer.write_all(&len.to_le_bytes())?;
+                    writer.write_all(field.as_bytes())?;
+                }
+                write_tlvs(writer, tlvs)
+            }
+            Self::V1(message) => message.serialize_into(writer),
+        }
+    }
+
+    /// Deserializes the given buffer into a message.
+    pub fn deserialize(buffer: &[u8]) -> Result<Self> {
+        match buffer.split_first() {
+            Some((&cs::CONNECT, data)) => read_connect(buffer, data),
+            _ => Ok(Self::V1(PoolMessageCS::deserialize(buffer)?)),
+        }
+    }
+
+    /// Deserializes the given buffer into a message, keeping the solution of a `Submit` as a
+    /// slice of `buffer`, see `PoolMessageCS::deserialize_bytes`.
+    pub fn deserialize_bytes(buffer: Bytes) -> Result<Self> {
+        match buffer.split_first() {
+            Some((&cs::CONNECT, data)) => read_connect(&buffer, data),
+            _ => Ok(Self::V1(PoolMessageCS::deserialize_bytes(buffer)?)),
+        }
+    }
+}
+
+/// Reads the data of a V2 `Connect` := (header, name_length(u16), name, address_length(u16), address, tlvs).
+fn read_connect<N: Network>(buffer: &[u8], data: &[u8]) -> Result<PoolMessageCSv2<N>> {
+    let invalid = || anyhow!("Invalid 'Connect' message: {:?} {:?}", buffer, data);
+    let header = data.get(..5).ok_or_else(invalid)?;
+    let mut reader = &data[5..];
+    let mut fields = Vec::with_capacity(2);
+    for _ in 0..2 {
+        let len = reader.get(..2).ok_or_else(invalid)?;
+        let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+        let field = reader[2..].get(..len).ok_or_else(invalid)?;
+        fields.push(String::from_utf8(field.to_vec())?);
+        reader = &reader[2 + len..];
+    }
+    let (custom_name, address) = (fields.remove(0), fields.remove(0));
+    Ok(PoolMessageCSv2::Connect(
+        header[0],
+        header[1],
+        header[2],
+        header[3],
+        header[4],
+        custom_name,
+        address,
+        read_tlvs(reader, CONNECT_TAGS)?,
+    ))
+}
+
+impl<N: Network> TryFrom<PoolMessageCS<N>> for PoolMessageCSv2<N> {
+    type Error = anyhow::Error;
+
+    fn try_from(message: PoolMessageCS<N>) -> Result<Self> {
+        match message {
+            PoolMessageCS::Connect(
+                worker_type,
+                address_type,
+                v_major,
+                v_minor,
+                v_patch,
+                custom_name,
+                address,
+                tlvs,
+            ) => {
+                if custom_name.len() > MAXIMUM_CONNECT_FIELD_LENGTH_V2
+                    || address.len() > MAXIMUM_CONNECT_FIELD_LENGTH_V2
+                {
+                    return Err(anyhow!("Connect: field is too long"));
+                }
+                Ok(Self::Connect(
+                    worker_type,
+                    address_type,
+                    v_major,
+                    v_minor,
+                    v_patch,
+                    custom_name,
+                    address,
+                    tlvs,
+                ))
+            }
+            message => Ok(Self::V1(message)),
+        }
+    }
+}
+
+impl<N: Network> From<PoolMessageCSv2<N>> for PoolMessageCS<N> {
+    fn from(message: PoolMessageCSv2<N>) -> Self {
+        match message {
+            PoolMessageCSv2::Connect(
+                worker_type,
+                address_type,
+                v_major,
+                v_minor,
+                v_patch,
+                custom_name,
+                address,
+                tlvs,
+            ) => Self::Connect(
+                worker_type,
+                address_type,
+                v_major,
+                v_minor,
+                v_patch,
+                custom_name,
+                address,
+                tlvs,
+            ),
+            PoolMessageCSv2::V1(message) => message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::thread_rng;
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_v2_conversions() -> Result<()> {
+        let message = PoolMessageCS::<CurrentNetwork>::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            vec![],
+        );
+        let v2 = PoolMessageCSv2::try_from(message.clone())?;
+        assert!(matches!(v2, PoolMessageCSv2::Connect(..)));
+        assert_eq!(PoolMessageCS::from(v2).to_vec()?, message.to_vec()?);
+
+        let v2 = PoolMessageCSv2::<CurrentNetwork>::try_from(PoolMessageCS::Ping)?;
+        assert!(matches!(v2, PoolMessageCSv2::V1(PoolMessageCS::Ping)));
+        let v2 = PoolMessageSCv2::<CurrentNetwork>::try_from(PoolMessageSC::ShutDown)?;
+        assert!(matches!(v2, PoolMessageSCv2::V1(PoolMessageSC::ShutDown)));
+
+        // V2 strings are length-prefixed by a u16.
+        let message = PoolMessageCS::<CurrentNetwork>::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "x".repeat(MAXIMUM_CONNECT_FIELD_LENGTH_V2 + 1),
+            vec![],
+        );
+        assert!(PoolMessageCSv2::try_from(message.clone()).is_err());
+        assert!(PoolMessageCSv2::serialize_message_into(&message, &mut Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_truncated() -> Result<()> {
+        let message = PoolMessageCSv2::<CurrentNetwork>::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "w".to_string(),
+            "a@b".to_string(),
+            vec![Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0])],
+        );
+        let mut bytes = Vec::new();
+        message.serialize_into(&mut bytes)?;
+        assert!(PoolMessageCSv2::<CurrentNetwork>::deserialize(&bytes).is_ok());
+        // Every cut but the one dropping the whole TLV section is invalid.
+        for end in (1..bytes.len()).filter(|end| *end != bytes.len() - 7) {
+            assert!(PoolMessageCSv2::<CurrentNetwork>::deserialize(&bytes[..end]).is_err());
+        }
+        assert!(PoolMessageSCv2::<CurrentNetwork>::deserialize(&[sc::CONNECT_ACK, 1]).is_err());
+
+        // The signature length catches cuts anywhere in an accepted `ConnectAck`.
+        let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )?;
+        let message = PoolMessageSCv2::<CurrentNetwork>::ConnectAck(
+            true,
+            Address::new(Uniform::rand(&mut thread_rng())),
+            Some(7),
+            Some("testsignature".to_string()),
+            Some((3, 100000, epoch_challenge)),
+            vec![],
+        );
+        let mut bytes = Vec::new();
+        message.serialize_into(&mut bytes)?;
+        assert!(PoolMessageSCv2::<CurrentNetwork>::deserialize(&bytes).is_ok());
+        for end in 1..bytes.len() {
+            assert!(PoolMessageSCv2::<CurrentNetwork>::deserialize(&bytes[..end]).is_err());
+        }
+        Ok(())
+    }
+}
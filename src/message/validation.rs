@@ -0,0 +1,111 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::prelude::*;
+use snarkvm_algorithms::crypto_hash::sha256d_to_u64;
+
+use anyhow::Result;
+
+/// Returns true if `solution` meets `target` for `challenge`, skipping the KZG proof check.
+/// Pools call it after deserializing a `Submit` to drop junk shares cheaply, before the costly
+/// `ProverSolution::verify` and the network submission.
+pub fn meets_target<N: Network>(
+    solution: &ProverSolution<N>,
+    challenge: &EpochChallenge<N>,
+    target: u64,
+) -> Result<bool> {
+    // A hiding proof never verifies.
+    if solution.proof().is_hiding() {
+        return Ok(false);
+    }
+    // No solution can be proven against a challenge of another degree.
+    if challenge.degree() != N::COINBASE_PUZZLE_DEGREE {
+        return Ok(false);
+    }
+    Ok(solution.to_target()? >= target)
+}
+
+/// Returns the target a `SubmitCommitment` reaches, the same as its solution's `to_target`.
+/// A commitment alone proves no work: pools credit such shares on trust and should check a
+/// sample of them, and every block candidate, by answering `RequestProof`.
+pub fn commitment_target<N: Network>(commitment: &PuzzleCommitment<N>) -> Result<u64> {
+    match sha256d_to_u64(&commitment.to_bytes_le()?) {
+        0 => Ok(u64::MAX),
+        hash => Ok(u64::MAX / hash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::thread_rng;
+    use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+    type CurrentNetwork = Testnet3;
+
+    fn sample_prover_solution(hiding: bool) -> Result<ProverSolution<CurrentNetwork>> {
+        let rng = &mut thread_rng();
+        let address = Address::try_from(&PrivateKey::<CurrentNetwork>::new(rng)?)?;
+        let partial_solution =
+            PartialSolution::new(address, u64::rand(rng), KZGCommitment(rng.gen()));
+        Ok(ProverSolution::new(
+            partial_solution,
+            KZGProof {
+                w: rng.gen(),
+                random_v: hiding.then(|| rng.gen()),
+            },
+        ))
+    }
+
+    fn sample_epoch_challenge(degree: u32) -> Result<EpochChallenge<CurrentNetwork>> {
+        EpochChallenge::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            degree,
+        )
+    }
+
+    #[test]
+    fn test_meets_target() -> Result<()> {
+        let challenge = sample_epoch_challenge(CurrentNetwork::COINBASE_PUZZLE_DEGREE)?;
+        let solution = sample_prover_solution(false)?;
+        let solution_target = solution.to_target()?;
+        assert!(meets_target(&solution, &challenge, 0)?);
+        assert!(meets_target(&solution, &challenge, solution_target)?);
+        if solution_target < u64::MAX {
+            assert!(!meets_target(&solution, &challenge, solution_target + 1)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_commitment_target() -> Result<()> {
+        let solution = sample_prover_solution(false)?;
+        assert_eq!(
+            commitment_target(&solution.commitment())?,
+            solution.to_target()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_meets_target_junk() -> Result<()> {
+        let challenge = sample_epoch_challenge(CurrentNetwork::COINBASE_PUZZLE_DEGREE)?;
+        assert!(!meets_target(
+            &sample_prover_solution(true)?,
+            &challenge,
+            0
+        )?);
+
+        let other = sample_epoch_challenge((1 << 10) - 1)?;
+        assert!(!meets_target(&sample_prover_solution(false)?, &other, 0)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Canonical wire bytes of representative messages, for implementations in other languages.
+//!
+//! Each vector is a whole legacy frame, u32 length prefix included, of a Testnet3 message
+//! without negotiated capabilities. The inputs are deterministic: addresses are
+//! `Group::generator()`, the epoch challenge is (epoch 0, BHP1024 hash of 1024 `true` bits,
+//! `COINBASE_PUZZLE_DEGREE`), and the prover solution of `Submit` and `SubmitCommitment` has
+//! nonce 42 with the G1 generator as its commitment and proof. Signed `Submit`s are left out
+//! as their signatures are randomized.
+//!
+//! The vectors pin the wire format: update them only for an intentional layout change.
+
+/// Pool-to-client frames := (message, hex of `to_vec()`), in the order of the tests' `sc_messages`.
+pub const SC_VECTORS: &[(&str, &str)] = &[
+    (
+        "ConnectAck accepted",
+        concat!(
+            "3c0000000001c549be4b84827e13f383dda9338860e70650900db894b2160750",
+            "4702eb2468030100000000746573747369676e61747572650001040001000000",
+        ),
+    ),
+    (
+        "ConnectAck accepted with job",
+        concat!(
+            "6c0000000001c549be4b84827e13f383dda9338860e70650900db894b2160750",
+            "4702eb24680301000000010700000000000000ffffffffffffffff00000000b2",
+            "258f5e880420f5f3107ac446f8aa2ae3788a1cb7635d72f280cf02c95b5e0cff",
+            "1f0000746573747369676e6174757265",
+        ),
+    ),
+    (
+        "ConnectAck rejected",
+        concat!(
+            "220000000000c549be4b84827e13f383dda9338860e70650900db894b2160750",
+            "4702eb246803",
+        ),
+    ),
+    (
+        "Notify",
+        concat!(
+            "39000000010700000000000000ffffffffffffffff00000000b2258f5e880420",
+            "f5f3107ac446f8aa2ae3788a1cb7635d72f280cf02c95b5e0cff1f0000",
+        ),
+    ),
+    ("Shutdown", "0100000002"),
+    ("Pong", "0100000003"),
+    ("Pong with load factor", "0200000003c8"),
+    ("Throttle", "0500000004f4010000"),
+    ("Chunk", "0c000000050700000001000300010203"),
+    (
+        "NonceRange",
+        "1900000006070000000000000000000000000000000000000001000000",
+    ),
+    ("HelloAck", "050000000711000000"),
+    ("Pause", "050000000858020000"),
+    ("ResumeMining", "0100000009"),
+    ("Drain", "050000000a1e000000"),
+    ("UpgradeTlsAck", "020000000b01"),
+    ("JobHint", "110000000c070000000000000090d0030000000000"),
+    ("QueryInfo", "010000000d"),
+    ("ShareAckBatch", "0d0000000e020001000000000200000002"),
+    (
+        "NotifyBatch",
+        concat!(
+            "3a0000000f010800000000000000e80300000000000000000000b2258f5e8804",
+            "20f5f3107ac446f8aa2ae3788a1cb7635d72f280cf02c95b5e0cff1f0000",
+        ),
+    ),
+    ("RequestProof", "050000001007000000"),
+    ("Kick", "1400000011010000000e696e76616c696420736861726573"),
+    ("Bye", "0100000012"),
+];
+
+/// Client-to-pool frames := (message, hex of `to_vec()`), in the order of the tests' `cs_messages`.
+pub const CS_VECTORS: &[(&str, &str)] = &[
+    (
+        "Connect",
+        concat!(
+            "2a0000008000010001000b6d795f776f726b65725f3132313535383734303740",
+            "71712e636f6d0001040001000000",
+        ),
+    ),
+    (
+        "Submit",
+        concat!(
+            "9b000000810100000002000000030000000000000000c549be4b84827e13f383",
+            "dda9338860e70650900db894b21607504702eb2468032a0000000000000066db",
+            "6f1030f91f0f1824381daad6321c43739c828b3d8d69f75711a97a2d524c3d54",
+            "201c61a85ca1b3835901b6a2940066db6f1030f91f0f1824381daad6321c4373",
+            "9c828b3d8d69f75711a97a2d524c3d54201c61a85ca1b3835901b6a2940000",
+        ),
+    ),
+    ("Disconnect", "050000008201000000"),
+    ("Ping", "0100000083"),
+    ("JobStatus", "0d0000008401000000ffffffffffffffff"),
+    ("RegisterWorker", "0c0000008503000000067269672d3033"),
+    ("Chunk", "09000000860700000000000100"),
+    (
+        "ResumeSession",
+        concat!(
+            "1e00000087010000000b7465737461646472657373746573747369676e617475",
+            "7265",
+        ),
+    ),
+    ("Resume", "0900000088ffffffffffffffff"),
+    ("Hello", "050000008903000000"),
+    ("UpgradeTls", "010000008a"),
+    (
+        "InfoReport",
+        "140000008b0c7a6b776f726b2d6167656e7405312e322e30",
+    ),
+    ("SetNotifyInterval", "050000008c88130000"),
+    (
+        "SubmitCommitment",
+        concat!(
+            "3d0000008d07000000010000000000000066db6f1030f91f0f1824381daad632",
+            "1c43739c828b3d8d69f75711a97a2d524c3d54201c61a85ca1b3835901b6a294",
+            "00",
+        ),
+    ),
+    ("PowerStats", "0d0000008e070000005e010000b0040000"),
+    ("NotifyAck", "110000008f07000000000000000068e5cf8b010000"),
+    (
+        "SubmitChunk",
+        "140000009007000000010000000000000000000200010203",
+    ),
+    ("Bye", "0100000091"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{
+        bidirectional::*, capabilities::*, codec::*, error::*, poolmessage::*, tlv::*,
+    };
+    use ::bytes::Bytes;
+    use ::bytes::BytesMut;
+    use snarkvm::prelude::*;
+    use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+    use tokio_util::codec::{Decoder, Encoder};
+    type CurrentNetwork = Testnet3;
+
+    fn epoch_challenge() -> Result<EpochChallenge<CurrentNetwork>> {
+        EpochChallenge::new(
+            0,
+            CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+            CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+        )
+    }
+
+    fn prover_solution() -> ProverSolution<CurrentNetwork> {
+        let address = Address::new(Group::generator());
+        let commitment = KZGCommitment(AffineCurve::prime_subgroup_generator());
+        ProverSolution::new(
+            PartialSolution::new(address, 42, commitment),
+            KZGProof {
+                w: AffineCurve::prime_subgroup_generator(),
+                random_v: None,
+            },
+        )
+    }
+
+    /// The messages of `SC_VECTORS`, in order.
+    fn sc_messages() -> Result<Vec<PoolMessageSC<CurrentNetwork>>> {
+        let address = Address::new(Group::generator());
+        let epoch_challenge = epoch_challenge()?;
+        Ok(vec![
+            PoolMessageSC::ConnectAck(
+                true,
+                address,
+                Some(1),
+                Some(String::from("testsignature")),
+                None,
+                vec![Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0])],
+            ),
+            PoolMessageSC::ConnectAck(
+                true,
+                address,
+                Some(1),
+                Some(String::from("testsignature")),
+                Some((7, u64::MAX, epoch_challenge.clone())),
+                vec![],
+            ),
+            PoolMessageSC::ConnectAck(false, address, None, None, None, vec![]),
+            PoolMessageSC::Notify(7, u64::MAX, u64::MAX, epoch_challenge.clone(), vec![]),
+            PoolMessageSC::ShutDown,
+            PoolMessageSC::Pong(None),
+            PoolMessageSC::Pong(Some(200)),
+            PoolMessageSC::Throttle(500),
+            PoolMessageSC::Chunk(7, 1, 3, vec![1, 2, 3]),
+            PoolMessageSC::NonceRange(7, 0, 1 << 32),
+            PoolMessageSC::HelloAck(CAP_SEQUENCE | CAP_JSON),
+            PoolMessageSC::Pause(600),
+            PoolMessageSC::ResumeMining,
+            PoolMessageSC::Drain(30),
+            PoolMessageSC::UpgradeTlsAck(true),
+            PoolMessageSC::JobHint(7, 250_000),
+            PoolMessageSC::QueryInfo,
+            PoolMessageSC::ShareAckBatch(vec![(1, SHARE_ACCEPTED), (2, SHARE_STALE)]),
+            PoolMessageSC::NotifyBatch(vec![(8, 1_000, epoch_challenge)]),
+            PoolMessageSC::RequestProof(7),
+            PoolMessageSC::Kick(1, "invalid shares".to_string()),
+            PoolMessageSC::Bye,
+        ])
+    }
+
+    /// The messages of `CS_VECTORS`, in order.
+    fn cs_messages() -> Result<Vec<PoolMessageCS<CurrentNetwork>>> {
+        let prover_solution = prover_solution();
+        Ok(vec![
+            PoolMessageCS::Connect(
+                0,
+                1,
+                0,
+                1,
+                0,
+                "my_worker_1".to_string(),
+                "215587407@qq.com".to_string(),
+                vec![Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0])],
+            ),
+            PoolMessageCS::Submit(
+                1,
+                2,
+                3,
+                Data::Buffer(Bytes::from(prover_solution.to_bytes_le()?)),
+                None,
+            ),
+            PoolMessageCS::DisConnect(1),
+            PoolMessageCS::Ping,
+            PoolMessageCS::JobStatus(1, u64::MAX),
+            PoolMessageCS::RegisterWorker(3, "rig-03".to_string()),
+            PoolMessageCS::Chunk(7, 0, 1, vec![]),
+            PoolMessageCS::ResumeSession(1, "testaddress".to_string(), "testsignature".to_string()),
+            PoolMessageCS::Resume(u64::MAX),
+            PoolMessageCS::Hello(CAP_SEQUENCE | CAP_HMAC),
+            PoolMessageCS::UpgradeTls,
+            PoolMessageCS::InfoReport("zkwork-agent".to_string(), "1.2.0".to_string()),
+            PoolMessageCS::SetNotifyInterval(5_000),
+            PoolMessageCS::SubmitCommitment(7, 1, prover_solution.commitment()),
+            PoolMessageCS::PowerStats(7, 350, 1_200),
+            PoolMessageCS::NotifyAck(7, 1_700_000_000_000),
+            PoolMessageCS::SubmitChunk(7, 1, 0, 2, Bytes::from_static(&[1, 2, 3])),
+            PoolMessageCS::Bye,
+        ])
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_sc_vectors() -> Result<()> {
+        let messages = sc_messages()?;
+        assert_eq!(messages.len(), SC_VECTORS.len());
+        for (message, (name, hex)) in messages.iter().zip(SC_VECTORS) {
+            assert!(name.starts_with(message.name()), "{}", name);
+            let bytes = message.to_vec()?;
+            assert_eq!(to_hex(&bytes), *hex, "{}", name);
+            // The vectors decode back to the same bytes.
+            let decoded = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+            assert_eq!(to_hex(&decoded.to_vec()?), *hex, "{}", name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cs_vectors() -> Result<()> {
+        let messages = cs_messages()?;
+        assert_eq!(messages.len(), CS_VECTORS.len());
+        for (message, (name, hex)) in messages.iter().zip(CS_VECTORS) {
+            assert!(name.starts_with(message.name()), "{}", name);
+            let bytes = message.to_vec()?;
+            assert_eq!(to_hex(&bytes), *hex, "{}", name);
+            let decoded = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+            assert_eq!(to_hex(&decoded.to_vec()?), *hex, "{}", name);
+        }
+        Ok(())
+    }
+
+    /// Decodes every message with one byte flipped, or dropped with the length prefix adjusted.
+    /// Each mutation must decode to a message that encodes again, or fail cleanly; never panic.
+    /// Epoch challenges take most of a second to rebuild in debug builds, so their epoch and hash,
+    /// which snarkVM reads, are left alone, and mutations elsewhere also break their degree:
+    /// decoding stops at the challenge, and the fields after it are covered by the messages
+    /// without one.
+    fn check_mutations<M: WireMessage>(messages: &[M]) -> Result<()> {
+        let challenge = epoch_challenge()?.to_bytes_le()?;
+        for message in messages {
+            let mut frame = vec![0u8; 4];
+            message.serialize_into(&mut frame)?;
+            let length = (frame.len() - 4) as u32;
+            frame[..4].copy_from_slice(&length.to_le_bytes());
+            let starts = (0..=frame.len().saturating_sub(challenge.len()))
+                .filter(|start| frame[*start..].starts_with(&challenge))
+                .collect::<Vec<_>>();
+            let mut broken = frame.clone();
+            for start in &starts {
+                broken[start + EPOCH_CHALLENGE_SIZE - 4..start + EPOCH_CHALLENGE_SIZE].fill(0xff);
+            }
+
+            for position in 0..frame.len() {
+                let frame = match starts
+                    .iter()
+                    .find(|start| (**start..**start + EPOCH_CHALLENGE_SIZE).contains(&position))
+                {
+                    Some(start) if position < start + EPOCH_CHALLENGE_SIZE - 4 => continue,
+                    Some(_) => &frame,
+                    None => &broken,
+                };
+                let mut flipped = frame.clone();
+                flipped[position] ^= 0xff;
+                let mut mutations = vec![flipped];
+                if position >= 4 {
+                    let mut dropped = frame.clone();
+                    dropped.remove(position);
+                    let length = (dropped.len() - 4) as u32;
+                    dropped[..4].copy_from_slice(&length.to_le_bytes());
+                    mutations.push(dropped);
+                }
+                for mutated in mutations {
+                    let decoded = std::panic::catch_unwind(|| {
+                        let mut buffer = BytesMut::from(&mutated[..]);
+                        match MessageCodec::<M>::new().decode_eof(&mut buffer) {
+                            Ok(Some(message)) => message.serialize_into(&mut Vec::new()),
+                            _ => Ok(()),
+                        }
+                    });
+                    assert!(
+                        matches!(decoded, Ok(Ok(()))),
+                        "{} mutated at {}: {:?}",
+                        message.name(),
+                        position,
+                        decoded
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_vector_mutations() -> Result<()> {
+        check_mutations(&sc_messages()?)?;
+        check_mutations(&cs_messages()?)
+    }
+
+    #[test]
+    fn test_pool_message_codec_vectors() -> Result<()> {
+        let mut codec = PoolMessageCodec::<CurrentNetwork>::new();
+        let messages = sc_messages()?
+            .into_iter()
+            .map(PoolMessage::from)
+            .chain(cs_messages()?.into_iter().map(PoolMessage::from))
+            .collect::<Vec<_>>();
+        let mut buffer = BytesMut::new();
+        for message in &messages {
+            codec.encode(message.clone(), &mut buffer)?;
+        }
+        for message in &messages {
+            let decoded = codec.decode(&mut buffer)?.expect("a complete frame");
+            assert_eq!(decoded.direction(), message.direction());
+            assert_eq!(to_hex(&decoded.to_vec()?), to_hex(&message.to_vec()?));
+            assert_eq!(
+                to_hex(
+                    &PoolMessage::<CurrentNetwork>::deserialize(&message.to_vec()?[4..])?
+                        .to_vec()?
+                ),
+                to_hex(&message.to_vec()?)
+            );
+        }
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialized_size_vectors() -> Result<()> {
+        for message in sc_messages()? {
+            assert_eq!(
+                4 + message.serialized_size(),
+                message.to_vec()?.len(),
+                "{}",
+                message.name()
+            );
+        }
+        for message in cs_messages()? {
+            assert_eq!(
+                4 + message.serialized_size(),
+                message.to_vec()?.len(),
+                "{}",
+                message.name()
+            );
+        }
+        let solution = Data::Object(prover_solution());
+        let message = PoolMessageCS::<CurrentNetwork>::Submit(1, 2, 3, solution, None);
+        assert_eq!(4 + message.serialized_size(), message.to_vec()?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_peek_id_vectors() -> Result<()> {
+        for message in sc_messages()? {
+            let bytes = message.to_vec()?;
+            let decoded = PoolMessageSC::<CurrentNetwork>::deserialize(&bytes[4..])?;
+            assert_eq!(
+                PoolMessageSC::<CurrentNetwork>::peek_id(&bytes)?,
+                decoded.id()
+            );
+            assert!(matches!(
+                PoolMessageCS::<CurrentNetwork>::peek_id(&bytes)
+                    .unwrap_err()
+                    .downcast_ref(),
+                Some(PoolProtocolError::WrongDirection(_))
+            ));
+        }
+        for message in cs_messages()? {
+            let bytes = message.to_vec()?;
+            let decoded = PoolMessageCS::<CurrentNetwork>::deserialize(&bytes[4..])?;
+            assert_eq!(
+                PoolMessageCS::<CurrentNetwork>::peek_id(&bytes)?,
+                decoded.id()
+            );
+            assert!(matches!(
+                PoolMessageSC::<CurrentNetwork>::peek_id(&bytes)
+                    .unwrap_err()
+                    .downcast_ref(),
+                Some(PoolProtocolError::WrongDirection(_))
+            ));
+        }
+
+        assert!(PoolMessageSC::<CurrentNetwork>::peek_id(&[1, 0, 0, 0]).is_err());
+        assert!(PoolMessageSC::<CurrentNetwork>::peek_id(&[0, 0, 0, 0, 3]).is_err());
+        assert!(matches!(
+            PoolMessageSC::<CurrentNetwork>::peek_id(&[1, 0, 0, 0, 0x7f])
+                .unwrap_err()
+                .downcast_ref(),
+            Some(PoolProtocolError::UnknownMessageId(0x7f))
+        ));
+        Ok(())
+    }
+}
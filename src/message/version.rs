@@ -0,0 +1,315 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{capabilities::*, codec::*};
+
+use ::bytes::BytesMut;
+use anyhow::Result;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// The binary layout of message bodies.
+/// The handshake is always V1; `V2` applies once `CAP_PROTOCOL_V2` is negotiated by a `ProtocolCodec`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    /// The original layout of `PoolMessageSC` and `PoolMessageCS`.
+    #[default]
+    V1,
+    /// Length-prefixed strings instead of separators, see `PoolMessageSCv2` and `PoolMessageCSv2`.
+    V2,
+}
+
+impl ProtocolVersion {
+    /// Returns the version selected by the negotiated capabilities.
+    pub fn from_capabilities(capabilities: u32) -> Self {
+        match capabilities & CAP_PROTOCOL_V2 != 0 {
+            true => Self::V2,
+            false => Self::V1,
+        }
+    }
+
+    /// Returns the byte stamped on frames once `CAP_VERSION_BYTE` is negotiated.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    /// Returns the version stamped as `byte`, `None` for a version this build does not know.
+    pub fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            _ => None,
+        }
+    }
+}
+
+/// Codec that lays out message bodies in the negotiated `ProtocolVersion`.
+/// Messages are exchanged in their common representation, `PoolMessageSC` or `PoolMessageCS`,
+/// and converted to and from the V2 enums on the wire. A plain `MessageCodec` always uses V1.
+#[derive(Clone, Debug)]
+pub struct ProtocolCodec<M> {
+    inner: MessageCodec<M>,
+}
+
+impl<M> Default for ProtocolCodec<M> {
+    fn default() -> Self {
+        Self::new(MessageCodec::new())
+    }
+}
+
+impl<M> ProtocolCodec<M> {
+    /// Wraps a codec, selecting the version from its capabilities.
+    pub fn new(mut inner: MessageCodec<M>) -> Self {
+        inner.set_protocol_version(ProtocolVersion::from_capabilities(inner.capabilities()));
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &MessageCodec<M> {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut MessageCodec<M> {
+        &mut self.inner
+    }
+
+    /// Returns the version of the message bodies.
+    pub fn version(&self) -> ProtocolVersion {
+        self.inner.protocol_version()
+    }
+
+    /// Applies the capabilities agreed in the handshake to every following frame,
+    /// switching to V2 if `CAP_PROTOCOL_V2` was agreed.
+    pub fn set_capabilities(&mut self, capabilities: u32) {
+        self.inner.set_capabilities(capabilities);
+        self.inner
+            .set_protocol_version(ProtocolVersion::from_capabilities(capabilities));
+    }
+}
+
+impl<M: WireMessage> Encoder<M> for ProtocolCodec<M> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: M, dst: &mut BytesMut) -> Result<()> {
+        self.inner.encode(message, dst)
+    }
+}
+
+impl<M: WireMessage> Encoder<&M> for ProtocolCodec<M> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: &M, dst: &mut BytesMut) -> Result<()> {
+        self.inner.encode(message, dst)
+    }
+}
+
+impl<M: WireMessage> Decoder for ProtocolCodec<M> {
+    type Error = std::io::Error;
+    type Item = M;
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(source)
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_eof(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{PoolMessageCS, PoolMessageCSv2, PoolMessageSC, Tlv, TAG_SERVER_INFO};
+    use ::rand::thread_rng;
+    use snarkvm::prelude::{Address, Testnet3, ToBytes, Uniform};
+    type CurrentNetwork = Testnet3;
+
+    fn connect() -> PoolMessageCS<CurrentNetwork> {
+        PoolMessageCS::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "w".to_string(),
+            "a@b".to_string(),
+            vec![capabilities_tlv(CAP_SEQUENCE)],
+        )
+    }
+
+    fn encode<M: WireMessage>(codec: &mut ProtocolCodec<M>, message: M) -> Result<Vec<u8>> {
+        let mut buffer = BytesMut::new();
+        codec.encode(message, &mut buffer)?;
+        Ok(buffer.to_vec())
+    }
+
+    #[test]
+    fn test_v1_golden_bytes() -> Result<()> {
+        // Before negotiation, and without `CAP_PROTOCOL_V2`, bodies keep today's layout.
+        let mut codec = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        let expected = vec![
+            19, 0, 0, 0, 128, 0, 1, 0, 1, 0, 1, b'w', b'a', b'@', b'b', 0, 1, 4, 0, 1, 0, 0, 0,
+        ];
+        assert_eq!(codec.version(), ProtocolVersion::V1);
+        assert_eq!(encode(&mut codec, connect())?, expected);
+        assert_eq!(connect().to_vec()?, expected);
+
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(&mut thread_rng()));
+        let message = PoolMessageSC::ConnectAck(
+            true,
+            address,
+            Some(0x0403_0201),
+            Some("s".to_string()),
+            None,
+            vec![Tlv::new(TAG_SERVER_INFO, b"p".to_vec())],
+        );
+        let mut expected = vec![45, 0, 0, 0, 0, 1];
+        expected.extend_from_slice(&address.to_bytes_le()?);
+        expected.extend_from_slice(&[1, 2, 3, 4, 0, b's', 0, 6, 1, 0, b'p']);
+        let mut codec = ProtocolCodec::default();
+        codec.set_capabilities(CAP_SEQUENCE);
+        assert_eq!(codec.version(), ProtocolVersion::V1);
+        let bytes = encode(&mut codec, message.clone())?;
+        assert_eq!(&bytes[..4], &[49, 0, 0, 0]);
+        assert_eq!(&bytes[8..], &expected[4..]);
+        assert_eq!(message.to_vec()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_layout() -> Result<()> {
+        let mut encoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        let mut decoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        encoder.set_capabilities(CAP_PROTOCOL_V2);
+        decoder.set_capabilities(CAP_PROTOCOL_V2);
+        assert_eq!(encoder.version(), ProtocolVersion::V2);
+
+        // The name and address are prefixed by a u16 length instead of a byte and a separator.
+        let bytes = encode(&mut encoder, connect())?;
+        assert_eq!(
+            bytes,
+            vec![
+                21, 0, 0, 0, 128, 0, 1, 0, 1, 0, 1, 0, b'w', 3, 0, b'a', b'@', b'b', 1, 4, 0, 1, 0,
+                0, 0
+            ]
+        );
+        let message = decoder.decode(&mut BytesMut::from(&bytes[..]))?.unwrap();
+        assert_eq!(message.to_vec()?, connect().to_vec()?);
+
+        // Other messages keep their V1 layout.
+        let bytes = encode(&mut encoder, PoolMessageCS::JobStatus(1, 2))?;
+        assert_eq!(
+            bytes,
+            PoolMessageCS::<CurrentNetwork>::JobStatus(1, 2).to_vec()?
+        );
+
+        // A plain `MessageCodec` stays on V1 whatever the capabilities.
+        let mut codec = MessageCodec::<PoolMessageCS<CurrentNetwork>>::new();
+        codec.set_capabilities(CAP_PROTOCOL_V2);
+        assert_eq!(codec.protocol_version(), ProtocolVersion::V1);
+        let mut buffer = BytesMut::new();
+        codec.encode(connect(), &mut buffer)?;
+        assert_eq!(buffer.to_vec(), connect().to_vec()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_long_connect_name() -> Result<()> {
+        let name = "x".repeat(300);
+        let address = "a@b".to_string();
+        assert!(PoolMessageCS::<CurrentNetwork>::connect(
+            0,
+            1,
+            (0, 1, 0),
+            name.clone(),
+            address.clone(),
+            vec![]
+        )
+        .is_err());
+        let message = PoolMessageCSv2::<CurrentNetwork>::connect(
+            0,
+            1,
+            (0, 1, 0),
+            name.clone(),
+            address.clone(),
+            vec![],
+        )?;
+        let message = PoolMessageCS::from(message);
+
+        // V2 round trip.
+        let mut encoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        let mut decoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        encoder.set_capabilities(CAP_PROTOCOL_V2);
+        decoder.set_capabilities(CAP_PROTOCOL_V2);
+        let bytes = encode(&mut encoder, message.clone())?;
+        let decoded = decoder.decode(&mut BytesMut::from(&bytes[..]))?.unwrap();
+        assert!(
+            matches!(&decoded, PoolMessageCS::Connect(.., n, a, _) if *n == name && *a == address)
+        );
+
+        // V1 cannot carry it, and stays the layout decoded before `CAP_PROTOCOL_V2`.
+        let mut encoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        let mut decoder = ProtocolCodec::<PoolMessageCS<CurrentNetwork>>::default();
+        assert!(encode(&mut encoder, message).is_err());
+        let message = PoolMessageCS::connect(0, 1, (0, 1, 0), "w".repeat(255), address, vec![])?;
+        let bytes = encode(&mut encoder, message.clone())?;
+        assert_eq!(bytes, message.to_vec()?);
+        let decoded = decoder.decode(&mut BytesMut::from(&bytes[..]))?.unwrap();
+        assert_eq!(decoded.to_vec()?, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_connect_ack() -> Result<()> {
+        let address = Address::<CurrentNetwork>::new(Uniform::rand(&mut thread_rng()));
+        let tlvs = vec![Tlv::new(TAG_SERVER_INFO, b"p".to_vec())];
+        let mut encoder = ProtocolCodec::default();
+        let mut decoder = ProtocolCodec::<PoolMessageSC<CurrentNetwork>>::new(MessageCodec::new());
+        encoder.set_capabilities(CAP_PROTOCOL_V2);
+        decoder.set_capabilities(CAP_PROTOCOL_V2);
+
+        let message = PoolMessageSC::ConnectAck(
+            true,
+            address,
+            Some(0x0403_0201),
+            Some("s\0ig".to_string()),
+            None,
+            tlvs.clone(),
+        );
+        let bytes = encode(&mut encoder, message)?;
+        let mut expected = vec![0, 1];
+        expected.extend_from_slice(&address.to_bytes_le()?);
+        expected.extend_from_slice(&[1, 2, 3, 4, 0, 4, 0, b's', 0, b'i', b'g', 6, 1, 0, b'p']);
+        assert_eq!(&bytes[4..], &expected[..]);
+        // The signature may now hold any byte, the separator of V1 included.
+        match decoder.decode(&mut BytesMut::from(&bytes[..]))?.unwrap() {
+            PoolMessageSC::ConnectAck(
+                true,
+                decoded,
+                Some(0x0403_0201),
+                Some(signature),
+                None,
+                decoded_tlvs,
+            ) => {
+                assert_eq!(decoded, address);
+                assert_eq!(signature, "s\0ig");
+                assert_eq!(decoded_tlvs, tlvs);
+            }
+            message => panic!("Unexpected {:?}", message),
+        }
+
+        let message = PoolMessageSC::ConnectAck(false, address, None, None, None, tlvs);
+        let bytes = encode(&mut encoder, message.clone())?;
+        assert_eq!(bytes, message.to_vec()?);
+        Ok(())
+    }
+}
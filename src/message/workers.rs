@@ -0,0 +1,80 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::poolmessage::MAXIMUM_WORKER_NAME_LENGTH;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+/// The most sub-workers a single connection may register.
+pub const MAXIMUM_SUB_WORKERS: usize = 4096;
+
+/// Pool-side mapping of `RegisterWorker` ids to names for one connection.
+/// Submits with sub_worker_id 0 belong to the connection's own worker.
+#[derive(Clone, Debug, Default)]
+pub struct SubWorkers {
+    names: HashMap<u32, String>,
+}
+
+impl SubWorkers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers or renames a sub-worker.
+    pub fn register(&mut self, sub_worker_id: u32, name: String) -> Result<()> {
+        if sub_worker_id == 0 {
+            return Err(anyhow!("Sub-worker id 0 is reserved"));
+        }
+        if name.is_empty() || name.len() > MAXIMUM_WORKER_NAME_LENGTH {
+            return Err(anyhow!("Invalid sub-worker name length {}", name.len()));
+        }
+        if !self.names.contains_key(&sub_worker_id) && self.names.len() >= MAXIMUM_SUB_WORKERS {
+            return Err(anyhow!("Too many sub-workers"));
+        }
+        self.names.insert(sub_worker_id, name);
+        Ok(())
+    }
+
+    /// Returns the name of a registered sub-worker.
+    pub fn name(&self, sub_worker_id: u32) -> Option<&str> {
+        self.names.get(&sub_worker_id).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sub_workers() -> Result<()> {
+        let mut workers = SubWorkers::new();
+        workers.register(1, "rig-01".to_string())?;
+        workers.register(1, "rig-01b".to_string())?;
+        assert_eq!(workers.name(1), Some("rig-01b"));
+        assert_eq!(workers.name(2), None);
+        assert_eq!(workers.len(), 1);
+
+        assert!(workers.register(0, "main".to_string()).is_err());
+        assert!(workers.register(2, String::new()).is_err());
+        assert!(workers
+            .register(2, "x".repeat(MAXIMUM_WORKER_NAME_LENGTH + 1))
+            .is_err());
+        Ok(())
+    }
+}
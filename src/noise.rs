@@ -0,0 +1,236 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Optional Noise_XX encryption of the pool connection.
+//!
+//! Run `connect` (worker/agent) or `accept` (pool) right after the TCP connection is
+//! established, then use the returned `NoiseStream` with the usual codecs. Peers that skip
+//! the handshake keep speaking the plaintext protocol; both ends must agree out of band.
+//!
+//! Every Noise message travels as << length(u16, big endian), ciphertext >>.
+
+use anyhow::{anyhow, Result};
+use snow::{Builder, HandshakeState, Keypair, TransportState};
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// The Noise protocol used for the pool connection.
+pub const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+
+const MAX_NOISE_MESSAGE: usize = 65535;
+const TAG_SIZE: usize = 16;
+const MAX_PLAINTEXT: usize = MAX_NOISE_MESSAGE - TAG_SIZE;
+
+/// Generates a static keypair for `connect` or `accept`.
+pub fn generate_keypair() -> Result<Keypair> {
+    Ok(Builder::new(NOISE_PARAMS.parse()?).generate_keypair()?)
+}
+
+/// Performs the initiator side of the handshake.
+pub async fn connect<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    private_key: &[u8],
+) -> Result<NoiseStream<S>> {
+    let mut handshake = Builder::new(NOISE_PARAMS.parse()?)
+        .local_private_key(private_key)
+        .build_initiator()?;
+    // -> e
+    write_handshake_message(&mut stream, &mut handshake).await?;
+    // <- e, ee, s, es
+    read_handshake_message(&mut stream, &mut handshake).await?;
+    // -> s, se
+    write_handshake_message(&mut stream, &mut handshake).await?;
+    Ok(NoiseStream::new(stream, handshake.into_transport_mode()?))
+}
+
+/// Performs the responder side of the handshake.
+pub async fn accept<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    private_key: &[u8],
+) -> Result<NoiseStream<S>> {
+    let mut handshake = Builder::new(NOISE_PARAMS.parse()?)
+        .local_private_key(private_key)
+        .build_responder()?;
+    // -> e
+    read_handshake_message(&mut stream, &mut handshake).await?;
+    // <- e, ee, s, es
+    write_handshake_message(&mut stream, &mut handshake).await?;
+    // -> s, se
+    read_handshake_message(&mut stream, &mut handshake).await?;
+    Ok(NoiseStream::new(stream, handshake.into_transport_mode()?))
+}
+
+async fn write_handshake_message<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    handshake: &mut HandshakeState,
+) -> Result<()> {
+    let mut message = vec![0u8; MAX_NOISE_MESSAGE];
+    let len = handshake.write_message(&[], &mut message)?;
+    stream.write_all(&(len as u16).to_be_bytes()).await?;
+    stream.write_all(&message[..len]).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn read_handshake_message<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    handshake: &mut HandshakeState,
+) -> Result<()> {
+    let len = stream.read_u16().await? as usize;
+    let mut message = vec![0u8; len];
+    stream.read_exact(&mut message).await?;
+    let mut payload = vec![0u8; MAX_NOISE_MESSAGE];
+    handshake.read_message(&message, &mut payload)?;
+    Ok(())
+}
+
+/// An encrypted stream over which the pool codecs run unchanged.
+pub struct NoiseStream<S> {
+    inner: S,
+    transport: TransportState,
+    /// Ciphertext received but not yet decrypted.
+    incoming: Vec<u8>,
+    /// Decrypted bytes not yet handed to the reader.
+    plaintext: Vec<u8>,
+    plaintext_pos: usize,
+    /// Encrypted bytes not yet written to `inner`.
+    outgoing: Vec<u8>,
+    outgoing_pos: usize,
+}
+
+impl<S> NoiseStream<S> {
+    fn new(inner: S, transport: TransportState) -> Self {
+        Self {
+            inner,
+            transport,
+            incoming: Vec::new(),
+            plaintext: Vec::new(),
+            plaintext_pos: 0,
+            outgoing: Vec::new(),
+            outgoing_pos: 0,
+        }
+    }
+
+    /// Returns the peer's static public key, authenticated by the handshake.
+    pub fn remote_static(&self) -> Result<&[u8]> {
+        self.transport
+            .get_remote_static()
+            .ok_or_else(|| anyhow!("Missing remote static key"))
+    }
+
+    /// Decrypts the next complete message in `incoming`, if any.
+    fn decrypt_incoming(&mut self) -> io::Result<bool> {
+        if self.incoming.len() < 2 {
+            return Ok(false);
+        }
+        let len = u16::from_be_bytes([self.incoming[0], self.incoming[1]]) as usize;
+        if self.incoming.len() < 2 + len {
+            return Ok(false);
+        }
+        let mut plaintext = vec![0u8; len];
+        let n = self
+            .transport
+            .read_message(&self.incoming[2..2 + len], &mut plaintext)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        plaintext.truncate(n);
+        self.incoming.drain(..2 + len);
+        self.plaintext = plaintext;
+        self.plaintext_pos = 0;
+        Ok(true)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> NoiseStream<S> {
+    /// Writes out any buffered ciphertext.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.outgoing_pos < self.outgoing.len() {
+            let n = ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.outgoing[self.outgoing_pos..])
+            )?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.outgoing_pos += n;
+        }
+        self.outgoing.clear();
+        self.outgoing_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for NoiseStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.plaintext_pos < this.plaintext.len() {
+                let n = buf
+                    .remaining()
+                    .min(this.plaintext.len() - this.plaintext_pos);
+                buf.put_slice(&this.plaintext[this.plaintext_pos..this.plaintext_pos + n]);
+                this.plaintext_pos += n;
+                return Poll::Ready(Ok(()));
+            }
+            if this.decrypt_incoming()? {
+                continue;
+            }
+            let mut chunk = [0u8; 4096];
+            let mut read_buf = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+            if read_buf.filled().is_empty() {
+                return match this.incoming.is_empty() {
+                    true => Poll::Ready(Ok(())),
+                    false => Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into())),
+                };
+            }
+            this.incoming.extend_from_slice(read_buf.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for NoiseStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        let n = buf.len().min(MAX_PLAINTEXT);
+        let mut message = vec![0u8; n + TAG_SIZE];
+        let len = this
+            .transport
+            .write_message(&buf[..n], &mut message)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+        this.outgoing.extend_from_slice(&(len as u16).to_be_bytes());
+        this.outgoing.extend_from_slice(&message[..len]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Optional in-band upgrade of a plaintext pool connection to TLS, for deployments without
+//! a TLS terminator in front of the pool.
+//!
+//! The client sends `UpgradeTls` as its first message and the pool answers `UpgradeTlsAck`.
+//! After an accepted ack both sides run the TLS handshake on the same connection and keep their
+//! codecs over the TLS stream, so `Hello` and `Connect` are already encrypted. Both messages
+//! travel in the plain framing. A pool without TLS answers `UpgradeTlsAck(false)` itself and
+//! the client may go on in plaintext or disconnect.
+
+use crate::message::{PoolMessageCS, PoolMessageSC};
+use snarkvm::prelude::Network;
+
+use ::bytes::{Buf, BytesMut};
+use anyhow::{anyhow, Result};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio_rustls::{client, rustls::pki_types::ServerName, server, TlsAcceptor, TlsConnector};
+use tokio_util::codec::{Decoder, Framed};
+
+pub use tokio_rustls;
+
+/// Asks the pool to upgrade, then runs the client side of the TLS handshake.
+/// `framed` reads the pool's messages, e.g. with a `PoolCodecSC`; a refused upgrade is an error.
+pub async fn upgrade_client<N, T, C>(
+    framed: Framed<T, C>,
+    connector: &TlsConnector,
+    server_name: ServerName<'static>,
+) -> Result<Framed<client::TlsStream<Rewind<T>>, C>>
+where
+    N: Network,
+    T: AsyncRead + AsyncWrite + Unpin,
+    C: Decoder<Item = PoolMessageSC<N>>,
+    C::Error: Into<anyhow::Error>,
+{
+    let mut parts = framed.into_parts();
+    parts
+        .write_buf
+        .extend_from_slice(&PoolMessageCS::<N>::UpgradeTls.to_vec()?);
+    parts.io.write_all(&parts.write_buf).await?;
+    parts.io.flush().await?;
+    loop {
+        match parts
+            .codec
+            .decode(&mut parts.read_buf)
+            .map_err(Into::into)?
+        {
+            Some(PoolMessageSC::UpgradeTlsAck(true)) => break,
+            Some(message) => {
+                return Err(anyhow!("Pool refused the TLS upgrade: {}", message.name()))
+            }
+            None => {
+                if parts.io.read_buf(&mut parts.read_buf).await? == 0 {
+                    return Err(anyhow!("Connection closed before the TLS upgrade"));
+                }
+            }
+        }
+    }
+    let stream = connector
+        .connect(server_name, Rewind::new(parts.io, parts.read_buf))
+        .await?;
+    Ok(Framed::new(stream, parts.codec))
+}
+
+/// Accepts an `UpgradeTls` read from `framed`, e.g. with a `PoolCodecCS`, and runs the server
+/// side of the TLS handshake. Bytes the client sent after its request feed the handshake.
+pub async fn upgrade_server<N, T, C>(
+    framed: Framed<T, C>,
+    acceptor: &TlsAcceptor,
+) -> Result<Framed<server::TlsStream<Rewind<T>>, C>>
+where
+    N: Network,
+    T: AsyncRead + AsyncWrite + Unpin,
+    C: Decoder<Item = PoolMessageCS<N>>,
+{
+    let mut parts = framed.into_parts();
+    parts
+        .write_buf
+        .extend_from_slice(&PoolMessageSC::<N>::UpgradeTlsAck(true).to_vec()?);
+    parts.io.write_all(&parts.write_buf).await?;
+    parts.io.flush().await?;
+    let stream = acceptor
+        .accept(Rewind::new(parts.io, parts.read_buf))
+        .await?;
+    Ok(Framed::new(stream, parts.codec))
+}
+
+/// A stream that first replays bytes read ahead by the plaintext codec.
+#[derive(Debug)]
+pub struct Rewind<T> {
+    inner: T,
+    prefix: BytesMut,
+}
+
+impl<T> Rewind<T> {
+    pub fn new(inner: T, prefix: BytesMut) -> Self {
+        Self { inner, prefix }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Rewind<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if !self.prefix.is_empty() {
+            let len = self.prefix.len().min(buf.remaining());
+            buf.put_slice(&self.prefix[..len]);
+            self.prefix.advance(len);
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Rewind<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
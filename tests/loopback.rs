@@ -0,0 +1,119 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+use futures::{SinkExt, StreamExt};
+use rand::Rng;
+use snarkvm::prelude::{
+    Address, EpochChallenge, Network, PartialSolution, PrivateKey, ProverSolution, Testnet3,
+    Uniform,
+};
+use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::{FramedRead, FramedWrite};
+use zkwork_aleo_protocol::message::*;
+
+type CurrentNetwork = Testnet3;
+
+fn epoch_challenge() -> anyhow::Result<EpochChallenge<CurrentNetwork>> {
+    EpochChallenge::new(
+        0,
+        CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+        CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+    )
+}
+
+/// Runs Connect → ConnectAck → Notify → Submit → ShareAckBatch between a mock pool and an agent,
+/// each with its real codecs over a loopback TCP socket.
+#[tokio::test]
+async fn test_loopback_exchange() -> anyhow::Result<()> {
+    let rng = &mut rand::thread_rng();
+    let private_key = PrivateKey::<CurrentNetwork>::new(rng)?;
+    let pool_address = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng)?)?;
+    let prover_solution = ProverSolution::new(
+        PartialSolution::new(
+            Address::try_from(&private_key)?,
+            u64::rand(rng),
+            KZGCommitment(rng.gen()),
+        ),
+        KZGProof {
+            w: rng.gen(),
+            random_v: None,
+        },
+    );
+    let connect = PoolMessageCS::<CurrentNetwork>::Connect(
+        0,
+        ADDRESS_TYPE_EMAIL,
+        0,
+        1,
+        0,
+        "my_worker_1".to_string(),
+        "215587407@qq.com".to_string(),
+        vec![],
+    );
+    let submit =
+        PoolMessageCS::<CurrentNetwork>::Submit(1, 0, 8, Data::Object(prover_solution), None);
+    let pool_messages = vec![
+        PoolMessageSC::<CurrentNetwork>::ConnectAck(
+            true,
+            pool_address,
+            Some(1),
+            Some("signature".to_string()),
+            None,
+            vec![],
+        ),
+        PoolMessageSC::Notify(7, 1_000, 1_000, epoch_challenge()?, vec![]),
+        PoolMessageSC::Notify(8, 2_000, 2_000, epoch_challenge()?, vec![]),
+    ];
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let local_address = listener.local_addr()?;
+    let (expected_connect, expected_submit) = (connect.to_vec()?, submit.to_vec()?);
+    let replies = pool_messages.clone();
+    let pool = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await?;
+        let (reader, writer) = stream.into_split();
+        let mut reader = FramedRead::new(reader, PoolCodecCS::<CurrentNetwork>::new());
+        let mut writer = FramedWrite::new(writer, PoolCodecSC::<CurrentNetwork>::new());
+
+        let message = reader.next().await.unwrap()?;
+        assert_eq!(message.to_vec()?, expected_connect);
+        for reply in replies {
+            writer.send(reply).await?;
+        }
+        let message = reader.next().await.unwrap()?;
+        assert_eq!(message.to_vec()?, expected_submit);
+        writer
+            .send(PoolMessageSC::ShareAckBatch(vec![(1, SHARE_ACCEPTED)]))
+            .await?;
+        assert!(reader.next().await.is_none());
+        anyhow::Ok(())
+    });
+
+    let (reader, writer) = TcpStream::connect(local_address).await?.into_split();
+    let mut reader = FramedRead::new(reader, PoolCodecSC::<CurrentNetwork>::new());
+    let mut writer = FramedWrite::new(writer, PoolCodecCS::<CurrentNetwork>::new());
+    writer.send(connect).await?;
+    for expected in &pool_messages {
+        let message = reader.next().await.unwrap()?;
+        assert_eq!(message.to_vec()?, expected.to_vec()?);
+    }
+    writer.send(submit).await?;
+    match reader.next().await.unwrap()? {
+        PoolMessageSC::ShareAckBatch(acks) => assert_eq!(acks, vec![(1, SHARE_ACCEPTED)]),
+        message => panic!("unexpected message {:?}", message),
+    }
+
+    // Closing the agent's side ends the pool's stream cleanly.
+    drop(writer);
+    pool.await??;
+    assert!(reader.next().await.is_none());
+    Ok(())
+}
@@ -0,0 +1,155 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(feature = "noise")]
+
+use ::rand::thread_rng;
+use anyhow::Result;
+use futures::{SinkExt, StreamExt};
+use snarkvm::prelude::*;
+use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::{FramedRead, FramedWrite};
+use zkwork_aleo_protocol::{message::*, noise};
+
+type CurrentNetwork = Testnet3;
+
+fn sample_prover_solution() -> ProverSolution<CurrentNetwork> {
+    let rng = &mut thread_rng();
+    let address = Address::<CurrentNetwork>::new(Uniform::rand(rng));
+    let partial_solution = PartialSolution::new(address, u64::rand(rng), KZGCommitment(rng.gen()));
+    ProverSolution::new(
+        partial_solution,
+        KZGProof {
+            w: rng.gen(),
+            random_v: None,
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_noise_connect_notify_submit() -> Result<()> {
+    let pool_keys = noise::generate_keypair()?;
+    let worker_keys = noise::generate_keypair()?;
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let pool_address = listener.local_addr()?;
+    let prover_solution = sample_prover_solution();
+    let epoch_challenge = EpochChallenge::<CurrentNetwork>::new(
+        0,
+        CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+        CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+    )?;
+
+    let pool_challenge = epoch_challenge.clone();
+    let worker_public = worker_keys.public.clone();
+    let pool = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await?;
+        let stream = noise::accept(stream, &pool_keys.private).await?;
+        assert_eq!(stream.remote_static()?, &worker_public[..]);
+        let (reader, writer) = tokio::io::split(stream);
+        let mut reader = FramedRead::new(reader, PoolCodecCS::<CurrentNetwork>::new());
+        let mut writer = FramedWrite::new(writer, PoolCodecSC::<CurrentNetwork>::new());
+
+        let (name, address) = match reader.next().await.unwrap()? {
+            PoolMessageCS::Connect(_, _, _, _, _, name, address, _) => (name, address),
+            message => panic!("unexpected message {:?}", message),
+        };
+        let pool_address = Address::<CurrentNetwork>::new(Uniform::rand(&mut thread_rng()));
+        writer
+            .send(PoolMessageSC::ConnectAck(
+                true,
+                pool_address,
+                Some(1),
+                Some(String::from("testsignature")),
+                None,
+                vec![],
+            ))
+            .await?;
+        writer
+            .send(PoolMessageSC::Notify(
+                7,
+                100000,
+                100000,
+                pool_challenge,
+                vec![],
+            ))
+            .await?;
+        let submit = reader.next().await.unwrap()?;
+        anyhow::Ok((name, address, submit))
+    });
+
+    let stream = TcpStream::connect(pool_address).await?;
+    let stream = noise::connect(stream, &worker_keys.private).await?;
+    let (reader, writer) = tokio::io::split(stream);
+    let mut reader = FramedRead::new(reader, PoolCodecSC::<CurrentNetwork>::new());
+    let mut writer = FramedWrite::new(writer, PoolCodecCS::<CurrentNetwork>::new());
+
+    writer
+        .send(PoolMessageCS::Connect(
+            0,
+            1,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            vec![],
+        ))
+        .await?;
+    assert!(matches!(
+        reader.next().await.unwrap()?,
+        PoolMessageSC::ConnectAck(true, _, Some(1), Some(_), ..)
+    ));
+    match reader.next().await.unwrap()? {
+        PoolMessageSC::Notify(7, 100000, 100000, challenge, _) => {
+            assert_eq!(challenge, epoch_challenge)
+        }
+        message => panic!("unexpected message {:?}", message),
+    }
+    writer
+        .send(PoolMessageCS::Submit(
+            1,
+            0,
+            7,
+            Data::Object(prover_solution),
+            None,
+        ))
+        .await?;
+
+    let (name, address, submit) = pool.await??;
+    assert_eq!(name, "my_worker_1");
+    assert_eq!(address, "215587407@qq.com");
+    match submit {
+        PoolMessageCS::Submit(1, 0, 7, solution, None) => {
+            assert_eq!(solution.deserialize().await?, prover_solution)
+        }
+        message => panic!("unexpected message {:?}", message),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_noise_rejects_plaintext_peer() -> Result<()> {
+    let pool_keys = noise::generate_keypair()?;
+    let (client, server) = tokio::io::duplex(1024);
+    let pool = tokio::spawn(async move { noise::accept(server, &pool_keys.private).await });
+
+    // A plaintext client sends a framed Ping instead of a handshake message.
+    let mut client = client;
+    tokio::io::AsyncWriteExt::write_all(
+        &mut client,
+        &PoolMessageCS::<CurrentNetwork>::Ping.to_vec()?,
+    )
+    .await?;
+    drop(client);
+    assert!(pool.await?.is_err());
+    Ok(())
+}
@@ -0,0 +1,134 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(feature = "tls")]
+
+use futures::{SinkExt, StreamExt};
+use snarkvm::prelude::{Address, PrivateKey, Testnet3};
+use std::sync::Arc;
+use tokio_util::codec::{Framed, FramedRead, FramedWrite};
+use zkwork_aleo_protocol::{
+    message::*,
+    tls::{
+        tokio_rustls::{
+            rustls::{
+                pki_types::{PrivatePkcs8KeyDer, ServerName},
+                ClientConfig, RootCertStore, ServerConfig,
+            },
+            TlsAcceptor, TlsConnector,
+        },
+        *,
+    },
+};
+
+type CurrentNetwork = Testnet3;
+
+const SERVER_NAME: &str = "pool.zk.work";
+
+fn tls_configs() -> anyhow::Result<(TlsConnector, TlsAcceptor)> {
+    let certified = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])?;
+    let certificate = certified.cert.der().clone();
+    let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate.clone())?;
+    let client = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate], key.into())?;
+    Ok((
+        TlsConnector::from(Arc::new(client)),
+        TlsAcceptor::from(Arc::new(server)),
+    ))
+}
+
+#[tokio::test]
+async fn test_connect_over_upgraded_tls() -> anyhow::Result<()> {
+    let (connector, acceptor) = tls_configs()?;
+    let (client, server) = tokio::io::duplex(1 << 16);
+    let rng = &mut rand::thread_rng();
+    let pool_address = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng)?)?;
+
+    let pool = tokio::spawn(async move {
+        let mut framed = Framed::new(server, PoolCodecCS::<CurrentNetwork>::new());
+        let request = framed.next().await.unwrap()?;
+        assert_eq!(request.name(), "UpgradeTls");
+        let framed = upgrade_server(framed, &acceptor).await?;
+
+        let (reader, writer) = tokio::io::split(framed.into_inner());
+        let mut reader = FramedRead::new(reader, PoolCodecCS::<CurrentNetwork>::new());
+        let mut writer = FramedWrite::new(writer, PoolCodecSC::<CurrentNetwork>::new());
+        let connect = reader.next().await.unwrap()?;
+        assert_eq!(connect.name(), "Connect");
+        writer
+            .send(PoolMessageSC::ConnectAck(
+                true,
+                pool_address,
+                Some(1),
+                Some("signature".to_string()),
+                None,
+                vec![],
+            ))
+            .await?;
+        anyhow::Ok(())
+    });
+
+    let framed = Framed::new(client, PoolCodecSC::<CurrentNetwork>::new());
+    let server_name = ServerName::try_from(SERVER_NAME)?;
+    let framed = upgrade_client(framed, &connector, server_name).await?;
+
+    let (reader, writer) = tokio::io::split(framed.into_inner());
+    let mut reader = FramedRead::new(reader, PoolCodecSC::<CurrentNetwork>::new());
+    let mut writer = FramedWrite::new(writer, PoolCodecCS::<CurrentNetwork>::new());
+    writer
+        .send(PoolMessageCS::Connect(
+            0,
+            ADDRESS_TYPE_EMAIL,
+            0,
+            1,
+            0,
+            "my_worker_1".to_string(),
+            "215587407@qq.com".to_string(),
+            vec![],
+        ))
+        .await?;
+    match reader.next().await.unwrap()? {
+        PoolMessageSC::ConnectAck(true, address, Some(1), ..) => assert_eq!(address, pool_address),
+        message => panic!("unexpected message {:?}", message),
+    }
+    pool.await??;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upgrade_refused() -> anyhow::Result<()> {
+    let (connector, _) = tls_configs()?;
+    let (client, server) = tokio::io::duplex(1 << 16);
+
+    let pool = tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server);
+        let mut reader = FramedRead::new(reader, PoolCodecCS::<CurrentNetwork>::new());
+        let mut writer = FramedWrite::new(writer, PoolCodecSC::<CurrentNetwork>::new());
+        assert_eq!(reader.next().await.unwrap()?.name(), "UpgradeTls");
+        writer.send(PoolMessageSC::UpgradeTlsAck(false)).await?;
+        anyhow::Ok(())
+    });
+
+    let framed = Framed::new(client, PoolCodecSC::<CurrentNetwork>::new());
+    let server_name = ServerName::try_from(SERVER_NAME)?;
+    assert!(upgrade_client(framed, &connector, server_name)
+        .await
+        .is_err());
+    pool.await??;
+    Ok(())
+}
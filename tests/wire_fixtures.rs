@@ -0,0 +1,278 @@
+// Copyright (C) 2019-2022 6block.
+// This file is the zk.work pool protocol for Aleo.
+
+// The zkwork_aleo_protol library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// You should have received a copy of the GNU General Public License
+// along with the zkwork_aleo_protocol library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Golden frames of the V1 layout this library writes, written out by hand so an encoding change
+//! fails here even if the encoder and decoder change together. Each fixture is a whole frame,
+//! u32 length prefix included, without negotiated capabilities.
+//!
+//! Only some of them match what pools and miners built on the original protocol speak, pinned as
+//! the `BASELINE_*` fixtures: `connect server` without TLVs, `notify job` without TLVs, the
+//! rejected `connect server ack`, `disconnect`, `ping`, `pong` and `pool shutdown` are unchanged.
+//! `submit solution` gained `sub_worker_id` and the has-signature byte, and the accepted
+//! `connect server ack` the has-job byte, so the baseline frames of both fail to decode.
+
+use ::bytes::BytesMut;
+use snarkvm::prelude::{
+    Address, AffineCurve, EpochChallenge, Group, Network, PartialSolution, ProverSolution,
+    Testnet3, ToBytes,
+};
+use snarkvm_algorithms::polycommit::kzg10::{KZGCommitment, KZGProof};
+use tokio_util::codec::{Decoder, Encoder};
+use zkwork_aleo_protocol::message::*;
+
+type CurrentNetwork = Testnet3;
+
+const CONNECT: &str = concat!(
+    "2a0000008000010001000b6d795f776f726b65725f3132313535383734303740",
+    "71712e636f6d0001040001000000",
+);
+const CONNECT_ACK_ACCEPTED: &str = concat!(
+    "3c0000000001c549be4b84827e13f383dda9338860e70650900db894b2160750",
+    "4702eb2468030100000000746573747369676e61747572650001040001000000",
+);
+const CONNECT_ACK_REJECTED: &str = concat!(
+    "220000000000c549be4b84827e13f383dda9338860e70650900db894b2160750",
+    "4702eb246803",
+);
+const NOTIFY: &str = concat!(
+    "39000000010700000000000000ffffffffffffffff00000000b2258f5e880420",
+    "f5f3107ac446f8aa2ae3788a1cb7635d72f280cf02c95b5e0cff1f0000",
+);
+// `NOTIFY` with a share target of 1_000, appended as TLV tag 11.
+const NOTIFY_SHARE_TARGET: &str = concat!(
+    "44000000010700000000000000ffffffffffffffff00000000b2258f5e880420",
+    "f5f3107ac446f8aa2ae3788a1cb7635d72f280cf02c95b5e0cff1f00000b0800",
+    "e803000000000000",
+);
+const SUBMIT: &str = concat!(
+    "9b000000810100000002000000030000000000000000c549be4b84827e13f383",
+    "dda9338860e70650900db894b21607504702eb2468032a0000000000000066db",
+    "6f1030f91f0f1824381daad6321c43739c828b3d8d69f75711a97a2d524c3d54",
+    "201c61a85ca1b3835901b6a2940066db6f1030f91f0f1824381daad6321c4373",
+    "9c828b3d8d69f75711a97a2d524c3d54201c61a85ca1b3835901b6a2940000",
+);
+// The original layouts := << 129, worker_id, job_id, prover_solution >> and
+// << 0, 1, pool_address, worker_id, signature >>, and a `CONNECT` without TLVs.
+const BASELINE_SUBMIT: &str = concat!(
+    "9600000081010000000300000000000000c549be4b84827e13f383dda9338860",
+    "e70650900db894b21607504702eb2468032a0000000000000066db6f1030f91f",
+    "0f1824381daad6321c43739c828b3d8d69f75711a97a2d524c3d54201c61a85c",
+    "a1b3835901b6a2940066db6f1030f91f0f1824381daad6321c43739c828b3d8d",
+    "69f75711a97a2d524c3d54201c61a85ca1b3835901b6a2940000",
+);
+const BASELINE_CONNECT_ACK_ACCEPTED: &str = concat!(
+    "330000000001c549be4b84827e13f383dda9338860e70650900db894b2160750",
+    "4702eb24680301000000746573747369676e6174757265",
+);
+const BASELINE_CONNECT: &str = concat!(
+    "220000008000010001000b6d795f776f726b65725f3132313535383734303740",
+    "71712e636f6d",
+);
+const DISCONNECT: &str = "050000008201000000";
+const PING: &str = "0100000083";
+const PONG: &str = "0100000003";
+const SHUT_DOWN: &str = "0100000002";
+
+/// The pool and worker address of the fixtures.
+fn address() -> Address<CurrentNetwork> {
+    Address::new(Group::generator())
+}
+
+/// Epoch 0 with the BHP1024 hash of 1024 `true` bits as its block hash.
+fn epoch_challenge() -> anyhow::Result<EpochChallenge<CurrentNetwork>> {
+    EpochChallenge::new(
+        0,
+        CurrentNetwork::hash_bhp1024(&[true; 1024])?.into(),
+        CurrentNetwork::COINBASE_PUZZLE_DEGREE,
+    )
+}
+
+/// Nonce 42, with the G1 generator as its commitment and proof.
+fn prover_solution() -> ProverSolution<CurrentNetwork> {
+    let commitment = KZGCommitment(AffineCurve::prime_subgroup_generator());
+    ProverSolution::new(
+        PartialSolution::new(address(), 42, commitment),
+        KZGProof {
+            w: AffineCurve::prime_subgroup_generator(),
+            random_v: None,
+        },
+    )
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("valid hex"))
+        .collect()
+}
+
+fn encode_cs(message: PoolMessageCS<CurrentNetwork>) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = BytesMut::new();
+    PoolCodecCS::<CurrentNetwork>::new().encode(message, &mut buffer)?;
+    Ok(buffer.to_vec())
+}
+
+fn encode_sc(message: PoolMessageSC<CurrentNetwork>) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = BytesMut::new();
+    PoolCodecSC::<CurrentNetwork>::new().encode(message, &mut buffer)?;
+    Ok(buffer.to_vec())
+}
+
+fn decode_cs(hex: &str) -> anyhow::Result<PoolMessageCS<CurrentNetwork>> {
+    let mut buffer = BytesMut::from(&from_hex(hex)[..]);
+    let message = PoolCodecCS::<CurrentNetwork>::new().decode(&mut buffer)?;
+    assert!(buffer.is_empty(), "{}", hex);
+    Ok(message.expect("a complete frame"))
+}
+
+fn decode_sc(hex: &str) -> anyhow::Result<PoolMessageSC<CurrentNetwork>> {
+    let mut buffer = BytesMut::from(&from_hex(hex)[..]);
+    let message = PoolCodecSC::<CurrentNetwork>::new().decode(&mut buffer)?;
+    assert!(buffer.is_empty(), "{}", hex);
+    Ok(message.expect("a complete frame"))
+}
+
+#[test]
+fn test_connect_fixture() -> anyhow::Result<()> {
+    let capabilities = Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0]);
+    let connect = PoolMessageCS::Connect(
+        0,
+        ADDRESS_TYPE_EMAIL,
+        0,
+        1,
+        0,
+        "my_worker_1".to_string(),
+        "215587407@qq.com".to_string(),
+        vec![capabilities.clone()],
+    );
+    assert_eq!(encode_cs(connect)?, from_hex(CONNECT));
+
+    match decode_cs(CONNECT)? {
+        PoolMessageCS::Connect(0, ADDRESS_TYPE_EMAIL, 0, 1, 0, name, address, tlvs) => {
+            assert_eq!(name, "my_worker_1");
+            assert_eq!(address, "215587407@qq.com");
+            assert_eq!(tlvs, [capabilities]);
+        }
+        message => panic!("unexpected message {:?}", message),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_connect_ack_fixtures() -> anyhow::Result<()> {
+    let capabilities = Tlv::new(TAG_CAPABILITIES, vec![1, 0, 0, 0]);
+    let accepted = PoolMessageSC::ConnectAck(
+        true,
+        address(),
+        Some(1),
+        Some("testsignature".to_string()),
+        None,
+        vec![capabilities.clone()],
+    );
+    assert_eq!(encode_sc(accepted)?, from_hex(CONNECT_ACK_ACCEPTED));
+    let rejected = PoolMessageSC::ConnectAck(false, address(), None, None, None, vec![]);
+    assert_eq!(encode_sc(rejected)?, from_hex(CONNECT_ACK_REJECTED));
+
+    match decode_sc(CONNECT_ACK_ACCEPTED)? {
+        PoolMessageSC::ConnectAck(true, pool_address, Some(1), Some(signature), None, tlvs) => {
+            assert_eq!(pool_address, address());
+            assert_eq!(signature, "testsignature");
+            assert_eq!(tlvs, [capabilities]);
+        }
+        message => panic!("unexpected message {:?}", message),
+    }
+    match decode_sc(CONNECT_ACK_REJECTED)? {
+        PoolMessageSC::ConnectAck(false, pool_address, None, None, None, tlvs) => {
+            assert_eq!(pool_address, address());
+            assert!(tlvs.is_empty());
+        }
+        message => panic!("unexpected message {:?}", message),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_notify_fixture() -> anyhow::Result<()> {
+    let notify = PoolMessageSC::Notify(7, u64::MAX, u64::MAX, epoch_challenge()?, vec![]);
+    assert_eq!(encode_sc(notify)?, from_hex(NOTIFY));
+    let notify = PoolMessageSC::Notify(7, u64::MAX, 1_000, epoch_challenge()?, vec![]);
+    assert_eq!(encode_sc(notify)?, from_hex(NOTIFY_SHARE_TARGET));
+
+    // Frames without the share target TLV mine at the block target.
+    match decode_sc(NOTIFY)? {
+        PoolMessageSC::Notify(7, u64::MAX, u64::MAX, challenge, tlvs) => {
+            assert_eq!(challenge, epoch_challenge()?);
+            assert!(tlvs.is_empty());
+        }
+        message => panic!("unexpected message {:?}", message),
+    }
+    match decode_sc(NOTIFY_SHARE_TARGET)? {
+        PoolMessageSC::Notify(7, u64::MAX, 1_000, _, tlvs) => assert!(tlvs.is_empty()),
+        message => panic!("unexpected message {:?}", message),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_submit_fixture() -> anyhow::Result<()> {
+    let solution = prover_solution().to_bytes_le()?;
+    let submit = PoolMessageCS::Submit(1, 2, 3, Data::Buffer(solution.clone().into()), None);
+    assert_eq!(encode_cs(submit)?, from_hex(SUBMIT));
+    // The encoding does not depend on whether the solution is still an object.
+    let submit = PoolMessageCS::Submit(1, 2, 3, Data::Object(prover_solution()), None);
+    assert_eq!(encode_cs(submit)?, from_hex(SUBMIT));
+
+    match decode_cs(SUBMIT)? {
+        PoolMessageCS::Submit(1, 2, 3, data, None) => {
+            assert_eq!(data.deserialize_blocking()?, prover_solution());
+        }
+        message => panic!("unexpected message {:?}", message),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_control_fixtures() -> anyhow::Result<()> {
+    assert_eq!(
+        encode_cs(PoolMessageCS::DisConnect(1))?,
+        from_hex(DISCONNECT)
+    );
+    assert_eq!(encode_cs(PoolMessageCS::Ping)?, from_hex(PING));
+    assert_eq!(encode_sc(PoolMessageSC::Pong(None))?, from_hex(PONG));
+    assert_eq!(encode_sc(PoolMessageSC::ShutDown)?, from_hex(SHUT_DOWN));
+
+    assert!(matches!(
+        decode_cs(DISCONNECT)?,
+        PoolMessageCS::DisConnect(1)
+    ));
+    assert!(matches!(decode_cs(PING)?, PoolMessageCS::Ping));
+    assert!(matches!(decode_sc(PONG)?, PoolMessageSC::Pong(None)));
+    assert!(matches!(decode_sc(SHUT_DOWN)?, PoolMessageSC::ShutDown));
+    Ok(())
+}
+
+#[test]
+fn test_baseline_fixtures() -> anyhow::Result<()> {
+    // Unchanged layouts decode and encode back to the same bytes.
+    let connect = decode_cs(BASELINE_CONNECT)?;
+    assert!(matches!(
+        &connect,
+        PoolMessageCS::Connect(0, ADDRESS_TYPE_EMAIL, 0, 1, 0, name, _, tlvs)
+            if name == "my_worker_1" && tlvs.is_empty()
+    ));
+    assert_eq!(encode_cs(connect)?, from_hex(BASELINE_CONNECT));
+    assert_eq!(encode_sc(decode_sc(NOTIFY)?)?, from_hex(NOTIFY));
+
+    // The changed layouts are rejected rather than misread.
+    assert!(decode_cs(BASELINE_SUBMIT).is_err());
+    assert!(decode_sc(BASELINE_CONNECT_ACK_ACCEPTED).is_err());
+    Ok(())
+}